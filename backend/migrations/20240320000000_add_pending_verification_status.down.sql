@@ -0,0 +1,2 @@
+-- Postgres can't remove a value from an enum type. 'pending_verification' stays defined but
+-- unused once the next down migration has moved users back to 'inactive'.
@@ -0,0 +1,4 @@
+-- Move unverified users back to the inactive status
+UPDATE users
+SET status = 'inactive'
+WHERE status = 'pending_verification';
@@ -0,0 +1,8 @@
+-- Role-based access control for users
+CREATE TYPE user_role AS ENUM ('user', 'moderator', 'admin');
+
+ALTER TABLE users ADD COLUMN role user_role NOT NULL DEFAULT 'user';
+
+CREATE INDEX idx_users_role ON users(role) WHERE role <> 'user';
+
+COMMENT ON COLUMN users.role IS 'Access level of the user; admin endpoints require the admin role';
@@ -0,0 +1,7 @@
+-- Track failed password attempts so accounts can be temporarily locked
+ALTER TABLE users
+    ADD COLUMN failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN locked_until TIMESTAMPTZ;
+
+COMMENT ON COLUMN users.failed_login_attempts IS 'Consecutive failed logins since the last successful one';
+COMMENT ON COLUMN users.locked_until IS 'Logins are refused until this time after too many failures';
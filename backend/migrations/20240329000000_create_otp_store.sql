@@ -0,0 +1,10 @@
+-- Short-lived OTP state (codes, attempt counters, login links) when OTP_STORE=postgres
+CREATE TABLE otp_store (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_otp_store_expires_at ON otp_store(expires_at);
+
+COMMENT ON TABLE otp_store IS 'Key-value store with expiry used by the Postgres OTP store backend';
@@ -0,0 +1,4 @@
+-- Preferred language for emails
+ALTER TABLE users ADD COLUMN locale VARCHAR(10) NOT NULL DEFAULT 'en';
+
+COMMENT ON COLUMN users.locale IS 'Language tag used to pick email templates, e.g. en or fr';
@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS email_outbox_notify ON email_outbox;
+DROP FUNCTION IF EXISTS notify_email_outbox();
+DROP TABLE IF EXISTS email_outbox;
+DROP TYPE IF EXISTS email_status;
@@ -0,0 +1,38 @@
+-- Emails waiting to be delivered. Rows are written in the same transaction as the change
+-- that triggers them, so a crash can't lose a message or send one for a rolled back change.
+CREATE TYPE email_status AS ENUM ('pending', 'sent', 'dead');
+
+CREATE TABLE email_outbox (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    recipient VARCHAR(255) NOT NULL,
+    template VARCHAR(50) NOT NULL,
+    locale VARCHAR(10) NOT NULL,
+    -- Cleared once the email is sent, as it may contain codes and login links
+    variables JSONB NOT NULL DEFAULT '{}',
+    status email_status NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_outbox_due ON email_outbox(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX idx_email_outbox_status ON email_outbox(status, created_at);
+
+-- Wake up delivery workers when an email is queued or replayed
+CREATE FUNCTION notify_email_outbox() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('email_outbox', NEW.id::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER email_outbox_notify
+    AFTER INSERT OR UPDATE OF status ON email_outbox
+    FOR EACH ROW
+    WHEN (NEW.status = 'pending')
+    EXECUTE FUNCTION notify_email_outbox();
+
+COMMENT ON TABLE email_outbox IS 'Transactional outbox of emails delivered by the background worker';
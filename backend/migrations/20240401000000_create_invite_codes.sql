@@ -0,0 +1,17 @@
+-- Invite codes for REGISTRATION_MODE=invite
+-- Only the SHA-256 hash of a code is stored; the prefix is kept for display.
+CREATE TABLE invite_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    code_prefix VARCHAR(8) NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    note VARCHAR(200),
+    max_uses INTEGER NOT NULL DEFAULT 1 CHECK (max_uses > 0),
+    uses INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_invite_codes_code_hash ON invite_codes(code_hash);
+
+COMMENT ON TABLE invite_codes IS 'Single or multi-use invite codes required to register in invite-only mode';
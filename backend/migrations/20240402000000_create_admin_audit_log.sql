@@ -0,0 +1,15 @@
+-- Actions taken by admins on user accounts
+-- target_user_id has no foreign key so entries outlive deleted accounts.
+CREATE TABLE admin_audit_log (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    admin_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR(50) NOT NULL,
+    target_user_id UUID,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_admin_audit_log_created_at ON admin_audit_log(created_at DESC);
+CREATE INDEX idx_admin_audit_log_target_user_id ON admin_audit_log(target_user_id);
+
+COMMENT ON TABLE admin_audit_log IS 'Audit trail of admin actions with the acting admin';
@@ -0,0 +1,2 @@
+-- Bumped to end all login sessions of a user, e.g. when an admin suspends them
+ALTER TABLE users ADD COLUMN session_version INTEGER NOT NULL DEFAULT 0;
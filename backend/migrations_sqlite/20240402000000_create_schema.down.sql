@@ -0,0 +1,10 @@
+DROP TRIGGER IF EXISTS update_links_updated_at;
+DROP TRIGGER IF EXISTS update_user_verification;
+DROP TRIGGER IF EXISTS update_users_updated_at;
+DROP TABLE IF EXISTS admin_audit_log;
+DROP TABLE IF EXISTS invite_codes;
+DROP TABLE IF EXISTS email_outbox;
+DROP TABLE IF EXISTS user_identities;
+DROP TABLE IF EXISTS api_tokens;
+DROP TABLE IF EXISTS links;
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,162 @@
+-- SQLite version of the schema built by the Postgres migrations up to
+-- 20240402000000_create_admin_audit_log. Changes to the Postgres schema need a matching
+-- migration here.
+--
+-- SQLite has no UUID, enum, JSONB or TIMESTAMPTZ types, so:
+-- * UUIDs are hyphenated TEXT, generated by the application
+-- * enums are TEXT with a CHECK constraint listing the Postgres enum values
+-- * JSON is TEXT checked with json_valid()
+-- * timestamps are RFC 3339 TEXT in UTC, which sorts chronologically
+-- Triggers replace the plpgsql functions that maintain updated_at and the verification state.
+-- The otp_store table is not mirrored: the postgres OTP store needs a Postgres database.
+
+CREATE TABLE users (
+    id TEXT PRIMARY KEY NOT NULL,
+    email VARCHAR(255) NOT NULL CHECK (email LIKE '%_@_%._%'),
+    username VARCHAR(50) NOT NULL CHECK (
+        length(username) BETWEEN 3 AND 50 AND username NOT GLOB '*[^A-Za-z0-9_]*'
+    ),
+    password_hash VARCHAR(255) NOT NULL,
+    gender TEXT NOT NULL CHECK (gender IN ('male', 'female', 'other')),
+    status TEXT NOT NULL DEFAULT 'inactive'
+        CHECK (status IN ('active', 'inactive', 'suspended', 'pending_verification')),
+    role TEXT NOT NULL DEFAULT 'user' CHECK (role IN ('user', 'moderator', 'admin')),
+    is_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    verification_attempts INTEGER NOT NULL DEFAULT 0,
+    -- Consecutive failed logins since the last successful one
+    failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    -- Logins are refused until this time after too many failures
+    locked_until TEXT,
+    -- Language tag used to pick email templates, e.g. en or fr
+    locale VARCHAR(10) NOT NULL DEFAULT 'en',
+    session_version INTEGER NOT NULL DEFAULT 0,
+    verified_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE UNIQUE INDEX idx_users_username ON users(username);
+CREATE INDEX idx_users_email ON users(email);
+-- Ensures email uniqueness only for verified users
+CREATE UNIQUE INDEX idx_users_verified_email ON users(email) WHERE is_verified = TRUE;
+CREATE INDEX idx_users_status ON users(status);
+CREATE INDEX idx_users_role ON users(role) WHERE role <> 'user';
+
+CREATE TABLE links (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    title VARCHAR(255),
+    description TEXT,
+    click_count INTEGER NOT NULL DEFAULT 0,
+    preview TEXT NOT NULL DEFAULT '{}' CHECK (json_valid(preview)),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE INDEX idx_links_user_id ON links(user_id);
+CREATE INDEX idx_links_created_at ON links(created_at DESC);
+
+CREATE TABLE api_tokens (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    token_prefix VARCHAR(16) NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    -- JSON array of scope names
+    scopes TEXT NOT NULL DEFAULT '[]' CHECK (json_valid(scopes)),
+    expires_at TEXT,
+    last_used_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE UNIQUE INDEX idx_api_tokens_token_hash ON api_tokens(token_hash);
+CREATE INDEX idx_api_tokens_user_id ON api_tokens(user_id);
+
+CREATE TABLE user_identities (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    email VARCHAR(255),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    last_login_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE UNIQUE INDEX idx_user_identities_issuer_subject ON user_identities(issuer, subject);
+CREATE INDEX idx_user_identities_user_id ON user_identities(user_id);
+
+CREATE TABLE email_outbox (
+    id TEXT PRIMARY KEY NOT NULL,
+    recipient VARCHAR(255) NOT NULL,
+    template VARCHAR(50) NOT NULL,
+    locale VARCHAR(10) NOT NULL,
+    -- Cleared once the email is sent, as it may contain codes and login links
+    variables TEXT NOT NULL DEFAULT '{}' CHECK (json_valid(variables)),
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'sent', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    sent_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE INDEX idx_email_outbox_due ON email_outbox(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX idx_email_outbox_status ON email_outbox(status, created_at);
+
+CREATE TABLE invite_codes (
+    id TEXT PRIMARY KEY NOT NULL,
+    code_prefix VARCHAR(8) NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    note VARCHAR(200),
+    max_uses INTEGER NOT NULL DEFAULT 1 CHECK (max_uses > 0),
+    uses INTEGER NOT NULL DEFAULT 0,
+    expires_at TEXT,
+    created_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE UNIQUE INDEX idx_invite_codes_code_hash ON invite_codes(code_hash);
+
+CREATE TABLE admin_audit_log (
+    id TEXT PRIMARY KEY NOT NULL,
+    admin_id TEXT REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR(50) NOT NULL,
+    target_user_id TEXT,
+    details TEXT NOT NULL DEFAULT '{}' CHECK (json_valid(details)),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE INDEX idx_admin_audit_log_created_at ON admin_audit_log(created_at DESC);
+CREATE INDEX idx_admin_audit_log_target_user_id ON admin_audit_log(target_user_id);
+
+-- Triggers run after the update, as SQLite can't change NEW. Recursive triggers are off by
+-- default, so their own updates don't fire them again.
+CREATE TRIGGER update_users_updated_at
+    AFTER UPDATE ON users
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE users
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_user_verification
+    AFTER UPDATE OF verified_at ON users
+    FOR EACH ROW
+    WHEN NEW.verified_at IS NOT NULL AND OLD.verified_at IS NULL
+BEGIN
+    UPDATE users SET is_verified = TRUE, status = 'active' WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_links_updated_at
+    AFTER UPDATE ON links
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE links
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')
+    WHERE id = NEW.id;
+END;
@@ -1,6 +1,9 @@
 use crate::api::models::{ResendOtpRequest, VerifyEmailRequest};
 use crate::api::{ApiResponse, ErrorResponse};
-use crate::models::auth::{AuthResponse, LoginRequest, RegisterRequest};
+use crate::models::auth::{
+    AuthResponse, ConsumeMagicLinkRequest, LoginRequest, MagicLinkRequest, OidcCallbackRequest,
+    RegisterRequest,
+};
 type EmptyResponse = ApiResponse<()>;
 type AuthResponseWrapper = ApiResponse<AuthResponse>;
 
@@ -10,8 +13,9 @@ type AuthResponseWrapper = ApiResponse<AuthResponse>;
     request_body = RegisterRequest,
     responses(
         (status = 200, description = "Registration initiated successfully", body = EmptyResponse),
-        (status = 400, description = "Invalid request data", body = ErrorResponse),
+        (status = 403, description = "Invite code missing or invalid, or email domain not allowed", body = ErrorResponse),
         (status = 409, description = "Email or username already exists", body = ErrorResponse),
+        (status = 422, description = "Invalid request data (VALIDATION_ERROR) or disposable email address (DISPOSABLE_EMAIL)", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
     tag = "auth"
@@ -24,8 +28,10 @@ pub fn register_docs() {}
     request_body = VerifyEmailRequest,
     responses(
         (status = 200, description = "Email verified successfully", body = EmptyResponse),
-        (status = 400, description = "Invalid verification code", body = ErrorResponse),
-        (status = 404, description = "Registration not found", body = ErrorResponse),
+        (status = 400, description = "Invalid or expired verification code (INVALID_OTP); details include remaining_attempts", body = ErrorResponse),
+        (status = 404, description = "No unverified account with this email (NOT_FOUND)", body = ErrorResponse),
+        (status = 422, description = "Invalid request data (VALIDATION_ERROR)", body = ErrorResponse),
+        (status = 429, description = "Too many incorrect codes, a new code must be requested (TOO_MANY_OTP_ATTEMPTS)", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
     tag = "auth"
@@ -38,7 +44,9 @@ pub fn verify_email_docs() {}
     request_body = ResendOtpRequest,
     responses(
         (status = 200, description = "Verification code resent", body = EmptyResponse),
+        (status = 400, description = "Account is not pending verification (INVALID_STATE)", body = ErrorResponse),
         (status = 404, description = "Email not found", body = ErrorResponse),
+        (status = 422, description = "Invalid request data (VALIDATION_ERROR)", body = ErrorResponse),
         (status = 429, description = "Too many attempts", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
@@ -52,9 +60,9 @@ pub fn resend_otp_docs() {}
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = AuthResponseWrapper),
-        (status = 400, description = "Invalid credentials", body = ErrorResponse),
-        (status = 401, description = "Email not verified", body = ErrorResponse),
-        (status = 403, description = "Account not active", body = ErrorResponse),
+        (status = 401, description = "Invalid email or password, or the account is temporarily locked (INVALID_CREDENTIALS)", body = ErrorResponse),
+        (status = 403, description = "Email not verified (EMAIL_NOT_VERIFIED), account suspended (ACCOUNT_SUSPENDED) or inactive (ACCOUNT_INACTIVE)", body = ErrorResponse),
+        (status = 429, description = "Too many failed attempts from this IP (TOO_MANY_ATTEMPTS)", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
     tag = "auth"
@@ -63,14 +71,69 @@ pub fn login_docs() {}
 
 #[utoipa::path(
     post,
-    path = "/api/auth/admin/reset-otp-attempts",
-    params(
-        ("X-Admin-Token" = String, Header, description = "Admin authentication token")
+    path = "/api/auth/magic-link",
+    request_body = MagicLinkRequest,
+    responses(
+        (status = 200, description = "Login link sent if an active account exists for the email", body = EmptyResponse),
+        (status = 422, description = "Invalid email (VALIDATION_ERROR)", body = ErrorResponse),
+        (status = 429, description = "Too many emails sent to this address (TOO_MANY_ATTEMPTS)", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    tag = "auth"
+)]
+pub fn request_magic_link_docs() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/magic-link/consume",
+    request_body = ConsumeMagicLinkRequest,
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponseWrapper),
+        (status = 401, description = "Link is invalid, expired or already used (INVALID_MAGIC_LINK)", body = ErrorResponse),
+        (status = 403, description = "Account suspended (ACCOUNT_SUSPENDED) or inactive (ACCOUNT_INACTIVE)", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    tag = "auth"
+)]
+pub fn consume_magic_link_docs() {}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oidc/login",
+    responses(
+        (status = 303, description = "Redirect to the identity provider's authorization endpoint"),
+        (status = 404, description = "Single sign-on is not configured (SSO_DISABLED)", body = ErrorResponse),
+        (status = 502, description = "Identity provider unreachable (SSO_PROVIDER_ERROR)", body = ErrorResponse)
+    ),
+    tag = "auth"
+)]
+pub fn oidc_login_docs() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/oidc/callback",
+    request_body = OidcCallbackRequest,
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponseWrapper),
+        (status = 401, description = "Unknown or expired state (SSO_INVALID_STATE) or invalid ID token (SSO_INVALID_TOKEN)", body = ErrorResponse),
+        (status = 403, description = "Provider did not assert a verified email (SSO_EMAIL_NOT_VERIFIED), account suspended (ACCOUNT_SUSPENDED) or inactive (ACCOUNT_INACTIVE), or a new account isn't allowed to register (INVITE_REQUIRED, DOMAIN_NOT_ALLOWED)", body = ErrorResponse),
+        (status = 404, description = "Single sign-on is not configured (SSO_DISABLED)", body = ErrorResponse),
+        (status = 422, description = "A new account would use a disposable email address (DISPOSABLE_EMAIL)", body = ErrorResponse),
+        (status = 502, description = "Code exchange with the identity provider failed (SSO_PROVIDER_ERROR)", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
+    tag = "auth"
+)]
+pub fn oidc_callback_docs() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/auth/reset-otp-attempts",
     responses(
         (status = 200, description = "OTP attempts reset successfully", body = EmptyResponse),
         (status = 400, description = "Invalid request data", body = ErrorResponse),
-        (status = 403, description = "Insufficient permissions", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
         (status = 404, description = "User not found", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     ),
@@ -80,3 +143,22 @@ pub fn login_docs() {}
     tag = "auth"
 )]
 pub fn reset_otp_attempts_docs() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/auth/unlock-account",
+    request_body = ResendOtpRequest,
+    responses(
+        (status = 200, description = "Account unlocked successfully", body = EmptyResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 422, description = "Invalid request data", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub fn unlock_account_docs() {}
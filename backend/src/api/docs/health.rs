@@ -1,4 +1,5 @@
 use crate::api::{ApiResponse, ErrorResponse};
+use crate::services::readiness::ReadinessReport;
 use serde_json::Value;
 
 /// Public health check endpoint
@@ -12,18 +13,58 @@ use serde_json::Value;
 )]
 pub fn root_docs() {}
 
+/// Liveness probe, which doesn't check any dependency
+#[utoipa::path(
+    get,
+    path = "/livez",
+    responses(
+        (status = 200, description = "Process is alive", body = ApiResponse<Value>),
+    ),
+    tag = "health"
+)]
+pub fn livez_docs() {}
+
+/// Readiness probe with the status of each dependency
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "All critical dependencies are available", body = ApiResponse<ReadinessReport>),
+        (status = 503, description = "A critical dependency is unavailable, or shutdown is draining", body = ApiResponse<ReadinessReport>),
+    ),
+    tag = "health"
+)]
+pub fn readyz_docs() {}
+
 /// Admin database health check endpoint
 #[utoipa::path(
     get,
     path = "/api/admin/db/health",
-    params(
-        ("X-Admin-Token" = String, Header, description = "Admin authentication token")
-    ),
     responses(
         (status = 200, description = "Database connection healthy", body = ApiResponse<Value>),
-        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
         (status = 503, description = "Database connection failed", body = ErrorResponse)
     ),
+    security(
+        ("bearer_auth" = [])
+    ),
     tag = "admin"
 )]
 pub fn admin_db_health_docs() {}
+
+/// Prometheus metrics, served here unless `metrics.listen_addr` moves them to their own listener
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub fn metrics_docs() {}
@@ -1,18 +1,42 @@
 mod auth;
 mod health;
 mod links;
+mod tokens;
 
-use crate::api::models::VerifyEmailRequest;
-use crate::api::{ApiResponse, ErrorResponse};
-use crate::database::models::Link;
-use crate::models::auth::{AuthResponse, LoginRequest, RegisterRequest, User, UserStatus};
+use crate::api::models::{
+    AdminUserActionRequest, CreateApiTokenRequest, CreateInviteRequest, CreatedApiToken,
+    CreatedInvite, EmailTemplateInfo, LinkStats, PendingCleanupResult, PreviewEmailTemplateRequest,
+    UpdateLocaleRequest, VerifyEmailRequest,
+};
+use crate::api::{ApiResponse, ErrorResponse, PaginationMeta};
+use crate::database::models::{
+    ApiToken, AuditLogEntry, EmailStatus, InviteCode, Link, OutboxEmail, UserOverview,
+};
+use crate::models::auth::{
+    AuthResponse, ConsumeMagicLinkRequest, LoginRequest, MagicLinkRequest, OidcCallbackRequest,
+    RegisterRequest, TokenScope, User, UserRole, UserStatus,
+};
 use crate::models::user::Gender;
+use crate::services::email_templates::{Locale, RenderedEmail};
+use crate::services::readiness::{CheckResult, CheckStatus, ReadinessReport};
 use utoipa::OpenApi;
 
 type EmptyResponse = ApiResponse<()>;
 type AuthResponseWrapper = ApiResponse<AuthResponse>;
 type LinkResponse = ApiResponse<Link>;
 type LinksResponse = ApiResponse<Vec<Link>>;
+type LinkStatsResponse = ApiResponse<LinkStats>;
+type ApiTokensResponse = ApiResponse<Vec<ApiToken>>;
+type CreatedApiTokenResponse = ApiResponse<CreatedApiToken>;
+type InviteCodesResponse = ApiResponse<Vec<InviteCode>>;
+type CreatedInviteResponse = ApiResponse<CreatedInvite>;
+type PendingCleanupResponse = ApiResponse<PendingCleanupResult>;
+type OutboxEmailsResponse = ApiResponse<Vec<OutboxEmail>>;
+type EmailTemplatesResponse = ApiResponse<Vec<EmailTemplateInfo>>;
+type RenderedEmailResponse = ApiResponse<RenderedEmail>;
+type UserListResponse = ApiResponse<Vec<UserOverview>>;
+type UserOverviewResponse = ApiResponse<UserOverview>;
+type AuditLogResponse = ApiResponse<Vec<AuditLogEntry>>;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -20,16 +44,47 @@ type LinksResponse = ApiResponse<Vec<Link>>;
         crate::api::docs::auth::register_docs,
         crate::api::docs::auth::verify_email_docs,
         crate::api::docs::auth::login_docs,
+        crate::api::docs::auth::request_magic_link_docs,
+        crate::api::docs::auth::consume_magic_link_docs,
+        crate::api::docs::auth::oidc_login_docs,
+        crate::api::docs::auth::oidc_callback_docs,
+        crate::api::docs::auth::unlock_account_docs,
         crate::api::docs::links::get_links_docs,
         crate::api::docs::links::create_link_docs,
         crate::api::docs::links::delete_link_docs,
         crate::api::docs::links::track_click_docs,
+        crate::routes::links::link_stats,
+        crate::api::docs::tokens::list_tokens_docs,
+        crate::api::docs::tokens::create_token_docs,
+        crate::api::docs::tokens::revoke_token_docs,
         crate::api::docs::health::root_docs,
-        crate::api::docs::health::admin_db_health_docs
+        crate::api::docs::health::livez_docs,
+        crate::api::docs::health::readyz_docs,
+        crate::api::docs::health::admin_db_health_docs,
+        crate::api::docs::health::metrics_docs,
+        crate::routes::account::update_locale,
+        crate::routes::admin_users::list_users,
+        crate::routes::admin_users::get_user,
+        crate::routes::admin_users::suspend_user,
+        crate::routes::admin_users::deactivate_user,
+        crate::routes::admin_users::reactivate_user,
+        crate::routes::admin_users::verify_user,
+        crate::routes::admin_users::list_audit_log,
+        crate::routes::admin_users::cleanup_pending,
+        crate::routes::invites::list_invites,
+        crate::routes::invites::create_invite,
+        crate::routes::invites::revoke_invite,
+        crate::routes::email_outbox::list_emails,
+        crate::routes::email_outbox::replay_email,
+        crate::routes::email_templates::list_templates,
+        crate::routes::email_templates::preview_template
     ),
     components(schemas(
         RegisterRequest,
         LoginRequest,
+        MagicLinkRequest,
+        ConsumeMagicLinkRequest,
+        OidcCallbackRequest,
         AuthResponse,
         User,
         Gender,
@@ -39,8 +94,44 @@ type LinksResponse = ApiResponse<Vec<Link>>;
         AuthResponseWrapper,
         LinkResponse,
         LinksResponse,
+        LinkStats,
+        LinkStatsResponse,
         ErrorResponse,
-        Link
+        Link,
+        ApiToken,
+        TokenScope,
+        CreateApiTokenRequest,
+        CreatedApiToken,
+        ApiTokensResponse,
+        CreatedApiTokenResponse,
+        Locale,
+        UpdateLocaleRequest,
+        EmailTemplateInfo,
+        PreviewEmailTemplateRequest,
+        RenderedEmail,
+        EmailTemplatesResponse,
+        RenderedEmailResponse,
+        EmailStatus,
+        OutboxEmail,
+        OutboxEmailsResponse,
+        InviteCode,
+        CreateInviteRequest,
+        CreatedInvite,
+        InviteCodesResponse,
+        CreatedInviteResponse,
+        PendingCleanupResult,
+        PendingCleanupResponse,
+        UserRole,
+        UserOverview,
+        AuditLogEntry,
+        AdminUserActionRequest,
+        PaginationMeta,
+        UserListResponse,
+        UserOverviewResponse,
+        AuditLogResponse,
+        ReadinessReport,
+        CheckResult,
+        CheckStatus
     ))
 )]
 pub struct ApiDoc;
@@ -0,0 +1,62 @@
+use crate::api::models::{CreateApiTokenRequest, CreatedApiToken};
+use crate::api::{ApiResponse, ErrorResponse};
+use crate::database::models::ApiToken;
+
+type EmptyResponse = ApiResponse<()>;
+type ApiTokensResponse = ApiResponse<Vec<ApiToken>>;
+type CreatedApiTokenResponse = ApiResponse<CreatedApiToken>;
+
+/// Personal Access Token Endpoints
+#[utoipa::path(
+    get,
+    path = "/api/tokens",
+    responses(
+        (status = 200, description = "Tokens retrieved successfully", body = ApiTokensResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "Called with a personal access token", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "tokens"
+)]
+pub fn list_tokens_docs() {}
+
+#[utoipa::path(
+    post,
+    path = "/api/tokens",
+    request_body = CreateApiTokenRequest,
+    responses(
+        (status = 201, description = "Token created successfully", body = CreatedApiTokenResponse),
+        (status = 422, description = "Invalid request data", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "Called with a personal access token", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "tokens"
+)]
+pub fn create_token_docs() {}
+
+#[utoipa::path(
+    delete,
+    path = "/api/tokens/{id}",
+    params(
+        ("id" = Uuid, Path, description = "ID of the token to revoke")
+    ),
+    responses(
+        (status = 200, description = "Token revoked successfully", body = EmptyResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "Called with a personal access token", body = ErrorResponse),
+        (status = 404, description = "Token not found", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "tokens"
+)]
+pub fn revoke_token_docs() {}
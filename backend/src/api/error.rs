@@ -0,0 +1,187 @@
+//! The error type returned by handlers.
+//!
+//! Every handler error goes through [`AppError`], so the mapping to a status code and an
+//! error code lives in one place. Server errors are logged with the request ID and reach
+//! the client only as a generic message.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Map, Value};
+use validator::ValidationErrors;
+
+use crate::{
+    api::ErrorResponse,
+    middleware::request_logger::current_request_id,
+    services::{auth::AuthError, oidc::OidcError, registration::RegistrationError},
+};
+
+/// Message returned in place of the details of a server error
+const INTERNAL_ERROR_MESSAGE: &str = "An internal error occurred. Please try again later.";
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Validation failed")]
+    Validation(#[from] ValidationErrors),
+    /// A request the client can correct, with the status and code to report
+    #[error("{message}")]
+    Client {
+        status: StatusCode,
+        code: &'static str,
+        message: String,
+        details: Option<Value>,
+    },
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+    #[error(transparent)]
+    Registration(#[from] RegistrationError),
+    #[error(transparent)]
+    Oidc(#[from] OidcError),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("{context}: {source}")]
+    Internal {
+        context: &'static str,
+        source: BoxError,
+    },
+}
+
+impl AppError {
+    pub fn client(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        AppError::Client {
+            status,
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::client(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    pub fn unauthorized(code: &'static str, message: impl Into<String>) -> Self {
+        Self::client(StatusCode::UNAUTHORIZED, code, message)
+    }
+
+    pub fn forbidden(code: &'static str, message: impl Into<String>) -> Self {
+        Self::client(StatusCode::FORBIDDEN, code, message)
+    }
+
+    pub fn not_found(code: &'static str, message: impl Into<String>) -> Self {
+        Self::client(StatusCode::NOT_FOUND, code, message)
+    }
+
+    pub fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        Self::client(StatusCode::CONFLICT, code, message)
+    }
+
+    pub fn too_many_requests(code: &'static str, message: impl Into<String>) -> Self {
+        Self::client(StatusCode::TOO_MANY_REQUESTS, code, message)
+    }
+
+    /// A failure that isn't the client's fault. `context` is logged, never returned.
+    pub fn internal(context: &'static str, source: impl Into<BoxError>) -> Self {
+        AppError::Internal {
+            context,
+            source: source.into(),
+        }
+    }
+
+    /// Attaches details to a client error; other errors are returned unchanged
+    pub fn with_details(mut self, value: Value) -> Self {
+        if let AppError::Client { details, .. } = &mut self {
+            *details = Some(value);
+        }
+        self
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Client { status, .. } => *status,
+            AppError::Auth(e) => e.status_code(),
+            AppError::Registration(e) => e.status_code(),
+            AppError::Oidc(e) => e.status_code(),
+            AppError::Database(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND,
+            AppError::Database(_) | AppError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "VALIDATION_ERROR",
+            AppError::Client { code, .. } => code,
+            AppError::Auth(e) => e.code(),
+            AppError::Registration(e) => e.code(),
+            AppError::Oidc(e) => e.code(),
+            AppError::Database(sqlx::Error::RowNotFound) => "NOT_FOUND",
+            AppError::Database(_) | AppError::Internal { .. } => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            AppError::Validation(errors) => Some(validation_details(errors)),
+            AppError::Client { details, .. } => details.clone(),
+            AppError::Auth(e) => e.details(),
+            AppError::Registration(e) => e.details(),
+            AppError::Oidc(_) | AppError::Database(_) | AppError::Internal { .. } => None,
+        }
+    }
+
+    fn client_message(&self) -> String {
+        match self {
+            AppError::Database(sqlx::Error::RowNotFound) => "Resource not found".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// Messages for each invalid field, keyed by field name
+fn validation_details(errors: &ValidationErrors) -> Value {
+    let fields: Map<String, Value> = errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|e| match &e.message {
+                    Some(message) => message.to_string(),
+                    None => e.code.to_string(),
+                })
+                .collect();
+            (field.to_string(), json!(messages))
+        })
+        .collect();
+    json!({ "fields": fields })
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let message = if status.is_server_error() {
+            tracing::error!(
+                request_id = current_request_id().as_deref(),
+                code = self.code(),
+                "Request failed: {self}"
+            );
+            INTERNAL_ERROR_MESSAGE.to_string()
+        } else {
+            if let AppError::Oidc(e) = &self {
+                tracing::warn!("Single sign-on rejected: {e}");
+            }
+            self.client_message()
+        };
+
+        let mut error = ErrorResponse::new(message).with_code(self.code());
+        if let Some(details) = self.details() {
+            error = error.with_details(details);
+        }
+        (status, Json(error)).into_response()
+    }
+}
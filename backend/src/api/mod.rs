@@ -1,8 +1,12 @@
 #![allow(dead_code)]
 pub mod docs;
+pub mod error;
 pub mod models;
 pub mod utils;
 
+pub use error::AppError;
+
+use crate::middleware::request_logger::current_request_id;
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -33,6 +37,13 @@ pub struct ErrorResponse {
     pub success: bool,
     pub message: String,
     pub code: String,
+    /// Machine-readable context that helps the client recover from the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+    /// ID of the request, for matching the error with server logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -69,6 +80,8 @@ impl ErrorResponse {
             success: false,
             message: message.into(),
             code: String::new(),
+            details: None,
+            request_id: current_request_id(),
             timestamp: Utc::now(),
         }
     }
@@ -77,6 +90,11 @@ impl ErrorResponse {
         self.code = code.into();
         self
     }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
 }
 
 impl IntoResponse for ErrorResponse {
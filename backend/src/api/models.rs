@@ -1,7 +1,12 @@
+use crate::database::models::EmailStatus;
+use crate::models::auth::UserStatus;
+use crate::services::email_templates::Locale;
 use regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use url::Url;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 use validator::Validate;
 
 /// Request payload for creating a new link
@@ -43,6 +48,167 @@ impl CreateLinkRequest {
     }
 }
 
+/// Click statistics of a link
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LinkStats {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub link_id: Uuid,
+    /// Number of times the link has been clicked
+    #[schema(example = 42)]
+    pub click_count: i32,
+    /// When the link was created
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Request payload for creating a personal access token
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateApiTokenRequest {
+    /// A name that helps recognise the token later
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    #[schema(example = "Browser extension")]
+    pub name: String,
+
+    /// Scopes granted to the token
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    #[schema(example = json!(["links:read", "links:write"]))]
+    pub scopes: Vec<crate::models::auth::TokenScope>,
+
+    /// Number of days until the token expires. Omit for a token that never expires.
+    #[validate(range(min = 1, max = 365, message = "Expiry must be between 1 and 365 days"))]
+    #[schema(example = 90)]
+    pub expires_in_days: Option<u32>,
+}
+
+/// A newly created personal access token. The token is only ever returned here.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedApiToken {
+    /// The full token to use as a bearer credential
+    #[schema(example = "lsp_a1B2c3D4_5e6F7g8H9i0J1k2L3m4N5o6P7q8R9s0T1u2V3w4X")]
+    pub token: String,
+    #[serde(flatten)]
+    pub details: crate::database::models::ApiToken,
+}
+
+/// Request payload for creating an invite code
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateInviteRequest {
+    /// Who or what the code is for
+    #[validate(length(max = 200, message = "Note must be at most 200 characters"))]
+    #[schema(example = "Marketing team")]
+    pub note: Option<String>,
+
+    /// How many accounts can be created with the code. Defaults to a single use.
+    #[validate(range(min = 1, max = 10000, message = "Uses must be between 1 and 10000"))]
+    #[schema(example = 10)]
+    pub max_uses: Option<i32>,
+
+    /// Number of days until the code expires. Omit for a code that never expires.
+    #[validate(range(min = 1, max = 365, message = "Expiry must be between 1 and 365 days"))]
+    #[schema(example = 30)]
+    pub expires_in_days: Option<u32>,
+}
+
+/// A newly created invite code. The code is only ever returned here.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedInvite {
+    #[schema(example = "K7QX-M2PD-9WTA")]
+    pub code: String,
+    #[serde(flatten)]
+    pub details: crate::database::models::InviteCode,
+}
+
+/// Outcome of deleting unverified accounts
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PendingCleanupResult {
+    /// Number of accounts deleted
+    #[schema(example = 12)]
+    pub deleted: u64,
+    /// Accounts pending verification for longer than this were deleted
+    #[schema(example = 72)]
+    pub older_than_hours: u64,
+}
+
+/// Request payload for changing the language emails are sent in
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateLocaleRequest {
+    #[schema(example = "fr")]
+    pub locale: Locale,
+}
+
+/// A template that can be previewed, with the variables it expects
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EmailTemplateInfo {
+    #[schema(example = "otp_verification")]
+    pub name: String,
+    /// Locales the template is translated to
+    pub locales: Vec<Locale>,
+    /// Variables with the sample values used when a preview doesn't provide them
+    #[schema(example = json!({"otp": "123456", "minutes": "5"}))]
+    pub variables: HashMap<String, String>,
+}
+
+/// Request payload for previewing an email template
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct PreviewEmailTemplateRequest {
+    /// Defaults to English
+    pub locale: Option<Locale>,
+    /// Overrides for the template's sample values
+    #[serde(default)]
+    #[schema(example = json!({"otp": "654321"}))]
+    pub variables: HashMap<String, String>,
+}
+
+/// Filters for listing the email outbox
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OutboxQuery {
+    /// Only emails in this state. Defaults to `dead`.
+    pub status: Option<EmailStatus>,
+    /// Maximum number of emails to return, 50 by default and at most 200
+    pub limit: Option<i64>,
+}
+
+/// Filters and paging for listing users
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserListQuery {
+    /// Only users whose email or username contains this text, ignoring case
+    pub q: Option<String>,
+    /// Only users with this status, e.g. `Suspended`
+    pub status: Option<UserStatus>,
+    /// Page to return, starting at 1
+    pub page: Option<u32>,
+    /// Users per page, 20 by default and at most 100
+    pub per_page: Option<u32>,
+}
+
+/// Filters and paging for the admin audit log
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditLogQuery {
+    /// Only actions taken on this user
+    pub user_id: Option<Uuid>,
+    /// Only actions taken by this admin
+    pub admin_id: Option<Uuid>,
+    /// Page to return, starting at 1
+    pub page: Option<u32>,
+    /// Entries per page, 20 by default and at most 100
+    pub per_page: Option<u32>,
+}
+
+/// Optional context for an admin action on a user, kept in the audit log
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct AdminUserActionRequest {
+    #[validate(length(max = 500, message = "Reason must be at most 500 characters"))]
+    #[schema(example = "Spam reports")]
+    pub reason: Option<String>,
+}
+
 lazy_static::lazy_static! {
     static ref USERNAME_REGEX: regex::Regex = regex::Regex::new(r"^[a-zA-Z0-9_]{3,50}$").unwrap();
 }
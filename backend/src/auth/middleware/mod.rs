@@ -1,4 +1,4 @@
-use crate::models::auth::Claims;
+use crate::{models::auth::Claims, services::jwt::JwtKeys};
 use axum::{
     body::Body,
     extract::State,
@@ -6,16 +6,15 @@ use axum::{
     middleware::Next,
     response::Response,
 };
-use jsonwebtoken::{decode, DecodingKey, Validation};
 
 #[derive(Clone)]
 pub struct AuthMiddlewareState {
-    jwt_secret: String,
+    jwt_keys: JwtKeys,
 }
 
 impl AuthMiddlewareState {
-    pub fn new(jwt_secret: String) -> Self {
-        Self { jwt_secret }
+    pub fn new(jwt_keys: JwtKeys) -> Self {
+        Self { jwt_keys }
     }
 }
 
@@ -36,12 +35,10 @@ pub async fn auth_middleware(
     }
 
     let token = auth_header.trim_start_matches("Bearer ").trim();
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(state.jwt_secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let token_data = state
+        .jwt_keys
+        .verify::<Claims>(token)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
 
     request.extensions_mut().insert(token_data.claims);
     Ok(next.run(request).await)
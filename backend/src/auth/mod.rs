@@ -1,9 +1,31 @@
 pub mod middleware;
 pub mod routes;
 
+use crate::config::OidcConfig;
+use crate::database::repository::Repositories;
+use crate::middleware::rate_limit::RateLimiter;
+use crate::services::{
+    email::EmailService, jwt::JwtKeys, otp_store::OtpStore, registration::RegistrationPolicy,
+};
 use axum::Router;
-use sqlx::PgPool;
+use std::sync::Arc;
 
-pub fn create_router(pool: PgPool) -> Router {
-    Router::new().merge(routes::create_router(pool))
+pub fn create_router(
+    repositories: Repositories,
+    jwt_keys: JwtKeys,
+    email_service: EmailService,
+    registration: RegistrationPolicy,
+    oidc: Option<OidcConfig>,
+    otp_store: Arc<dyn OtpStore>,
+    rate_limiter: RateLimiter,
+) -> Router {
+    Router::new().merge(routes::create_router(
+        repositories,
+        jwt_keys,
+        email_service,
+        registration,
+        oidc,
+        otp_store,
+        rate_limiter,
+    ))
 }
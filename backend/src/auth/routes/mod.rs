@@ -1,32 +1,99 @@
-use crate::handlers::auth::{admin_reset_otp_attempts, login, register, resend_otp, verify_email};
-use crate::services::{auth::AuthService, email::EmailService};
-use axum::{routing::post, Router};
-use sqlx::PgPool;
-use std::env;
+use crate::config::OidcConfig;
+use crate::database::repository::Repositories;
+use crate::handlers::auth::{
+    admin_reset_otp_attempts, admin_unlock_account, consume_magic_link, jwks, login, oidc_callback,
+    oidc_login, register, request_magic_link, resend_otp, verify_email,
+};
+use crate::middleware::auth::{auth, require_role};
+use crate::middleware::rate_limit::{rate_limit, RateLimiter, RouteGroup};
+use crate::models::auth::UserRole;
+use crate::services::{
+    auth::AuthService, email::EmailService, jwt::JwtKeys, oidc::OidcClient, otp_store::OtpStore,
+    registration::RegistrationPolicy,
+};
+use axum::{
+    middleware::from_fn_with_state,
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub auth_service: AuthService,
     pub email_service: EmailService,
+    /// `None` when single sign-on is not configured
+    pub oidc: Option<OidcClient>,
+    pub registration: RegistrationPolicy,
+    /// Resolves client addresses behind trusted proxies
+    pub rate_limiter: RateLimiter,
 }
 
-pub fn create_router(pool: PgPool) -> Router {
-    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    let auth_service = AuthService::new(pool, jwt_secret);
-    let email_service = EmailService::new().expect("Failed to create email service");
+pub fn create_router(
+    repositories: Repositories,
+    jwt_keys: JwtKeys,
+    email_service: EmailService,
+    registration: RegistrationPolicy,
+    oidc: Option<OidcConfig>,
+    otp_store: Arc<dyn OtpStore>,
+    rate_limiter: RateLimiter,
+) -> Router {
+    let auth_service = AuthService::new(repositories, jwt_keys.clone());
     let state = AppState {
-        auth_service,
+        auth_service: auth_service.clone(),
         email_service,
+        oidc: oidc.map(|config| OidcClient::new(config, otp_store, jwt_keys)),
+        registration,
+        rate_limiter: rate_limiter.clone(),
     };
 
-    Router::new()
-        .route("/api/auth/register", post(register))
-        .route("/api/auth/login", post(login))
-        .route("/api/auth/verify", post(verify_email))
-        .route("/api/auth/resend-otp", post(resend_otp))
+    // Admin routes require a logged in user with the admin role
+    let admin_routes = Router::new()
         .route(
             "/api/admin/auth/reset-otp-attempts",
             post(admin_reset_otp_attempts),
         )
+        .route("/api/admin/auth/unlock-account", post(admin_unlock_account))
+        .route_layer(from_fn_with_state(UserRole::Admin, require_role))
+        .route_layer(from_fn_with_state(auth_service, auth));
+
+    let auth_limit = from_fn_with_state(rate_limiter.group(RouteGroup::Auth), rate_limit);
+    let email_limit = from_fn_with_state(rate_limiter.group(RouteGroup::Email), rate_limit);
+
+    Router::new()
+        .route(
+            "/api/auth/register",
+            post(register).route_layer(email_limit.clone()),
+        )
+        .route(
+            "/api/auth/login",
+            post(login).route_layer(auth_limit.clone()),
+        )
+        .route(
+            "/api/auth/verify",
+            post(verify_email).route_layer(auth_limit.clone()),
+        )
+        .route(
+            "/api/auth/resend-otp",
+            post(resend_otp).route_layer(email_limit.clone()),
+        )
+        .route(
+            "/api/auth/magic-link",
+            post(request_magic_link).route_layer(email_limit),
+        )
+        .route(
+            "/api/auth/magic-link/consume",
+            post(consume_magic_link).route_layer(auth_limit.clone()),
+        )
+        .route(
+            "/api/auth/oidc/login",
+            get(oidc_login).route_layer(auth_limit.clone()),
+        )
+        .route(
+            "/api/auth/oidc/callback",
+            post(oidc_callback).route_layer(auth_limit),
+        )
+        .route("/.well-known/jwks.json", get(jwks))
+        .merge(admin_routes)
         .with_state(state)
 }
@@ -0,0 +1,577 @@
+//! Command line tool for operational tasks.
+//!
+//! Reads the same config file and environment as the server, so it can be run next to it with
+//! the same settings. Changes to users are recorded in the admin audit log without an acting
+//! admin.
+
+use anyhow::{anyhow, bail};
+use backend::{
+    config::{AppConfig, OtpStoreBackend},
+    database::{
+        self,
+        export::{self, Export},
+        queries,
+        repository::Repositories,
+        PgPool,
+    },
+    models::auth::{Gender, RegisterRequest, UserStatus},
+    services::{
+        audit::{self, AuditAction},
+        email::EmailService,
+        email_templates::Locale,
+        jwt::{self, JwtKeys, PRIVATE_KEY_SUFFIX, PUBLIC_KEY_SUFFIX},
+        link_preview::fetch_link_preview,
+        otp_store,
+    },
+};
+use dotenv::dotenv;
+use rand::{distr::Alphanumeric, Rng};
+use serde_json::json;
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+use validator::Validate;
+
+const USAGE: &str = "\
+Usage: linksphere-admin [--config <path>] <command>
+
+Commands:
+  migrate status                      List migrations and whether they are applied
+  migrate up                          Apply pending migrations
+  migrate down [<steps>] --yes        Revert the latest <steps> migrations (default 1)
+  create-admin <email> <username> [--password-stdin]
+                                      Create a verified admin account. Without
+                                      --password-stdin a password is generated and printed.
+  user verify <email>                 Mark a user's email as verified
+  user suspend <email> [--reason <text>]
+                                      Suspend a user and end their sessions
+  user delete <email> --yes           Delete a user and everything they own
+  reset-otp-attempts <email>          Clear OTP codes and attempt counters of an email
+  requeue-previews [--limit <n>]      Fetch previews of links that don't have one (default 100)
+  rotate-jwt-key [--retire-old]       Add a new JWT signing key. With --retire-old the private
+                                      keys it replaces are swapped for their public halves.
+  export <file>                       Write all user data to a JSON file
+  import <file>                       Add the rows of an export, keeping existing ones";
+
+enum Command {
+    MigrateStatus,
+    MigrateUp,
+    MigrateDown {
+        steps: usize,
+    },
+    CreateAdmin {
+        email: String,
+        username: String,
+        password_stdin: bool,
+    },
+    VerifyUser {
+        email: String,
+    },
+    SuspendUser {
+        email: String,
+        reason: Option<String>,
+    },
+    DeleteUser {
+        email: String,
+    },
+    ResetOtpAttempts {
+        email: String,
+    },
+    RequeuePreviews {
+        limit: i64,
+    },
+    RotateJwtKey {
+        retire_old: bool,
+    },
+    Export {
+        file: PathBuf,
+    },
+    Import {
+        file: PathBuf,
+    },
+}
+
+/// Command line arguments
+struct Args {
+    /// Config file to read instead of the default one
+    config: Option<PathBuf>,
+    command: Command,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut config = None;
+        let mut yes = false;
+        let mut password_stdin = false;
+        let mut retire_old = false;
+        let mut reason = None;
+        let mut limit = None;
+        let mut positional = Vec::new();
+
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => config = Some(iter.next().ok_or("--config requires a path")?),
+                "--reason" => reason = Some(iter.next().ok_or("--reason requires a text")?),
+                "--limit" => limit = Some(iter.next().ok_or("--limit requires a number")?),
+                "--yes" => yes = true,
+                "--password-stdin" => password_stdin = true,
+                "--retire-old" => retire_old = true,
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                other => match other.strip_prefix("--config=") {
+                    Some(path) => config = Some(path.to_string()),
+                    None if other.starts_with("--") => {
+                        return Err(format!("unknown option '{other}'\n\n{USAGE}"))
+                    }
+                    None => positional.push(other.to_string()),
+                },
+            }
+        }
+
+        let positional: Vec<&str> = positional.iter().map(String::as_str).collect();
+        let command = match positional.as_slice() {
+            ["migrate", "status"] => Command::MigrateStatus,
+            ["migrate", "up"] => Command::MigrateUp,
+            ["migrate", "down", rest @ ..] => {
+                let steps = match rest {
+                    [] => 1,
+                    [steps] => steps
+                        .parse()
+                        .map_err(|_| format!("invalid number of steps '{steps}'"))?,
+                    _ => return Err(USAGE.to_string()),
+                };
+                if !yes {
+                    return Err("migrate down drops data, confirm with --yes".to_string());
+                }
+                Command::MigrateDown { steps }
+            }
+            ["create-admin", email, username] => Command::CreateAdmin {
+                email: email.to_string(),
+                username: username.to_string(),
+                password_stdin,
+            },
+            ["user", "verify", email] => Command::VerifyUser {
+                email: email.to_string(),
+            },
+            ["user", "suspend", email] => Command::SuspendUser {
+                email: email.to_string(),
+                reason,
+            },
+            ["user", "delete", email] => {
+                if !yes {
+                    return Err("user delete can't be undone, confirm with --yes".to_string());
+                }
+                Command::DeleteUser {
+                    email: email.to_string(),
+                }
+            }
+            ["reset-otp-attempts", email] => Command::ResetOtpAttempts {
+                email: email.to_string(),
+            },
+            ["requeue-previews"] => Command::RequeuePreviews {
+                limit: match limit {
+                    Some(limit) => limit
+                        .parse()
+                        .ok()
+                        .filter(|limit| *limit > 0)
+                        .ok_or_else(|| format!("invalid limit '{limit}'"))?,
+                    None => 100,
+                },
+            },
+            ["rotate-jwt-key"] => Command::RotateJwtKey { retire_old },
+            ["export", file] => Command::Export {
+                file: PathBuf::from(file),
+            },
+            ["import", file] => Command::Import {
+                file: PathBuf::from(file),
+            },
+            _ => return Err(USAGE.to_string()),
+        };
+
+        Ok(Self {
+            config: config.map(PathBuf::from),
+            command,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+
+    // Only warnings and errors, on stderr, unless RUST_LOG asks for more
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .with_writer(io::stderr)
+        .init();
+
+    let config = match AppConfig::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = run(args.command, &config).await {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(command: Command, config: &AppConfig) -> anyhow::Result<()> {
+    // Rotating keys only touches files
+    if let Command::RotateJwtKey { retire_old } = command {
+        return rotate_jwt_key(config, retire_old);
+    }
+
+    let url = config.database.url.expose();
+    if url.starts_with("sqlite:") {
+        bail!("linksphere-admin only supports Postgres databases");
+    }
+    let pool = database::connect(url)
+        .await
+        .map_err(|e| anyhow!("Failed to connect to the database: {e}"))?;
+    match command {
+        Command::MigrateStatus => migrate_status(&pool).await,
+        Command::MigrateUp => {
+            let pending: Vec<i64> = database::migration_status(&pool)
+                .await?
+                .into_iter()
+                .filter(|migration| migration.installed_on.is_none())
+                .map(|migration| migration.version)
+                .collect();
+            database::run_migrations(&pool)
+                .await
+                .map_err(|e| anyhow!("Failed to run migrations: {e}"))?;
+            println!("Applied {} migrations", pending.len());
+            Ok(())
+        }
+        Command::MigrateDown { steps } => {
+            let reverted = database::revert_migrations(&pool, steps).await?;
+            for version in &reverted {
+                println!("Reverted {version}");
+            }
+            println!("Reverted {} migrations", reverted.len());
+            Ok(())
+        }
+        Command::CreateAdmin {
+            email,
+            username,
+            password_stdin,
+        } => create_admin(&pool, email, username, password_stdin).await,
+        Command::VerifyUser { email } => verify_user(&pool, &email).await,
+        Command::SuspendUser { email, reason } => suspend_user(&pool, &email, reason).await,
+        Command::DeleteUser { email } => delete_user(&pool, &email).await,
+        Command::ResetOtpAttempts { email } => reset_otp_attempts(&pool, config, &email).await,
+        Command::RequeuePreviews { limit } => requeue_previews(&pool, limit).await,
+        Command::Export { file } => export_data(&pool, &file).await,
+        Command::Import { file } => import_data(&pool, &file).await,
+        Command::RotateJwtKey { .. } => unreachable!("handled above"),
+    }
+}
+
+async fn migrate_status(pool: &PgPool) -> anyhow::Result<()> {
+    let migrations = database::migration_status(pool).await?;
+    for migration in &migrations {
+        let state = match migration.installed_on {
+            Some(installed_on) => format!("applied {}", installed_on.format("%Y-%m-%d %H:%M")),
+            None => "pending".to_string(),
+        };
+        let down = if migration.reversible {
+            ""
+        } else {
+            " (no down)"
+        };
+        println!(
+            "{:<16} {:<24} {}{down}",
+            migration.version, state, migration.description
+        );
+    }
+    let pending = migrations
+        .iter()
+        .filter(|migration| migration.installed_on.is_none())
+        .count();
+    println!("{} migrations, {pending} pending", migrations.len());
+    Ok(())
+}
+
+async fn create_admin(
+    pool: &PgPool,
+    email: String,
+    username: String,
+    password_stdin: bool,
+) -> anyhow::Result<()> {
+    let (password, generated) = if password_stdin {
+        let mut line = String::new();
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| anyhow!("Failed to read the password: {e}"))?;
+        (line.trim_end_matches(['\r', '\n']).to_string(), false)
+    } else {
+        let password: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(24)
+            .map(char::from)
+            .collect();
+        (password, true)
+    };
+
+    // Same rules as registration
+    let request = RegisterRequest {
+        email,
+        username,
+        password,
+        gender: Gender::Other,
+        locale: Locale::default(),
+        invite_code: None,
+    };
+    request.validate()?;
+
+    let password_hash = bcrypt::hash(request.password.as_bytes(), bcrypt::DEFAULT_COST)?;
+    let mut tx = pool.begin().await?;
+    let user_id =
+        queries::create_admin_user(&mut *tx, &request.email, &request.username, &password_hash)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    anyhow!("A user with this email or username already exists")
+                }
+                e => e.into(),
+            })?;
+    audit::record_cli(
+        &mut *tx,
+        AuditAction::CreateAdmin,
+        Some(user_id),
+        json!({ "email": request.email, "username": request.username }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    println!("Created admin {} ({user_id})", request.username);
+    if generated {
+        println!("Password: {}", request.password);
+    }
+    Ok(())
+}
+
+async fn verify_user(pool: &PgPool, email: &str) -> anyhow::Result<()> {
+    let user_id = find_user(pool, email).await?;
+    let user = queries::get_user_overview(pool, user_id)
+        .await?
+        .ok_or_else(|| anyhow!("No user with email {email}"))?;
+    if user.is_verified {
+        bail!("{email} is already verified");
+    }
+
+    let mut tx = pool.begin().await?;
+    queries::mark_email_verified(&mut *tx, user_id).await?;
+    audit::record_cli(&mut *tx, AuditAction::VerifyEmail, Some(user_id), json!({})).await?;
+    tx.commit().await?;
+
+    println!("Verified {email}");
+    Ok(())
+}
+
+async fn suspend_user(pool: &PgPool, email: &str, reason: Option<String>) -> anyhow::Result<()> {
+    let user_id = find_user(pool, email).await?;
+    let user = queries::get_user_overview(pool, user_id)
+        .await?
+        .ok_or_else(|| anyhow!("No user with email {email}"))?;
+    if user.status == UserStatus::Suspended {
+        bail!("{email} is already suspended");
+    }
+
+    let mut tx = pool.begin().await?;
+    queries::set_user_status(&mut *tx, user_id, UserStatus::Suspended, true).await?;
+    audit::record_cli(
+        &mut *tx,
+        AuditAction::SuspendUser,
+        Some(user_id),
+        json!({
+            "from": user.status,
+            "to": UserStatus::Suspended,
+            "reason": reason,
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    println!("Suspended {email} and ended their sessions");
+    Ok(())
+}
+
+async fn delete_user(pool: &PgPool, email: &str) -> anyhow::Result<()> {
+    let user_id = find_user(pool, email).await?;
+
+    let mut tx = pool.begin().await?;
+    if !queries::delete_user(&mut *tx, user_id).await? {
+        bail!("No user with email {email}");
+    }
+    audit::record_cli(
+        &mut *tx,
+        AuditAction::DeleteUser,
+        Some(user_id),
+        json!({ "email": email }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    println!("Deleted {email}");
+    Ok(())
+}
+
+async fn reset_otp_attempts(pool: &PgPool, config: &AppConfig, email: &str) -> anyhow::Result<()> {
+    if let OtpStoreBackend::Memory = config.otp.store {
+        bail!("otp.store is memory, so OTP state only exists inside the server process");
+    }
+    let store = otp_store::from_config(&config.otp.store, Some(pool.clone())).await?;
+    let email_service = EmailService::new(
+        Repositories::postgres(pool.clone()).outbox,
+        store,
+        &config.server.frontend_url,
+        config.otp.hmac_key.as_ref(),
+    );
+    email_service
+        .admin_reset_attempts(email)
+        .await
+        .map_err(|e| anyhow!("Failed to reset OTP attempts: {e}"))?;
+
+    let user_id = queries::find_user_id_by_email(pool, email).await?;
+    audit::record_cli(
+        pool,
+        AuditAction::ResetOtpAttempts,
+        user_id,
+        json!({ "email": email }),
+    )
+    .await?;
+
+    println!("Reset OTP attempts of {email}");
+    Ok(())
+}
+
+async fn requeue_previews(pool: &PgPool, limit: i64) -> anyhow::Result<()> {
+    let links = queries::links_without_preview(pool, limit).await?;
+    let mut fetched = 0;
+    for (link_id, url) in &links {
+        match fetch_link_preview(url).await {
+            Ok(preview) => {
+                queries::set_link_preview(pool, *link_id, &preview).await?;
+                fetched += 1;
+            }
+            Err(e) => eprintln!("Failed to fetch preview of {url}: {e}"),
+        }
+    }
+
+    println!("Fetched {fetched} of {} missing previews", links.len());
+    Ok(())
+}
+
+/// Adds a key named after the current time, which makes it the newest and so the signing key
+/// after the server restarts
+fn rotate_jwt_key(config: &AppConfig, retire_old: bool) -> anyhow::Result<()> {
+    let dir = config
+        .jwt
+        .keys_dir
+        .as_deref()
+        .ok_or_else(|| anyhow!("jwt.keys_dir is not set, there are no keys to rotate"))?;
+    if let Some(kid) = &config.jwt.active_kid {
+        eprintln!(
+            "Warning: jwt.active_kid pins the signing key to '{kid}'. \
+             Unset it or point it at the new key."
+        );
+    }
+
+    let old_kids: Vec<String> = fs::read_dir(dir)
+        .map_err(|e| anyhow!("Failed to read {}: {e}", dir.display()))?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| !name.ends_with(PUBLIC_KEY_SUFFIX))
+        .filter_map(|name| name.strip_suffix(PRIVATE_KEY_SUFFIX).map(str::to_string))
+        .collect();
+
+    let kid = chrono::Utc::now().format("%Y-%m-%dT%H%M%S").to_string();
+    let private_path = dir.join(format!("{kid}{PRIVATE_KEY_SUFFIX}"));
+    write_private_file(&private_path, jwt::generate_private_key_pem()?.as_bytes())?;
+    println!("Wrote {}", private_path.display());
+
+    if retire_old {
+        // Keep verifying tokens signed with the old keys until they expire
+        for old_kid in &old_kids {
+            let private_path = dir.join(format!("{old_kid}{PRIVATE_KEY_SUFFIX}"));
+            let public_pem = jwt::public_key_pem(old_kid, &fs::read(&private_path)?)?;
+            let public_path = dir.join(format!("{old_kid}{PUBLIC_KEY_SUFFIX}"));
+            fs::write(&public_path, public_pem)?;
+            fs::remove_file(&private_path)?;
+            println!("Retired {old_kid}, kept {}", public_path.display());
+        }
+    }
+
+    let keys = JwtKeys::from_dir(dir, config.jwt.active_kid.as_deref(), None)
+        .map_err(|e| anyhow!("The key directory no longer loads: {e}"))?;
+    println!(
+        "Signing key after restart: {}. Restart every instance to use it.",
+        keys.active_kid()
+    );
+    Ok(())
+}
+
+async fn export_data(pool: &PgPool, file: &Path) -> anyhow::Result<()> {
+    let export = export::export(pool).await?;
+    // Contains password hashes
+    write_private_file(file, &serde_json::to_vec_pretty(&export)?)?;
+
+    for (table, rows) in &export.tables {
+        println!("{table:<16} {} rows", rows.len());
+    }
+    println!("Exported to {}", file.display());
+    Ok(())
+}
+
+async fn import_data(pool: &PgPool, file: &Path) -> anyhow::Result<()> {
+    let contents = fs::read(file).map_err(|e| anyhow!("Failed to read {}: {e}", file.display()))?;
+    let export: Export =
+        serde_json::from_slice(&contents).map_err(|e| anyhow!("Not a valid export: {e}"))?;
+
+    for (table, rows, inserted) in export::import(pool, &export).await? {
+        println!("{table:<16} {inserted} of {rows} rows imported");
+    }
+    Ok(())
+}
+
+async fn find_user(pool: &PgPool, email: &str) -> anyhow::Result<Uuid> {
+    queries::find_user_id_by_email(pool, email)
+        .await?
+        .ok_or_else(|| anyhow!("No user with email {email}"))
+}
+
+/// Creates `path` readable only by its owner
+fn write_private_file(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .map_err(|e| anyhow!("Failed to create {}: {e}", path.display()))?;
+    file.write_all(contents)?;
+    Ok(())
+}
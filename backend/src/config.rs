@@ -0,0 +1,781 @@
+//! Application configuration.
+//!
+//! Settings are read once at startup from an optional TOML file and from environment
+//! variables, which take precedence over the file. Every setting keeps the environment
+//! variable it has always been read from, e.g. `server.port` can also be set with `PORT`.
+//! All problems are collected and reported together, so a broken deployment can be fixed in
+//! one go instead of one restart per missing variable.
+
+use ipnet::IpNet;
+use serde::{Serialize, Serializer};
+use std::{
+    collections::HashSet,
+    env, fmt, fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::middleware::rate_limit::{RateLimitStoreKind, RatePolicy};
+use crate::services::{
+    email_sender::EmailBackendKind, otp_store::OtpStoreKind, registration::RegistrationMode,
+};
+
+/// Read when no other file is given and it exists in the working directory
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+/// Environment variable naming the config file
+pub const CONFIG_FILE_ENV: &str = "LINKSPHERE_CONFIG";
+
+const DEFAULT_SENDER_EMAIL: &str = "no-reply@linksphere.local";
+const DEFAULT_SENDER_NAME: &str = "LinkSphere Team";
+const DEFAULT_EMAIL_DROP_DIR: &str = "./mail";
+const DEFAULT_OIDC_SCOPES: &str = "openid email profile";
+const DEFAULT_PENDING_MAX_AGE_HOURS: u64 = 72;
+const DEFAULT_PENDING_CLEANUP_INTERVAL_MINUTES: u64 = 60;
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_HEALTH_CHECK_TIMEOUT_MS: u64 = 2000;
+const DEFAULT_AUTH_RATE: RatePolicy = RatePolicy::per_minute(10);
+const DEFAULT_EMAIL_RATE: RatePolicy = RatePolicy::per_minute(5);
+const DEFAULT_LINKS_RATE: RatePolicy = RatePolicy::per_minute(30);
+const DEFAULT_SERVICE_NAME: &str = "linksphere-backend";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Invalid configuration:\n{}", format_problems(.0))]
+    Invalid(Vec<String>),
+}
+
+fn format_problems(problems: &[String]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("  - {problem}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A setting that must not show up in logs or in `--print-config` output
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(********)")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("********")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub jwt: JwtConfig,
+    pub email: EmailConfig,
+    pub otp: OtpConfig,
+    pub registration: RegistrationConfig,
+    pub pending_cleanup: PendingCleanupConfig,
+    pub rate_limit: RateLimitConfig,
+    pub metrics: MetricsConfig,
+    pub tracing: TracingConfig,
+    pub health: HealthConfig,
+    /// `None` when single sign-on is not configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oidc: Option<OidcConfig>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Origin of the frontend, allowed by CORS and used in links sent by email
+    pub frontend_url: String,
+    /// How long shutdown waits for in-flight requests and background tasks
+    pub drain_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseConfig {
+    pub url: Secret,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JwtConfig {
+    /// Directory of signing keys. Required unless `ephemeral_key` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys_dir: Option<PathBuf>,
+    /// Sign with a key generated at startup instead, for development. Tokens are invalid
+    /// after a restart and on other instances.
+    pub ephemeral_key: bool,
+    /// Key to sign with, the newest one by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_kid: Option<String>,
+    /// Shared secret of HS256 tokens issued before the switch to EdDSA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub legacy_secret: Option<Secret>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmailConfig {
+    #[serde(flatten)]
+    pub backend: EmailBackend,
+    pub from_name: String,
+    pub from_email: String,
+}
+
+/// How emails leave the process, with the settings of that backend
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum EmailBackend {
+    Smtp {
+        smtp_host: String,
+        smtp_port: u16,
+        smtp_username: String,
+        smtp_password: Secret,
+    },
+    Resend {
+        resend_api_key: Secret,
+    },
+    File {
+        drop_dir: PathBuf,
+    },
+    Log,
+}
+
+impl EmailBackend {
+    pub fn kind(&self) -> EmailBackendKind {
+        match self {
+            EmailBackend::Smtp { .. } => EmailBackendKind::Smtp,
+            EmailBackend::Resend { .. } => EmailBackendKind::Resend,
+            EmailBackend::File { .. } => EmailBackendKind::File,
+            EmailBackend::Log => EmailBackendKind::Log,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OtpConfig {
+    #[serde(flatten)]
+    pub store: OtpStoreBackend,
+    /// Key for the HMAC stored in place of each OTP. A random key is used when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hmac_key: Option<Secret>,
+}
+
+/// Where OTP state is kept, with the settings of that store
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "store", rename_all = "lowercase")]
+pub enum OtpStoreBackend {
+    Memory,
+    Redis {
+        redis_url: Secret,
+    },
+    Postgres,
+    Upstash {
+        upstash_url: String,
+        upstash_token: Secret,
+    },
+}
+
+impl OtpStoreBackend {
+    pub fn kind(&self) -> OtpStoreKind {
+        match self {
+            OtpStoreBackend::Memory => OtpStoreKind::Memory,
+            OtpStoreBackend::Redis { .. } => OtpStoreKind::Redis,
+            OtpStoreBackend::Postgres => OtpStoreKind::Postgres,
+            OtpStoreBackend::Upstash { .. } => OtpStoreKind::Upstash,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistrationConfig {
+    pub mode: RegistrationMode,
+    /// Lowercase domains accepted in `domain` mode
+    pub allowed_domains: Vec<String>,
+    pub block_disposable_emails: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingCleanupConfig {
+    /// Unverified accounts older than this are deleted
+    pub max_age_hours: u64,
+    pub interval_minutes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub store: RateLimitStoreKind,
+    /// Proxies whose `X-Forwarded-For` header is believed
+    pub trusted_proxies: Vec<IpNet>,
+    /// Login, OTP verification, magic link and SSO sign-in, per IP
+    pub auth: RatePolicy,
+    /// Registration, OTP resend and magic link requests, per IP
+    pub email: RatePolicy,
+    /// Link creation, per user
+    pub links: RatePolicy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// Serves `/metrics` without authentication on this address instead of behind the admin
+    /// guard on the main listener
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub listen_addr: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthConfig {
+    /// How long each readiness check may take before it counts as failed
+    pub check_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TracingConfig {
+    /// OTLP/HTTP collector base URL, e.g. `http://localhost:4318`. Spans are only exported
+    /// when it is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+/// Relying-party settings for an OpenID Connect provider
+#[derive(Debug, Clone, Serialize)]
+pub struct OidcConfig {
+    /// Issuer URL; the discovery document is loaded from `<issuer>/.well-known/openid-configuration`
+    pub issuer_url: String,
+    pub client_id: String,
+    /// Empty for public clients that only rely on PKCE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<Secret>,
+    /// Where the provider sends the user back to, usually a frontend page that
+    /// forwards `code` and `state` to `POST /api/auth/oidc/callback`
+    pub redirect_url: String,
+    pub scopes: String,
+}
+
+/// A setting's path in the config file and the environment variable that overrides it
+#[derive(Debug, Clone, Copy)]
+struct Key {
+    path: &'static str,
+    env: &'static str,
+}
+
+const fn key(path: &'static str, env: &'static str) -> Key {
+    Key { path, env }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.path, self.env)
+    }
+}
+
+const HOST: Key = key("server.host", "HOST");
+const PORT: Key = key("server.port", "PORT");
+const FRONTEND_URL: Key = key("server.frontend_url", "FRONTEND_REQUEST_URL");
+const DRAIN_TIMEOUT: Key = key("server.drain_timeout_secs", "SHUTDOWN_DRAIN_TIMEOUT_SECS");
+const DATABASE_URL: Key = key("database.url", "DATABASE_URL");
+const JWT_KEYS_DIR: Key = key("jwt.keys_dir", "JWT_KEYS_DIR");
+const JWT_EPHEMERAL_KEY: Key = key("jwt.ephemeral_key", "JWT_EPHEMERAL_KEY");
+const JWT_ACTIVE_KID: Key = key("jwt.active_kid", "JWT_ACTIVE_KID");
+const JWT_SECRET: Key = key("jwt.legacy_secret", "JWT_SECRET");
+const EMAIL_BACKEND: Key = key("email.backend", "EMAIL_BACKEND");
+const SMTP_HOST: Key = key("email.smtp_host", "SMTP_HOST");
+const SMTP_PORT: Key = key("email.smtp_port", "SMTP_PORT");
+const SMTP_USERNAME: Key = key("email.smtp_username", "SMTP_USERNAME");
+const SMTP_PASSWORD: Key = key("email.smtp_password", "SMTP_PASSWORD");
+const RESEND_API_KEY: Key = key("email.resend_api_key", "RESEND_API_KEY");
+const EMAIL_DROP_DIR: Key = key("email.drop_dir", "EMAIL_DROP_DIR");
+const SMTP_FROM_NAME: Key = key("email.from_name", "SMTP_FROM_NAME");
+const SMTP_FROM_EMAIL: Key = key("email.from_email", "SMTP_FROM_EMAIL");
+const OTP_STORE: Key = key("otp.store", "OTP_STORE");
+const REDIS_URL: Key = key("otp.redis_url", "REDIS_URL");
+const UPSTASH_URL: Key = key("otp.upstash_url", "UPSTASH_REDIS_REST_URL");
+const UPSTASH_TOKEN: Key = key("otp.upstash_token", "UPSTASH_REDIS_REST_TOKEN");
+const OTP_HMAC_KEY: Key = key("otp.hmac_key", "OTP_HMAC_KEY");
+const REGISTRATION_MODE: Key = key("registration.mode", "REGISTRATION_MODE");
+const ALLOWED_DOMAINS: Key = key(
+    "registration.allowed_domains",
+    "REGISTRATION_ALLOWED_DOMAINS",
+);
+const BLOCK_DISPOSABLE: Key = key(
+    "registration.block_disposable_emails",
+    "BLOCK_DISPOSABLE_EMAILS",
+);
+const PENDING_MAX_AGE: Key = key(
+    "pending_cleanup.max_age_hours",
+    "PENDING_ACCOUNT_MAX_AGE_HOURS",
+);
+const PENDING_INTERVAL: Key = key(
+    "pending_cleanup.interval_minutes",
+    "PENDING_CLEANUP_INTERVAL_MINUTES",
+);
+const RATE_LIMIT_ENABLED: Key = key("rate_limit.enabled", "RATE_LIMIT_ENABLED");
+const RATE_LIMIT_STORE: Key = key("rate_limit.store", "RATE_LIMIT_STORE");
+const TRUSTED_PROXIES: Key = key("rate_limit.trusted_proxies", "RATE_LIMIT_TRUSTED_PROXIES");
+const AUTH_RATE: Key = key("rate_limit.auth", "RATE_LIMIT_AUTH");
+const EMAIL_RATE: Key = key("rate_limit.email", "RATE_LIMIT_EMAIL");
+const LINKS_RATE: Key = key("rate_limit.links", "RATE_LIMIT_LINKS");
+const METRICS_ENABLED: Key = key("metrics.enabled", "METRICS_ENABLED");
+const METRICS_LISTEN_ADDR: Key = key("metrics.listen_addr", "METRICS_LISTEN_ADDR");
+const OTLP_ENDPOINT: Key = key("tracing.otlp_endpoint", "OTEL_EXPORTER_OTLP_ENDPOINT");
+const SERVICE_NAME: Key = key("tracing.service_name", "OTEL_SERVICE_NAME");
+const HEALTH_CHECK_TIMEOUT: Key = key("health.check_timeout_ms", "HEALTH_CHECK_TIMEOUT_MS");
+const OIDC_ISSUER_URL: Key = key("oidc.issuer_url", "OIDC_ISSUER_URL");
+const OIDC_CLIENT_ID: Key = key("oidc.client_id", "OIDC_CLIENT_ID");
+const OIDC_CLIENT_SECRET: Key = key("oidc.client_secret", "OIDC_CLIENT_SECRET");
+const OIDC_REDIRECT_URL: Key = key("oidc.redirect_url", "OIDC_REDIRECT_URL");
+const OIDC_SCOPES: Key = key("oidc.scopes", "OIDC_SCOPES");
+
+impl AppConfig {
+    /// Loads the configuration from the environment and a TOML file.
+    ///
+    /// The file is `path` if given, otherwise the one named by `LINKSPHERE_CONFIG`, otherwise
+    /// `config.toml` if it exists. Environment variables override the file.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let explicit = path.map(Path::to_path_buf).or_else(|| {
+            env::var_os(CONFIG_FILE_ENV)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+        });
+
+        let file = match explicit {
+            Some(path) => Some(read_file(&path)?),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Some(read_file(Path::new(DEFAULT_CONFIG_FILE))?)
+            }
+            None => None,
+        };
+
+        let mut loader = Loader {
+            file: file.unwrap_or_default(),
+            used: HashSet::new(),
+            problems: Vec::new(),
+        };
+
+        let config = AppConfig {
+            server: loader.server(),
+            database: DatabaseConfig {
+                url: loader.required(DATABASE_URL),
+            },
+            jwt: loader.jwt(),
+            email: loader.email(),
+            otp: loader.otp(),
+            registration: loader.registration(),
+            pending_cleanup: PendingCleanupConfig {
+                max_age_hours: loader.positive(PENDING_MAX_AGE, DEFAULT_PENDING_MAX_AGE_HOURS),
+                interval_minutes: loader
+                    .positive(PENDING_INTERVAL, DEFAULT_PENDING_CLEANUP_INTERVAL_MINUTES),
+            },
+            rate_limit: loader.rate_limit(),
+            metrics: MetricsConfig {
+                enabled: loader.flag(METRICS_ENABLED, true),
+                listen_addr: loader.optional(METRICS_LISTEN_ADDR),
+            },
+            tracing: loader.tracing(),
+            health: HealthConfig {
+                check_timeout_ms: loader
+                    .positive(HEALTH_CHECK_TIMEOUT, DEFAULT_HEALTH_CHECK_TIMEOUT_MS),
+            },
+            oidc: loader.oidc(),
+        };
+
+        loader.check_unknown_settings();
+        if loader.problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::Invalid(loader.problems))
+        }
+    }
+
+    /// The effective settings as TOML, with secrets replaced by asterisks
+    pub fn to_redacted_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("Configuration can always be serialized")
+    }
+}
+
+fn read_file(path: &Path) -> Result<toml::Table, ConfigError> {
+    let contents = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    contents.parse().map_err(|source| ConfigError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Reads settings and collects every problem with them
+struct Loader {
+    file: toml::Table,
+    /// Paths of the settings that were asked for, to find unknown ones in the file
+    used: HashSet<&'static str>,
+    problems: Vec<String>,
+}
+
+impl Loader {
+    /// Returns the setting as text. Empty values count as unset.
+    fn raw(&mut self, key: Key) -> Option<String> {
+        self.used.insert(key.path);
+
+        if let Some(value) = env::var(key.env).ok().filter(|value| !value.is_empty()) {
+            return Some(value);
+        }
+
+        let (section, name) = key.path.split_once('.').expect("Keys have a section");
+        let value = self.file.get(section)?.as_table()?.get(name)?;
+        let text = match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Array(items) => {
+                let mut parts = Vec::with_capacity(items.len());
+                for item in items {
+                    match item.as_str() {
+                        Some(part) => parts.push(part.to_string()),
+                        None => {
+                            self.problems
+                                .push(format!("{key}: expected a list of strings"));
+                            return None;
+                        }
+                    }
+                }
+                parts.join(",")
+            }
+            toml::Value::Table(_) => {
+                self.problems
+                    .push(format!("{key}: expected a value, not a table"));
+                return None;
+            }
+            other => other.to_string(),
+        };
+        Some(text).filter(|text| !text.is_empty())
+    }
+
+    fn optional<T>(&mut self, key: Key) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let raw = self.raw(key)?;
+        match raw.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.problems
+                    .push(format!("{key}: invalid value '{raw}': {e}"));
+                None
+            }
+        }
+    }
+
+    /// Returns the setting, or records a problem and a placeholder if it is missing
+    fn required<T>(&mut self, key: Key) -> T
+    where
+        T: FromStr + Default,
+        T::Err: fmt::Display,
+    {
+        if self.raw(key).is_none() {
+            self.problems.push(format!("{key} must be set"));
+            return T::default();
+        }
+        self.optional(key).unwrap_or_default()
+    }
+
+    fn or<T>(&mut self, key: Key, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.optional(key).unwrap_or(default)
+    }
+
+    fn flag(&mut self, key: Key, default: bool) -> bool {
+        let Some(raw) = self.raw(key) else {
+            return default;
+        };
+        match raw.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => true,
+            "false" | "0" | "no" => false,
+            _ => {
+                self.problems
+                    .push(format!("{key}: must be true or false, got '{raw}'"));
+                default
+            }
+        }
+    }
+
+    fn positive(&mut self, key: Key, default: u64) -> u64 {
+        match self.optional::<u64>(key) {
+            Some(0) => {
+                self.problems.push(format!("{key}: must be greater than 0"));
+                default
+            }
+            Some(value) => value,
+            None => default,
+        }
+    }
+
+    fn server(&mut self) -> ServerConfig {
+        let frontend_url: String = self.required(FRONTEND_URL);
+        if !frontend_url.is_empty() {
+            if let Err(e) = url::Url::parse(&frontend_url) {
+                self.problems
+                    .push(format!("{FRONTEND_URL}: invalid URL '{frontend_url}': {e}"));
+            }
+        }
+
+        let host: String = self.required(HOST);
+        if !host.is_empty() {
+            if let Err(e) = host.parse::<std::net::IpAddr>() {
+                self.problems
+                    .push(format!("{HOST}: invalid IP address '{host}': {e}"));
+            }
+        }
+
+        ServerConfig {
+            host,
+            port: self.required(PORT),
+            frontend_url: frontend_url.trim_end_matches('/').to_string(),
+            drain_timeout_secs: self.positive(DRAIN_TIMEOUT, DEFAULT_DRAIN_TIMEOUT_SECS),
+        }
+    }
+
+    fn email(&mut self) -> EmailConfig {
+        let kind = match self.optional(EMAIL_BACKEND) {
+            Some(kind) => kind,
+            // SMTP stays the default for deployments configured before EMAIL_BACKEND existed
+            None if self.raw(SMTP_HOST).is_some() => EmailBackendKind::Smtp,
+            None => EmailBackendKind::Log,
+        };
+
+        let backend = match kind {
+            EmailBackendKind::Smtp => EmailBackend::Smtp {
+                smtp_host: self.required(SMTP_HOST),
+                smtp_port: self.required(SMTP_PORT),
+                smtp_username: self.required(SMTP_USERNAME),
+                smtp_password: self.required(SMTP_PASSWORD),
+            },
+            EmailBackendKind::Resend => EmailBackend::Resend {
+                resend_api_key: self.required(RESEND_API_KEY),
+            },
+            EmailBackendKind::File => EmailBackend::File {
+                drop_dir: self.or(EMAIL_DROP_DIR, PathBuf::from(DEFAULT_EMAIL_DROP_DIR)),
+            },
+            EmailBackendKind::Log => EmailBackend::Log,
+        };
+
+        let from_email = self.or(SMTP_FROM_EMAIL, DEFAULT_SENDER_EMAIL.to_string());
+        if let Err(e) = from_email.parse::<lettre::Address>() {
+            self.problems.push(format!(
+                "{SMTP_FROM_EMAIL}: invalid address '{from_email}': {e}"
+            ));
+        }
+
+        // Settings of the backends that are not selected are still valid in the file
+        for unused in [
+            SMTP_HOST,
+            SMTP_PORT,
+            SMTP_USERNAME,
+            SMTP_PASSWORD,
+            RESEND_API_KEY,
+            EMAIL_DROP_DIR,
+        ] {
+            self.used.insert(unused.path);
+        }
+
+        EmailConfig {
+            backend,
+            from_name: self.or(SMTP_FROM_NAME, DEFAULT_SENDER_NAME.to_string()),
+            from_email,
+        }
+    }
+
+    fn otp(&mut self) -> OtpConfig {
+        let kind = match self.optional(OTP_STORE) {
+            Some(kind) => kind,
+            None if self.raw(UPSTASH_URL).is_some() => OtpStoreKind::Upstash,
+            None => OtpStoreKind::Memory,
+        };
+
+        let store = match kind {
+            OtpStoreKind::Memory => OtpStoreBackend::Memory,
+            OtpStoreKind::Redis => OtpStoreBackend::Redis {
+                redis_url: self.required(REDIS_URL),
+            },
+            OtpStoreKind::Postgres => OtpStoreBackend::Postgres,
+            OtpStoreKind::Upstash => OtpStoreBackend::Upstash {
+                upstash_url: self.required(UPSTASH_URL),
+                upstash_token: self.required(UPSTASH_TOKEN),
+            },
+        };
+
+        for unused in [REDIS_URL, UPSTASH_URL, UPSTASH_TOKEN] {
+            self.used.insert(unused.path);
+        }
+
+        OtpConfig {
+            store,
+            hmac_key: self.optional(OTP_HMAC_KEY),
+        }
+    }
+
+    fn registration(&mut self) -> RegistrationConfig {
+        let mode = self.or(REGISTRATION_MODE, RegistrationMode::default());
+
+        let allowed_domains: Vec<String> = self
+            .raw(ALLOWED_DOMAINS)
+            .unwrap_or_default()
+            .split(',')
+            .map(|domain| domain.trim().trim_start_matches('@').to_ascii_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+        if mode == RegistrationMode::Domain && allowed_domains.is_empty() {
+            self.problems.push(format!(
+                "{ALLOWED_DOMAINS} must be set when {REGISTRATION_MODE} is domain"
+            ));
+        }
+
+        RegistrationConfig {
+            mode,
+            allowed_domains,
+            block_disposable_emails: self.flag(BLOCK_DISPOSABLE, true),
+        }
+    }
+
+    fn jwt(&mut self) -> JwtConfig {
+        let keys_dir = self.optional(JWT_KEYS_DIR);
+        let ephemeral_key = self.flag(JWT_EPHEMERAL_KEY, false);
+        if keys_dir.is_none() && !ephemeral_key {
+            self.problems.push(format!(
+                "{JWT_KEYS_DIR} must be set, or {JWT_EPHEMERAL_KEY} enabled for development"
+            ));
+        }
+
+        JwtConfig {
+            keys_dir,
+            ephemeral_key,
+            active_kid: self.optional(JWT_ACTIVE_KID),
+            legacy_secret: self.optional(JWT_SECRET),
+        }
+    }
+
+    fn rate_limit(&mut self) -> RateLimitConfig {
+        let mut trusted_proxies = Vec::new();
+        for proxy in self.raw(TRUSTED_PROXIES).unwrap_or_default().split(',') {
+            let proxy = proxy.trim();
+            if proxy.is_empty() {
+                continue;
+            }
+            match proxy
+                .parse::<IpNet>()
+                .or_else(|_| proxy.parse::<std::net::IpAddr>().map(IpNet::from))
+            {
+                Ok(net) => trusted_proxies.push(net),
+                Err(_) => self.problems.push(format!(
+                    "{TRUSTED_PROXIES}: invalid IP address or CIDR range '{proxy}'"
+                )),
+            }
+        }
+
+        RateLimitConfig {
+            enabled: self.flag(RATE_LIMIT_ENABLED, true),
+            store: self.or(RATE_LIMIT_STORE, RateLimitStoreKind::default()),
+            trusted_proxies,
+            auth: self.or(AUTH_RATE, DEFAULT_AUTH_RATE),
+            email: self.or(EMAIL_RATE, DEFAULT_EMAIL_RATE),
+            links: self.or(LINKS_RATE, DEFAULT_LINKS_RATE),
+        }
+    }
+
+    fn tracing(&mut self) -> TracingConfig {
+        let otlp_endpoint: Option<String> = self.optional(OTLP_ENDPOINT);
+        if let Some(endpoint) = &otlp_endpoint {
+            if let Err(e) = url::Url::parse(endpoint) {
+                self.problems
+                    .push(format!("{OTLP_ENDPOINT}: invalid URL '{endpoint}': {e}"));
+            }
+        }
+
+        TracingConfig {
+            otlp_endpoint: otlp_endpoint.map(|endpoint| endpoint.trim_end_matches('/').to_string()),
+            service_name: self.or(SERVICE_NAME, DEFAULT_SERVICE_NAME.to_string()),
+        }
+    }
+
+    fn oidc(&mut self) -> Option<OidcConfig> {
+        let Some(issuer_url) = self.raw(OIDC_ISSUER_URL) else {
+            for unused in [
+                OIDC_CLIENT_ID,
+                OIDC_CLIENT_SECRET,
+                OIDC_REDIRECT_URL,
+                OIDC_SCOPES,
+            ] {
+                self.used.insert(unused.path);
+            }
+            return None;
+        };
+
+        Some(OidcConfig {
+            issuer_url: issuer_url.trim_end_matches('/').to_string(),
+            client_id: self.required(OIDC_CLIENT_ID),
+            client_secret: self.optional(OIDC_CLIENT_SECRET),
+            redirect_url: self.required(OIDC_REDIRECT_URL),
+            scopes: self.or(OIDC_SCOPES, DEFAULT_OIDC_SCOPES.to_string()),
+        })
+    }
+
+    /// Records settings in the file that no part of the application reads, which are
+    /// usually typos
+    fn check_unknown_settings(&mut self) {
+        let mut unknown = Vec::new();
+        for (section, value) in &self.file {
+            match value.as_table() {
+                Some(table) => unknown.extend(
+                    table
+                        .keys()
+                        .map(|name| format!("{section}.{name}"))
+                        .filter(|path| !self.used.contains(path.as_str())),
+                ),
+                None => unknown.push(section.clone()),
+            }
+        }
+        unknown.sort();
+        self.problems.extend(
+            unknown
+                .into_iter()
+                .map(|path| format!("{path}: unknown setting in the config file")),
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Data export and import for `linksphere-admin`.
+//!
+//! An export is one JSON document with the rows of every table that holds user data, keyed
+//! by table name. OTP codes, queued emails and the migration history are left out: they are
+//! short lived or belong to the schema. Imports only add rows, so importing into a database
+//! that already has some of them keeps the existing ones.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+
+use super::migration_status;
+
+/// Format version of the export document
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Exported tables, parents before the tables that reference them
+pub const EXPORTED_TABLES: [&str; 6] = [
+    "users",
+    "links",
+    "api_tokens",
+    "user_identities",
+    "invite_codes",
+    "admin_audit_log",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Latest migration applied to the exporting database
+    pub schema_version: i64,
+    /// Rows of each table as JSON objects
+    pub tables: BTreeMap<String, Vec<JsonValue>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Unsupported export version {0}, expected {EXPORT_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("Export is from schema version {export}, but the database is at {database}")]
+    SchemaMismatch { export: i64, database: i64 },
+    #[error("Unknown table '{0}' in export")]
+    UnknownTable(String),
+}
+
+/// Number of rows in the export and how many of them were new, per table
+pub type ImportSummary = Vec<(&'static str, usize, u64)>;
+
+/// Reads all exported tables
+pub async fn export(pool: &PgPool) -> Result<Export, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    // One snapshot for all tables
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut tables = BTreeMap::new();
+    for table in EXPORTED_TABLES {
+        let rows: JsonValue = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(json_agg(t), '[]'::json) FROM {table} t"
+        ))
+        .fetch_one(&mut *tx)
+        .await?;
+        let rows = match rows {
+            JsonValue::Array(rows) => rows,
+            _ => Vec::new(),
+        };
+        tables.insert(table.to_string(), rows);
+    }
+    tx.commit().await?;
+
+    Ok(Export {
+        version: EXPORT_VERSION,
+        exported_at: Utc::now(),
+        schema_version: schema_version(pool).await?,
+        tables,
+    })
+}
+
+/// Inserts the rows of `export` in one transaction, skipping rows that already exist. The
+/// database must be at the schema version the export was taken from.
+pub async fn import(pool: &PgPool, export: &Export) -> Result<ImportSummary, ImportError> {
+    if export.version != EXPORT_VERSION {
+        return Err(ImportError::UnsupportedVersion(export.version));
+    }
+    let database = schema_version(pool).await?;
+    if export.schema_version != database {
+        return Err(ImportError::SchemaMismatch {
+            export: export.schema_version,
+            database,
+        });
+    }
+    if let Some(table) = export
+        .tables
+        .keys()
+        .find(|table| !EXPORTED_TABLES.contains(&table.as_str()))
+    {
+        return Err(ImportError::UnknownTable(table.clone()));
+    }
+
+    let mut summary = Vec::new();
+    let mut tx = pool.begin().await?;
+    for table in EXPORTED_TABLES {
+        let Some(rows) = export.tables.get(table) else {
+            continue;
+        };
+        let inserted = sqlx::query(&format!(
+            "INSERT INTO {table} SELECT * FROM jsonb_populate_recordset(NULL::{table}, $1) \
+             ON CONFLICT DO NOTHING"
+        ))
+        .bind(JsonValue::Array(rows.clone()))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        summary.push((table, rows.len(), inserted));
+    }
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+/// Version of the latest applied migration, 0 on an empty database
+async fn schema_version(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    Ok(migration_status(pool)
+        .await?
+        .iter()
+        .filter(|migration| migration.installed_on.is_some())
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0))
+}
@@ -1,19 +1,235 @@
+pub mod export;
 pub mod models;
 pub mod queries;
+pub mod repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub use queries::get_all_links;
 pub use sqlx::PgPool;
 
-use sqlx::migrate::MigrateError;
+use chrono::{DateTime, Utc};
+use sqlx::migrate::{MigrateError, Migrator};
 use sqlx::postgres::PgPoolOptions;
+use std::collections::HashMap;
+
+use self::repository::Repositories;
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// A migration built into this binary
+#[derive(Debug)]
+pub struct MigrationState {
+    pub version: i64,
+    pub description: String,
+    /// When the migration was applied, `None` while it is pending
+    pub installed_on: Option<DateTime<Utc>>,
+    /// Whether it has a down script
+    pub reversible: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RevertError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Failed to revert migrations: {0}")]
+    Migrate(#[from] MigrateError),
+    #[error("Migration {0} has no down script")]
+    NotReversible(i64),
+}
+
+/// The database selected by the scheme of `database.url`: `postgres://` (or
+/// `postgresql://`), or `sqlite:` when built with the `sqlite` feature.
+///
+/// Everything behind the repository traits works on both. The admin API, the Postgres OTP
+/// store and `linksphere-admin` query Postgres directly and need [`Database::postgres`].
+#[derive(Clone)]
+pub enum Database {
+    Postgres(PgPool),
+    #[cfg(feature = "sqlite")]
+    Sqlite(sqlx::SqlitePool),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Unsupported database URL scheme '{0}', expected postgres:// or sqlite:")]
+    UnsupportedScheme(String),
+    #[error("SQLite support is not built in, rebuild with `--features sqlite`")]
+    SqliteNotBuilt,
+}
+
+impl Database {
+    /// Connects to the database the URL points to
+    pub async fn connect(database_url: &str) -> Result<Self, ConnectError> {
+        let scheme = database_url
+            .split_once(':')
+            .map(|(scheme, _)| scheme.to_ascii_lowercase())
+            .unwrap_or_default();
+        match scheme.as_str() {
+            "postgres" | "postgresql" => Ok(Self::Postgres(connect(database_url).await?)),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Self::Sqlite(sqlite::connect(database_url).await?)),
+            #[cfg(not(feature = "sqlite"))]
+            "sqlite" => Err(ConnectError::SqliteNotBuilt),
+            _ => Err(ConnectError::UnsupportedScheme(scheme)),
+        }
+    }
+
+    /// Name of the backend, for logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Postgres(_) => "postgres",
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => "sqlite",
+        }
+    }
+
+    /// The pool, when the database is Postgres
+    pub fn postgres(&self) -> Option<&PgPool> {
+        match self {
+            Self::Postgres(pool) => Some(pool),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => None,
+        }
+    }
+
+    /// Repositories backed by this database
+    pub fn repositories(&self) -> Repositories {
+        match self {
+            Self::Postgres(pool) => Repositories::postgres(pool.clone()),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(pool) => Repositories::sqlite(pool.clone()),
+        }
+    }
+
+    pub async fn run_migrations(&self) -> Result<(), MigrateError> {
+        match self {
+            Self::Postgres(pool) => run_migrations(pool).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(pool) => sqlite::run_migrations(pool).await,
+        }
+    }
+
+    /// Versions of the migrations built into this binary that the database hasn't applied
+    pub async fn pending_migrations(&self) -> Result<Vec<i64>, sqlx::Error> {
+        match self {
+            Self::Postgres(pool) => pending_migrations(pool).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(pool) => sqlite::pending_migrations(pool).await,
+        }
+    }
+
+    /// Runs a trivial query
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        match self {
+            Self::Postgres(pool) => sqlx::query("SELECT 1").execute(pool).await.map(drop),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(pool) => sqlx::query("SELECT 1").execute(pool).await.map(drop),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Self::Postgres(pool) => pool.close().await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(pool) => pool.close().await,
+        }
+    }
+}
 
 pub async fn create_pool(database_url: &str) -> PgPool {
+    connect(database_url)
+        .await
+        .expect("Failed to create database pool")
+}
+
+/// Like [`create_pool`], but returns the error instead of panicking
+pub async fn connect(database_url: &str) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new()
         .max_connections(5)
         .connect(database_url)
         .await
-        .expect("Failed to create database pool")
 }
 
 pub async fn run_migrations(pool: &PgPool) -> Result<(), MigrateError> {
-    sqlx::migrate!("./migrations").run(pool).await
+    MIGRATOR.run(pool).await
+}
+
+/// Versions of the migrations built into this binary that the database hasn't applied
+pub async fn pending_migrations(pool: &PgPool) -> Result<Vec<i64>, sqlx::Error> {
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
+
+/// Every migration built into this binary, oldest first, with whether it has been applied
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationState>, sqlx::Error> {
+    let table_exists: bool =
+        sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    let applied: HashMap<i64, DateTime<Utc>> = if table_exists {
+        sqlx::query_as("SELECT version, installed_on FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let reversible: Vec<i64> = MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .collect();
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| MigrationState {
+            version: migration.version,
+            description: migration.description.to_string(),
+            installed_on: applied.get(&migration.version).copied(),
+            reversible: reversible.contains(&migration.version),
+        })
+        .collect())
+}
+
+/// Reverts the `steps` most recently applied migrations with their down scripts. Returns
+/// the reverted versions, newest first.
+pub async fn revert_migrations(pool: &PgPool, steps: usize) -> Result<Vec<i64>, RevertError> {
+    let mut applied: Vec<MigrationState> = migration_status(pool)
+        .await?
+        .into_iter()
+        .filter(|migration| migration.installed_on.is_some())
+        .collect();
+    applied.reverse();
+
+    let reverted: Vec<i64> = applied
+        .iter()
+        .take(steps)
+        .map(|migration| migration.version)
+        .collect();
+    if let Some(migration) = applied
+        .iter()
+        .take(steps)
+        .find(|migration| !migration.reversible)
+    {
+        return Err(RevertError::NotReversible(migration.version));
+    }
+
+    // Everything newer than the first migration that stays is reverted
+    let target = applied.get(steps).map_or(0, |migration| migration.version);
+    MIGRATOR.undo(pool, target).await?;
+    Ok(reverted)
 }
@@ -2,9 +2,12 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use sqlx::types::Json;
+use std::collections::HashMap;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::models::auth::{Gender, TokenScope, UserRole, UserStatus};
+
 /// Represents a link preview metadata
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
 pub struct LinkPreview {
@@ -107,6 +110,220 @@ pub struct Link {
     pub user: Option<SimpleUser>,
 }
 
+/// Represents a personal access token. The secret itself is never stored.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct ApiToken {
+    /// Unique identifier for the token
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    /// ID of the user who owns the token
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    /// Human readable name chosen by the user
+    #[schema(example = "Browser extension")]
+    pub name: String,
+    /// Leading characters of the token, used to recognise it in listings
+    #[schema(example = "lsp_a1B2c3D4")]
+    pub token_prefix: String,
+    /// Scopes granted to the token
+    pub scopes: Vec<TokenScope>,
+    /// When the token stops being accepted, if ever
+    #[schema(example = "2024-06-10T15:00:00Z")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// When the token was last used to authenticate a request
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// When the token was created
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// A token looked up by its hash, together with the owning user's details
+#[derive(Debug, Clone)]
+pub struct ApiTokenOwner {
+    pub token: ApiToken,
+    pub email: String,
+    pub username: String,
+    pub role: UserRole,
+}
+
+/// A user account as shown to admins, without credentials
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct UserOverview {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "user@example.com")]
+    pub email: String,
+    #[schema(example = "john_doe")]
+    pub username: String,
+    pub role: UserRole,
+    pub status: UserStatus,
+    pub is_verified: bool,
+    #[schema(example = "en")]
+    pub locale: String,
+    /// Failed logins since the last successful one
+    #[schema(example = 0)]
+    pub failed_login_attempts: i32,
+    /// Set while the account is locked after too many failed logins
+    pub locked_until: Option<DateTime<Utc>>,
+    /// Number of links the user has created
+    #[schema(example = 12)]
+    pub link_count: i64,
+    pub verified_at: Option<DateTime<Utc>>,
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub created_at: DateTime<Utc>,
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An admin action recorded in the audit log
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct AuditLogEntry {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    /// The admin who took the action, unless their account has been deleted
+    pub admin_id: Option<Uuid>,
+    #[schema(example = "suspend_user")]
+    pub action: String,
+    /// The user the action was taken on
+    pub target_user_id: Option<Uuid>,
+    /// Action specific context, such as the reason given
+    #[schema(value_type = Object)]
+    pub details: JsonValue,
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// An invite code for invite-only registration. The code itself is never stored.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct InviteCode {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    /// First characters of the code, used to recognise it in listings
+    #[schema(example = "K7QX")]
+    pub code_prefix: String,
+    /// Who or what the code is for
+    #[schema(example = "Marketing team")]
+    pub note: Option<String>,
+    /// How many accounts can be created with the code
+    #[schema(example = 10)]
+    pub max_uses: i32,
+    /// How many accounts have been created with the code
+    #[schema(example = 3)]
+    pub uses: i32,
+    /// When the code stops being accepted, if ever
+    #[schema(example = "2024-06-10T15:00:00Z")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The admin who created the code
+    pub created_by: Option<Uuid>,
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Delivery state of an email in the outbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "email_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum EmailStatus {
+    /// Waiting for its first or next delivery attempt
+    Pending,
+    Sent,
+    /// Gave up after too many failed attempts; can be replayed by an admin
+    Dead,
+}
+
+/// An email in the outbox. Template variables are left out as they may contain secrets.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct OutboxEmail {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "user@example.com")]
+    pub recipient: String,
+    #[schema(example = "otp_verification")]
+    pub template: String,
+    #[schema(example = "en")]
+    pub locale: String,
+    pub status: EmailStatus,
+    /// Delivery attempts made so far
+    #[schema(example = 3)]
+    pub attempts: i32,
+    /// Error of the most recent failed attempt
+    #[schema(example = "Connection refused")]
+    pub last_error: Option<String>,
+    /// When the next delivery attempt is due, for pending emails
+    #[schema(example = "2024-03-10T15:05:00Z")]
+    pub next_attempt_at: DateTime<Utc>,
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub sent_at: Option<DateTime<Utc>>,
+    #[schema(example = "2024-03-10T15:00:00Z")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// An email claimed by the delivery worker, with everything needed to render it
+#[derive(Debug, Clone)]
+pub struct QueuedEmail {
+    pub id: Uuid,
+    pub recipient: String,
+    pub template: String,
+    pub locale: String,
+    pub variables: HashMap<String, String>,
+    /// Attempts including the one about to be made
+    pub attempts: i32,
+}
+
+/// A user account to create
+#[derive(Debug, Clone)]
+pub struct NewUser {
+    pub email: String,
+    pub username: String,
+    pub password_hash: String,
+    pub gender: Gender,
+    pub status: UserStatus,
+    /// Verified accounts get `verified_at` set to the time of creation
+    pub is_verified: bool,
+    pub locale: String,
+}
+
+/// A link to create, without a preview
+#[derive(Debug, Clone)]
+pub struct NewLink {
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    pub user_id: Uuid,
+}
+
+/// A personal access token to store. Only the hash of its secret is kept.
+#[derive(Debug, Clone)]
+pub struct NewApiToken {
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    pub token_hash: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// An invite code to store. Only the hash of the code is kept.
+#[derive(Debug, Clone)]
+pub struct NewInvite {
+    pub code_prefix: String,
+    pub code_hash: String,
+    pub note: Option<String>,
+    pub max_uses: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: Uuid,
+}
+
+/// An email to add to the outbox
+#[derive(Debug, Clone)]
+pub struct NewEmail {
+    pub recipient: String,
+    pub template: String,
+    pub locale: String,
+    pub variables: HashMap<String, String>,
+}
+
 // Custom serialization for preview field to handle JSON conversion
 mod preview_serde {
     use super::*;
@@ -1,6 +1,11 @@
-use super::models::{JsonLinkPreview, Link, LinkPreview, OptionalJsonUser};
-use chrono::Utc;
-use sqlx::PgPool;
+use super::models::{
+    ApiToken, ApiTokenOwner, AuditLogEntry, EmailStatus, InviteCode, JsonLinkPreview, Link,
+    LinkPreview, NewUser, OptionalJsonUser, OutboxEmail, QueuedEmail, UserOverview,
+};
+use crate::models::auth::{Gender, TokenScope, User, UserRole, UserStatus};
+use chrono::{DateTime, Utc};
+use sqlx::{types::Json, PgExecutor, PgPool};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Retrieves all links from the database
@@ -207,6 +212,139 @@ pub async fn check_user_exists(
     Ok(count > 0)
 }
 
+/// Creates a user account
+///
+/// # Returns
+/// * `Result<User, sqlx::Error>` - The created user or an error, e.g. if the email or
+///   username is taken
+pub async fn insert_user<'e>(
+    executor: impl PgExecutor<'e>,
+    user: &NewUser,
+) -> Result<User, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        INSERT INTO users (
+            email, username, password_hash, gender,
+            status, is_verified, verification_attempts, locale, verified_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, 0, $7, CASE WHEN $6 THEN NOW() END)
+        RETURNING
+            id, email, username, password_hash,
+            gender as "gender: _",
+            status as "status: _",
+            role as "role: _",
+            is_verified,
+            verification_attempts,
+            failed_login_attempts,
+            locked_until,
+            locale,
+            session_version,
+            verified_at,
+            created_at,
+            updated_at
+        "#,
+        user.email,
+        user.username,
+        user.password_hash,
+        user.gender.clone() as _,
+        user.status.clone() as _,
+        user.is_verified,
+        user.locale
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Looks up a user by email
+pub async fn find_user_by_email(pool: &PgPool, email: &str) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT
+            id, email, username, password_hash,
+            gender as "gender: _",
+            status as "status: _",
+            role as "role: _",
+            is_verified,
+            verification_attempts,
+            failed_login_attempts,
+            locked_until,
+            locale,
+            session_version,
+            verified_at,
+            created_at,
+            updated_at
+        FROM users
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Looks up a user by ID
+pub async fn find_user_by_id(pool: &PgPool, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT
+            id, email, username, password_hash,
+            gender as "gender: _",
+            status as "status: _",
+            role as "role: _",
+            is_verified,
+            verification_attempts,
+            failed_login_attempts,
+            locked_until,
+            locale,
+            session_version,
+            verified_at,
+            created_at,
+            updated_at
+        FROM users
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Looks up a user that has either the email or the username
+pub async fn find_user_by_email_or_username(
+    pool: &PgPool,
+    email: &str,
+    username: &str,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT
+            id, email, username, password_hash,
+            gender as "gender: _",
+            status as "status: _",
+            role as "role: _",
+            is_verified,
+            verification_attempts,
+            failed_login_attempts,
+            locked_until,
+            locale,
+            session_version,
+            verified_at,
+            created_at,
+            updated_at
+        FROM users
+        WHERE email = $1 OR username = $2
+        "#,
+        email,
+        username
+    )
+    .fetch_optional(pool)
+    .await
+}
+
 #[allow(dead_code)]
 pub async fn create_unverified_user(
     pool: &PgPool,
@@ -265,3 +403,998 @@ pub async fn is_user_verified(pool: &PgPool, email: &str) -> Result<bool, sqlx::
 
     Ok(result.map(|r| r.is_verified).unwrap_or(false))
 }
+
+fn parse_scopes(scopes: Vec<String>) -> Vec<TokenScope> {
+    scopes.iter().filter_map(|s| s.parse().ok()).collect()
+}
+
+/// Stores a new personal access token
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - The ID of the user owning the token
+/// * `name` - The name chosen for the token
+/// * `token_prefix` - The displayable prefix of the token
+/// * `token_hash` - The SHA-256 hash of the full token
+/// * `scopes` - The scopes granted to the token
+/// * `expires_at` - When the token expires, if ever
+///
+/// # Returns
+/// * `Result<ApiToken, sqlx::Error>` - The stored token metadata or an error
+pub async fn create_api_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    token_prefix: &str,
+    token_hash: &str,
+    scopes: &[TokenScope],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<ApiToken, sqlx::Error> {
+    let scopes: Vec<String> = scopes.iter().map(|s| s.as_str().to_string()).collect();
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO api_tokens (user_id, name, token_prefix, token_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at
+        "#,
+        user_id,
+        name,
+        token_prefix,
+        token_hash,
+        &scopes,
+        expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(ApiToken {
+        id: row.id,
+        user_id: row.user_id,
+        name: row.name,
+        token_prefix: row.token_prefix,
+        scopes: parse_scopes(row.scopes),
+        expires_at: row.expires_at,
+        last_used_at: row.last_used_at,
+        created_at: row.created_at,
+    })
+}
+
+/// Lists the personal access tokens of a user, newest first
+pub async fn list_api_tokens(pool: &PgPool, user_id: Uuid) -> Result<Vec<ApiToken>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ApiToken {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            token_prefix: row.token_prefix,
+            scopes: parse_scopes(row.scopes),
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+            created_at: row.created_at,
+        })
+        .collect())
+}
+
+/// Looks up an unexpired token of an active user by the hash of its secret
+pub async fn find_api_token_by_hash(
+    pool: &PgPool,
+    token_hash: &str,
+) -> Result<Option<ApiTokenOwner>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            t.id, t.user_id, t.name, t.token_prefix, t.scopes,
+            t.expires_at, t.last_used_at, t.created_at,
+            u.email, u.username, u.role as "role: UserRole"
+        FROM api_tokens t
+        JOIN users u ON u.id = t.user_id
+        WHERE t.token_hash = $1
+          AND (t.expires_at IS NULL OR t.expires_at > NOW())
+          AND u.status = 'active'
+        "#,
+        token_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| ApiTokenOwner {
+        token: ApiToken {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            token_prefix: row.token_prefix,
+            scopes: parse_scopes(row.scopes),
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+            created_at: row.created_at,
+        },
+        email: row.email,
+        username: row.username,
+        role: row.role,
+    }))
+}
+
+/// Records that a token has just been used
+pub async fn touch_api_token(pool: &PgPool, token_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE api_tokens SET last_used_at = NOW() WHERE id = $1",
+        token_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes a token owned by the given user
+///
+/// # Returns
+/// * `Result<bool, sqlx::Error>` - Whether a token was deleted
+pub async fn delete_api_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    token_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM api_tokens WHERE id = $1 AND user_id = $2",
+        token_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records a failed login for a user and locks the account once `max_attempts`
+/// consecutive failures are reached. Every further failure doubles the lock
+/// duration, up to 32 times `base_lock_seconds`.
+///
+/// # Returns
+/// * `Result<Option<DateTime<Utc>>, sqlx::Error>` - When the account is locked until, if it is locked
+pub async fn record_failed_login(
+    pool: &PgPool,
+    user_id: Uuid,
+    max_attempts: i32,
+    base_lock_seconds: f64,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            failed_login_attempts = failed_login_attempts + 1,
+            locked_until = CASE
+                WHEN failed_login_attempts + 1 >= $2 THEN NOW() + make_interval(
+                    secs => $3 * power(2, LEAST(failed_login_attempts + 1 - $2, 5))
+                )
+                ELSE locked_until
+            END
+        WHERE id = $1
+        RETURNING locked_until
+        "#,
+        user_id,
+        max_attempts,
+        base_lock_seconds
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.locked_until)
+}
+
+/// Clears the failed login counter after a successful login
+pub async fn reset_failed_logins(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET failed_login_attempts = 0, locked_until = NULL
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Unlocks an account and clears its failed login counter
+///
+/// # Returns
+/// * `Result<bool, sqlx::Error>` - Whether a user with that email exists
+pub async fn unlock_account(pool: &PgPool, email: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET failed_login_attempts = 0, locked_until = NULL
+        WHERE email = $1
+        "#,
+        email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Finds the account linked to an external OpenID Connect identity
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `issuer` - Issuer URL of the identity provider
+/// * `subject` - Subject identifier at that provider
+///
+/// # Returns
+/// * `Result<Option<Uuid>, sqlx::Error>` - The linked user ID, if any
+pub async fn find_identity_user(
+    pool: &PgPool,
+    issuer: &str,
+    subject: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM user_identities
+        WHERE issuer = $1 AND subject = $2
+        "#,
+        issuer,
+        subject
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.user_id))
+}
+
+/// Links an external identity to an account, or records a new login for an existing link
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `user_id` - The account to link
+/// * `issuer` - Issuer URL of the identity provider
+/// * `subject` - Subject identifier at that provider
+/// * `email` - Email address asserted by the provider
+pub async fn upsert_identity(
+    pool: &PgPool,
+    user_id: Uuid,
+    issuer: &str,
+    subject: &str,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_identities (user_id, issuer, subject, email)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (issuer, subject)
+        DO UPDATE SET email = EXCLUDED.email, last_login_at = NOW()
+        "#,
+        user_id,
+        issuer,
+        subject,
+        email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Marks an account's email as verified, activating it if it was waiting for verification
+pub async fn mark_email_verified<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            is_verified = true,
+            verified_at = COALESCE(verified_at, NOW()),
+            status = CASE
+                WHEN status = 'pending_verification' THEN 'active'::user_status
+                ELSE status
+            END,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Checks whether a username is already taken
+pub async fn username_exists(pool: &PgPool, username: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM users WHERE username = $1) as "exists!""#,
+        username
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.exists)
+}
+
+/// Sets the language a user's emails are sent in
+///
+/// # Returns
+/// * `Result<bool, sqlx::Error>` - Whether the user exists
+pub async fn update_user_locale(
+    pool: &PgPool,
+    user_id: Uuid,
+    locale: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET locale = $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id,
+        locale
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Queues an email for delivery by the outbox worker
+///
+/// Pass the transaction of the change that triggers the email, so the email is only sent if
+/// the change is committed.
+///
+/// # Returns
+/// * `Result<Uuid, sqlx::Error>` - The ID of the queued email
+pub async fn enqueue_email<'e>(
+    executor: impl PgExecutor<'e>,
+    recipient: &str,
+    template: &str,
+    locale: &str,
+    variables: &HashMap<String, String>,
+) -> Result<Uuid, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO email_outbox (recipient, template, locale, variables)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+        recipient,
+        template,
+        locale,
+        Json(variables) as _
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.id)
+}
+
+/// Claims pending emails that are due for delivery
+///
+/// Claimed emails are postponed by `lease_seconds`, so other workers skip them and they are
+/// retried if this worker dies before recording the outcome.
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `limit` - Maximum number of emails to claim
+/// * `lease_seconds` - How long the claim lasts
+pub async fn claim_due_emails(
+    pool: &PgPool,
+    limit: i64,
+    lease_seconds: i64,
+) -> Result<Vec<QueuedEmail>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET
+            attempts = attempts + 1,
+            next_attempt_at = NOW() + make_interval(secs => $2),
+            updated_at = NOW()
+        WHERE id IN (
+            SELECT id
+            FROM email_outbox
+            WHERE status = 'pending' AND next_attempt_at <= NOW()
+            ORDER BY next_attempt_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING
+            id, recipient, template, locale,
+            variables as "variables: Json<HashMap<String, String>>",
+            attempts
+        "#,
+        limit,
+        lease_seconds as f64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| QueuedEmail {
+            id: row.id,
+            recipient: row.recipient,
+            template: row.template,
+            locale: row.locale,
+            variables: row.variables.0,
+            attempts: row.attempts,
+        })
+        .collect())
+}
+
+/// Marks an email as delivered and drops its template variables
+pub async fn mark_email_sent(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET
+            status = 'sent',
+            variables = '{}',
+            last_error = NULL,
+            sent_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a failed delivery attempt
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `id` - The email that failed
+/// * `error` - Why delivery failed
+/// * `retry_in_seconds` - Delay before the next attempt, or `None` to move the email to the
+///   dead letter state
+pub async fn mark_email_failed(
+    pool: &PgPool,
+    id: Uuid,
+    error: &str,
+    retry_in_seconds: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    let status = match retry_in_seconds {
+        Some(_) => EmailStatus::Pending,
+        None => EmailStatus::Dead,
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET
+            status = $2,
+            last_error = $3,
+            next_attempt_at = NOW() + make_interval(secs => $4),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        status as _,
+        error,
+        retry_in_seconds.unwrap_or_default() as f64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Lists outbox emails with the given status, newest first
+pub async fn list_outbox_emails(
+    pool: &PgPool,
+    status: EmailStatus,
+    limit: i64,
+) -> Result<Vec<OutboxEmail>, sqlx::Error> {
+    sqlx::query_as!(
+        OutboxEmail,
+        r#"
+        SELECT
+            id, recipient, template, locale,
+            status as "status: _",
+            attempts, last_error, next_attempt_at, sent_at, created_at
+        FROM email_outbox
+        WHERE status = $1
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        status as _,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Puts a dead email back in the queue for immediate delivery
+///
+/// # Returns
+/// * `Result<bool, sqlx::Error>` - Whether a dead email with that ID exists
+pub async fn replay_email(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET status = 'pending', attempts = 0, next_attempt_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND status = 'dead'
+        "#,
+        id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Stores a new invite code
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `code_prefix` - The displayable prefix of the code
+/// * `code_hash` - The SHA-256 hash of the normalized code
+/// * `note` - Who or what the code is for
+/// * `max_uses` - How many accounts can be created with the code
+/// * `expires_at` - When the code expires, if ever
+/// * `created_by` - The admin creating the code
+pub async fn create_invite_code(
+    pool: &PgPool,
+    code_prefix: &str,
+    code_hash: &str,
+    note: Option<&str>,
+    max_uses: i32,
+    expires_at: Option<DateTime<Utc>>,
+    created_by: Uuid,
+) -> Result<InviteCode, sqlx::Error> {
+    sqlx::query_as!(
+        InviteCode,
+        r#"
+        INSERT INTO invite_codes (code_prefix, code_hash, note, max_uses, expires_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, code_prefix, note, max_uses, uses, expires_at, created_by, created_at
+        "#,
+        code_prefix,
+        code_hash,
+        note,
+        max_uses,
+        expires_at,
+        created_by
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Lists all invite codes, newest first
+pub async fn list_invite_codes(pool: &PgPool) -> Result<Vec<InviteCode>, sqlx::Error> {
+    sqlx::query_as!(
+        InviteCode,
+        r#"
+        SELECT id, code_prefix, note, max_uses, uses, expires_at, created_by, created_at
+        FROM invite_codes
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Deletes an invite code so it can no longer be used
+///
+/// # Returns
+/// * `Result<bool, sqlx::Error>` - Whether the code existed
+pub async fn delete_invite_code(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM invite_codes WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Uses up one use of an invite code if it is still valid
+///
+/// # Returns
+/// * `Result<bool, sqlx::Error>` - Whether the code exists, hasn't expired and had uses left
+pub async fn redeem_invite_code<'e>(
+    executor: impl PgExecutor<'e>,
+    code_hash: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE invite_codes
+        SET uses = uses + 1
+        WHERE code_hash = $1
+            AND uses < max_uses
+            AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+        code_hash
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Deletes accounts that are still waiting for email verification, freeing their usernames
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `created_before` - Only accounts registered before this time are deleted
+///
+/// # Returns
+/// * `Result<Vec<String>, sqlx::Error>` - The usernames of the deleted accounts
+pub async fn delete_stale_pending_users(
+    pool: &PgPool,
+    created_before: DateTime<Utc>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        DELETE FROM users
+        WHERE status = 'pending_verification' AND created_at < $1
+        RETURNING username
+        "#,
+        created_before
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| row.username).collect())
+}
+
+/// Deletes the unverified accounts registered with an email address, so nobody who never
+/// proved they own the address keeps a password on an account for it
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `email` - The email address whose unverified accounts are deleted
+///
+/// # Returns
+/// * `Result<u64, sqlx::Error>` - The number of deleted accounts
+pub async fn delete_unverified_users(pool: &PgPool, email: &str) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM users
+        WHERE email = $1 AND is_verified = false
+        "#,
+        email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Looks up what decides whether a user's login sessions are still valid
+///
+/// # Returns
+/// * `Result<Option<(UserStatus, i32)>, sqlx::Error>` - The account status and session version
+pub async fn find_session_state(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<(UserStatus, i32)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT status as "status: UserStatus", session_version
+        FROM users
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| (row.status, row.session_version)))
+}
+
+/// Pages through users, newest first
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `search` - Only users whose email or username contains this, ignoring case
+/// * `status` - Only users with this status
+/// * `limit` - Page size
+/// * `offset` - Number of users to skip
+///
+/// # Returns
+/// * `Result<(Vec<UserOverview>, i64), sqlx::Error>` - The page and the total number of matches
+pub async fn search_users(
+    pool: &PgPool,
+    search: Option<&str>,
+    status: Option<UserStatus>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<UserOverview>, i64), sqlx::Error> {
+    let users = sqlx::query_as!(
+        UserOverview,
+        r#"
+        SELECT
+            u.id, u.email, u.username,
+            u.role as "role: _",
+            u.status as "status: _",
+            u.is_verified, u.locale, u.failed_login_attempts, u.locked_until,
+            (SELECT COUNT(*) FROM links l WHERE l.user_id = u.id) as "link_count!",
+            u.verified_at, u.created_at, u.updated_at
+        FROM users u
+        WHERE ($1::text IS NULL
+                OR strpos(lower(u.email), lower($1)) > 0
+                OR strpos(lower(u.username), lower($1)) > 0)
+            AND ($2::user_status IS NULL OR u.status = $2)
+        ORDER BY u.created_at DESC, u.id
+        LIMIT $3 OFFSET $4
+        "#,
+        search,
+        status.clone() as Option<UserStatus>,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM users u
+        WHERE ($1::text IS NULL
+                OR strpos(lower(u.email), lower($1)) > 0
+                OR strpos(lower(u.username), lower($1)) > 0)
+            AND ($2::user_status IS NULL OR u.status = $2)
+        "#,
+        search,
+        status as Option<UserStatus>
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((users, total))
+}
+
+/// Retrieves a user with their link count
+pub async fn get_user_overview(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<UserOverview>, sqlx::Error> {
+    sqlx::query_as!(
+        UserOverview,
+        r#"
+        SELECT
+            u.id, u.email, u.username,
+            u.role as "role: _",
+            u.status as "status: _",
+            u.is_verified, u.locale, u.failed_login_attempts, u.locked_until,
+            (SELECT COUNT(*) FROM links l WHERE l.user_id = u.id) as "link_count!",
+            u.verified_at, u.created_at, u.updated_at
+        FROM users u
+        WHERE u.id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Changes the status of an account
+///
+/// # Arguments
+/// * `executor` - The connection or transaction to run the update on
+/// * `user_id` - The account to change
+/// * `status` - The new status
+/// * `end_sessions` - Whether to invalidate all login tokens issued so far
+pub async fn set_user_status<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+    status: UserStatus,
+    end_sessions: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            status = $2,
+            session_version = session_version + CASE WHEN $3 THEN 1 ELSE 0 END,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id,
+        status as UserStatus,
+        end_sessions
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Records an admin action in the audit log
+///
+/// # Arguments
+/// * `executor` - Pass the transaction of the action, so it is only logged if it happens
+/// * `admin_id` - The admin taking the action, `None` for the command line tool
+/// * `action` - What was done, e.g. `suspend_user`
+/// * `target_user_id` - The user the action was taken on
+/// * `details` - Action specific context
+pub async fn insert_audit_log<'e>(
+    executor: impl PgExecutor<'e>,
+    admin_id: Option<Uuid>,
+    action: &str,
+    target_user_id: Option<Uuid>,
+    details: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO admin_audit_log (admin_id, action, target_user_id, details)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        admin_id,
+        action,
+        target_user_id,
+        details
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Pages through the audit log, newest first
+///
+/// # Arguments
+/// * `pool` - The database connection pool
+/// * `target_user_id` - Only actions taken on this user
+/// * `admin_id` - Only actions taken by this admin
+/// * `limit` - Page size
+/// * `offset` - Number of entries to skip
+///
+/// # Returns
+/// * `Result<(Vec<AuditLogEntry>, i64), sqlx::Error>` - The page and the total number of matches
+pub async fn list_audit_log(
+    pool: &PgPool,
+    target_user_id: Option<Uuid>,
+    admin_id: Option<Uuid>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<AuditLogEntry>, i64), sqlx::Error> {
+    let entries = sqlx::query_as!(
+        AuditLogEntry,
+        r#"
+        SELECT id, admin_id, action, target_user_id, details, created_at
+        FROM admin_audit_log
+        WHERE ($1::uuid IS NULL OR target_user_id = $1)
+            AND ($2::uuid IS NULL OR admin_id = $2)
+        ORDER BY created_at DESC, id
+        LIMIT $3 OFFSET $4
+        "#,
+        target_user_id,
+        admin_id,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM admin_audit_log
+        WHERE ($1::uuid IS NULL OR target_user_id = $1)
+            AND ($2::uuid IS NULL OR admin_id = $2)
+        "#,
+        target_user_id,
+        admin_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((entries, total))
+}
+
+/// Looks up the ID of the user with the given email
+pub async fn find_user_id_by_email(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!("SELECT id FROM users WHERE email = $1", email)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Creates a verified, active admin account
+///
+/// # Returns
+/// * `Result<Uuid, sqlx::Error>` - The ID of the new user
+pub async fn create_admin_user<'e>(
+    executor: impl PgExecutor<'e>,
+    email: &str,
+    username: &str,
+    password_hash: &str,
+) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO users (
+            email, username, password_hash, gender,
+            status, role, is_verified, verification_attempts, verified_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, true, 0, NOW())
+        RETURNING id
+        "#,
+        email,
+        username,
+        password_hash,
+        Gender::Other as Gender,
+        UserStatus::Active as UserStatus,
+        UserRole::Admin as UserRole
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Deletes a user together with their links, tokens and linked identities
+///
+/// # Returns
+/// * `Result<bool, sqlx::Error>` - Whether the user existed
+pub async fn delete_user<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Oldest links whose preview was never stored, e.g. because fetching it failed
+///
+/// # Returns
+/// * `Result<Vec<(Uuid, String)>, sqlx::Error>` - The IDs and URLs of the links
+pub async fn links_without_preview(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<(Uuid, String)>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, url
+        FROM links
+        WHERE preview IS NULL OR preview IN ('null'::jsonb, '{}'::jsonb)
+        ORDER BY created_at
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|row| (row.id, row.url)).collect())
+}
+
+/// Stores the fetched preview of a link
+pub async fn set_link_preview(
+    pool: &PgPool,
+    link_id: Uuid,
+    preview: &LinkPreview,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE links SET preview = $1 WHERE id = $2",
+        serde_json::to_value(preview).ok() as _,
+        link_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
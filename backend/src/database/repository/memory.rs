@@ -0,0 +1,851 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value as JsonValue;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+use super::{
+    AdminAction, ApiTokenRepository, AuditRepository, InviteRepository, LinkRepository,
+    OutboxRepository, Registration, UserRepository,
+};
+use crate::{
+    database::models::{
+        ApiToken, ApiTokenOwner, AuditLogEntry, EmailStatus, InviteCode, Link, LinkPreview,
+        NewApiToken, NewEmail, NewInvite, NewLink, NewUser, OutboxEmail, QueuedEmail, SimpleUser,
+        UserOverview,
+    },
+    models::auth::{User, UserRole, UserStatus},
+    services::{audit::AuditAction, registration::hash_invite_code},
+};
+
+struct StoredLink {
+    id: Uuid,
+    url: String,
+    title: String,
+    description: String,
+    user_id: Uuid,
+    click_count: i32,
+    preview: Option<LinkPreview>,
+    created_at: DateTime<Utc>,
+}
+
+struct Identity {
+    issuer: String,
+    subject: String,
+    user_id: Uuid,
+}
+
+struct StoredInvite {
+    invite: InviteCode,
+    code_hash: String,
+}
+
+struct StoredEmail {
+    id: Uuid,
+    email: NewEmail,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct Tables {
+    users: Vec<User>,
+    identities: Vec<Identity>,
+    links: Vec<StoredLink>,
+    api_tokens: Vec<(ApiToken, String)>,
+    invites: Vec<StoredInvite>,
+    emails: Vec<StoredEmail>,
+    audit_log: Vec<AuditLogEntry>,
+}
+
+impl Tables {
+    fn user(&self, id: Uuid) -> Option<&User> {
+        self.users.iter().find(|user| user.id == id)
+    }
+
+    fn user_mut(&mut self, id: Uuid) -> Option<&mut User> {
+        self.users.iter_mut().find(|user| user.id == id)
+    }
+
+    fn insert_user(&mut self, new: NewUser) -> Result<User, sqlx::Error> {
+        if self
+            .users
+            .iter()
+            .any(|user| user.email == new.email || user.username == new.username)
+        {
+            return Err(sqlx::Error::Protocol(
+                "duplicate key value violates unique constraint on users".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let user = User {
+            id: Uuid::new_v4(),
+            email: new.email,
+            username: new.username,
+            password_hash: new.password_hash,
+            gender: new.gender,
+            status: new.status,
+            role: UserRole::User,
+            locale: new.locale,
+            session_version: 0,
+            is_verified: new.is_verified,
+            verification_attempts: 0,
+            failed_login_attempts: 0,
+            locked_until: None,
+            verified_at: new.is_verified.then_some(now),
+            created_at: now,
+            updated_at: now,
+        };
+        self.users.push(user.clone());
+        Ok(user)
+    }
+
+    fn queue_email(&mut self, email: NewEmail) -> Uuid {
+        let id = Uuid::new_v4();
+        self.emails.push(StoredEmail {
+            id,
+            email,
+            created_at: Utc::now(),
+        });
+        id
+    }
+
+    fn record_action(&mut self, target_user_id: Option<Uuid>, action: AdminAction) {
+        self.audit_log.push(AuditLogEntry {
+            id: Uuid::new_v4(),
+            admin_id: Some(action.admin_id),
+            action: action.action.as_str().to_string(),
+            target_user_id,
+            details: action.details,
+            created_at: Utc::now(),
+        });
+    }
+
+    fn overview(&self, user: &User) -> UserOverview {
+        UserOverview {
+            id: user.id,
+            email: user.email.clone(),
+            username: user.username.clone(),
+            role: user.role,
+            status: user.status.clone(),
+            is_verified: user.is_verified,
+            locale: user.locale.clone(),
+            failed_login_attempts: user.failed_login_attempts,
+            locked_until: user.locked_until,
+            link_count: self
+                .links
+                .iter()
+                .filter(|link| link.user_id == user.id)
+                .count() as i64,
+            verified_at: user.verified_at,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+
+    fn link(&self, stored: &StoredLink) -> Link {
+        Link {
+            id: stored.id,
+            url: stored.url.clone(),
+            title: stored.title.clone(),
+            description: stored.description.clone(),
+            user_id: stored.user_id,
+            click_count: stored.click_count,
+            created_at: stored.created_at,
+            updated_at: stored.created_at,
+            preview: stored.preview.clone(),
+            user: self.user(stored.user_id).map(|user| SimpleUser {
+                username: user.username.clone(),
+            }),
+        }
+    }
+}
+
+/// Repositories kept in process memory, for tests. Nothing survives the process, and the
+/// outbox is only recorded: emails are never delivered.
+#[derive(Default)]
+pub struct MemoryRepository {
+    tables: Mutex<Tables>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an invite code that can be used `max_uses` times
+    pub fn add_invite(&self, code: &str, max_uses: i32) {
+        self.tables().invites.push(StoredInvite {
+            invite: InviteCode {
+                id: Uuid::new_v4(),
+                code_prefix: code.chars().take(4).collect(),
+                note: None,
+                max_uses,
+                uses: 0,
+                expires_at: None,
+                created_by: None,
+                created_at: Utc::now(),
+            },
+            code_hash: hash_invite_code(code),
+        });
+    }
+
+    /// Gives a user a role, which otherwise only an admin can do
+    pub fn set_role(&self, user_id: Uuid, role: UserRole) {
+        if let Some(user) = self.tables().user_mut(user_id) {
+            user.role = role;
+        }
+    }
+
+    /// Changes a user's status, ending their sessions like an admin would
+    pub fn set_status(&self, user_id: Uuid, status: UserStatus) {
+        if let Some(user) = self.tables().user_mut(user_id) {
+            user.status = status;
+            user.session_version += 1;
+        }
+    }
+
+    /// Emails queued so far, oldest first
+    pub fn emails(&self) -> Vec<NewEmail> {
+        self.tables()
+            .emails
+            .iter()
+            .map(|stored| stored.email.clone())
+            .collect()
+    }
+
+    /// Audit log entries recorded so far, oldest first
+    pub fn audit_log(&self) -> Vec<AuditLogEntry> {
+        self.tables().audit_log.clone()
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().expect("memory repository lock poisoned")
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        Ok(self.tables().user(id).cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        Ok(self
+            .tables()
+            .users
+            .iter()
+            .find(|user| user.email == email)
+            .cloned())
+    }
+
+    async fn find_by_email_or_username(
+        &self,
+        email: &str,
+        username: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        Ok(self
+            .tables()
+            .users
+            .iter()
+            .find(|user| user.email == email || user.username == username)
+            .cloned())
+    }
+
+    async fn find_id_by_email(&self, email: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        Ok(self
+            .tables()
+            .users
+            .iter()
+            .find(|user| user.email == email)
+            .map(|user| user.id))
+    }
+
+    async fn username_exists(&self, username: &str) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .tables()
+            .users
+            .iter()
+            .any(|user| user.username == username))
+    }
+
+    async fn create(&self, user: NewUser) -> Result<User, sqlx::Error> {
+        self.tables().insert_user(user)
+    }
+
+    async fn register(
+        &self,
+        user: NewUser,
+        invite_hash: Option<&str>,
+        verification: NewEmail,
+    ) -> Result<Registration, sqlx::Error> {
+        // One lock for the whole registration, so it is as atomic as the transaction
+        let mut tables = self.tables();
+        let now = Utc::now();
+        let invite = match invite_hash {
+            Some(invite_hash) => {
+                let usable = tables.invites.iter().position(|stored| {
+                    stored.code_hash == invite_hash
+                        && stored.invite.uses < stored.invite.max_uses
+                        && stored
+                            .invite
+                            .expires_at
+                            .is_none_or(|expires_at| expires_at > now)
+                });
+                match usable {
+                    Some(index) => Some(index),
+                    None => return Ok(Registration::InvalidInvite),
+                }
+            }
+            None => None,
+        };
+
+        let user = tables.insert_user(user)?;
+        if let Some(index) = invite {
+            tables.invites[index].invite.uses += 1;
+        }
+        tables.queue_email(verification);
+        Ok(Registration::Created(user))
+    }
+
+    async fn complete_registration(&self, email: &str) -> Result<(), sqlx::Error> {
+        let mut tables = self.tables();
+        let user = tables
+            .users
+            .iter_mut()
+            .find(|user| user.email == email && !user.is_verified)
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        let now = Utc::now();
+        user.is_verified = true;
+        user.status = UserStatus::Active;
+        user.verified_at = Some(now);
+        Ok(())
+    }
+
+    async fn mark_email_verified(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        if let Some(user) = self.tables().user_mut(id) {
+            mark_verified(user);
+        }
+        Ok(())
+    }
+
+    async fn record_failed_login(
+        &self,
+        id: Uuid,
+        max_attempts: i32,
+        base_lock_seconds: f64,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let mut tables = self.tables();
+        let user = tables.user_mut(id).ok_or(sqlx::Error::RowNotFound)?;
+
+        user.failed_login_attempts += 1;
+        if user.failed_login_attempts >= max_attempts {
+            let doublings = (user.failed_login_attempts - max_attempts).min(5);
+            let lock_seconds = base_lock_seconds * 2f64.powi(doublings);
+            user.locked_until =
+                Some(Utc::now() + Duration::milliseconds((lock_seconds * 1000.0) as i64));
+        }
+        Ok(user.locked_until)
+    }
+
+    async fn reset_failed_logins(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        if let Some(user) = self.tables().user_mut(id) {
+            user.failed_login_attempts = 0;
+            user.locked_until = None;
+        }
+        Ok(())
+    }
+
+    async fn unlock_account(&self, email: &str) -> Result<bool, sqlx::Error> {
+        let mut tables = self.tables();
+        let Some(user) = tables.users.iter_mut().find(|user| user.email == email) else {
+            return Ok(false);
+        };
+        user.failed_login_attempts = 0;
+        user.locked_until = None;
+        Ok(true)
+    }
+
+    async fn session_state(&self, id: Uuid) -> Result<Option<(UserStatus, i32)>, sqlx::Error> {
+        Ok(self
+            .tables()
+            .user(id)
+            .map(|user| (user.status.clone(), user.session_version)))
+    }
+
+    async fn update_locale(&self, id: Uuid, locale: &str) -> Result<bool, sqlx::Error> {
+        let mut tables = self.tables();
+        let Some(user) = tables.user_mut(id) else {
+            return Ok(false);
+        };
+        user.locale = locale.to_string();
+        user.updated_at = Utc::now();
+        Ok(true)
+    }
+
+    async fn find_identity_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        Ok(self
+            .tables()
+            .identities
+            .iter()
+            .find(|identity| identity.issuer == issuer && identity.subject == subject)
+            .map(|identity| identity.user_id))
+    }
+
+    async fn upsert_identity(
+        &self,
+        user_id: Uuid,
+        issuer: &str,
+        subject: &str,
+        _email: &str,
+    ) -> Result<(), sqlx::Error> {
+        let mut tables = self.tables();
+        let exists = tables
+            .identities
+            .iter()
+            .any(|identity| identity.issuer == issuer && identity.subject == subject);
+        if !exists {
+            tables.identities.push(Identity {
+                issuer: issuer.to_string(),
+                subject: subject.to_string(),
+                user_id,
+            });
+        }
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        search: Option<&str>,
+        status: Option<UserStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<UserOverview>, i64), sqlx::Error> {
+        let tables = self.tables();
+        let search = search.map(str::to_lowercase);
+        let mut matches: Vec<&User> = tables
+            .users
+            .iter()
+            .filter(|user| {
+                search.as_deref().is_none_or(|search| {
+                    user.email.to_lowercase().contains(search)
+                        || user.username.to_lowercase().contains(search)
+                })
+            })
+            .filter(|user| status.as_ref().is_none_or(|status| user.status == *status))
+            .collect();
+        matches.sort_by_key(|user| (std::cmp::Reverse(user.created_at), user.id));
+
+        let total = matches.len() as i64;
+        let page = matches
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .map(|user| tables.overview(user))
+            .collect();
+        Ok((page, total))
+    }
+
+    async fn overview(&self, id: Uuid) -> Result<Option<UserOverview>, sqlx::Error> {
+        let tables = self.tables();
+        Ok(tables.user(id).map(|user| tables.overview(user)))
+    }
+
+    async fn change_status(
+        &self,
+        id: Uuid,
+        status: UserStatus,
+        end_sessions: bool,
+        action: AdminAction,
+    ) -> Result<(), sqlx::Error> {
+        let mut tables = self.tables();
+        if let Some(user) = tables.user_mut(id) {
+            user.status = status;
+            if end_sessions {
+                user.session_version += 1;
+            }
+            user.updated_at = Utc::now();
+        }
+        tables.record_action(Some(id), action);
+        Ok(())
+    }
+
+    async fn verify_email(&self, id: Uuid, action: AdminAction) -> Result<(), sqlx::Error> {
+        let mut tables = self.tables();
+        if let Some(user) = tables.user_mut(id) {
+            mark_verified(user);
+        }
+        tables.record_action(Some(id), action);
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<(), sqlx::Error> {
+        Ok(())
+    }
+
+    async fn delete_unverified(&self, email: &str) -> Result<u64, sqlx::Error> {
+        let mut tables = self.tables();
+        let (unverified, kept): (Vec<User>, Vec<User>) = tables
+            .users
+            .drain(..)
+            .partition(|user| user.email == email && !user.is_verified);
+        tables.users = kept;
+
+        let unverified_ids: Vec<Uuid> = unverified.iter().map(|user| user.id).collect();
+        tables
+            .identities
+            .retain(|identity| !unverified_ids.contains(&identity.user_id));
+        tables
+            .links
+            .retain(|link| !unverified_ids.contains(&link.user_id));
+        tables
+            .api_tokens
+            .retain(|(token, _)| !unverified_ids.contains(&token.user_id));
+        Ok(unverified.len() as u64)
+    }
+
+    async fn delete_stale_pending(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let mut tables = self.tables();
+        let (stale, kept): (Vec<User>, Vec<User>) = tables.users.drain(..).partition(|user| {
+            user.status == UserStatus::PendingVerification && user.created_at < created_before
+        });
+        tables.users = kept;
+
+        let stale_ids: Vec<Uuid> = stale.iter().map(|user| user.id).collect();
+        tables
+            .identities
+            .retain(|identity| !stale_ids.contains(&identity.user_id));
+        tables
+            .links
+            .retain(|link| !stale_ids.contains(&link.user_id));
+        tables
+            .api_tokens
+            .retain(|(token, _)| !stale_ids.contains(&token.user_id));
+        Ok(stale.into_iter().map(|user| user.username).collect())
+    }
+}
+
+#[async_trait]
+impl LinkRepository for MemoryRepository {
+    async fn list(&self) -> Result<Vec<Link>, sqlx::Error> {
+        let tables = self.tables();
+        let mut links: Vec<Link> = tables
+            .links
+            .iter()
+            .map(|stored| tables.link(stored))
+            .collect();
+        links.sort_by_key(|link| std::cmp::Reverse(link.created_at));
+        Ok(links)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Link>, sqlx::Error> {
+        let tables = self.tables();
+        Ok(tables
+            .links
+            .iter()
+            .find(|stored| stored.id == id)
+            .map(|stored| tables.link(stored)))
+    }
+
+    async fn create(&self, link: NewLink) -> Result<Link, sqlx::Error> {
+        let mut tables = self.tables();
+        if tables.user(link.user_id).is_none() {
+            return Err(sqlx::Error::Protocol(
+                "insert on links violates foreign key constraint on user_id".to_string(),
+            ));
+        }
+
+        let stored = StoredLink {
+            id: Uuid::new_v4(),
+            url: link.url,
+            title: link.title,
+            description: link.description,
+            user_id: link.user_id,
+            click_count: 0,
+            preview: None,
+            created_at: Utc::now(),
+        };
+        let link = tables.link(&stored);
+        tables.links.push(stored);
+        Ok(link)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        self.tables().links.retain(|stored| stored.id != id);
+        Ok(())
+    }
+
+    async fn increment_clicks(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        if let Some(stored) = self
+            .tables()
+            .links
+            .iter_mut()
+            .find(|stored| stored.id == id)
+        {
+            stored.click_count += 1;
+        }
+        Ok(())
+    }
+
+    async fn set_preview(&self, id: Uuid, preview: &LinkPreview) -> Result<(), sqlx::Error> {
+        if let Some(stored) = self
+            .tables()
+            .links
+            .iter_mut()
+            .find(|stored| stored.id == id)
+        {
+            stored.preview = Some(preview.clone());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ApiTokenRepository for MemoryRepository {
+    async fn create(&self, token: NewApiToken) -> Result<ApiToken, sqlx::Error> {
+        let stored = ApiToken {
+            id: Uuid::new_v4(),
+            user_id: token.user_id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            last_used_at: None,
+            created_at: Utc::now(),
+        };
+        self.tables()
+            .api_tokens
+            .push((stored.clone(), token.token_hash));
+        Ok(stored)
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<ApiToken>, sqlx::Error> {
+        let mut tokens: Vec<ApiToken> = self
+            .tables()
+            .api_tokens
+            .iter()
+            .filter(|(token, _)| token.user_id == user_id)
+            .map(|(token, _)| token.clone())
+            .collect();
+        tokens.sort_by_key(|token| std::cmp::Reverse(token.created_at));
+        Ok(tokens)
+    }
+
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<ApiTokenOwner>, sqlx::Error> {
+        let tables = self.tables();
+        let now = Utc::now();
+        Ok(tables
+            .api_tokens
+            .iter()
+            .filter(|(token, hash)| {
+                hash == token_hash && token.expires_at.is_none_or(|expires_at| expires_at > now)
+            })
+            .find_map(|(token, _)| {
+                let user = tables
+                    .user(token.user_id)
+                    .filter(|user| user.status == UserStatus::Active)?;
+                Some(ApiTokenOwner {
+                    token: token.clone(),
+                    email: user.email.clone(),
+                    username: user.username.clone(),
+                    role: user.role,
+                })
+            }))
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        let mut tables = self.tables();
+        if let Some((token, _)) = tables
+            .api_tokens
+            .iter_mut()
+            .find(|(token, _)| token.id == id)
+        {
+            token.last_used_at = Some(Utc::now());
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut tables = self.tables();
+        let before = tables.api_tokens.len();
+        tables
+            .api_tokens
+            .retain(|(token, _)| !(token.id == id && token.user_id == user_id));
+        Ok(tables.api_tokens.len() < before)
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for MemoryRepository {
+    async fn enqueue(&self, email: NewEmail) -> Result<Uuid, sqlx::Error> {
+        Ok(self.tables().queue_email(email))
+    }
+
+    /// Queued emails are only recorded, so there is never anything to deliver
+    async fn claim_due(
+        &self,
+        _limit: i64,
+        _lease_seconds: i64,
+    ) -> Result<Vec<QueuedEmail>, sqlx::Error> {
+        Ok(Vec::new())
+    }
+
+    async fn mark_sent(&self, _id: Uuid) -> Result<(), sqlx::Error> {
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        _id: Uuid,
+        _error: &str,
+        _retry_in_seconds: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        Ok(())
+    }
+
+    /// Every recorded email stays pending, as none is ever delivered
+    async fn list(&self, status: EmailStatus, limit: i64) -> Result<Vec<OutboxEmail>, sqlx::Error> {
+        if status != EmailStatus::Pending {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .tables()
+            .emails
+            .iter()
+            .rev()
+            .take(limit.max(0) as usize)
+            .map(|stored| OutboxEmail {
+                id: stored.id,
+                recipient: stored.email.recipient.clone(),
+                template: stored.email.template.clone(),
+                locale: stored.email.locale.clone(),
+                status: EmailStatus::Pending,
+                attempts: 0,
+                last_error: None,
+                next_attempt_at: stored.created_at,
+                sent_at: None,
+                created_at: stored.created_at,
+            })
+            .collect())
+    }
+
+    async fn replay(&self, _id: Uuid) -> Result<bool, sqlx::Error> {
+        Ok(false)
+    }
+}
+
+#[async_trait]
+impl InviteRepository for MemoryRepository {
+    async fn create(&self, invite: NewInvite) -> Result<InviteCode, sqlx::Error> {
+        let mut tables = self.tables();
+        if tables
+            .invites
+            .iter()
+            .any(|stored| stored.code_hash == invite.code_hash)
+        {
+            return Err(sqlx::Error::Protocol(
+                "duplicate key value violates unique constraint on invite_codes".to_string(),
+            ));
+        }
+
+        let stored = InviteCode {
+            id: Uuid::new_v4(),
+            code_prefix: invite.code_prefix,
+            note: invite.note,
+            max_uses: invite.max_uses,
+            uses: 0,
+            expires_at: invite.expires_at,
+            created_by: Some(invite.created_by),
+            created_at: Utc::now(),
+        };
+        tables.invites.push(StoredInvite {
+            invite: stored.clone(),
+            code_hash: invite.code_hash,
+        });
+        Ok(stored)
+    }
+
+    async fn list(&self) -> Result<Vec<InviteCode>, sqlx::Error> {
+        let mut invites: Vec<InviteCode> = self
+            .tables()
+            .invites
+            .iter()
+            .map(|stored| stored.invite.clone())
+            .collect();
+        invites.sort_by_key(|invite| std::cmp::Reverse(invite.created_at));
+        Ok(invites)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut tables = self.tables();
+        let before = tables.invites.len();
+        tables.invites.retain(|stored| stored.invite.id != id);
+        Ok(tables.invites.len() < before)
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MemoryRepository {
+    async fn record(
+        &self,
+        admin_id: Uuid,
+        action: AuditAction,
+        target_user_id: Option<Uuid>,
+        details: JsonValue,
+    ) -> Result<(), sqlx::Error> {
+        self.tables().record_action(
+            target_user_id,
+            AdminAction {
+                admin_id,
+                action,
+                details,
+            },
+        );
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        target_user_id: Option<Uuid>,
+        admin_id: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<AuditLogEntry>, i64), sqlx::Error> {
+        let tables = self.tables();
+        let matches: Vec<&AuditLogEntry> = tables
+            .audit_log
+            .iter()
+            .rev()
+            .filter(|entry| target_user_id.is_none_or(|id| entry.target_user_id == Some(id)))
+            .filter(|entry| admin_id.is_none_or(|id| entry.admin_id == Some(id)))
+            .collect();
+
+        let total = matches.len() as i64;
+        let page = matches
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect();
+        Ok((page, total))
+    }
+}
+
+/// Marks a user's email as verified, activating the account if it was waiting for that
+fn mark_verified(user: &mut User) {
+    let now = Utc::now();
+    user.is_verified = true;
+    user.verified_at = user.verified_at.or(Some(now));
+    if user.status == UserStatus::PendingVerification {
+        user.status = UserStatus::Active;
+    }
+    user.updated_at = now;
+}
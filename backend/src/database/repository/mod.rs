@@ -0,0 +1,302 @@
+//! Data access used by the request handlers, behind traits.
+//!
+//! The server uses the Postgres implementation, or the SQLite one when built with the
+//! `sqlite` feature and given a `sqlite:` URL. The in-memory one keeps everything in a single
+//! process and is meant for tests, so the HTTP flows can be exercised without a database. All
+//! of them return `sqlx::Error`, which the handlers already know how to report.
+//!
+//! Writes that must happen together, such as creating an account and queueing its
+//! verification email, are single methods so each backend can make them atomic.
+
+mod memory;
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use self::memory::MemoryRepository;
+pub use self::postgres::PostgresRepository;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteRepository;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::models::{
+    ApiToken, ApiTokenOwner, AuditLogEntry, EmailStatus, InviteCode, Link, LinkPreview,
+    NewApiToken, NewEmail, NewInvite, NewLink, NewUser, OutboxEmail, QueuedEmail, UserOverview,
+};
+use crate::{
+    models::auth::{User, UserStatus},
+    services::audit::AuditAction,
+};
+
+/// Outcome of [`UserRepository::register`]
+#[derive(Debug)]
+pub enum Registration {
+    Created(User),
+    /// The invite code doesn't exist, has expired or has no uses left. Nothing was stored.
+    InvalidInvite,
+}
+
+/// An admin's change to an account, recorded in the audit log together with the change
+#[derive(Debug, Clone)]
+pub struct AdminAction {
+    pub admin_id: Uuid,
+    pub action: AuditAction,
+    pub details: JsonValue,
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error>;
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error>;
+
+    /// A user that has either the email or the username
+    async fn find_by_email_or_username(
+        &self,
+        email: &str,
+        username: &str,
+    ) -> Result<Option<User>, sqlx::Error>;
+
+    async fn find_id_by_email(&self, email: &str) -> Result<Option<Uuid>, sqlx::Error>;
+
+    async fn username_exists(&self, username: &str) -> Result<bool, sqlx::Error>;
+
+    async fn create(&self, user: NewUser) -> Result<User, sqlx::Error>;
+
+    /// Creates an account together with its verification email. With `invite_hash`, one use
+    /// of that invite code is taken as well, and nothing is stored if it can't be.
+    async fn register(
+        &self,
+        user: NewUser,
+        invite_hash: Option<&str>,
+        verification: NewEmail,
+    ) -> Result<Registration, sqlx::Error>;
+
+    /// Verifies and activates an account waiting for verification. Fails with
+    /// `RowNotFound` if there is none with that email.
+    async fn complete_registration(&self, email: &str) -> Result<(), sqlx::Error>;
+
+    /// Marks an email as verified, activating the account if it was waiting for that
+    async fn mark_email_verified(&self, id: Uuid) -> Result<(), sqlx::Error>;
+
+    /// Counts a failed login and locks the account from `max_attempts` failures on. Returns
+    /// when the account is locked until, if it is.
+    async fn record_failed_login(
+        &self,
+        id: Uuid,
+        max_attempts: i32,
+        base_lock_seconds: f64,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error>;
+
+    async fn reset_failed_logins(&self, id: Uuid) -> Result<(), sqlx::Error>;
+
+    /// Returns whether a user with that email exists
+    async fn unlock_account(&self, email: &str) -> Result<bool, sqlx::Error>;
+
+    /// The account status and session version, which decide whether a login token is valid
+    async fn session_state(&self, id: Uuid) -> Result<Option<(UserStatus, i32)>, sqlx::Error>;
+
+    /// Returns whether the user exists
+    async fn update_locale(&self, id: Uuid, locale: &str) -> Result<bool, sqlx::Error>;
+
+    /// The account linked to an OpenID Connect identity
+    async fn find_identity_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error>;
+
+    /// Links an identity to an account, or records a login through an existing link
+    async fn upsert_identity(
+        &self,
+        user_id: Uuid,
+        issuer: &str,
+        subject: &str,
+        email: &str,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Pages through accounts with their link counts, newest first, optionally only those
+    /// whose email or username contains `search` (ignoring case) or with `status`. Returns
+    /// the page and the total number of matches.
+    async fn search(
+        &self,
+        search: Option<&str>,
+        status: Option<UserStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<UserOverview>, i64), sqlx::Error>;
+
+    /// An account with its link count
+    async fn overview(&self, id: Uuid) -> Result<Option<UserOverview>, sqlx::Error>;
+
+    /// Changes an account's status for an admin and records the action. With `end_sessions`,
+    /// login tokens issued so far stop being accepted.
+    async fn change_status(
+        &self,
+        id: Uuid,
+        status: UserStatus,
+        end_sessions: bool,
+        action: AdminAction,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Marks an account's email as verified for an admin and records the action
+    async fn verify_email(&self, id: Uuid, action: AdminAction) -> Result<(), sqlx::Error>;
+
+    /// Checks that the database can be reached
+    async fn ping(&self) -> Result<(), sqlx::Error>;
+
+    /// Deletes the unverified accounts registered with `email`. Returns how many were deleted.
+    async fn delete_unverified(&self, email: &str) -> Result<u64, sqlx::Error>;
+
+    /// Deletes accounts still waiting for verification that were created before
+    /// `created_before`. Returns their usernames.
+    async fn delete_stale_pending(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait LinkRepository: Send + Sync {
+    /// All links, newest first
+    async fn list(&self) -> Result<Vec<Link>, sqlx::Error>;
+
+    async fn find(&self, id: Uuid) -> Result<Option<Link>, sqlx::Error>;
+
+    async fn create(&self, link: NewLink) -> Result<Link, sqlx::Error>;
+
+    async fn delete(&self, id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn increment_clicks(&self, id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn set_preview(&self, id: Uuid, preview: &LinkPreview) -> Result<(), sqlx::Error>;
+}
+
+#[async_trait]
+pub trait ApiTokenRepository: Send + Sync {
+    async fn create(&self, token: NewApiToken) -> Result<ApiToken, sqlx::Error>;
+
+    /// Tokens of a user, newest first
+    async fn list(&self, user_id: Uuid) -> Result<Vec<ApiToken>, sqlx::Error>;
+
+    /// An unexpired token of an active user, by the hash of its secret
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<ApiTokenOwner>, sqlx::Error>;
+
+    /// Records that a token has just been used
+    async fn touch(&self, id: Uuid) -> Result<(), sqlx::Error>;
+
+    /// Deletes a token of the user. Returns whether it existed.
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait OutboxRepository: Send + Sync {
+    /// Queues an email for the delivery worker and returns its ID
+    async fn enqueue(&self, email: NewEmail) -> Result<Uuid, sqlx::Error>;
+
+    /// Claims up to `limit` due emails, hiding them from other workers for `lease_seconds`
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_seconds: i64,
+    ) -> Result<Vec<QueuedEmail>, sqlx::Error>;
+
+    async fn mark_sent(&self, id: Uuid) -> Result<(), sqlx::Error>;
+
+    /// Records a failed delivery. Without `retry_in_seconds` the email is moved to the dead
+    /// letter state.
+    async fn mark_failed(
+        &self,
+        id: Uuid,
+        error: &str,
+        retry_in_seconds: Option<i64>,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Up to `limit` emails with `status`, newest first
+    async fn list(&self, status: EmailStatus, limit: i64) -> Result<Vec<OutboxEmail>, sqlx::Error>;
+
+    /// Queues a dead email for immediate delivery. Returns whether a dead email with that ID
+    /// exists.
+    async fn replay(&self, id: Uuid) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait InviteRepository: Send + Sync {
+    async fn create(&self, invite: NewInvite) -> Result<InviteCode, sqlx::Error>;
+
+    /// All invite codes, newest first
+    async fn list(&self) -> Result<Vec<InviteCode>, sqlx::Error>;
+
+    /// Deletes an invite code. Returns whether it existed.
+    async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    /// Records that `admin_id` took `action` on `target_user_id`
+    async fn record(
+        &self,
+        admin_id: Uuid,
+        action: AuditAction,
+        target_user_id: Option<Uuid>,
+        details: JsonValue,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Pages through the log, newest first, optionally only actions taken on
+    /// `target_user_id` or by `admin_id`. Returns the page and the total number of matches.
+    async fn list(
+        &self,
+        target_user_id: Option<Uuid>,
+        admin_id: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<AuditLogEntry>, i64), sqlx::Error>;
+}
+
+/// One backend behind all repository traits
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub links: Arc<dyn LinkRepository>,
+    pub api_tokens: Arc<dyn ApiTokenRepository>,
+    pub outbox: Arc<dyn OutboxRepository>,
+    pub invites: Arc<dyn InviteRepository>,
+    pub audit: Arc<dyn AuditRepository>,
+}
+
+impl Repositories {
+    pub fn new<R>(backend: Arc<R>) -> Self
+    where
+        R: UserRepository
+            + LinkRepository
+            + ApiTokenRepository
+            + OutboxRepository
+            + InviteRepository
+            + AuditRepository
+            + 'static,
+    {
+        Self {
+            users: backend.clone(),
+            links: backend.clone(),
+            api_tokens: backend.clone(),
+            outbox: backend.clone(),
+            invites: backend.clone(),
+            audit: backend,
+        }
+    }
+
+    pub fn postgres(pool: PgPool) -> Self {
+        Self::new(Arc::new(PostgresRepository::new(pool)))
+    }
+
+    #[cfg(feature = "sqlite")]
+    pub fn sqlite(pool: sqlx::SqlitePool) -> Self {
+        Self::new(Arc::new(SqliteRepository::new(pool)))
+    }
+}
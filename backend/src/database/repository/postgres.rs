@@ -0,0 +1,363 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{
+    AdminAction, ApiTokenRepository, AuditRepository, InviteRepository, LinkRepository,
+    OutboxRepository, Registration, UserRepository,
+};
+use crate::{
+    database::{
+        models::{
+            ApiToken, ApiTokenOwner, AuditLogEntry, EmailStatus, InviteCode, Link, LinkPreview,
+            NewApiToken, NewEmail, NewInvite, NewLink, NewUser, OutboxEmail, QueuedEmail,
+            UserOverview,
+        },
+        queries,
+    },
+    models::auth::{User, UserStatus},
+    services::audit::{self, AuditAction},
+};
+
+/// Repositories backed by the `queries` module
+#[derive(Clone)]
+pub struct PostgresRepository {
+    pool: PgPool,
+}
+
+impl PostgresRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for PostgresRepository {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        queries::find_user_by_id(&self.pool, id).await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        queries::find_user_by_email(&self.pool, email).await
+    }
+
+    async fn find_by_email_or_username(
+        &self,
+        email: &str,
+        username: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        queries::find_user_by_email_or_username(&self.pool, email, username).await
+    }
+
+    async fn find_id_by_email(&self, email: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        queries::find_user_id_by_email(&self.pool, email).await
+    }
+
+    async fn username_exists(&self, username: &str) -> Result<bool, sqlx::Error> {
+        queries::username_exists(&self.pool, username).await
+    }
+
+    async fn create(&self, user: NewUser) -> Result<User, sqlx::Error> {
+        queries::insert_user(&self.pool, &user).await
+    }
+
+    async fn register(
+        &self,
+        user: NewUser,
+        invite_hash: Option<&str>,
+        verification: NewEmail,
+    ) -> Result<Registration, sqlx::Error> {
+        // Dropping the transaction early rolls everything back
+        let mut tx = self.pool.begin().await?;
+        if let Some(invite_hash) = invite_hash {
+            if !queries::redeem_invite_code(&mut *tx, invite_hash).await? {
+                return Ok(Registration::InvalidInvite);
+            }
+        }
+        let user = queries::insert_user(&mut *tx, &user).await?;
+        queries::enqueue_email(
+            &mut *tx,
+            &verification.recipient,
+            &verification.template,
+            &verification.locale,
+            &verification.variables,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(Registration::Created(user))
+    }
+
+    async fn complete_registration(&self, email: &str) -> Result<(), sqlx::Error> {
+        queries::complete_registration(&self.pool, email).await
+    }
+
+    async fn mark_email_verified(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        queries::mark_email_verified(&self.pool, id).await
+    }
+
+    async fn record_failed_login(
+        &self,
+        id: Uuid,
+        max_attempts: i32,
+        base_lock_seconds: f64,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        queries::record_failed_login(&self.pool, id, max_attempts, base_lock_seconds).await
+    }
+
+    async fn reset_failed_logins(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        queries::reset_failed_logins(&self.pool, id).await
+    }
+
+    async fn unlock_account(&self, email: &str) -> Result<bool, sqlx::Error> {
+        queries::unlock_account(&self.pool, email).await
+    }
+
+    async fn session_state(&self, id: Uuid) -> Result<Option<(UserStatus, i32)>, sqlx::Error> {
+        queries::find_session_state(&self.pool, id).await
+    }
+
+    async fn update_locale(&self, id: Uuid, locale: &str) -> Result<bool, sqlx::Error> {
+        queries::update_user_locale(&self.pool, id, locale).await
+    }
+
+    async fn find_identity_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        queries::find_identity_user(&self.pool, issuer, subject).await
+    }
+
+    async fn upsert_identity(
+        &self,
+        user_id: Uuid,
+        issuer: &str,
+        subject: &str,
+        email: &str,
+    ) -> Result<(), sqlx::Error> {
+        queries::upsert_identity(&self.pool, user_id, issuer, subject, email).await
+    }
+
+    async fn search(
+        &self,
+        search: Option<&str>,
+        status: Option<UserStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<UserOverview>, i64), sqlx::Error> {
+        queries::search_users(&self.pool, search, status, limit, offset).await
+    }
+
+    async fn overview(&self, id: Uuid) -> Result<Option<UserOverview>, sqlx::Error> {
+        queries::get_user_overview(&self.pool, id).await
+    }
+
+    async fn change_status(
+        &self,
+        id: Uuid,
+        status: UserStatus,
+        end_sessions: bool,
+        action: AdminAction,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        queries::set_user_status(&mut *tx, id, status, end_sessions).await?;
+        audit::record(
+            &mut *tx,
+            action.admin_id,
+            action.action,
+            Some(id),
+            action.details,
+        )
+        .await?;
+        tx.commit().await
+    }
+
+    async fn verify_email(&self, id: Uuid, action: AdminAction) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        queries::mark_email_verified(&mut *tx, id).await?;
+        audit::record(
+            &mut *tx,
+            action.admin_id,
+            action.action,
+            Some(id),
+            action.details,
+        )
+        .await?;
+        tx.commit().await
+    }
+
+    async fn ping(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT 1").execute(&self.pool).await.map(drop)
+    }
+
+    async fn delete_unverified(&self, email: &str) -> Result<u64, sqlx::Error> {
+        queries::delete_unverified_users(&self.pool, email).await
+    }
+
+    async fn delete_stale_pending(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        queries::delete_stale_pending_users(&self.pool, created_before).await
+    }
+}
+
+#[async_trait]
+impl LinkRepository for PostgresRepository {
+    async fn list(&self) -> Result<Vec<Link>, sqlx::Error> {
+        queries::get_all_links(&self.pool).await
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Link>, sqlx::Error> {
+        queries::get_link_by_id(&self.pool, id).await
+    }
+
+    async fn create(&self, link: NewLink) -> Result<Link, sqlx::Error> {
+        queries::create_link(
+            &self.pool,
+            link.url,
+            link.title,
+            link.description,
+            link.user_id,
+            None,
+        )
+        .await
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        queries::delete_link(&self.pool, id).await
+    }
+
+    async fn increment_clicks(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        queries::increment_click_count(&self.pool, id).await
+    }
+
+    async fn set_preview(&self, id: Uuid, preview: &LinkPreview) -> Result<(), sqlx::Error> {
+        queries::set_link_preview(&self.pool, id, preview).await
+    }
+}
+
+#[async_trait]
+impl ApiTokenRepository for PostgresRepository {
+    async fn create(&self, token: NewApiToken) -> Result<ApiToken, sqlx::Error> {
+        queries::create_api_token(
+            &self.pool,
+            token.user_id,
+            &token.name,
+            &token.token_prefix,
+            &token.token_hash,
+            &token.scopes,
+            token.expires_at,
+        )
+        .await
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<ApiToken>, sqlx::Error> {
+        queries::list_api_tokens(&self.pool, user_id).await
+    }
+
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<ApiTokenOwner>, sqlx::Error> {
+        queries::find_api_token_by_hash(&self.pool, token_hash).await
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        queries::touch_api_token(&self.pool, id).await
+    }
+
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+        queries::delete_api_token(&self.pool, user_id, id).await
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for PostgresRepository {
+    async fn enqueue(&self, email: NewEmail) -> Result<Uuid, sqlx::Error> {
+        queries::enqueue_email(
+            &self.pool,
+            &email.recipient,
+            &email.template,
+            &email.locale,
+            &email.variables,
+        )
+        .await
+    }
+
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_seconds: i64,
+    ) -> Result<Vec<QueuedEmail>, sqlx::Error> {
+        queries::claim_due_emails(&self.pool, limit, lease_seconds).await
+    }
+
+    async fn mark_sent(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        queries::mark_email_sent(&self.pool, id).await
+    }
+
+    async fn mark_failed(
+        &self,
+        id: Uuid,
+        error: &str,
+        retry_in_seconds: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        queries::mark_email_failed(&self.pool, id, error, retry_in_seconds).await
+    }
+
+    async fn list(&self, status: EmailStatus, limit: i64) -> Result<Vec<OutboxEmail>, sqlx::Error> {
+        queries::list_outbox_emails(&self.pool, status, limit).await
+    }
+
+    async fn replay(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        queries::replay_email(&self.pool, id).await
+    }
+}
+
+#[async_trait]
+impl InviteRepository for PostgresRepository {
+    async fn create(&self, invite: NewInvite) -> Result<InviteCode, sqlx::Error> {
+        queries::create_invite_code(
+            &self.pool,
+            &invite.code_prefix,
+            &invite.code_hash,
+            invite.note.as_deref(),
+            invite.max_uses,
+            invite.expires_at,
+            invite.created_by,
+        )
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<InviteCode>, sqlx::Error> {
+        queries::list_invite_codes(&self.pool).await
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        queries::delete_invite_code(&self.pool, id).await
+    }
+}
+
+#[async_trait]
+impl AuditRepository for PostgresRepository {
+    async fn record(
+        &self,
+        admin_id: Uuid,
+        action: AuditAction,
+        target_user_id: Option<Uuid>,
+        details: JsonValue,
+    ) -> Result<(), sqlx::Error> {
+        audit::record(&self.pool, admin_id, action, target_user_id, details).await
+    }
+
+    async fn list(
+        &self,
+        target_user_id: Option<Uuid>,
+        admin_id: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<AuditLogEntry>, i64), sqlx::Error> {
+        queries::list_audit_log(&self.pool, target_user_id, admin_id, limit, offset).await
+    }
+}
@@ -0,0 +1,1051 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value as JsonValue;
+use sqlx::{sqlite::SqliteRow, types::Json, Row, SqliteExecutor, SqlitePool};
+use std::collections::HashMap;
+use uuid::{fmt::Hyphenated, Uuid};
+
+use super::{
+    AdminAction, ApiTokenRepository, AuditRepository, InviteRepository, LinkRepository,
+    OutboxRepository, Registration, UserRepository,
+};
+use crate::{
+    database::models::{
+        ApiToken, ApiTokenOwner, AuditLogEntry, EmailStatus, InviteCode, Link, LinkPreview,
+        NewApiToken, NewEmail, NewInvite, NewLink, NewUser, OutboxEmail, QueuedEmail, SimpleUser,
+        UserOverview,
+    },
+    models::auth::{Gender, TokenScope, User, UserRole, UserStatus},
+    services::audit::AuditAction,
+};
+
+const USER_COLUMNS: &str = "id, email, username, password_hash, gender, status, role, \
+    is_verified, verification_attempts, failed_login_attempts, locked_until, locale, \
+    session_version, verified_at, created_at, updated_at";
+
+const LINK_SELECT: &str = "SELECT l.id, l.url, l.title, l.description, l.user_id, \
+    l.click_count, l.created_at, l.updated_at, l.preview, u.username \
+    FROM links l LEFT JOIN users u ON l.user_id = u.id";
+
+const TOKEN_COLUMNS: &str =
+    "id, user_id, name, token_prefix, scopes, expires_at, last_used_at, created_at";
+
+const OVERVIEW_SELECT: &str = "SELECT u.id, u.email, u.username, u.role, u.status, \
+    u.is_verified, u.locale, u.failed_login_attempts, u.locked_until, \
+    (SELECT COUNT(*) FROM links l WHERE l.user_id = u.id) AS link_count, \
+    u.verified_at, u.created_at, u.updated_at \
+    FROM users u";
+
+/// Matches users whose email or username contains the first parameter, ignoring case, and
+/// whose status is the second. Either parameter may be NULL to skip its condition.
+const USER_SEARCH_FILTER: &str = "WHERE (?1 IS NULL \
+        OR instr(lower(u.email), lower(?1)) > 0 \
+        OR instr(lower(u.username), lower(?1)) > 0) \
+    AND (?2 IS NULL OR u.status = ?2)";
+
+const INVITE_COLUMNS: &str =
+    "id, code_prefix, note, max_uses, uses, expires_at, created_by, created_at";
+
+const OUTBOX_COLUMNS: &str = "id, recipient, template, locale, status, attempts, last_error, \
+    next_attempt_at, sent_at, created_at";
+
+/// Matches audit log entries on the user in the first parameter and by the admin in the
+/// second. Either parameter may be NULL to skip its condition.
+const AUDIT_FILTER: &str = "WHERE (?1 IS NULL OR target_user_id = ?1) \
+    AND (?2 IS NULL OR admin_id = ?2)";
+
+/// Repositories backed by a SQLite database with the schema in `migrations_sqlite`
+#[derive(Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteRepository {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {USER_COLUMNS} FROM users WHERE id = ?"))
+            .bind(id.hyphenated())
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| user_from_row(&row))
+            .transpose()
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {USER_COLUMNS} FROM users WHERE email = ?"))
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| user_from_row(&row))
+            .transpose()
+    }
+
+    async fn find_by_email_or_username(
+        &self,
+        email: &str,
+        username: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query(&format!(
+            "SELECT {USER_COLUMNS} FROM users WHERE email = ? OR username = ? LIMIT 1"
+        ))
+        .bind(email)
+        .bind(username)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| user_from_row(&row))
+        .transpose()
+    }
+
+    async fn find_id_by_email(&self, email: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        let id: Option<Hyphenated> = sqlx::query_scalar("SELECT id FROM users WHERE email = ?")
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(id.map(Hyphenated::into_uuid))
+    }
+
+    async fn username_exists(&self, username: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE username = ?)")
+            .bind(username)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn create(&self, user: NewUser) -> Result<User, sqlx::Error> {
+        insert_user(&self.pool, &user).await
+    }
+
+    async fn register(
+        &self,
+        user: NewUser,
+        invite_hash: Option<&str>,
+        verification: NewEmail,
+    ) -> Result<Registration, sqlx::Error> {
+        // Dropping the transaction early rolls everything back
+        let mut tx = self.pool.begin().await?;
+        if let Some(invite_hash) = invite_hash {
+            let redeemed = sqlx::query(
+                "UPDATE invite_codes SET uses = uses + 1 \
+                 WHERE code_hash = ? AND uses < max_uses \
+                 AND (expires_at IS NULL OR expires_at > ?)",
+            )
+            .bind(invite_hash)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+                > 0;
+            if !redeemed {
+                return Ok(Registration::InvalidInvite);
+            }
+        }
+        let user = insert_user(&mut *tx, &user).await?;
+        insert_email(&mut *tx, &verification).await?;
+        tx.commit().await?;
+
+        Ok(Registration::Created(user))
+    }
+
+    async fn complete_registration(&self, email: &str) -> Result<(), sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE users SET is_verified = TRUE, status = 'active', verified_at = ? \
+             WHERE email = ? AND is_verified = FALSE",
+        )
+        .bind(Utc::now())
+        .bind(email)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        Ok(())
+    }
+
+    async fn mark_email_verified(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        mark_verified(&self.pool, id).await
+    }
+
+    async fn record_failed_login(
+        &self,
+        id: Uuid,
+        max_attempts: i32,
+        base_lock_seconds: f64,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query(
+            "UPDATE users SET failed_login_attempts = failed_login_attempts + 1 \
+             WHERE id = ? RETURNING failed_login_attempts, locked_until",
+        )
+        .bind(id.hyphenated())
+        .fetch_one(&mut *tx)
+        .await?;
+        let attempts: i32 = row.try_get("failed_login_attempts")?;
+        let mut locked_until: Option<DateTime<Utc>> = row.try_get("locked_until")?;
+
+        if attempts >= max_attempts {
+            let lock_seconds = base_lock_seconds * 2f64.powi((attempts - max_attempts).min(5));
+            let until = Utc::now() + Duration::milliseconds((lock_seconds * 1000.0) as i64);
+            sqlx::query("UPDATE users SET locked_until = ? WHERE id = ?")
+                .bind(until)
+                .bind(id.hyphenated())
+                .execute(&mut *tx)
+                .await?;
+            locked_until = Some(until);
+        }
+        tx.commit().await?;
+
+        Ok(locked_until)
+    }
+
+    async fn reset_failed_logins(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET failed_login_attempts = 0, locked_until = NULL WHERE id = ?")
+            .bind(id.hyphenated())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn unlock_account(&self, email: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE users SET failed_login_attempts = 0, locked_until = NULL WHERE email = ?",
+        )
+        .bind(email)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn session_state(&self, id: Uuid) -> Result<Option<(UserStatus, i32)>, sqlx::Error> {
+        sqlx::query("SELECT status, session_version FROM users WHERE id = ?")
+            .bind(id.hyphenated())
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| {
+                Ok((
+                    decode(&row, "status", status_from_str)?,
+                    row.try_get("session_version")?,
+                ))
+            })
+            .transpose()
+    }
+
+    async fn update_locale(&self, id: Uuid, locale: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET locale = ?, updated_at = ? WHERE id = ?")
+            .bind(locale)
+            .bind(Utc::now())
+            .bind(id.hyphenated())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_identity_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        let id: Option<Hyphenated> = sqlx::query_scalar(
+            "SELECT user_id FROM user_identities WHERE issuer = ? AND subject = ?",
+        )
+        .bind(issuer)
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(id.map(Hyphenated::into_uuid))
+    }
+
+    async fn upsert_identity(
+        &self,
+        user_id: Uuid,
+        issuer: &str,
+        subject: &str,
+        email: &str,
+    ) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO user_identities \
+                (id, user_id, issuer, subject, email, created_at, last_login_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT (issuer, subject) \
+             DO UPDATE SET email = excluded.email, last_login_at = excluded.last_login_at",
+        )
+        .bind(Uuid::new_v4().hyphenated())
+        .bind(user_id.hyphenated())
+        .bind(issuer)
+        .bind(subject)
+        .bind(email)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        search: Option<&str>,
+        status: Option<UserStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<UserOverview>, i64), sqlx::Error> {
+        let status = status.as_ref().map(status_str);
+        let users = sqlx::query(&format!(
+            "{OVERVIEW_SELECT} {USER_SEARCH_FILTER} \
+             ORDER BY u.created_at DESC, u.id LIMIT ?3 OFFSET ?4"
+        ))
+        .bind(search)
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(overview_from_row)
+        .collect::<Result<_, _>>()?;
+
+        let total = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM users u {USER_SEARCH_FILTER}"
+        ))
+        .bind(search)
+        .bind(status)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((users, total))
+    }
+
+    async fn overview(&self, id: Uuid) -> Result<Option<UserOverview>, sqlx::Error> {
+        sqlx::query(&format!("{OVERVIEW_SELECT} WHERE u.id = ?"))
+            .bind(id.hyphenated())
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| overview_from_row(&row))
+            .transpose()
+    }
+
+    async fn change_status(
+        &self,
+        id: Uuid,
+        status: UserStatus,
+        end_sessions: bool,
+        action: AdminAction,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE users SET \
+                status = ?, \
+                session_version = session_version + CASE WHEN ? THEN 1 ELSE 0 END, \
+                updated_at = ? \
+             WHERE id = ?",
+        )
+        .bind(status_str(&status))
+        .bind(end_sessions)
+        .bind(Utc::now())
+        .bind(id.hyphenated())
+        .execute(&mut *tx)
+        .await?;
+        insert_audit_entry(&mut *tx, Some(id), action).await?;
+        tx.commit().await
+    }
+
+    async fn verify_email(&self, id: Uuid, action: AdminAction) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        mark_verified(&mut *tx, id).await?;
+        insert_audit_entry(&mut *tx, Some(id), action).await?;
+        tx.commit().await
+    }
+
+    async fn ping(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT 1").execute(&self.pool).await.map(drop)
+    }
+
+    async fn delete_unverified(&self, email: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM users WHERE email = ? AND is_verified = FALSE")
+            .bind(email)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_stale_pending(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "DELETE FROM users WHERE status = 'pending_verification' AND created_at < ? \
+             RETURNING username",
+        )
+        .bind(created_before)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
+
+#[async_trait]
+impl LinkRepository for SqliteRepository {
+    async fn list(&self) -> Result<Vec<Link>, sqlx::Error> {
+        sqlx::query(&format!("{LINK_SELECT} ORDER BY l.created_at DESC"))
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(link_from_row)
+            .collect()
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Link>, sqlx::Error> {
+        sqlx::query(&format!("{LINK_SELECT} WHERE l.id = ?"))
+            .bind(id.hyphenated())
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| link_from_row(&row))
+            .transpose()
+    }
+
+    async fn create(&self, link: NewLink) -> Result<Link, sqlx::Error> {
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO links \
+                (id, url, title, description, user_id, created_at, updated_at, preview) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(id.hyphenated())
+        .bind(&link.url)
+        .bind(&link.title)
+        .bind(&link.description)
+        .bind(link.user_id.hyphenated())
+        .bind(now)
+        .bind(now)
+        .bind(Json(None::<LinkPreview>))
+        .execute(&self.pool)
+        .await?;
+
+        self.find(id).await?.ok_or(sqlx::Error::RowNotFound)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM links WHERE id = ?")
+            .bind(id.hyphenated())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn increment_clicks(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE links SET click_count = click_count + 1 WHERE id = ?")
+            .bind(id.hyphenated())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_preview(&self, id: Uuid, preview: &LinkPreview) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE links SET preview = ? WHERE id = ?")
+            .bind(Json(preview))
+            .bind(id.hyphenated())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ApiTokenRepository for SqliteRepository {
+    async fn create(&self, token: NewApiToken) -> Result<ApiToken, sqlx::Error> {
+        let scopes: Vec<&str> = token.scopes.iter().map(TokenScope::as_str).collect();
+        let row = sqlx::query(&format!(
+            "INSERT INTO api_tokens \
+                (id, user_id, name, token_prefix, token_hash, scopes, expires_at, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?) \
+             RETURNING {TOKEN_COLUMNS}"
+        ))
+        .bind(Uuid::new_v4().hyphenated())
+        .bind(token.user_id.hyphenated())
+        .bind(&token.name)
+        .bind(&token.token_prefix)
+        .bind(&token.token_hash)
+        .bind(Json(scopes))
+        .bind(token.expires_at)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+
+        token_from_row(&row)
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<ApiToken>, sqlx::Error> {
+        sqlx::query(&format!(
+            "SELECT {TOKEN_COLUMNS} FROM api_tokens WHERE user_id = ? ORDER BY created_at DESC"
+        ))
+        .bind(user_id.hyphenated())
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(token_from_row)
+        .collect()
+    }
+
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<ApiTokenOwner>, sqlx::Error> {
+        sqlx::query(
+            "SELECT \
+                t.id, t.user_id, t.name, t.token_prefix, t.scopes, \
+                t.expires_at, t.last_used_at, t.created_at, \
+                u.email, u.username, u.role \
+             FROM api_tokens t \
+             JOIN users u ON u.id = t.user_id \
+             WHERE t.token_hash = ? \
+               AND (t.expires_at IS NULL OR t.expires_at > ?) \
+               AND u.status = 'active'",
+        )
+        .bind(token_hash)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| {
+            Ok(ApiTokenOwner {
+                token: token_from_row(&row)?,
+                email: row.try_get("email")?,
+                username: row.try_get("username")?,
+                role: decode(&row, "role", role_from_str)?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn touch(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = ? WHERE id = ?")
+            .bind(Utc::now())
+            .bind(id.hyphenated())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = ? AND user_id = ?")
+            .bind(id.hyphenated())
+            .bind(user_id.hyphenated())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for SqliteRepository {
+    async fn enqueue(&self, email: NewEmail) -> Result<Uuid, sqlx::Error> {
+        insert_email(&self.pool, &email).await
+    }
+
+    async fn claim_due(
+        &self,
+        limit: i64,
+        lease_seconds: i64,
+    ) -> Result<Vec<QueuedEmail>, sqlx::Error> {
+        // Writes are serialized by SQLite, so no other worker can claim the same rows
+        let now = Utc::now();
+        let rows = sqlx::query(
+            "UPDATE email_outbox \
+             SET attempts = attempts + 1, next_attempt_at = ?, updated_at = ? \
+             WHERE id IN ( \
+                SELECT id FROM email_outbox \
+                WHERE status = 'pending' AND next_attempt_at <= ? \
+                ORDER BY next_attempt_at \
+                LIMIT ? \
+             ) \
+             RETURNING id, recipient, template, locale, variables, attempts",
+        )
+        .bind(now + Duration::seconds(lease_seconds))
+        .bind(now)
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let variables: Json<HashMap<String, String>> = row.try_get("variables")?;
+                Ok(QueuedEmail {
+                    id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+                    recipient: row.try_get("recipient")?,
+                    template: row.try_get("template")?,
+                    locale: row.try_get("locale")?,
+                    variables: variables.0,
+                    attempts: row.try_get("attempts")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn mark_sent(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        sqlx::query(
+            "UPDATE email_outbox \
+             SET status = 'sent', variables = '{}', last_error = NULL, sent_at = ?, updated_at = ? \
+             WHERE id = ?",
+        )
+        .bind(now)
+        .bind(now)
+        .bind(id.hyphenated())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: Uuid,
+        error: &str,
+        retry_in_seconds: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        let status = match retry_in_seconds {
+            Some(_) => "pending",
+            None => "dead",
+        };
+        let now = Utc::now();
+        sqlx::query(
+            "UPDATE email_outbox \
+             SET status = ?, last_error = ?, next_attempt_at = ?, updated_at = ? \
+             WHERE id = ?",
+        )
+        .bind(status)
+        .bind(error)
+        .bind(now + Duration::seconds(retry_in_seconds.unwrap_or_default()))
+        .bind(now)
+        .bind(id.hyphenated())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list(&self, status: EmailStatus, limit: i64) -> Result<Vec<OutboxEmail>, sqlx::Error> {
+        sqlx::query(&format!(
+            "SELECT {OUTBOX_COLUMNS} FROM email_outbox \
+             WHERE status = ? ORDER BY created_at DESC LIMIT ?"
+        ))
+        .bind(email_status_str(status))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(outbox_email_from_row)
+        .collect()
+    }
+
+    async fn replay(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+        let result = sqlx::query(
+            "UPDATE email_outbox \
+             SET status = 'pending', attempts = 0, next_attempt_at = ?, updated_at = ? \
+             WHERE id = ? AND status = 'dead'",
+        )
+        .bind(now)
+        .bind(now)
+        .bind(id.hyphenated())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl InviteRepository for SqliteRepository {
+    async fn create(&self, invite: NewInvite) -> Result<InviteCode, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "INSERT INTO invite_codes \
+                (id, code_prefix, code_hash, note, max_uses, expires_at, created_by, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?) \
+             RETURNING {INVITE_COLUMNS}"
+        ))
+        .bind(Uuid::new_v4().hyphenated())
+        .bind(&invite.code_prefix)
+        .bind(&invite.code_hash)
+        .bind(&invite.note)
+        .bind(invite.max_uses)
+        .bind(invite.expires_at)
+        .bind(invite.created_by.hyphenated())
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+        invite_from_row(&row)
+    }
+
+    async fn list(&self) -> Result<Vec<InviteCode>, sqlx::Error> {
+        sqlx::query(&format!(
+            "SELECT {INVITE_COLUMNS} FROM invite_codes ORDER BY created_at DESC"
+        ))
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(invite_from_row)
+        .collect()
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM invite_codes WHERE id = ?")
+            .bind(id.hyphenated())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl AuditRepository for SqliteRepository {
+    async fn record(
+        &self,
+        admin_id: Uuid,
+        action: AuditAction,
+        target_user_id: Option<Uuid>,
+        details: JsonValue,
+    ) -> Result<(), sqlx::Error> {
+        insert_audit_entry(
+            &self.pool,
+            target_user_id,
+            AdminAction {
+                admin_id,
+                action,
+                details,
+            },
+        )
+        .await
+    }
+
+    async fn list(
+        &self,
+        target_user_id: Option<Uuid>,
+        admin_id: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<AuditLogEntry>, i64), sqlx::Error> {
+        let target_user_id = target_user_id.map(Uuid::hyphenated);
+        let admin_id = admin_id.map(Uuid::hyphenated);
+        let entries = sqlx::query(&format!(
+            "SELECT id, admin_id, action, target_user_id, details, created_at \
+             FROM admin_audit_log {AUDIT_FILTER} \
+             ORDER BY created_at DESC, id LIMIT ?3 OFFSET ?4"
+        ))
+        .bind(target_user_id)
+        .bind(admin_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(audit_entry_from_row)
+        .collect::<Result<_, _>>()?;
+
+        let total = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM admin_audit_log {AUDIT_FILTER}"
+        ))
+        .bind(target_user_id)
+        .bind(admin_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((entries, total))
+    }
+}
+
+/// Marks an account's email as verified, activating it if it was waiting for verification
+async fn mark_verified<'e>(executor: impl SqliteExecutor<'e>, id: Uuid) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query(
+        "UPDATE users SET \
+            is_verified = TRUE, \
+            verified_at = COALESCE(verified_at, ?), \
+            status = CASE WHEN status = 'pending_verification' THEN 'active' ELSE status END, \
+            updated_at = ? \
+         WHERE id = ?",
+    )
+    .bind(now)
+    .bind(now)
+    .bind(id.hyphenated())
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+async fn insert_audit_entry<'e>(
+    executor: impl SqliteExecutor<'e>,
+    target_user_id: Option<Uuid>,
+    action: AdminAction,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO admin_audit_log \
+            (id, admin_id, action, target_user_id, details, created_at) \
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4().hyphenated())
+    .bind(action.admin_id.hyphenated())
+    .bind(action.action.as_str())
+    .bind(target_user_id.map(Uuid::hyphenated))
+    .bind(Json(&action.details))
+    .bind(Utc::now())
+    .execute(executor)
+    .await?;
+
+    tracing::info!(
+        admin_id = %action.admin_id,
+        action = %action.action,
+        target_user_id = ?target_user_id,
+        "Recorded admin action"
+    );
+    Ok(())
+}
+
+async fn insert_user<'e>(
+    executor: impl SqliteExecutor<'e>,
+    user: &NewUser,
+) -> Result<User, sqlx::Error> {
+    let now = Utc::now();
+    let row = sqlx::query(&format!(
+        "INSERT INTO users ( \
+            id, email, username, password_hash, gender, status, is_verified, \
+            verification_attempts, locale, verified_at, created_at, updated_at \
+         ) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, 0, ?, ?, ?, ?) \
+         RETURNING {USER_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4().hyphenated())
+    .bind(&user.email)
+    .bind(&user.username)
+    .bind(&user.password_hash)
+    .bind(gender_str(&user.gender))
+    .bind(status_str(&user.status))
+    .bind(user.is_verified)
+    .bind(&user.locale)
+    .bind(user.is_verified.then_some(now))
+    .bind(now)
+    .bind(now)
+    .fetch_one(executor)
+    .await?;
+
+    user_from_row(&row)
+}
+
+async fn insert_email<'e>(
+    executor: impl SqliteExecutor<'e>,
+    email: &NewEmail,
+) -> Result<Uuid, sqlx::Error> {
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    sqlx::query(
+        "INSERT INTO email_outbox \
+            (id, recipient, template, locale, variables, next_attempt_at, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(id.hyphenated())
+    .bind(&email.recipient)
+    .bind(&email.template)
+    .bind(&email.locale)
+    .bind(Json(&email.variables))
+    .bind(now)
+    .bind(now)
+    .bind(now)
+    .execute(executor)
+    .await?;
+    Ok(id)
+}
+
+fn user_from_row(row: &SqliteRow) -> Result<User, sqlx::Error> {
+    Ok(User {
+        id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+        email: row.try_get("email")?,
+        username: row.try_get("username")?,
+        password_hash: row.try_get("password_hash")?,
+        gender: decode(row, "gender", gender_from_str)?,
+        status: decode(row, "status", status_from_str)?,
+        role: decode(row, "role", role_from_str)?,
+        locale: row.try_get("locale")?,
+        session_version: row.try_get("session_version")?,
+        is_verified: row.try_get("is_verified")?,
+        verification_attempts: row.try_get("verification_attempts")?,
+        failed_login_attempts: row.try_get("failed_login_attempts")?,
+        locked_until: row.try_get("locked_until")?,
+        verified_at: row.try_get("verified_at")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn link_from_row(row: &SqliteRow) -> Result<Link, sqlx::Error> {
+    let preview: String = row.try_get("preview")?;
+    let username: Option<String> = row.try_get("username")?;
+    Ok(Link {
+        id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+        url: row.try_get("url")?,
+        title: row
+            .try_get::<Option<String>, _>("title")?
+            .unwrap_or_default(),
+        description: row
+            .try_get::<Option<String>, _>("description")?
+            .unwrap_or_default(),
+        user_id: row.try_get::<Hyphenated, _>("user_id")?.into_uuid(),
+        click_count: row.try_get("click_count")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        preview: serde_json::from_str(&preview).ok().flatten(),
+        user: username.map(|username| SimpleUser { username }),
+    })
+}
+
+fn token_from_row(row: &SqliteRow) -> Result<ApiToken, sqlx::Error> {
+    let scopes: Json<Vec<String>> = row.try_get("scopes")?;
+    Ok(ApiToken {
+        id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+        user_id: row.try_get::<Hyphenated, _>("user_id")?.into_uuid(),
+        name: row.try_get("name")?,
+        token_prefix: row.try_get("token_prefix")?,
+        scopes: scopes.0.iter().filter_map(|s| s.parse().ok()).collect(),
+        expires_at: row.try_get("expires_at")?,
+        last_used_at: row.try_get("last_used_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn overview_from_row(row: &SqliteRow) -> Result<UserOverview, sqlx::Error> {
+    Ok(UserOverview {
+        id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+        email: row.try_get("email")?,
+        username: row.try_get("username")?,
+        role: decode(row, "role", role_from_str)?,
+        status: decode(row, "status", status_from_str)?,
+        is_verified: row.try_get("is_verified")?,
+        locale: row.try_get("locale")?,
+        failed_login_attempts: row.try_get("failed_login_attempts")?,
+        locked_until: row.try_get("locked_until")?,
+        link_count: row.try_get("link_count")?,
+        verified_at: row.try_get("verified_at")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn invite_from_row(row: &SqliteRow) -> Result<InviteCode, sqlx::Error> {
+    Ok(InviteCode {
+        id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+        code_prefix: row.try_get("code_prefix")?,
+        note: row.try_get("note")?,
+        max_uses: row.try_get("max_uses")?,
+        uses: row.try_get("uses")?,
+        expires_at: row.try_get("expires_at")?,
+        created_by: row
+            .try_get::<Option<Hyphenated>, _>("created_by")?
+            .map(Hyphenated::into_uuid),
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn outbox_email_from_row(row: &SqliteRow) -> Result<OutboxEmail, sqlx::Error> {
+    Ok(OutboxEmail {
+        id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+        recipient: row.try_get("recipient")?,
+        template: row.try_get("template")?,
+        locale: row.try_get("locale")?,
+        status: decode(row, "status", email_status_from_str)?,
+        attempts: row.try_get("attempts")?,
+        last_error: row.try_get("last_error")?,
+        next_attempt_at: row.try_get("next_attempt_at")?,
+        sent_at: row.try_get("sent_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn audit_entry_from_row(row: &SqliteRow) -> Result<AuditLogEntry, sqlx::Error> {
+    let details: Json<JsonValue> = row.try_get("details")?;
+    Ok(AuditLogEntry {
+        id: row.try_get::<Hyphenated, _>("id")?.into_uuid(),
+        admin_id: row
+            .try_get::<Option<Hyphenated>, _>("admin_id")?
+            .map(Hyphenated::into_uuid),
+        action: row.try_get("action")?,
+        target_user_id: row
+            .try_get::<Option<Hyphenated>, _>("target_user_id")?
+            .map(Hyphenated::into_uuid),
+        details: details.0,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+/// Reads a TEXT column holding one of the values of a Postgres enum
+fn decode<T>(
+    row: &SqliteRow,
+    column: &str,
+    parse: fn(&str) -> Option<T>,
+) -> Result<T, sqlx::Error> {
+    let value: String = row.try_get(column)?;
+    parse(&value).ok_or_else(|| sqlx::Error::ColumnDecode {
+        index: column.to_string(),
+        source: format!("unexpected value '{value}'").into(),
+    })
+}
+
+fn gender_str(gender: &Gender) -> &'static str {
+    match gender {
+        Gender::Male => "male",
+        Gender::Female => "female",
+        Gender::Other => "other",
+    }
+}
+
+fn gender_from_str(value: &str) -> Option<Gender> {
+    match value {
+        "male" => Some(Gender::Male),
+        "female" => Some(Gender::Female),
+        "other" => Some(Gender::Other),
+        _ => None,
+    }
+}
+
+fn status_str(status: &UserStatus) -> &'static str {
+    match status {
+        UserStatus::Active => "active",
+        UserStatus::Inactive => "inactive",
+        UserStatus::Suspended => "suspended",
+        UserStatus::PendingVerification => "pending_verification",
+    }
+}
+
+fn status_from_str(value: &str) -> Option<UserStatus> {
+    match value {
+        "active" => Some(UserStatus::Active),
+        "inactive" => Some(UserStatus::Inactive),
+        "suspended" => Some(UserStatus::Suspended),
+        "pending_verification" => Some(UserStatus::PendingVerification),
+        _ => None,
+    }
+}
+
+fn role_from_str(value: &str) -> Option<UserRole> {
+    match value {
+        "user" => Some(UserRole::User),
+        "moderator" => Some(UserRole::Moderator),
+        "admin" => Some(UserRole::Admin),
+        _ => None,
+    }
+}
+
+fn email_status_str(status: EmailStatus) -> &'static str {
+    match status {
+        EmailStatus::Pending => "pending",
+        EmailStatus::Sent => "sent",
+        EmailStatus::Dead => "dead",
+    }
+}
+
+fn email_status_from_str(value: &str) -> Option<EmailStatus> {
+    match value {
+        "pending" => Some(EmailStatus::Pending),
+        "sent" => Some(EmailStatus::Sent),
+        "dead" => Some(EmailStatus::Dead),
+        _ => None,
+    }
+}
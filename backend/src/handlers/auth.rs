@@ -1,384 +1,348 @@
 use crate::{
-    api::{ApiResponse, ErrorResponse},
+    api::{ApiResponse, AppError},
     auth::routes::AppState,
+    database::repository::Registration,
+    middleware::auth::AuthUser,
     models::auth::{
-        LoginRequest, RegisterRequest, ResendOtpRequest, User, UserStatus, VerifyEmailRequest,
+        ConsumeMagicLinkRequest, LoginRequest, MagicLinkRequest, OidcCallbackRequest,
+        RegisterRequest, ResendOtpRequest, UserStatus, VerifyEmailRequest,
+    },
+    services::{
+        audit::AuditAction,
+        auth::{AuthError, MAGIC_LINK_TTL_MINUTES},
+        email::OtpVerification,
+        email_templates::Locale,
+        oidc::{OidcError, STATE_COOKIE},
+        registration::RegistrationError,
     },
 };
 use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
-    response::IntoResponse,
+    extract::{ConnectInfo, Extension, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect},
     Json,
 };
 use axum_macros::debug_handler;
 use serde::Deserialize;
 use serde_json::json;
+use std::net::SocketAddr;
 use validator::Validate;
 
 /// Register a new user
 pub async fn register(
     State(state): State<AppState>,
     Json(payload): Json<RegisterRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     // Validate the request first - this must be synchronous
-    if let Err(validation_errors) = payload.validate() {
-        let error = ErrorResponse::new(format!("Validation error: {validation_errors}"))
-            .with_code("VALIDATION_ERROR");
-        return (StatusCode::UNPROCESSABLE_ENTITY, Json(error)).into_response();
-    }
+    payload.validate()?;
+    state.registration.check_email(&payload.email)?;
 
     // Check if user exists and get their status - this needs to be synchronous to make the right decision
-    let existing_user = sqlx::query_as!(
-        User,
-        r#"
-        SELECT 
-            id, email, username, password_hash, 
-            gender as "gender: _",
-            status as "status: _",
-            is_verified,
-            verification_attempts,
-            verified_at,
-            created_at, 
-            updated_at
-        FROM users
-        WHERE email = $1 OR username = $2
-        "#,
-        payload.email,
-        payload.username
-    )
-    .fetch_optional(state.auth_service.get_pool())
-    .await;
+    let existing_user = state
+        .auth_service
+        .repositories()
+        .users
+        .find_by_email_or_username(&payload.email, &payload.username)
+        .await?;
 
-    match existing_user {
-        Ok(Some(user)) => {
-            if user.status != UserStatus::PendingVerification {
-                // Only reject if user exists and is not pending verification
-                let error = ErrorResponse::new("User with this email or username already exists")
-                    .with_code("USER_EXISTS");
-                return (StatusCode::CONFLICT, Json(error)).into_response();
-            }
-
-            // For pending verification users, proceed with new OTP
-            let email_clone = user.email.clone();
-            let state_clone = state.clone();
-            tokio::spawn(async move {
-                if let Err(e) = state_clone
-                    .email_service
-                    .initiate_otp_process(&email_clone)
-                    .await
-                {
-                    tracing::error!("Failed to send OTP to existing pending user: {e}");
-                }
-            });
-
-            // Wait 2 seconds for better UX
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-            // Return success for pending verification users
-            let response = ApiResponse::success_with_message(
-                json!({
-                    "id": user.id,
-                    "email": user.email,
-                    "username": user.username,
-                    "status": "pending_verification",
-                    "redirect": "/verify-email"
-                }),
-                "Please enter the verification code sent to your email.",
-            );
-            (StatusCode::OK, Json(response)).into_response()
-        }
-        Ok(None) => {
-            // For new users, start background processing
-            let state_clone = state.clone();
-            let payload_clone = payload.clone();
-            tokio::spawn(async move {
-                match state_clone.auth_service.register(payload_clone).await {
-                    Ok(new_user) => {
-                        if let Err(e) = state_clone
-                            .email_service
-                            .initiate_otp_process(&new_user.email)
-                            .await
-                        {
-                            tracing::error!("Failed to send OTP to new user: {e}");
-                        }
-                    }
-                    Err(e) => tracing::error!("Failed to register new user: {e}"),
-                }
-            });
-
-            // Wait 2 seconds for better UX
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-            // Return success for new users
-            let response = ApiResponse::success_with_message(
-                json!({
-                    "email": payload.email,
-                    "username": payload.username,
-                    "status": "pending_verification",
-                    "redirect": "/verify-email"
-                }),
-                "Please enter the verification code sent to your email.",
-            );
-            (StatusCode::CREATED, Json(response)).into_response()
+    if let Some(user) = existing_user {
+        if user.status != UserStatus::PendingVerification {
+            // Only reject if user exists and is not pending verification
+            return Err(AppError::conflict(
+                "USER_EXISTS",
+                "User with this email or username already exists",
+            ));
         }
-        Err(e) => {
-            let error =
-                ErrorResponse::new(format!("Database error: {e}")).with_code("DATABASE_ERROR");
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+
+        // For pending verification users, send a new OTP
+        if let Err(e) = state
+            .email_service
+            .initiate_otp_process(&user.email, Locale::from_tag(&user.locale))
+            .await
+        {
+            tracing::error!("Failed to send OTP to existing pending user: {e}");
         }
+
+        // Return success for pending verification users
+        let response = ApiResponse::success_with_message(
+            json!({
+                "id": user.id,
+                "email": user.email,
+                "username": user.username,
+                "status": "pending_verification",
+                "redirect": "/verify-email"
+            }),
+            "Please enter the verification code sent to your email.",
+        );
+        return Ok((StatusCode::OK, Json(response)));
+    }
+
+    let invite_hash = state
+        .registration
+        .invite_hash(payload.invite_code.as_deref())?;
+    let verification = state
+        .email_service
+        .issue_otp(&payload.email, payload.locale)
+        .await
+        .map_err(|e| AppError::internal("Failed to queue OTP for new user", e))?;
+    // The user and their OTP email are stored together, so a crash can't leave an account
+    // whose verification code was never sent. The invite is only used up if both are.
+    let registration = state
+        .auth_service
+        .register(payload.clone(), invite_hash.as_deref(), verification)
+        .await?;
+    if let Registration::InvalidInvite = registration {
+        return Err(RegistrationError::InvalidInvite.into());
     }
+    state.email_service.record_email_sent(&payload.email).await;
+
+    // Return success for new users
+    let response = ApiResponse::success_with_message(
+        json!({
+            "email": payload.email,
+            "username": payload.username,
+            "status": "pending_verification",
+            "redirect": "/verify-email"
+        }),
+        "Please enter the verification code sent to your email.",
+    );
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 /// Login user
 pub async fn login(
     State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
-) -> impl IntoResponse {
-    match state
+) -> Result<impl IntoResponse, AppError> {
+    // The same address the rate limiter sees, so clients behind a proxy aren't lumped together
+    let client_ip = state.rate_limiter.client_ip(addr.ip(), &headers);
+    let auth_response = state
         .auth_service
-        .login(&payload.email, &payload.password)
+        .login(&payload.email, &payload.password, client_ip)
+        .await?;
+    let response = ApiResponse::success_with_message(auth_response, "Login successful");
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Email a single-use login link
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    Json(payload): Json<MagicLinkRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+
+    // Only accounts count sends, so a 429 would reveal that the email has one. Respond the
+    // same way for unknown, inactive and exhausted accounts instead.
+    let exhausted = state
+        .email_service
+        .attempts_exhausted(&payload.email)
         .await
-    {
-        Ok(auth_response) => {
-            let response = ApiResponse::success_with_message(auth_response, "Login successful");
-            (StatusCode::OK, Json(response)).into_response()
-        }
-        Err(e) => {
-            let error = ErrorResponse::new(format!("Login failed: {e}")).with_code("LOGIN_ERROR");
-            (StatusCode::UNAUTHORIZED, Json(error)).into_response()
-        }
+        .map_err(|e| AppError::internal("Failed to check email attempts", e))?;
+    if exhausted {
+        tracing::info!(email = %payload.email, "Not sending login link, email attempts exhausted");
+    } else if let Some(link) = state.auth_service.create_magic_link(&payload.email).await? {
+        state
+            .email_service
+            .initiate_magic_link(
+                &payload.email,
+                &link.token,
+                &link.jti,
+                MAGIC_LINK_TTL_MINUTES,
+                link.locale,
+            )
+            .await
+            .map_err(|e| AppError::internal("Failed to send login link", e))?;
     }
+
+    let response = ApiResponse::success_with_message(
+        json!({ "email": payload.email }),
+        "If an account exists for this email, a login link has been sent.",
+    );
+    Ok((StatusCode::OK, Json(response)))
 }
 
-/// Verify email with OTP
-pub async fn verify_email(
+/// Exchange an emailed login link for a session token
+pub async fn consume_magic_link(
     State(state): State<AppState>,
-    Json(payload): Json<VerifyEmailRequest>,
-) -> impl IntoResponse {
-    // Log verification attempt
-    println!("Starting email verification for: {}", payload.email);
-
-    // Validate the request
-    if let Err(validation_errors) = payload.validate() {
-        println!("Validation error: {validation_errors}");
-        let error = ErrorResponse::new(format!("Validation error: {validation_errors}"))
-            .with_code("VALIDATION_ERROR");
-        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
-    }
+    Json(payload): Json<ConsumeMagicLinkRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let claims = state.auth_service.decode_magic_link(&payload.token)?;
 
-    // Verify OTP
-    let otp_valid = state
+    let consumed = state
         .email_service
-        .verify_otp(&payload.email, &payload.otp)
-        .await;
-
-    if !otp_valid {
-        println!("Invalid OTP for email: {}", payload.email);
-        let error = ErrorResponse::new("Invalid or expired OTP").with_code("INVALID_OTP");
-        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        .consume_magic_link(&claims.jti)
+        .await
+        .map_err(|e| AppError::internal("Failed to consume login link", e))?;
+    if !consumed {
+        return Err(AuthError::InvalidMagicLink.into());
     }
 
-    println!("OTP verified successfully for: {}", payload.email);
+    let auth_response = state.auth_service.login_with_magic_link(claims).await?;
+    let response = ApiResponse::success_with_message(auth_response, "Login successful");
+    Ok((StatusCode::OK, Json(response)))
+}
 
-    // Complete verification
-    match state
+/// Start single sign-on by redirecting to the OpenID Connect provider
+pub async fn oidc_login(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let oidc = state.oidc.as_ref().ok_or(OidcError::NotConfigured)?;
+    let request = oidc.authorization_request().await?;
+    Ok((
+        [(header::SET_COOKIE, oidc.state_cookie(&request.state_cookie))],
+        Redirect::to(&request.url),
+    ))
+}
+
+/// Finish single sign-on with the code returned by the OpenID Connect provider.
+/// The request must carry the state cookie set when the login started.
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<OidcCallbackRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let oidc = state.oidc.as_ref().ok_or(OidcError::NotConfigured)?;
+    let state_cookie = cookie(&headers, STATE_COOKIE);
+    let identity = oidc
+        .exchange_code(&payload.code, &payload.state, state_cookie)
+        .await?;
+
+    let auth_response = state
         .auth_service
-        .complete_verification(&payload.email)
+        .login_with_oidc(identity, &state.registration)
+        .await?;
+    let response = ApiResponse::success_with_message(auth_response, "Login successful");
+    Ok((
+        StatusCode::OK,
+        [(header::SET_COOKIE, oidc.expired_state_cookie())],
+        Json(response),
+    ))
+}
+
+/// Value of the cookie called `name` in the request
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Verify email with OTP
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+
+    let verification = state
+        .email_service
+        .verify_otp(&payload.email, &payload.otp)
         .await
-    {
-        Ok(_) => {
-            println!("Email verification completed for: {}", payload.email);
-            let response = ApiResponse::success_with_message(
-                json!({"email": payload.email}),
-                "Email verified successfully. You can now login to your account.",
+        .map_err(|e| AppError::internal("Failed to verify OTP", e))?;
+    match verification {
+        OtpVerification::Valid => {}
+        OtpVerification::Invalid { remaining_attempts } => {
+            tracing::debug!(email = %payload.email, "Invalid OTP");
+            return Err(
+                AppError::bad_request("INVALID_OTP", "Invalid or expired OTP")
+                    .with_details(json!({ "remaining_attempts": remaining_attempts })),
             );
-            (StatusCode::OK, Json(response)).into_response()
         }
-        Err(e) => {
-            println!("Verification failed for {0}: {e}", payload.email);
-            let error = ErrorResponse::new(format!("Verification failed: {e}"))
-                .with_code("VERIFICATION_ERROR");
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        OtpVerification::Expired => {
+            tracing::debug!(email = %payload.email, "No pending OTP");
+            return Err(AppError::bad_request(
+                "INVALID_OTP",
+                "Invalid or expired OTP",
+            ));
+        }
+        OtpVerification::TooManyAttempts => {
+            tracing::info!(email = %payload.email, "Too many incorrect OTPs");
+            return Err(AppError::too_many_requests(
+                "TOO_MANY_OTP_ATTEMPTS",
+                "Too many incorrect codes. Please request a new verification code.",
+            )
+            .with_details(json!({ "resend_otp_endpoint": "/api/auth/resend-otp" })));
         }
     }
+
+    state
+        .auth_service
+        .complete_verification(&payload.email)
+        .await?;
+    tracing::info!(email = %payload.email, "Email verification completed");
+
+    let response = ApiResponse::success_with_message(
+        json!({"email": payload.email}),
+        "Email verified successfully. You can now login to your account.",
+    );
+    Ok((StatusCode::OK, Json(response)))
 }
 
 /// Resend OTP for email verification
 pub async fn resend_otp(
     State(state): State<AppState>,
     Json(payload): Json<ResendOtpRequest>,
-) -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
-    // Validate the request
-    if let Err(validation_errors) = payload.validate() {
-        let response = ApiResponse {
-            success: false,
-            message: format!("Validation error: {validation_errors}"),
-            data: json!({ "code": "VALIDATION_ERROR" }),
-            pagination: None,
-            timestamp: chrono::Utc::now(),
-        };
-        return (StatusCode::UNPROCESSABLE_ENTITY, Json(response));
-    }
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
 
     // Check if user exists and is in pending verification state
-    let user = match sqlx::query_as!(
-        User,
-        r#"
-        SELECT 
-            id, email, username, password_hash, 
-            gender as "gender: _",
-            status as "status: _",
-            is_verified,
-            verification_attempts,
-            verified_at,
-            created_at, 
-            updated_at
-        FROM users
-        WHERE email = $1
-        "#,
-        payload.email
-    )
-    .fetch_optional(state.auth_service.get_pool())
-    .await
-    {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let response = ApiResponse {
-                success: false,
-                message: "User not found".to_string(),
-                data: json!({ "code": "USER_NOT_FOUND" }),
-                pagination: None,
-                timestamp: chrono::Utc::now(),
-            };
-            return (StatusCode::NOT_FOUND, Json(response));
-        }
-        Err(e) => {
-            let response = ApiResponse {
-                success: false,
-                message: format!("Database error: {e}"),
-                data: json!({ "code": "DATABASE_ERROR" }),
-                pagination: None,
-                timestamp: chrono::Utc::now(),
-            };
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
-        }
-    };
+    let user = state
+        .auth_service
+        .repositories()
+        .users
+        .find_by_email(&payload.email)
+        .await?
+        .ok_or_else(|| AppError::not_found("USER_NOT_FOUND", "User not found"))?;
 
     // Check if user is in pending verification state
     if user.status != UserStatus::PendingVerification {
-        let response = ApiResponse {
-            success: false,
-            message: "User is not in pending verification state".to_string(),
-            data: json!({ "code": "INVALID_STATE" }),
-            pagination: None,
-            timestamp: chrono::Utc::now(),
-        };
-        return (StatusCode::BAD_REQUEST, Json(response));
+        return Err(AppError::bad_request(
+            "INVALID_STATE",
+            "User is not in pending verification state",
+        ));
     }
 
     // Send new OTP
-    match state
+    ensure_email_attempts_left(&state, &payload.email).await?;
+    state
         .email_service
-        .initiate_otp_process(&payload.email)
+        .initiate_otp_process(&payload.email, Locale::from_tag(&user.locale))
         .await
-    {
-        Ok(_) => {
-            let response = ApiResponse::success_with_message(
-                json!({"email": payload.email}),
-                "OTP resent successfully",
-            );
-            (StatusCode::OK, Json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse {
-                success: false,
-                message: format!("Failed to send OTP: {e}"),
-                data: json!({ "code": "EMAIL_ERROR" }),
-                pagination: None,
-                timestamp: chrono::Utc::now(),
-            };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
-        }
-    }
+        .map_err(|e| AppError::internal("Failed to send OTP", e))?;
+
+    let response = ApiResponse::success_with_message(
+        json!({"email": payload.email}),
+        "OTP resent successfully",
+    );
+    Ok((StatusCode::OK, Json(response)))
 }
 
 /// Reset OTP attempts counter for an email
 pub async fn reset_otp_attempts(
     State(state): State<AppState>,
     Json(payload): Json<ResendOtpRequest>,
-) -> impl IntoResponse {
-    // Validate the request
-    if let Err(validation_errors) = payload.validate() {
-        let response = ApiResponse {
-            success: false,
-            message: format!("Validation error: {validation_errors}"),
-            data: json!({ "code": "VALIDATION_ERROR" }),
-            pagination: None,
-            timestamp: chrono::Utc::now(),
-        };
-        return (StatusCode::UNPROCESSABLE_ENTITY, Json(response));
-    }
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
 
     // Check if user exists and is in pending verification state
-    let user = match sqlx::query_as!(
-        User,
-        r#"
-        SELECT 
-            id, email, username, password_hash, 
-            gender as "gender: _",
-            status as "status: _",
-            is_verified,
-            verification_attempts,
-            verified_at,
-            created_at, 
-            updated_at
-        FROM users
-        WHERE email = $1
-        "#,
-        payload.email
-    )
-    .fetch_optional(state.auth_service.get_pool())
-    .await
-    {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            let response = ApiResponse {
-                success: false,
-                message: "User not found".to_string(),
-                data: json!({ "code": "USER_NOT_FOUND" }),
-                pagination: None,
-                timestamp: chrono::Utc::now(),
-            };
-            return (StatusCode::NOT_FOUND, Json(response));
-        }
-        Err(e) => {
-            let response = ApiResponse {
-                success: false,
-                message: format!("Database error: {e}"),
-                data: json!({ "code": "DATABASE_ERROR" }),
-                pagination: None,
-                timestamp: chrono::Utc::now(),
-            };
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
-        }
-    };
+    let user = state
+        .auth_service
+        .repositories()
+        .users
+        .find_by_email(&payload.email)
+        .await?
+        .ok_or_else(|| AppError::not_found("USER_NOT_FOUND", "User not found"))?;
 
     // Reset attempts counter with admin privileges
-    if let Err(e) = state
+    state
         .email_service
-        .admin_reset_attempts(&payload.email, "")
+        .admin_reset_attempts(&payload.email)
         .await
-    {
-        let response = ApiResponse {
-            success: false,
-            message: format!("Failed to reset attempts: {e}"),
-            data: json!({ "code": "RESET_ERROR" }),
-            pagination: None,
-            timestamp: chrono::Utc::now(),
-        };
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
-    }
+        .map_err(|e| AppError::internal("Failed to reset OTP attempts", e))?;
 
     let response = ApiResponse::success_with_message(
         json!({
@@ -388,7 +352,7 @@ pub async fn reset_otp_attempts(
         }),
         "OTP attempts reset successfully. You can now request a new OTP.",
     );
-    (StatusCode::OK, Json(response))
+    Ok((StatusCode::OK, Json(response)))
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -397,67 +361,36 @@ pub struct AdminResetOtpRequest {
     pub email: String,
 }
 
-/// Admin-only endpoint to reset OTP attempts for blocked users
+/// Admin-only endpoint to reset OTP attempts for blocked users.
+/// Access is enforced by the `require_role` layer on the admin routes.
 #[debug_handler]
 pub async fn admin_reset_otp_attempts(
     State(state): State<AppState>,
-    headers: HeaderMap,
+    Extension(admin): Extension<AuthUser>,
     Json(payload): Json<AdminResetOtpRequest>,
-) -> impl IntoResponse {
-    // Validate the request
-    if let Err(validation_errors) = payload.validate() {
-        let response = ApiResponse {
-            success: false,
-            message: format!("Validation error: {validation_errors}"),
-            data: json!({ "code": "VALIDATION_ERROR" }),
-            pagination: None,
-            timestamp: chrono::Utc::now(),
-        };
-        return (StatusCode::UNPROCESSABLE_ENTITY, Json(response));
-    }
-
-    // Get admin token from headers
-    let admin_token = match headers.get("X-Admin-Token") {
-        Some(token) => match token.to_str() {
-            Ok(t) => t,
-            Err(_) => {
-                let response = ApiResponse {
-                    success: false,
-                    message: "Invalid admin token format".to_string(),
-                    data: json!({ "code": "INVALID_TOKEN" }),
-                    pagination: None,
-                    timestamp: chrono::Utc::now(),
-                };
-                return (StatusCode::UNAUTHORIZED, Json(response));
-            }
-        },
-        None => {
-            let response = ApiResponse {
-                success: false,
-                message: "Missing admin token".to_string(),
-                data: json!({ "code": "MISSING_TOKEN" }),
-                pagination: None,
-                timestamp: chrono::Utc::now(),
-            };
-            return (StatusCode::UNAUTHORIZED, Json(response));
-        }
-    };
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
 
     // Reset attempts counter with admin privileges
-    if let Err(e) = state
+    state
         .email_service
-        .admin_reset_attempts(&payload.email, admin_token)
+        .admin_reset_attempts(&payload.email)
         .await
-    {
-        let response = ApiResponse {
-            success: false,
-            message: format!("Failed to reset attempts: {e}"),
-            data: json!({ "code": "RESET_ERROR" }),
-            pagination: None,
-            timestamp: chrono::Utc::now(),
-        };
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
-    }
+        .map_err(|e| AppError::internal("Failed to reset OTP attempts", e))?;
+
+    tracing::info!(
+        admin_id = %admin.id,
+        admin_username = %admin.username,
+        email = %payload.email,
+        "Admin reset OTP attempts"
+    );
+    record_admin_action(
+        &state,
+        &admin,
+        AuditAction::ResetOtpAttempts,
+        &payload.email,
+    )
+    .await;
 
     let response = ApiResponse::success_with_message(
         json!({
@@ -466,5 +399,74 @@ pub async fn admin_reset_otp_attempts(
         }),
         "OTP attempts reset successfully by admin. User can now request a new OTP.",
     );
-    (StatusCode::OK, Json(response))
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Admin-only endpoint to unlock an account locked after failed logins.
+/// Access is enforced by the `require_role` layer on the admin routes.
+pub async fn admin_unlock_account(
+    State(state): State<AppState>,
+    Extension(admin): Extension<AuthUser>,
+    Json(payload): Json<AdminResetOtpRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+
+    if !state.auth_service.unlock_account(&payload.email).await? {
+        return Err(AppError::not_found("USER_NOT_FOUND", "User not found"));
+    }
+
+    tracing::info!(
+        admin_id = %admin.id,
+        admin_username = %admin.username,
+        email = %payload.email,
+        "Admin unlocked account"
+    );
+    record_admin_action(&state, &admin, AuditAction::UnlockAccount, &payload.email).await;
+    let response = ApiResponse::success_with_message(
+        json!({ "email": payload.email, "status": "unlocked" }),
+        "Account unlocked successfully.",
+    );
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Rejects the request once an address has used up its emails, which an admin has to reset
+async fn ensure_email_attempts_left(state: &AppState, email: &str) -> Result<(), AppError> {
+    let exhausted = state
+        .email_service
+        .attempts_exhausted(email)
+        .await
+        .map_err(|e| AppError::internal("Failed to check email attempts", e))?;
+    if exhausted {
+        return Err(AppError::too_many_requests(
+            "TOO_MANY_ATTEMPTS",
+            "Maximum email attempts exceeded. Please contact support to unlock your account.",
+        ));
+    }
+    Ok(())
+}
+
+/// Adds an action taken by email address to the audit log. The action itself has already
+/// happened, so a failure to record it is logged rather than returned.
+async fn record_admin_action(state: &AppState, admin: &AuthUser, action: AuditAction, email: &str) {
+    let repositories = state.auth_service.repositories();
+    let result = async {
+        let target = repositories.users.find_id_by_email(email).await?;
+        repositories
+            .audit
+            .record(admin.id, action, target, json!({ "email": email }))
+            .await
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!(admin_id = %admin.id, action = %action, "Failed to record admin action: {e}");
+    }
+}
+
+/// Public keys used to verify LinkSphere tokens, in JWKS format
+pub async fn jwks(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(state.auth_service.jwt_keys().jwks()),
+    )
 }
@@ -1,9 +1,12 @@
 pub mod api;
 pub mod auth;
+pub mod config;
 pub mod database;
 pub mod handlers;
 pub mod logging;
+pub mod metrics;
 pub mod middleware;
 pub mod models;
 pub mod routes;
 pub mod services;
+pub mod shutdown;
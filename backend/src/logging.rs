@@ -1,11 +1,42 @@
+use crate::config::TracingConfig;
+use opentelemetry::{global, trace::TracerProvider};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
 use std::time::Duration;
 use tracing::Level;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{fmt::format::FmtSpan, prelude::*, EnvFilter};
 use uuid::Uuid;
 
-/// Initialize the logging system with custom configuration
-pub fn init_logging() {
+/// Sets up W3C trace context propagation and, when a collector is configured, the provider
+/// that exports spans to it. The provider has to be shut down on exit to flush its spans.
+pub fn init_tracer(
+    config: &TracingConfig,
+) -> Result<Option<SdkTracerProvider>, ExporterBuildError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{endpoint}/v1/traces"))
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+    global::set_tracer_provider(provider.clone());
+    Ok(Some(provider))
+}
+
+/// Initialize the logging system with custom configuration. Spans are also exported
+/// through `tracer_provider` if one is given.
+pub fn init_logging(tracer_provider: Option<&SdkTracerProvider>) {
     // Create a rolling file appender for logs
     let file_appender = RollingFileAppender::new(Rotation::DAILY, "logs", "app.log");
 
@@ -41,11 +72,15 @@ pub fn init_logging() {
         .add_directive("tower_http=debug".parse().unwrap())
         .add_directive("sqlx=warn".parse().unwrap());
 
+    let otel_layer = tracer_provider
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("linksphere")));
+
     // Initialize the subscriber
     tracing_subscriber::registry()
         .with(filter_layer)
         .with(console_layer)
         .with(file_layer)
+        .with(otel_layer)
         .init();
 
     // Log startup message
@@ -1,11 +1,23 @@
 use backend::{
     api::docs::ApiDoc,
     auth::{self},
-    database,
-    logging::init_logging,
-    middleware::{auth::auth, request_logger::request_logger},
+    config::AppConfig,
+    database::Database,
+    logging::{init_logging, init_tracer},
+    metrics::{self, MetricsState},
+    middleware::{
+        auth::{auth, require_role},
+        rate_limit::{RateLimiter, RATE_LIMIT_HEADERS},
+        request_logger::{request_logger, REQUEST_ID_HEADER},
+    },
+    models::auth::UserRole,
     routes,
-    services::auth::AuthService,
+    services::{
+        auth::AuthService, email::EmailService, email_outbox::OutboxWorker, email_sender,
+        jwt::JwtKeys, otp_store, pending_cleanup::PendingAccountCleanup, readiness::Readiness,
+        registration::RegistrationPolicy,
+    },
+    shutdown::Shutdown,
 };
 
 use axum::routing::get;
@@ -15,67 +27,325 @@ use axum::{
     Router,
 };
 use dotenv::dotenv;
-use std::env;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 use tower_http::cors::CorsLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+/// Command line flags
+#[derive(Default)]
+struct Args {
+    /// Config file to read instead of the default one
+    config: Option<PathBuf>,
+    /// Print the effective configuration and exit
+    print_config: bool,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--print-config" => args.print_config = true,
+                "--config" => {
+                    let path = iter.next().ok_or("--config requires a path")?;
+                    args.config = Some(PathBuf::from(path));
+                }
+                other => match other.strip_prefix("--config=") {
+                    Some(path) => args.config = Some(PathBuf::from(path)),
+                    None => {
+                        return Err(format!(
+                            "unknown argument '{other}', expected --config <path> or --print-config"
+                        ))
+                    }
+                },
+            }
+        }
+        Ok(args)
+    }
+}
+
 #[tokio::main]
 async fn main() {
     // Load environment variables
     dotenv().ok();
 
-    // Initialize logging
-    init_logging();
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+
+    if args.print_config {
+        match AppConfig::load(args.config.as_deref()) {
+            Ok(config) => print!("{}", config.to_redacted_toml()),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Settings from the config file and the environment
+    let config = AppConfig::load(args.config.as_deref());
+
+    // Initialize logging, exporting spans if a collector is configured
+    let tracer_provider = match config.as_ref().map(|config| init_tracer(&config.tracing)) {
+        Ok(Ok(provider)) => provider,
+        Ok(Err(e)) => {
+            eprintln!("Failed to set up trace export: {e}");
+            std::process::exit(1);
+        }
+        Err(_) => None,
+    };
+    init_logging(tracer_provider.as_ref());
+
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    // Stop on SIGTERM or Ctrl+C, waiting for requests and background tasks
+    let shutdown = Shutdown::new();
+    tokio::spawn(shutdown.clone().listen_for_signals());
 
     // Database connection
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = database::create_pool(&database_url).await;
+    let database = match Database::connect(config.database.url.expose()).await {
+        Ok(database) => database,
+        Err(e) => {
+            tracing::error!("Failed to connect to the database: {e}");
+            std::process::exit(1);
+        }
+    };
+    tracing::info!(backend = database.kind(), "Connected to the database");
 
     // Run database migrations
-    if let Err(e) = database::run_migrations(&pool).await {
+    if let Err(e) = database.run_migrations().await {
         tracing::error!("Failed to run database migrations: {:?}", e);
         std::process::exit(1);
     }
 
-    // JWT secret
-    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    let auth_service = AuthService::new(pool.clone(), jwt_secret.clone());
-    let frontend_request_url =
-        env::var("FRONTEND_REQUEST_URL").expect("FRONTEND_REQUEST_URL must be set");
+    // Data access for the request handlers
+    let repositories = database.repositories();
+
+    // JWT signing keys
+    let jwt_keys = match JwtKeys::from_config(&config.jwt) {
+        Ok(keys) => keys,
+        Err(e) => {
+            tracing::error!("Failed to load JWT keys: {e}");
+            std::process::exit(1);
+        }
+    };
+    let auth_service = AuthService::new(repositories.clone(), jwt_keys.clone());
+
+    // Email delivery backend
+    let email_sender = match email_sender::from_config(&config.email) {
+        Ok(sender) => sender,
+        Err(e) => {
+            tracing::error!("Failed to initialize email sender: {e}");
+            std::process::exit(1);
+        }
+    };
+    // Deliver queued emails in the background
+    let mut outbox_worker = OutboxWorker::new(repositories.outbox.clone(), email_sender.clone());
+    if let Some(pool) = database.postgres() {
+        outbox_worker = outbox_worker.listen_on(pool.clone());
+    }
+    outbox_worker.spawn(&shutdown);
+
+    // Storage for OTP codes and attempt counters
+    let otp_store =
+        match otp_store::from_config(&config.otp.store, database.postgres().cloned()).await {
+            Ok(store) => store,
+            Err(e) => {
+                tracing::error!("Failed to initialize OTP store: {e}");
+                std::process::exit(1);
+            }
+        };
+    // Expired entries the store doesn't drop by itself are purged in the background
+    otp_store::spawn_purge(otp_store.clone(), &shutdown);
+
+    let email_service = EmailService::new(
+        repositories.outbox.clone(),
+        otp_store.clone(),
+        &config.server.frontend_url,
+        config.otp.hmac_key.as_ref(),
+    );
+
+    // Per route group request limits, in memory or shared through the OTP store
+    let rate_limiter = RateLimiter::from_config(&config.rate_limit, otp_store.clone());
+
+    // Dependency checks for the readiness probe
+    let readiness = Readiness::new(
+        database.clone(),
+        otp_store.clone(),
+        email_sender,
+        shutdown.clone(),
+        &config.health,
+    );
+
+    // Who may register
+    let registration = RegistrationPolicy::from_config(&config.registration);
+
+    // Periodically delete sign-ups that were never verified
+    let pending_cleanup =
+        PendingAccountCleanup::from_config(repositories.users.clone(), &config.pending_cleanup);
+    pending_cleanup.clone().spawn(&shutdown);
+
+    let frontend_origin = match config.server.frontend_url.parse() {
+        Ok(origin) => origin,
+        Err(e) => {
+            tracing::error!("Frontend URL can't be used as a CORS origin: {e}");
+            std::process::exit(1);
+        }
+    };
     let cors = CorsLayer::new()
-        .allow_origin([frontend_request_url.parse().unwrap()])
+        .allow_origin([frontend_origin])
         .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
         .allow_headers([
             HeaderName::from_static("authorization"),
             HeaderName::from_static("content-type"),
+            HeaderName::from_static(REQUEST_ID_HEADER),
+            HeaderName::from_static("traceparent"),
         ])
+        .expose_headers(
+            std::iter::once(HeaderName::from_static(REQUEST_ID_HEADER))
+                .chain(RATE_LIMIT_HEADERS)
+                .collect::<Vec<_>>(),
+        )
+        // The single sign-on callback needs the state cookie
         .allow_credentials(true);
 
+    // Prometheus metrics, served on their own listener or to admins only
+    let mut admin_metrics = None;
+    if config.metrics.enabled {
+        let handle = match metrics::install() {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::error!("Failed to install metrics recorder: {e}");
+                std::process::exit(1);
+            }
+        };
+        let router = metrics::router(MetricsState::new(handle, database.clone()));
+        match config.metrics.listen_addr {
+            Some(addr) => {
+                let listener = match tokio::net::TcpListener::bind(addr).await {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        tracing::error!("Failed to bind metrics listener to {addr}: {e}");
+                        std::process::exit(1);
+                    }
+                };
+                tracing::info!("Metrics listening on {addr}");
+                let stop = shutdown.triggered_owned();
+                shutdown.spawn(async move {
+                    let server = axum::serve(listener, router).with_graceful_shutdown(stop);
+                    if let Err(e) = server.await {
+                        tracing::error!("Metrics server failed: {e}");
+                    }
+                });
+            }
+            None => admin_metrics = Some(router),
+        }
+    }
+
     // Build our application with routes
-    let app = Router::new()
+    let mut app = Router::new()
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .route("/health", get(routes::health::root))
-        .merge(routes::create_ping_router(pool.clone()))
-        .merge(auth::create_router(pool.clone()))
-        .merge(routes::create_protected_router(pool).layer(from_fn_with_state(auth_service, auth)))
+        .merge(routes::create_probe_router(readiness))
+        .merge(auth::create_router(
+            repositories.clone(),
+            jwt_keys,
+            email_service,
+            registration,
+            config.oidc.clone(),
+            otp_store,
+            rate_limiter.clone(),
+        ))
+        .merge(
+            routes::create_protected_router(repositories.clone(), shutdown.clone(), rate_limiter)
+                .layer(from_fn_with_state(auth_service.clone(), auth)),
+        );
+    app = app.merge(
+        routes::create_admin_router(repositories, pending_cleanup)
+            .route_layer(from_fn_with_state(UserRole::Admin, require_role))
+            .route_layer(from_fn_with_state(auth_service.clone(), auth)),
+    );
+    if let Some(router) = admin_metrics {
+        app = app.merge(
+            router
+                .route_layer(from_fn_with_state(UserRole::Admin, require_role))
+                .route_layer(from_fn_with_state(auth_service, auth)),
+        );
+    }
+    let app = app
         .layer(cors)
+        .layer(from_fn(metrics::track_requests))
         .layer(from_fn(request_logger));
 
     // Run it
-    let port = env::var("PORT")
-        .expect("PORT must be set")
-        .parse::<u16>()
-        .expect("PORT must be a valid number");
-    let host = env::var("HOST").expect("HOST must be set");
-    let addr: SocketAddr = format!("{host}:{port}")
+    let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port)
         .parse()
-        .expect("Invalid HOST:PORT combination");
+        .expect("Server address is validated when the config is loaded");
     let listener = tokio::net::TcpListener::bind(addr)
         .await
         .expect("Failed to bind to address");
     tracing::info!("Server listening on {addr}");
 
-    axum::serve(listener, app).await.expect("Server failed");
+    let mut server = tokio::spawn(
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown.triggered_owned())
+        .into_future(),
+    );
+
+    // Once shutdown starts, in-flight requests and background tasks share one deadline
+    let drain_timeout = Duration::from_secs(config.server.drain_timeout_secs);
+    let deadline = tokio::select! {
+        result = &mut server => {
+            if let Ok(Err(e)) = result {
+                tracing::error!("Server failed: {e}");
+            }
+            shutdown.trigger();
+            tokio::time::Instant::now() + drain_timeout
+        }
+        _ = shutdown.triggered() => {
+            tracing::info!("Shutting down, waiting up to {drain_timeout:?} for requests and background tasks");
+            let deadline = tokio::time::Instant::now() + drain_timeout;
+            if tokio::time::timeout_at(deadline, &mut server).await.is_err() {
+                tracing::warn!("Aborting requests still running at the drain deadline");
+                server.abort();
+            }
+            deadline
+        }
+    };
+    let remaining = shutdown.drain(deadline).await;
+    if remaining > 0 {
+        tracing::warn!(
+            "{remaining} background tasks were still running at the drain deadline; \
+             unsent emails stay queued and links may be left without a preview"
+        );
+    }
+
+    database.close().await;
+    tracing::info!("Shutdown complete");
+
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!("Failed to flush exported spans: {e}");
+        }
+    }
 }
@@ -0,0 +1,188 @@
+//! Prometheus metrics.
+//!
+//! Metrics are recorded through the `metrics` facade where things happen and rendered in the
+//! Prometheus text format at `/metrics`. Names and labels are defined here so dashboards have
+//! one place to look. Until [`install`] is called, recording is a no-op.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::{Database as SqlxDatabase, Pool};
+use std::time::{Duration, Instant};
+
+use crate::database::Database;
+
+const HTTP_REQUESTS: &str = "http_requests_total";
+const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+const DB_POOL_CONNECTIONS: &str = "db_pool_connections";
+const DB_POOL_IDLE: &str = "db_pool_idle_connections";
+const DB_POOL_MAX: &str = "db_pool_max_connections";
+const DB_POOL_ACQUIRE_WAIT: &str = "db_pool_acquire_wait_seconds";
+const PREVIEW_FETCHES: &str = "link_preview_fetches_total";
+const PREVIEW_FETCH_DURATION: &str = "link_preview_fetch_duration_seconds";
+const EMAIL_DELIVERIES: &str = "email_deliveries_total";
+const OTP_VERIFICATIONS: &str = "otp_verifications_total";
+const RATE_LIMITED: &str = "rate_limited_requests_total";
+
+/// Histogram buckets for durations, from 5ms to 10s
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+/// How often histogram samples are folded into their buckets between scrapes
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+/// Route label of requests that didn't match a route, so scanners can't create new series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Installs the global recorder and returns the handle `/metrics` renders from.
+/// Must be called from within the Tokio runtime.
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Suffix("_duration_seconds".into()),
+            DURATION_BUCKETS,
+        )?
+        .install_recorder()?;
+    describe();
+
+    let upkeep = handle.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            upkeep.run_upkeep();
+        }
+    });
+
+    Ok(handle)
+}
+
+fn describe() {
+    describe_counter!(HTTP_REQUESTS, "HTTP requests by method, route and status");
+    describe_histogram!(
+        HTTP_REQUEST_DURATION,
+        metrics::Unit::Seconds,
+        "Time to produce a response, by method, route and status"
+    );
+    describe_gauge!(DB_POOL_CONNECTIONS, "Open database connections");
+    describe_gauge!(DB_POOL_IDLE, "Idle database connections");
+    describe_gauge!(DB_POOL_MAX, "Maximum database connections");
+    describe_gauge!(
+        DB_POOL_ACQUIRE_WAIT,
+        metrics::Unit::Seconds,
+        "Time the last scrape waited for a database connection"
+    );
+    describe_counter!(
+        PREVIEW_FETCHES,
+        "Link preview fetches by extractor and result"
+    );
+    describe_histogram!(
+        PREVIEW_FETCH_DURATION,
+        metrics::Unit::Seconds,
+        "Time to fetch a link preview, by extractor"
+    );
+    describe_counter!(
+        EMAIL_DELIVERIES,
+        "Outbox delivery attempts by template and result"
+    );
+    describe_counter!(OTP_VERIFICATIONS, "Submitted OTPs by outcome");
+    describe_counter!(
+        RATE_LIMITED,
+        "Requests rejected by the rate limiter, by route group"
+    );
+}
+
+/// Counts requests and their latency. The route is the matched template, e.g.
+/// `/api/links/{id}`, so IDs don't create new series.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE, MatchedPath::as_str)
+        .to_string();
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    counter!(HTTP_REQUESTS, &labels).increment(1);
+    histogram!(HTTP_REQUEST_DURATION, &labels).record(start.elapsed());
+    response
+}
+
+/// Records the outcome of a link preview fetch
+pub fn record_preview_fetch(extractor: &'static str, success: bool, duration: Duration) {
+    let result = if success { "success" } else { "failure" };
+    counter!(PREVIEW_FETCHES, "extractor" => extractor, "result" => result).increment(1);
+    histogram!(PREVIEW_FETCH_DURATION, "extractor" => extractor).record(duration);
+}
+
+/// Records a delivery attempt of an outbox email. `result` is `sent`, `retrying` or `dead`.
+pub fn record_email_delivery(template: &str, result: &'static str) {
+    counter!(EMAIL_DELIVERIES, "template" => template.to_string(), "result" => result).increment(1);
+}
+
+/// Records the outcome of checking a submitted OTP
+pub fn record_otp_verification(outcome: &'static str) {
+    counter!(OTP_VERIFICATIONS, "outcome" => outcome).increment(1);
+}
+
+/// Records a request rejected by the rate limiter
+pub fn record_rate_limited(group: &'static str) {
+    counter!(RATE_LIMITED, "group" => group).increment(1);
+}
+
+#[derive(Clone)]
+pub struct MetricsState {
+    handle: PrometheusHandle,
+    database: Database,
+}
+
+impl MetricsState {
+    pub fn new(handle: PrometheusHandle, database: Database) -> Self {
+        Self { handle, database }
+    }
+}
+
+/// `/metrics`, without any access control of its own
+pub fn router(state: MetricsState) -> Router {
+    Router::new()
+        .route("/metrics", get(render))
+        .with_state(state)
+}
+
+async fn render(State(state): State<MetricsState>) -> impl IntoResponse {
+    match &state.database {
+        Database::Postgres(pool) => record_pool_stats(pool).await,
+        #[cfg(feature = "sqlite")]
+        Database::Sqlite(pool) => record_pool_stats(pool).await,
+    }
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.handle.render(),
+    )
+}
+
+/// Pool gauges are sampled on every scrape rather than tracked as connections move
+async fn record_pool_stats<DB: SqlxDatabase>(pool: &Pool<DB>) {
+    gauge!(DB_POOL_CONNECTIONS).set(pool.size());
+    gauge!(DB_POOL_IDLE).set(pool.num_idle() as f64);
+    gauge!(DB_POOL_MAX).set(pool.options().get_max_connections());
+
+    let start = Instant::now();
+    match pool.acquire().await {
+        Ok(_connection) => gauge!(DB_POOL_ACQUIRE_WAIT).set(start.elapsed()),
+        Err(e) => tracing::warn!("Failed to acquire a database connection for metrics: {e}"),
+    }
+}
@@ -1,20 +1,44 @@
-use axum::{
-    body::Body,
-    extract::State,
-    http::{Request, StatusCode},
-    middleware::Next,
-    response::Response,
-};
-use jsonwebtoken::{decode, DecodingKey, Validation};
+use axum::{body::Body, extract::State, http::Request, middleware::Next, response::Response};
 use uuid::Uuid;
 
-use crate::{api::ErrorResponse, models::auth::Claims, services::auth::AuthService};
+use crate::{
+    api::AppError,
+    models::auth::{Claims, TokenScope, UserRole, UserStatus},
+    services::{
+        api_token::{hash_api_token, is_api_token},
+        auth::AuthService,
+    },
+};
 
 #[derive(Clone, Debug)]
 pub struct AuthUser {
     pub id: Uuid,
     pub email: String,
     pub username: String,
+    pub role: UserRole,
+    /// Scopes of the personal access token used for the request.
+    /// `None` when the request was authenticated with a session JWT.
+    pub scopes: Option<Vec<TokenScope>>,
+}
+
+impl AuthUser {
+    /// Returns true if the credential used for the request grants `scope`
+    pub fn has_scope(&self, scope: TokenScope) -> bool {
+        match &self.scopes {
+            Some(scopes) => scopes.contains(&scope),
+            None => true,
+        }
+    }
+
+    /// Returns true if the user's role is at least `role`
+    pub fn has_role(&self, role: UserRole) -> bool {
+        self.role >= role
+    }
+
+    /// Returns true if the request was authenticated with a personal access token
+    pub fn is_api_token(&self) -> bool {
+        self.scopes.is_some()
+    }
 }
 
 impl From<Claims> for AuthUser {
@@ -23,6 +47,8 @@ impl From<Claims> for AuthUser {
             id: claims.sub,
             email: claims.email,
             username: claims.username,
+            role: claims.role,
+            scopes: None,
         }
     }
 }
@@ -31,7 +57,7 @@ pub async fn auth(
     State(auth_service): State<AuthService>,
     mut request: Request<Body>,
     next: Next,
-) -> Result<Response, (StatusCode, ErrorResponse)> {
+) -> Result<Response, AppError> {
     // Get the token from the Authorization header
     let token = request
         .headers()
@@ -39,26 +65,136 @@ pub async fn auth(
         .and_then(|auth_header| auth_header.to_str().ok())
         .and_then(|auth_str| auth_str.strip_prefix("Bearer "))
         .ok_or_else(|| {
-            let error = ErrorResponse::new("Missing or invalid authorization header")
-                .with_code("UNAUTHORIZED");
-            (StatusCode::UNAUTHORIZED, error)
+            AppError::unauthorized("UNAUTHORIZED", "Missing or invalid authorization header")
         })?;
 
-    // Validate the token
-    let jwt_secret = auth_service.get_jwt_secret();
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(jwt_secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|e| {
-        let error = ErrorResponse::new(format!("Invalid token: {e}")).with_code("INVALID_TOKEN");
-        (StatusCode::UNAUTHORIZED, error)
-    })?;
-
-    // Add the claims to the request extensions
-    let auth_user = AuthUser::from(token_data.claims);
+    let auth_user = if is_api_token(token) {
+        authenticate_api_token(&auth_service, token).await?
+    } else {
+        // Validate the token
+        let token_data = auth_service
+            .jwt_keys()
+            .verify::<Claims>(token)
+            .map_err(|e| {
+                tracing::debug!("Rejected session token: {e}");
+                AppError::unauthorized("INVALID_TOKEN", "Invalid or expired token")
+            })?;
+
+        ensure_session_active(&auth_service, &token_data.claims).await?;
+        AuthUser::from(token_data.claims)
+    };
+
+    // Add the authenticated user to the request extensions
     request.extensions_mut().insert(auth_user);
 
     Ok(next.run(request).await)
 }
+
+/// Rejects login tokens of users who are no longer active or whose sessions were ended
+/// after the token was issued, e.g. because an admin suspended the account
+async fn ensure_session_active(
+    auth_service: &AuthService,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    let state = auth_service
+        .repositories()
+        .users
+        .session_state(claims.sub)
+        .await
+        .map_err(|e| AppError::internal("Failed to look up session state", e))?;
+
+    match state {
+        Some((UserStatus::Active, version)) if version == claims.session_version => Ok(()),
+        _ => Err(AppError::unauthorized(
+            "SESSION_REVOKED",
+            "Session has ended, please log in again",
+        )),
+    }
+}
+
+async fn authenticate_api_token(
+    auth_service: &AuthService,
+    token: &str,
+) -> Result<AuthUser, AppError> {
+    let api_tokens = &auth_service.repositories().api_tokens;
+    let owner = api_tokens
+        .find_by_hash(&hash_api_token(token))
+        .await
+        .map_err(|e| AppError::internal("Failed to look up API token", e))?
+        .ok_or_else(|| AppError::unauthorized("INVALID_TOKEN", "Invalid or expired API token"))?;
+
+    if let Err(e) = api_tokens.touch(owner.token.id).await {
+        tracing::warn!("Failed to record API token usage: {e}");
+    }
+
+    Ok(AuthUser {
+        id: owner.token.user_id,
+        email: owner.email,
+        username: owner.username,
+        role: owner.role,
+        scopes: Some(owner.token.scopes),
+    })
+}
+
+/// Rejects requests whose credential does not grant the scope given as state.
+/// Must run after [`auth`].
+pub async fn require_scope(
+    State(scope): State<TokenScope>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let allowed = request
+        .extensions()
+        .get::<AuthUser>()
+        .is_some_and(|user| user.has_scope(scope));
+
+    if !allowed {
+        return Err(AppError::forbidden(
+            "INSUFFICIENT_SCOPE",
+            format!("Token is missing the required scope: {scope}"),
+        ));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Rejects requests authenticated with a personal access token rather than a
+/// login session. Must run after [`auth`].
+pub async fn require_session(request: Request<Body>, next: Next) -> Result<Response, AppError> {
+    let is_session = request
+        .extensions()
+        .get::<AuthUser>()
+        .is_some_and(|user| !user.is_api_token());
+
+    if !is_session {
+        return Err(AppError::forbidden(
+            "SESSION_REQUIRED",
+            "This endpoint requires a login session",
+        ));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Rejects requests from users below the role given as state. Personal access
+/// tokens never carry elevated privileges, so they are rejected as well.
+/// Must run after [`auth`].
+pub async fn require_role(
+    State(role): State<UserRole>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let allowed = request
+        .extensions()
+        .get::<AuthUser>()
+        .is_some_and(|user| !user.is_api_token() && user.has_role(role));
+
+    if !allowed {
+        return Err(AppError::forbidden(
+            "FORBIDDEN",
+            format!("This endpoint requires the {role} role"),
+        ));
+    }
+
+    Ok(next.run(request).await)
+}
@@ -1,2 +1,3 @@
 pub mod auth;
+pub mod rate_limit;
 pub mod request_logger;
@@ -0,0 +1,347 @@
+//! Token bucket rate limiting.
+//!
+//! Each route group has its own policy, e.g. `10/min`: a bucket of 10 requests that refills
+//! evenly over a minute. Unauthenticated groups are limited per client IP, authenticated ones
+//! per user. Buckets live in memory or in the OTP store, which shares them between instances.
+//! If the store fails, requests are let through rather than locking everyone out.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    api::AppError,
+    config::RateLimitConfig,
+    metrics,
+    middleware::auth::AuthUser,
+    services::otp_store::{MemoryOtpStore, OtpStore, TokenBucket, TokenBucketState},
+};
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+const RATELIMIT_POLICY: HeaderName = HeaderName::from_static("ratelimit-policy");
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Headers set on rate limited responses, for CORS to expose
+pub const RATE_LIMIT_HEADERS: [HeaderName; 5] = [
+    RATELIMIT_LIMIT,
+    RATELIMIT_REMAINING,
+    RATELIMIT_RESET,
+    RATELIMIT_POLICY,
+    header::RETRY_AFTER,
+];
+
+/// `requests` per `period`, written as e.g. `10/min`, `100/15min` or `5/h`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RatePolicy {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl RatePolicy {
+    pub const fn per_minute(requests: u32) -> Self {
+        Self {
+            requests,
+            period: Duration::from_secs(60),
+        }
+    }
+
+    fn bucket(&self) -> TokenBucket {
+        let interval_ms = (self.period.as_millis() as i64 / i64::from(self.requests)).max(1);
+        TokenBucket {
+            interval_ms,
+            capacity_ms: interval_ms * i64::from(self.requests),
+        }
+    }
+}
+
+impl FromStr for RatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const FORMAT: &str = "expected <requests>/<period>, e.g. 10/min or 100/15min";
+
+        let (requests, period) = s.split_once('/').ok_or(FORMAT)?;
+        let requests: u32 = requests.trim().parse().map_err(|_| FORMAT)?;
+        if requests == 0 {
+            return Err("must allow at least one request".to_string());
+        }
+
+        let period = period.trim();
+        let split = period.find(|c: char| !c.is_ascii_digit()).ok_or(FORMAT)?;
+        let (count, unit) = period.split_at(split);
+        let count: u64 = match count {
+            "" => 1,
+            count => count.parse().map_err(|_| FORMAT)?,
+        };
+        let unit_secs = match unit.to_ascii_lowercase().as_str() {
+            "s" | "sec" | "second" => 1,
+            "m" | "min" | "minute" => 60,
+            "h" | "hour" => 60 * 60,
+            _ => return Err(FORMAT.to_string()),
+        };
+        if count == 0 {
+            return Err("period must be longer than zero".to_string());
+        }
+
+        Ok(Self {
+            requests,
+            period: Duration::from_secs(count * unit_secs),
+        })
+    }
+}
+
+impl fmt::Display for RatePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.period.as_secs();
+        let (count, unit) = if secs.is_multiple_of(3600) {
+            (secs / 3600, "h")
+        } else if secs.is_multiple_of(60) {
+            (secs / 60, "min")
+        } else {
+            (secs, "s")
+        };
+        match count {
+            1 => write!(f, "{}/{unit}", self.requests),
+            count => write!(f, "{}/{count}{unit}", self.requests),
+        }
+    }
+}
+
+impl Serialize for RatePolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Where buckets are kept, selected with `rate_limit.store`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitStoreKind {
+    /// Per instance, so each instance allows the full rate
+    #[default]
+    Memory,
+    /// The OTP store, shared by all instances
+    Shared,
+}
+
+impl FromStr for RateLimitStoreKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "memory" => Ok(Self::Memory),
+            "shared" => Ok(Self::Shared),
+            _ => Err("expected memory or shared".to_string()),
+        }
+    }
+}
+
+/// Routes that share a policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    /// Credential checks: login, OTP verification, magic link and SSO sign-in. Per IP.
+    Auth,
+    /// Requests that send an email: registration, OTP resend, magic link requests. Per IP.
+    Email,
+    /// Link creation. Per user.
+    Links,
+}
+
+impl RouteGroup {
+    fn name(self) -> &'static str {
+        match self {
+            RouteGroup::Auth => "auth",
+            RouteGroup::Email => "email",
+            RouteGroup::Links => "links",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: Arc<dyn OtpStore>,
+    config: Arc<RateLimitConfig>,
+}
+
+impl RateLimiter {
+    /// `shared_store` is used when `rate_limit.store` is `shared`
+    pub fn from_config(config: &RateLimitConfig, shared_store: Arc<dyn OtpStore>) -> Self {
+        let store: Arc<dyn OtpStore> = match config.store {
+            RateLimitStoreKind::Memory => Arc::new(MemoryOtpStore::new()),
+            RateLimitStoreKind::Shared => shared_store,
+        };
+        tracing::info!(
+            enabled = config.enabled,
+            store = ?config.store,
+            trusted_proxies = config.trusted_proxies.len(),
+            "Rate limiter initialized"
+        );
+
+        Self {
+            store,
+            config: Arc::new(config.clone()),
+        }
+    }
+
+    /// Middleware state limiting the routes of `group`
+    pub fn group(&self, group: RouteGroup) -> GroupLimiter {
+        GroupLimiter {
+            limiter: self.clone(),
+            group,
+        }
+    }
+
+    fn policy(&self, group: RouteGroup) -> RatePolicy {
+        match group {
+            RouteGroup::Auth => self.config.auth,
+            RouteGroup::Email => self.config.email,
+            RouteGroup::Links => self.config.links,
+        }
+    }
+
+    /// The client's address for a request from `peer`. `X-Forwarded-For` is only believed
+    /// when the peer is a trusted proxy; it is then read from the right, and the first hop
+    /// that isn't a trusted proxy is the client.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let trusted = |ip: &IpAddr| {
+            self.config
+                .trusted_proxies
+                .iter()
+                .any(|net| net.contains(ip))
+        };
+        if !trusted(&peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+        let hops = headers
+            .get_all(FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        for hop in hops.into_iter().rev() {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !trusted(&ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+#[derive(Clone)]
+pub struct GroupLimiter {
+    limiter: RateLimiter,
+    group: RouteGroup,
+}
+
+/// Takes a token for the request, or rejects it with 429 when the bucket is empty
+pub async fn rate_limit(
+    State(GroupLimiter { limiter, group }): State<GroupLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !limiter.config.enabled {
+        return next.run(request).await;
+    }
+
+    let client = match (group, request.extensions().get::<AuthUser>()) {
+        (RouteGroup::Links, Some(user)) => format!("user:{}", user.id),
+        _ => match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(peer)) => {
+                format!("ip:{}", limiter.client_ip(peer.ip(), request.headers()))
+            }
+            None => "ip:unknown".to_string(),
+        },
+    };
+    let key = format!("ratelimit:{}:{client}", group.name());
+
+    let policy = limiter.policy(group);
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let state = match limiter
+        .store
+        .take_token(&key, policy.bucket(), now_ms)
+        .await
+    {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::warn!(
+                group = group.name(),
+                "Rate limit check failed, allowing request: {e}"
+            );
+            return next.run(request).await;
+        }
+    };
+    let headers = rate_limit_headers(policy, state, now_ms);
+
+    if !state.allowed {
+        metrics::record_rate_limited(group.name());
+        let retry_after = headers
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        let error = AppError::too_many_requests(
+            "RATE_LIMITED",
+            "Too many requests. Please try again later.",
+        )
+        .with_details(json!({ "retry_after_secs": retry_after }));
+        return (headers, error).into_response();
+    }
+
+    let mut response = next.run(request).await;
+    response.headers_mut().extend(headers);
+    response
+}
+
+fn rate_limit_headers(policy: RatePolicy, state: TokenBucketState, now_ms: i64) -> HeaderMap {
+    let bucket = policy.bucket();
+    let used_ms = (state.full_at_ms - now_ms).max(0);
+    let remaining = if state.allowed {
+        (bucket.capacity_ms - used_ms) / bucket.interval_ms
+    } else {
+        0
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(policy.requests));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(remaining));
+    headers.insert(RATELIMIT_RESET, HeaderValue::from(ceil_secs(used_ms)));
+    if let Ok(value) = HeaderValue::from_str(&format!(
+        "{};w={}",
+        policy.requests,
+        policy.period.as_secs()
+    )) {
+        headers.insert(RATELIMIT_POLICY, value);
+    }
+    if !state.allowed {
+        // The next token is due once the bucket has room for it again
+        let wait_ms = used_ms + bucket.interval_ms - bucket.capacity_ms;
+        headers.insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(ceil_secs(wait_ms).max(1)),
+        );
+    }
+    headers
+}
+
+fn ceil_secs(ms: i64) -> i64 {
+    (ms.max(0) + 999) / 1000
+}
@@ -1,31 +1,110 @@
 use crate::logging::{generate_request_id, log_request};
 use axum::{
     body::Body,
-    http::{Request, Response},
+    http::{HeaderMap, HeaderValue, Request, Response},
     middleware::Next,
 };
+use opentelemetry::{
+    propagation::Extractor,
+    trace::{TraceContextExt, TraceId},
+    Context,
+};
+use tracing::{field, Instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Header carrying the request ID in both directions
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longest request ID accepted from a client
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// ID of the request being handled by the current task, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
 
+/// Runs every request in its own span. The request ID is taken from `X-Request-Id`, otherwise
+/// it is the trace ID, which continues the caller's trace when a `traceparent` header is sent.
 pub async fn request_logger(req: Request<Body>, next: Next) -> Response<Body> {
-    let request_id = generate_request_id();
     let method = req.method().clone();
     let path = req.uri().path().to_string();
     let start = std::time::Instant::now();
 
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(req.headers()))
+    });
+    let span = tracing::info_span!(
+        "request",
+        request_id = field::Empty,
+        method = %method,
+        path = %path,
+        status = field::Empty,
+    );
+    span.set_parent(parent.clone());
+
+    let request_id = incoming_request_id(req.headers())
+        .or_else(|| trace_id(&span.context()))
+        .or_else(|| trace_id(&parent))
+        .unwrap_or_else(generate_request_id);
+    span.record("request_id", request_id.as_str());
+
     // Add request ID to extensions
     let mut req = req;
     req.extensions_mut().insert(request_id.clone());
 
-    // Process the request
-    let response = next.run(req).await;
+    // Process the request with the ID available to error responses
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(req))
+        .instrument(span.clone())
+        .await;
+
+    span.record("status", response.status().as_u16());
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
 
     // Log the request
-    log_request(
-        method.as_str(),
-        &path,
-        response.status().as_u16(),
-        start.elapsed(),
-        &request_id,
-    );
+    span.in_scope(|| {
+        log_request(
+            method.as_str(),
+            &path,
+            response.status().as_u16(),
+            start.elapsed(),
+            &request_id,
+        )
+    });
 
     response
 }
+
+/// A client-supplied request ID, unless it is too long or contains characters that
+/// don't belong in logs
+fn incoming_request_id(headers: &HeaderMap) -> Option<String> {
+    let id = headers.get(REQUEST_ID_HEADER)?.to_str().ok()?.trim();
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    valid.then(|| id.to_string())
+}
+
+fn trace_id(cx: &Context) -> Option<String> {
+    let trace_id = cx.span().span_context().trace_id();
+    (trace_id != TraceId::INVALID).then(|| trace_id.to_string())
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
@@ -4,6 +4,8 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::services::{email_templates::Locale, jwt::TokenClaims};
+
 #[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema, PartialEq, Clone)]
 #[sqlx(type_name = "user_gender", rename_all = "lowercase")]
 pub enum Gender {
@@ -27,6 +29,40 @@ pub enum UserStatus {
     PendingVerification,
 }
 
+/// Access level of a user. Variants are ordered from least to most privileged.
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    ToSchema,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserRole::User => f.write_str("user"),
+            UserRole::Moderator => f.write_str("moderator"),
+            UserRole::Admin => f.write_str("admin"),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema, Clone)]
 pub struct User {
     #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
@@ -47,8 +83,19 @@ pub struct User {
     pub password_hash: String,
     pub gender: Gender,
     pub status: UserStatus,
+    pub role: UserRole,
+    /// Preferred language for emails
+    #[schema(example = "en")]
+    pub locale: String,
+    /// Incremented to invalidate all of the user's login sessions
+    #[serde(skip)]
+    pub session_version: i32,
     pub is_verified: bool,
     pub verification_attempts: i32,
+    #[serde(skip)]
+    pub failed_login_attempts: i32,
+    #[serde(skip)]
+    pub locked_until: Option<DateTime<Utc>>,
     pub verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -73,6 +120,14 @@ pub struct RegisterRequest {
     pub password: String,
 
     pub gender: Gender,
+
+    /// Language for emails, English if omitted
+    #[serde(default)]
+    pub locale: Locale,
+
+    /// Required when registration is invite-only
+    #[schema(example = "K7QX-M2PD-9WTA")]
+    pub invite_code: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -85,6 +140,28 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct MagicLinkRequest {
+    #[validate(email(message = "Invalid email format"))]
+    #[schema(example = "user@example.com")]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConsumeMagicLinkRequest {
+    /// The `token` query parameter of the emailed link
+    pub token: String,
+}
+
+/// Parameters the identity provider appended to the redirect URL
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OidcCallbackRequest {
+    #[schema(example = "SplxlOBeZQQYbYS6WxSbIA")]
+    pub code: String,
+    #[schema(example = "af0ifjsldkj")]
+    pub state: String,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct AuthResponse {
     #[schema(example = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9...")]
@@ -110,12 +187,76 @@ pub struct ResendOtpRequest {
     pub email: String,
 }
 
+/// Permission granted to a personal access token
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq, Clone, Copy)]
+pub enum TokenScope {
+    #[serde(rename = "links:read")]
+    LinksRead,
+    #[serde(rename = "links:write")]
+    LinksWrite,
+    #[serde(rename = "analytics:read")]
+    AnalyticsRead,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::LinksRead => "links:read",
+            TokenScope::LinksWrite => "links:write",
+            TokenScope::AnalyticsRead => "analytics:read",
+        }
+    }
+}
+
+impl std::fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for TokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "links:read" => Ok(TokenScope::LinksRead),
+            "links:write" => Ok(TokenScope::LinksWrite),
+            "analytics:read" => Ok(TokenScope::AnalyticsRead),
+            other => Err(format!("Unknown token scope: {other}")),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: Uuid,
     pub exp: i64,
     pub email: String,
     pub username: String,
+    #[serde(default)]
+    pub role: UserRole,
+    /// Must match the user's current session version for the token to be accepted
+    #[serde(default)]
+    pub session_version: i32,
+}
+
+impl TokenClaims for Claims {
+    const AUDIENCE: &'static str = "linksphere:session";
+    const ACCEPTS_LEGACY: bool = true;
+}
+
+/// Claims of a magic login link
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MagicLinkClaims {
+    pub sub: Uuid,
+    pub exp: i64,
+    pub email: String,
+    /// Unique ID of the link, stored until it is used
+    pub jti: String,
+}
+
+impl TokenClaims for MagicLinkClaims {
+    const AUDIENCE: &'static str = "linksphere:magic_link";
 }
 
 fn validate_username(username: &str) -> Result<(), validator::ValidationError> {
@@ -0,0 +1,50 @@
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use std::sync::Arc;
+
+use crate::{
+    api::{models::UpdateLocaleRequest, ApiResponse, AppError, ErrorResponse},
+    database::repository::UserRepository,
+    middleware::auth::AuthUser,
+};
+
+type EmptyResponse = ApiResponse<()>;
+
+/// Set the email language
+///
+/// Changes the language verification codes, login links and notifications are sent in.
+/// Requires Authentication: Bearer token from /api/auth/login
+#[utoipa::path(
+    put,
+    path = "/api/account/locale",
+    request_body = UpdateLocaleRequest,
+    responses(
+        (status = 200, description = "Language updated successfully", body = EmptyResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "Called with a personal access token", body = ErrorResponse),
+        (status = 422, description = "Unsupported locale", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "account"
+)]
+pub async fn update_locale(
+    State(users): State<Arc<dyn UserRepository>>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<UpdateLocaleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if !users
+        .update_locale(user.id, payload.locale.as_str())
+        .await?
+    {
+        return Err(AppError::not_found("USER_NOT_FOUND", "User not found"));
+    }
+    let response = ApiResponse::success_with_message((), "Language updated successfully");
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -0,0 +1,453 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    api::{
+        models::{AdminUserActionRequest, AuditLogQuery, PendingCleanupResult, UserListQuery},
+        ApiResponse, AppError, ErrorResponse, PaginationMeta,
+    },
+    database::{
+        models::{AuditLogEntry, UserOverview},
+        repository::{AdminAction, AuditRepository, UserRepository},
+    },
+    middleware::auth::AuthUser,
+    models::auth::UserStatus,
+    services::{audit::AuditAction, pending_cleanup::PendingAccountCleanup},
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+type PendingCleanupResponse = ApiResponse<PendingCleanupResult>;
+type UserListResponse = ApiResponse<Vec<UserOverview>>;
+type UserOverviewResponse = ApiResponse<UserOverview>;
+type AuditLogResponse = ApiResponse<Vec<AuditLogEntry>>;
+
+/// Page number and size from a query, with defaults and bounds applied
+fn page_params(page: Option<u32>, per_page: Option<u32>) -> (u32, u32) {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    (page, per_page)
+}
+
+fn pagination(page: u32, per_page: u32, total: i64) -> PaginationMeta {
+    let total_items = total.max(0) as u64;
+    PaginationMeta {
+        current_page: page,
+        page_size: per_page,
+        total_items,
+        total_pages: total_items.div_ceil(u64::from(per_page)) as u32,
+    }
+}
+
+/// List users
+///
+/// Pages through all accounts, newest first, optionally filtered by a search term and status.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    get,
+    path = "/api/admin/users",
+    params(UserListQuery),
+    responses(
+        (status = 200, description = "Users retrieved successfully", body = UserListResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_users(
+    State(users): State<Arc<dyn UserRepository>>,
+    Query(query): Query<UserListQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let (page, per_page) = page_params(query.page, query.per_page);
+    let search = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    let offset = i64::from(page - 1) * i64::from(per_page);
+
+    let (page_users, total) = users
+        .search(search, query.status, i64::from(per_page), offset)
+        .await?;
+    let response =
+        ApiResponse::success(page_users).with_pagination(pagination(page, per_page, total));
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Get a user
+///
+/// Returns an account's details and the number of links it has created.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    get,
+    path = "/api/admin/users/{id}",
+    params(
+        ("id" = Uuid, Path, description = "ID of the user")
+    ),
+    responses(
+        (status = 200, description = "User retrieved successfully", body = UserOverviewResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn get_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = load_user(users.as_ref(), user_id).await?;
+    Ok((StatusCode::OK, Json(ApiResponse::success(user))))
+}
+
+/// Suspend a user
+///
+/// Blocks the account from logging in and ends all of its sessions. Admins cannot suspend
+/// themselves.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/suspend",
+    params(
+        ("id" = Uuid, Path, description = "ID of the user")
+    ),
+    request_body = AdminUserActionRequest,
+    responses(
+        (status = 200, description = "User suspended", body = UserOverviewResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin, or tried to suspend themselves", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "User is already suspended", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn suspend_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    Extension(admin): Extension<AuthUser>,
+    Path(user_id): Path<Uuid>,
+    payload: Option<Json<AdminUserActionRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    change_status(
+        users.as_ref(),
+        &admin,
+        user_id,
+        AuditAction::SuspendUser,
+        payload,
+    )
+    .await
+}
+
+/// Deactivate a user
+///
+/// Disables the account and ends all of its sessions. Admins cannot deactivate themselves.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/deactivate",
+    params(
+        ("id" = Uuid, Path, description = "ID of the user")
+    ),
+    request_body = AdminUserActionRequest,
+    responses(
+        (status = 200, description = "User deactivated", body = UserOverviewResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin, or tried to deactivate themselves", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "User is already inactive", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn deactivate_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    Extension(admin): Extension<AuthUser>,
+    Path(user_id): Path<Uuid>,
+    payload: Option<Json<AdminUserActionRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    change_status(
+        users.as_ref(),
+        &admin,
+        user_id,
+        AuditAction::DeactivateUser,
+        payload,
+    )
+    .await
+}
+
+/// Reactivate a user
+///
+/// Lifts a suspension or deactivation. Accounts that never verified their email go back to
+/// pending verification.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/reactivate",
+    params(
+        ("id" = Uuid, Path, description = "ID of the user")
+    ),
+    request_body = AdminUserActionRequest,
+    responses(
+        (status = 200, description = "User reactivated", body = UserOverviewResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "User is neither suspended nor inactive", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn reactivate_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    Extension(admin): Extension<AuthUser>,
+    Path(user_id): Path<Uuid>,
+    payload: Option<Json<AdminUserActionRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    change_status(
+        users.as_ref(),
+        &admin,
+        user_id,
+        AuditAction::ReactivateUser,
+        payload,
+    )
+    .await
+}
+
+/// Verify a user's email
+///
+/// Marks the account's email as verified without an OTP. Accounts pending verification
+/// become active.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/verify",
+    params(
+        ("id" = Uuid, Path, description = "ID of the user")
+    ),
+    request_body = AdminUserActionRequest,
+    responses(
+        (status = 200, description = "Email marked as verified", body = UserOverviewResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "Email is already verified", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn verify_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    Extension(admin): Extension<AuthUser>,
+    Path(user_id): Path<Uuid>,
+    payload: Option<Json<AdminUserActionRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    payload.validate()?;
+
+    let user = load_user(users.as_ref(), user_id).await?;
+    if user.is_verified {
+        return Err(AppError::conflict(
+            "ALREADY_VERIFIED",
+            "Email is already verified",
+        ));
+    }
+
+    users
+        .verify_email(
+            user_id,
+            AdminAction {
+                admin_id: admin.id,
+                action: AuditAction::VerifyEmail,
+                details: json!({ "email": user.email, "reason": payload.reason }),
+            },
+        )
+        .await?;
+
+    updated_user_response(users.as_ref(), user_id, "Email marked as verified").await
+}
+
+/// View the audit log
+///
+/// Pages through actions admins have taken on user accounts, newest first.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit-log",
+    params(AuditLogQuery),
+    responses(
+        (status = 200, description = "Audit log retrieved successfully", body = AuditLogResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_audit_log(
+    State(audit): State<Arc<dyn AuditRepository>>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let (page, per_page) = page_params(query.page, query.per_page);
+    let offset = i64::from(page - 1) * i64::from(per_page);
+
+    let (entries, total) = audit
+        .list(query.user_id, query.admin_id, i64::from(per_page), offset)
+        .await?;
+    let response = ApiResponse::success(entries).with_pagination(pagination(page, per_page, total));
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Delete unverified accounts
+///
+/// Deletes accounts that are still pending email verification after the configured maximum
+/// age, freeing their usernames. The same cleanup also runs periodically in the background.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/cleanup-pending",
+    responses(
+        (status = 200, description = "Cleanup completed", body = PendingCleanupResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn cleanup_pending(
+    State(cleanup): State<PendingAccountCleanup>,
+    Extension(admin): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    tracing::info!(admin_id = %admin.id, "Admin requested cleanup of unverified accounts");
+
+    let deleted = cleanup.run_once().await?;
+    let result = PendingCleanupResult {
+        deleted,
+        older_than_hours: cleanup.max_age().as_secs() / 3600,
+    };
+    let response =
+        ApiResponse::success_with_message(result, format!("Deleted {deleted} unverified accounts"));
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Applies a status change and records it in the audit log together with it
+async fn change_status(
+    users: &dyn UserRepository,
+    admin: &AuthUser,
+    user_id: Uuid,
+    action: AuditAction,
+    payload: AdminUserActionRequest,
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+
+    let ends_sessions = matches!(
+        action,
+        AuditAction::SuspendUser | AuditAction::DeactivateUser
+    );
+    if ends_sessions && user_id == admin.id {
+        return Err(AppError::forbidden(
+            "CANNOT_MODIFY_SELF",
+            "Admins cannot suspend or deactivate their own account",
+        ));
+    }
+
+    let user = load_user(users, user_id).await?;
+
+    let new_status = match action {
+        AuditAction::SuspendUser => UserStatus::Suspended,
+        AuditAction::DeactivateUser => UserStatus::Inactive,
+        AuditAction::ReactivateUser if user.is_verified => UserStatus::Active,
+        AuditAction::ReactivateUser => UserStatus::PendingVerification,
+        _ => unreachable!("{action} is not a status change"),
+    };
+
+    let allowed = match action {
+        AuditAction::ReactivateUser => {
+            matches!(user.status, UserStatus::Suspended | UserStatus::Inactive)
+        }
+        _ => user.status != new_status,
+    };
+    if !allowed {
+        return Err(AppError::conflict(
+            "INVALID_STATUS",
+            format!("User status is already {:?}", user.status),
+        )
+        .with_details(json!({ "status": user.status })));
+    }
+
+    let details = json!({
+        "from": user.status,
+        "to": new_status,
+        "reason": payload.reason,
+    });
+    users
+        .change_status(
+            user_id,
+            new_status,
+            ends_sessions,
+            AdminAction {
+                admin_id: admin.id,
+                action,
+                details,
+            },
+        )
+        .await?;
+
+    let message = match action {
+        AuditAction::SuspendUser => "User suspended",
+        AuditAction::DeactivateUser => "User deactivated",
+        _ => "User reactivated",
+    };
+    updated_user_response(users, user_id, message).await
+}
+
+async fn load_user(users: &dyn UserRepository, user_id: Uuid) -> Result<UserOverview, AppError> {
+    users
+        .overview(user_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("USER_NOT_FOUND", "User not found"))
+}
+
+async fn updated_user_response(
+    users: &dyn UserRepository,
+    user_id: Uuid,
+    message: &str,
+) -> Result<impl IntoResponse, AppError> {
+    let user = load_user(users, user_id).await?;
+    let response = ApiResponse::success_with_message(user, message);
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -0,0 +1,92 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    api::{models::OutboxQuery, ApiResponse, AppError, ErrorResponse},
+    database::{
+        models::{EmailStatus, OutboxEmail},
+        repository::OutboxRepository,
+    },
+    middleware::auth::AuthUser,
+};
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 200;
+
+type EmptyResponse = ApiResponse<()>;
+type OutboxEmailsResponse = ApiResponse<Vec<OutboxEmail>>;
+
+/// List outbox emails
+///
+/// Returns emails in the outbox, by default the ones that could not be delivered.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    get,
+    path = "/api/admin/email-outbox",
+    params(OutboxQuery),
+    responses(
+        (status = 200, description = "Emails retrieved successfully", body = OutboxEmailsResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_emails(
+    State(outbox): State<Arc<dyn OutboxRepository>>,
+    Query(query): Query<OutboxQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let status = query.status.unwrap_or(EmailStatus::Dead);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let emails = outbox.list(status, limit).await?;
+    Ok((StatusCode::OK, Json(ApiResponse::success(emails))))
+}
+
+/// Replay a failed email
+///
+/// Queues an email in the dead letter state for another round of delivery attempts.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    post,
+    path = "/api/admin/email-outbox/{id}/replay",
+    params(
+        ("id" = Uuid, Path, description = "ID of the email to replay")
+    ),
+    responses(
+        (status = 200, description = "Email queued for delivery", body = EmptyResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 404, description = "No failed email with this ID", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn replay_email(
+    State(outbox): State<Arc<dyn OutboxRepository>>,
+    Extension(admin): Extension<AuthUser>,
+    Path(email_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    if !outbox.replay(email_id).await? {
+        return Err(AppError::not_found(
+            "NOT_FOUND",
+            "No failed email with this ID",
+        ));
+    }
+
+    tracing::info!(admin_id = %admin.id, email_id = %email_id, "Outbox email replayed");
+    let response = ApiResponse::success_with_message((), "Email queued for delivery");
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Json};
+
+use crate::{
+    api::{
+        models::{EmailTemplateInfo, PreviewEmailTemplateRequest},
+        ApiResponse, AppError, ErrorResponse,
+    },
+    services::email_templates::{self, EmailTemplate, Locale, RenderedEmail, TemplateError},
+};
+
+type EmailTemplatesResponse = ApiResponse<Vec<EmailTemplateInfo>>;
+type RenderedEmailResponse = ApiResponse<RenderedEmail>;
+
+/// List email templates
+///
+/// Returns every email template with its locales and the variables it expects.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    get,
+    path = "/api/admin/email-templates",
+    responses(
+        (status = 200, description = "Templates retrieved successfully", body = EmailTemplatesResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_templates() -> impl IntoResponse {
+    let templates: Vec<EmailTemplateInfo> = EmailTemplate::ALL
+        .into_iter()
+        .map(|template| EmailTemplateInfo {
+            name: template.name().to_string(),
+            locales: Locale::ALL.to_vec(),
+            variables: sample_variables(template),
+        })
+        .collect();
+
+    (StatusCode::OK, Json(ApiResponse::success(templates))).into_response()
+}
+
+/// Preview an email template
+///
+/// Renders a template with sample values, overridden by the variables in the request.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    post,
+    path = "/api/admin/email-templates/{name}/preview",
+    params(
+        ("name" = String, Path, description = "Template name, e.g. otp_verification")
+    ),
+    request_body = PreviewEmailTemplateRequest,
+    responses(
+        (status = 200, description = "Template rendered successfully", body = RenderedEmailResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 404, description = "Unknown template", body = ErrorResponse),
+        (status = 500, description = "Template could not be rendered", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn preview_template(
+    Path(name): Path<String>,
+    payload: Option<Json<PreviewEmailTemplateRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let template: EmailTemplate = name
+        .parse()
+        .map_err(|e: TemplateError| AppError::not_found("TEMPLATE_NOT_FOUND", e.to_string()))?;
+
+    let Json(payload) = payload.unwrap_or_default();
+    let mut variables = sample_variables(template);
+    variables.extend(payload.variables);
+
+    let rendered =
+        email_templates::render(template, payload.locale.unwrap_or_default(), &variables)
+            .map_err(|e| AppError::internal("Failed to render email template", e))?;
+    Ok((StatusCode::OK, Json(ApiResponse::success(rendered))))
+}
+
+fn sample_variables(template: EmailTemplate) -> HashMap<String, String> {
+    template
+        .sample_variables()
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
@@ -1,14 +1,16 @@
 use crate::api::{ApiResponse, ErrorResponse};
+use crate::database::repository::UserRepository;
+use crate::middleware::auth::AuthUser;
+use crate::services::readiness::Readiness;
 use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
+    extract::{Extension, State},
+    http::StatusCode,
     response::IntoResponse,
     Json,
 };
 use serde::Serialize;
 use serde_json::json;
-use sqlx::PgPool;
-use std::env;
+use std::sync::Arc;
 use utoipa::ToSchema;
 
 #[derive(Serialize, ToSchema)]
@@ -33,40 +35,61 @@ pub async fn root() -> impl IntoResponse {
     (StatusCode::OK, axum::Json(response)).into_response()
 }
 
-/// Health check endpoint protected by admin token
+/// Liveness probe. Answers as long as the process can serve requests, without touching any
+/// dependency, so a database outage doesn't get the instance restarted.
+pub async fn livez() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        Json(ApiResponse::success(json!({ "status": "alive" }))),
+    )
+}
+
+/// Readiness probe. 503 while a critical dependency is unavailable or shutdown is draining.
+pub async fn readyz(State(readiness): State<Readiness>) -> impl IntoResponse {
+    let report = readiness.check().await;
+    let (status, message) = if report.ready {
+        (StatusCode::OK, "Ready to serve requests")
+    } else if report.draining {
+        (StatusCode::SERVICE_UNAVAILABLE, "Shutting down")
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "A dependency is unavailable",
+        )
+    };
+
+    let response = ApiResponse {
+        success: report.ready,
+        message: message.to_string(),
+        data: report,
+        pagination: None,
+        timestamp: chrono::Utc::now(),
+    };
+    (status, Json(response))
+}
+
+/// Health check endpoint restricted to admins
 #[utoipa::path(
     get,
-    path = "/api/admin/health",
-    params(
-        ("X-Admin-Token" = String, Header, description = "Admin authentication token")
-    ),
+    path = "/api/admin/db/health",
     responses(
         (status = 200, description = "Database connection healthy", body = ApiResponse<serde_json::Value>),
-        (status = 401, description = "Missing or invalid admin token", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
         (status = 503, description = "Database connection failed", body = ErrorResponse)
     ),
+    security(
+        ("bearer_auth" = [])
+    ),
     tag = "admin"
 )]
-pub async fn health_check(State(pool): State<PgPool>, headers: HeaderMap) -> impl IntoResponse {
-    // Check admin token
-    let admin_token = env::var("ADMIN_SECRET_KEY").unwrap_or_default();
-    let provided_token = headers
-        .get("X-Admin-Token")
-        .and_then(|value| value.to_str().ok())
-        .unwrap_or_default();
-
-    if admin_token.is_empty() || admin_token != provided_token {
-        let response = ApiResponse {
-            success: false,
-            message: "Missing or invalid authorization header".to_string(),
-            data: json!({ "code": "UNAUTHORIZED" }),
-            pagination: None,
-            timestamp: chrono::Utc::now(),
-        };
-        return (StatusCode::UNAUTHORIZED, Json(response));
-    }
+pub async fn health_check(
+    State(users): State<Arc<dyn UserRepository>>,
+    Extension(admin): Extension<AuthUser>,
+) -> impl IntoResponse {
+    tracing::info!(admin_id = %admin.id, "Admin requested database health check");
 
-    match sqlx::query("SELECT 1").execute(&pool).await {
+    match users.ping().await {
         Ok(_) => {
             let response = ApiResponse {
                 success: true,
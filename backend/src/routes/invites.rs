@@ -0,0 +1,143 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    api::{
+        models::{CreateInviteRequest, CreatedInvite},
+        ApiResponse, AppError, ErrorResponse,
+    },
+    database::{
+        models::{InviteCode, NewInvite},
+        repository::InviteRepository,
+    },
+    middleware::auth::AuthUser,
+    services::registration::generate_invite_code,
+};
+
+type EmptyResponse = ApiResponse<()>;
+type InviteCodesResponse = ApiResponse<Vec<InviteCode>>;
+type CreatedInviteResponse = ApiResponse<CreatedInvite>;
+
+/// List invite codes
+///
+/// Returns all invite codes with their usage. Codes themselves are never included.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    get,
+    path = "/api/admin/invites",
+    responses(
+        (status = 200, description = "Invite codes retrieved successfully", body = InviteCodesResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_invites(
+    State(invites): State<Arc<dyn InviteRepository>>,
+) -> Result<impl IntoResponse, AppError> {
+    let invites = invites.list().await?;
+    Ok((StatusCode::OK, Json(ApiResponse::success(invites))))
+}
+
+/// Create an invite code
+///
+/// Creates a single or multi-use code for invite-only registration. The full code is only
+/// returned in this response.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    post,
+    path = "/api/admin/invites",
+    request_body = CreateInviteRequest,
+    responses(
+        (status = 201, description = "Invite code created successfully", body = CreatedInviteResponse),
+        (status = 422, description = "Invalid request data", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn create_invite(
+    State(invites): State<Arc<dyn InviteRepository>>,
+    Extension(admin): Extension<AuthUser>,
+    Json(payload): Json<CreateInviteRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+
+    let expires_at = payload
+        .expires_in_days
+        .map(|days| Utc::now() + Duration::days(i64::from(days)));
+
+    let generated = generate_invite_code();
+    let details = invites
+        .create(NewInvite {
+            code_prefix: generated.prefix,
+            code_hash: generated.hash,
+            note: payload.note,
+            max_uses: payload.max_uses.unwrap_or(1),
+            expires_at,
+            created_by: admin.id,
+        })
+        .await?;
+
+    tracing::info!(admin_id = %admin.id, invite_id = %details.id, "Invite code created");
+    let response = ApiResponse::success_with_message(
+        CreatedInvite {
+            code: generated.code,
+            details,
+        },
+        "Invite code created. Copy it now, it will not be shown again.",
+    );
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Revoke an invite code
+///
+/// Deletes an invite code. Accounts already created with it are not affected.
+/// Requires Authentication: Bearer token of an admin
+#[utoipa::path(
+    delete,
+    path = "/api/admin/invites/{id}",
+    params(
+        ("id" = Uuid, Path, description = "ID of the invite code to revoke")
+    ),
+    responses(
+        (status = 200, description = "Invite code revoked successfully", body = EmptyResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "User is not an admin", body = ErrorResponse),
+        (status = 404, description = "Invite code not found", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn revoke_invite(
+    State(invites): State<Arc<dyn InviteRepository>>,
+    Extension(admin): Extension<AuthUser>,
+    Path(invite_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    if !invites.delete(invite_id).await? {
+        return Err(AppError::not_found("NOT_FOUND", "Invite code not found"));
+    }
+
+    tracing::info!(admin_id = %admin.id, invite_id = %invite_id, "Invite code revoked");
+    let response = ApiResponse::success_with_message((), "Invite code revoked successfully");
+    Ok((StatusCode::OK, Json(response)))
+}
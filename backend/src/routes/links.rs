@@ -5,18 +5,26 @@ use axum::{
     Json,
 };
 
-use crate::database::queries::{create_link, increment_click_count};
 use crate::{
-    api::{models::CreateLinkRequest, ApiResponse, ErrorResponse},
-    database::{self, models::Link, PgPool},
+    api::{
+        models::{CreateLinkRequest, LinkStats},
+        ApiResponse, AppError, ErrorResponse,
+    },
+    database::{
+        models::{Link, NewLink},
+        repository::LinkRepository,
+    },
     middleware::auth::AuthUser,
     services::link_preview::fetch_link_preview,
+    shutdown::Shutdown,
 };
+use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
 type LinkResponse = ApiResponse<Link>;
 type LinksResponse = ApiResponse<Vec<Link>>;
+type LinkStatsResponse = ApiResponse<LinkStats>;
 /// Get all links
 ///
 /// Returns a list of all links in the system
@@ -34,18 +42,11 @@ type LinksResponse = ApiResponse<Vec<Link>>;
     ),
     tag = "links"
 )]
-pub async fn get_links(State(pool): State<PgPool>) -> impl IntoResponse {
-    match database::get_all_links(&pool).await {
-        Ok(links) => {
-            let response = ApiResponse::success(links);
-            (StatusCode::OK, Json(response)).into_response()
-        }
-        Err(e) => {
-            let error = ErrorResponse::new(format!("Failed to fetch links: {e}"))
-                .with_code("LINKS_FETCH_ERROR");
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
-        }
-    }
+pub async fn get_links(
+    State(links): State<Arc<dyn LinkRepository>>,
+) -> Result<impl IntoResponse, AppError> {
+    let links = links.list().await?;
+    Ok((StatusCode::OK, Json(ApiResponse::success(links))))
 }
 
 /// Create a new link
@@ -69,88 +70,104 @@ pub async fn get_links(State(pool): State<PgPool>) -> impl IntoResponse {
     tag = "links"
 )]
 pub async fn handle_create_link(
-    State(pool): State<PgPool>,
+    State(links): State<Arc<dyn LinkRepository>>,
+    State(shutdown): State<Shutdown>,
     Extension(user): Extension<AuthUser>,
     Json(payload): Json<CreateLinkRequest>,
-) -> impl IntoResponse {
-    // Validate the request payload
-    if let Err(validation_errors) = payload.validate() {
-        let error = ErrorResponse::new(format!("Validation error: {validation_errors}"))
-            .with_code("VALIDATION_ERROR");
-        return (StatusCode::UNPROCESSABLE_ENTITY, Json(error)).into_response();
-    }
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
 
     // Validate URL format
     if let Err(url_error) = payload.validate_url() {
-        let error =
-            ErrorResponse::new(format!("Invalid URL format: {url_error}")).with_code("INVALID_URL");
-        return (StatusCode::UNPROCESSABLE_ENTITY, Json(error)).into_response();
+        return Err(AppError::client(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_URL",
+            format!("Invalid URL format: {url_error}"),
+        ));
     }
 
     // Create the link first without preview
-    let link = match create_link(
-        &pool,
-        payload.url.clone(),
-        payload.title,
-        payload.description,
-        user.id,
-        None, // No preview initially
-    )
-    .await
-    {
-        Ok(link) => link,
-        Err(e) => {
-            let error = ErrorResponse::new(format!("Failed to create link: {e}"))
-                .with_code("LINK_CREATE_ERROR");
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
-        }
-    };
+    let link = links
+        .create(NewLink {
+            url: payload.url.clone(),
+            title: payload.title,
+            description: payload.description,
+            user_id: user.id,
+        })
+        .await?;
 
-    // Spawn a task to fetch and update the preview asynchronously
-    let pool_clone = pool.clone();
+    // Spawn a task to fetch and update the preview asynchronously. Shutdown waits for it;
+    // links it doesn't finish stay without a preview.
     let url = payload.url.clone();
     let link_id = link.id;
 
-    tokio::spawn(async move {
+    shutdown.spawn(async move {
         if let Ok(preview) = fetch_link_preview(&url).await {
             // Update the link with the preview
-            let _ = sqlx::query!(
-                r#"
-                UPDATE links 
-                SET preview = $1 
-                WHERE id = $2
-                "#,
-                serde_json::to_value(preview).ok() as _,
-                link_id
-            )
-            .execute(&pool_clone)
-            .await;
+            let _ = links.set_preview(link_id, &preview).await;
         }
     });
 
     // Return the created link immediately
     let response = ApiResponse::success_with_message(link, "Link created successfully");
-    (StatusCode::CREATED, Json(response)).into_response()
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 /// Track a link click
 ///
 /// Increments the click count for a link
 pub async fn track_click(
-    State(pool): State<PgPool>,
+    State(links): State<Arc<dyn LinkRepository>>,
     Path(link_id): Path<Uuid>,
-) -> impl IntoResponse {
-    match increment_click_count(&pool, link_id).await {
-        Ok(_) => {
-            let response = ApiResponse::success(());
-            (StatusCode::OK, Json(response)).into_response()
-        }
-        Err(e) => {
-            let error = ErrorResponse::new(format!("Failed to track click: {e}"))
-                .with_code("CLICK_TRACK_ERROR");
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
-        }
+) -> Result<impl IntoResponse, AppError> {
+    links.increment_clicks(link_id).await?;
+    Ok((StatusCode::OK, Json(ApiResponse::success(()))))
+}
+
+/// Get a link's statistics
+///
+/// Returns the click statistics of one of the current user's links.
+/// Personal access tokens need the `analytics:read` scope.
+#[utoipa::path(
+    get,
+    path = "/api/links/{id}/stats",
+    params(
+        ("id" = Uuid, Path, description = "ID of the link")
+    ),
+    responses(
+        (status = 200, description = "Statistics retrieved successfully", body = LinkStatsResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "Not the owner of the link, or a token without the analytics:read scope", body = ErrorResponse),
+        (status = 404, description = "Link not found", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "links"
+)]
+pub async fn link_stats(
+    State(links): State<Arc<dyn LinkRepository>>,
+    Extension(user): Extension<AuthUser>,
+    Path(link_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let link = links
+        .find(link_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("NOT_FOUND", "Link not found"))?;
+    if link.user_id != user.id {
+        return Err(AppError::forbidden(
+            "FORBIDDEN",
+            "You don't have permission to view this link's statistics",
+        ));
     }
+
+    let stats = LinkStats {
+        link_id: link.id,
+        click_count: link.click_count,
+        created_at: link.created_at,
+    };
+    Ok((StatusCode::OK, Json(ApiResponse::success(stats))))
 }
 
 /// Delete a link
@@ -237,47 +254,29 @@ pub async fn track_click(
 ///               properties:
 ///                 error:
 ///                   type: string
-///                   example: Failed to delete link
+///                   example: An internal error occurred. Please try again later.
 ///                 code:
 ///                   type: string
-///                   example: LINK_DELETE_ERROR
+///                   example: INTERNAL_ERROR
 /// ```
 pub async fn delete_link(
-    State(pool): State<PgPool>,
+    State(links): State<Arc<dyn LinkRepository>>,
     Extension(user): Extension<AuthUser>,
     Path(link_id): Path<Uuid>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     // First check if the link exists and belongs to the user
-    match database::queries::get_link_by_id(&pool, link_id).await {
-        Ok(Some(link)) => {
-            if link.user_id != user.id {
-                let error = ErrorResponse::new("You don't have permission to delete this link")
-                    .with_code("FORBIDDEN");
-                return (StatusCode::FORBIDDEN, Json(error)).into_response();
-            }
-
-            // If the user owns the link, proceed with deletion
-            match database::queries::delete_link(&pool, link_id).await {
-                Ok(_) => {
-                    let response =
-                        ApiResponse::success_with_message((), "Link deleted successfully");
-                    (StatusCode::OK, Json(response)).into_response()
-                }
-                Err(e) => {
-                    let error = ErrorResponse::new(format!("Failed to delete link: {e}"))
-                        .with_code("LINK_DELETE_ERROR");
-                    (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
-                }
-            }
-        }
-        Ok(None) => {
-            let error = ErrorResponse::new("Link not found").with_code("NOT_FOUND");
-            (StatusCode::NOT_FOUND, Json(error)).into_response()
-        }
-        Err(e) => {
-            let error = ErrorResponse::new(format!("Failed to fetch link: {e}"))
-                .with_code("LINK_FETCH_ERROR");
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
-        }
+    let link = links
+        .find(link_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("NOT_FOUND", "Link not found"))?;
+    if link.user_id != user.id {
+        return Err(AppError::forbidden(
+            "FORBIDDEN",
+            "You don't have permission to delete this link",
+        ));
     }
+
+    links.delete(link_id).await?;
+    let response = ApiResponse::success_with_message((), "Link deleted successfully");
+    Ok((StatusCode::OK, Json(response)))
 }
@@ -1,24 +1,213 @@
+pub mod account;
+pub mod admin_users;
+pub mod email_outbox;
+pub mod email_templates;
 pub mod health;
+pub mod invites;
 pub mod links;
+pub mod tokens;
 
-use crate::database::PgPool;
+use crate::database::repository::{
+    ApiTokenRepository, AuditRepository, InviteRepository, LinkRepository, OutboxRepository,
+    Repositories, UserRepository,
+};
+use crate::middleware::auth::{require_scope, require_session};
+use crate::middleware::rate_limit::{rate_limit, RateLimiter, RouteGroup};
+use crate::models::auth::TokenScope;
+use crate::services::{pending_cleanup::PendingAccountCleanup, readiness::Readiness};
+use crate::shutdown::Shutdown;
 use axum::{
-    routing::{delete, get, post},
+    extract::FromRef,
+    middleware::{from_fn, from_fn_with_state},
+    routing::{delete, get, post, put},
     Router,
 };
+use std::sync::Arc;
 
-pub fn create_ping_router(pool: PgPool) -> Router {
+// Unauthenticated probes for orchestrators
+pub fn create_probe_router(readiness: Readiness) -> Router {
+    Router::new()
+        .route("/livez", get(health::livez))
+        .route("/readyz", get(health::readyz))
+        .with_state(readiness)
+}
+
+/// State of the admin routes. Handlers extract the part they need.
+#[derive(Clone)]
+pub struct AdminState {
+    pub repositories: Repositories,
+    pub pending_cleanup: PendingAccountCleanup,
+}
+
+impl FromRef<AdminState> for Arc<dyn UserRepository> {
+    fn from_ref(state: &AdminState) -> Self {
+        state.repositories.users.clone()
+    }
+}
+
+impl FromRef<AdminState> for Arc<dyn InviteRepository> {
+    fn from_ref(state: &AdminState) -> Self {
+        state.repositories.invites.clone()
+    }
+}
+
+impl FromRef<AdminState> for Arc<dyn OutboxRepository> {
+    fn from_ref(state: &AdminState) -> Self {
+        state.repositories.outbox.clone()
+    }
+}
+
+impl FromRef<AdminState> for Arc<dyn AuditRepository> {
+    fn from_ref(state: &AdminState) -> Self {
+        state.repositories.audit.clone()
+    }
+}
+
+impl FromRef<AdminState> for PendingAccountCleanup {
+    fn from_ref(state: &AdminState) -> Self {
+        state.pending_cleanup.clone()
+    }
+}
+
+// Routes only available to admins
+pub fn create_admin_router(
+    repositories: Repositories,
+    pending_cleanup: PendingAccountCleanup,
+) -> Router {
     Router::new()
         .route("/api/admin/db/health", get(health::health_check))
-        .with_state(pool)
+        .route("/api/admin/users", get(admin_users::list_users))
+        .route("/api/admin/users/{id}", get(admin_users::get_user))
+        .route(
+            "/api/admin/users/cleanup-pending",
+            post(admin_users::cleanup_pending),
+        )
+        .route(
+            "/api/admin/users/{id}/suspend",
+            post(admin_users::suspend_user),
+        )
+        .route(
+            "/api/admin/users/{id}/deactivate",
+            post(admin_users::deactivate_user),
+        )
+        .route(
+            "/api/admin/users/{id}/reactivate",
+            post(admin_users::reactivate_user),
+        )
+        .route(
+            "/api/admin/users/{id}/verify",
+            post(admin_users::verify_user),
+        )
+        .route("/api/admin/audit-log", get(admin_users::list_audit_log))
+        .route(
+            "/api/admin/invites",
+            get(invites::list_invites).post(invites::create_invite),
+        )
+        .route("/api/admin/invites/{id}", delete(invites::revoke_invite))
+        .route("/api/admin/email-outbox", get(email_outbox::list_emails))
+        .route(
+            "/api/admin/email-outbox/{id}/replay",
+            post(email_outbox::replay_email),
+        )
+        .route(
+            "/api/admin/email-templates",
+            get(email_templates::list_templates),
+        )
+        .route(
+            "/api/admin/email-templates/{name}/preview",
+            post(email_templates::preview_template),
+        )
+        .with_state(AdminState {
+            repositories,
+            pending_cleanup,
+        })
+}
+
+/// State of the protected routes. Handlers extract the part they need.
+#[derive(Clone)]
+pub struct ProtectedState {
+    pub repositories: Repositories,
+    /// Link previews are fetched in tasks that shutdown waits for
+    pub shutdown: Shutdown,
+}
+
+impl FromRef<ProtectedState> for Arc<dyn LinkRepository> {
+    fn from_ref(state: &ProtectedState) -> Self {
+        state.repositories.links.clone()
+    }
+}
+
+impl FromRef<ProtectedState> for Arc<dyn ApiTokenRepository> {
+    fn from_ref(state: &ProtectedState) -> Self {
+        state.repositories.api_tokens.clone()
+    }
+}
+
+impl FromRef<ProtectedState> for Arc<dyn UserRepository> {
+    fn from_ref(state: &ProtectedState) -> Self {
+        state.repositories.users.clone()
+    }
+}
+
+impl FromRef<ProtectedState> for Shutdown {
+    fn from_ref(state: &ProtectedState) -> Self {
+        state.shutdown.clone()
+    }
 }
 
 // Protected routes that require authentication
-pub fn create_protected_router(pool: PgPool) -> Router {
+pub fn create_protected_router(
+    repositories: Repositories,
+    shutdown: Shutdown,
+    rate_limiter: RateLimiter,
+) -> Router {
+    let links_read = from_fn_with_state(TokenScope::LinksRead, require_scope);
+    let links_write = from_fn_with_state(TokenScope::LinksWrite, require_scope);
+    let analytics_read = from_fn_with_state(TokenScope::AnalyticsRead, require_scope);
+    let links_limit = from_fn_with_state(rate_limiter.group(RouteGroup::Links), rate_limit);
+
     Router::new()
-        .route("/api/links", get(links::get_links))
-        .route("/api/links", post(links::handle_create_link))
-        .route("/api/links/{id}", delete(links::delete_link))
-        .route("/api/links/{id}/click", post(links::track_click))
-        .with_state(pool)
+        .route(
+            "/api/links",
+            get(links::get_links).route_layer(links_read.clone()),
+        )
+        .route(
+            "/api/links",
+            post(links::handle_create_link)
+                .route_layer(links_limit)
+                .route_layer(links_write.clone()),
+        )
+        .route(
+            "/api/links/{id}",
+            delete(links::delete_link).route_layer(links_write.clone()),
+        )
+        .route(
+            "/api/links/{id}/click",
+            post(links::track_click).route_layer(links_write),
+        )
+        .route(
+            "/api/links/{id}/stats",
+            get(links::link_stats).route_layer(analytics_read),
+        )
+        // Token management is only available to login sessions, never to tokens themselves
+        .route(
+            "/api/tokens",
+            get(tokens::list_tokens).route_layer(from_fn(require_session)),
+        )
+        .route(
+            "/api/tokens",
+            post(tokens::create_token).route_layer(from_fn(require_session)),
+        )
+        .route(
+            "/api/tokens/{id}",
+            delete(tokens::revoke_token).route_layer(from_fn(require_session)),
+        )
+        .route(
+            "/api/account/locale",
+            put(account::update_locale).route_layer(from_fn(require_session)),
+        )
+        .with_state(ProtectedState {
+            repositories,
+            shutdown,
+        })
 }
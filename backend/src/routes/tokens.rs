@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    api::{
+        models::{CreateApiTokenRequest, CreatedApiToken},
+        ApiResponse, AppError, ErrorResponse,
+    },
+    database::{
+        models::{ApiToken, NewApiToken},
+        repository::ApiTokenRepository,
+    },
+    middleware::auth::AuthUser,
+    services::api_token::generate_api_token,
+};
+
+type EmptyResponse = ApiResponse<()>;
+type ApiTokensResponse = ApiResponse<Vec<ApiToken>>;
+type CreatedApiTokenResponse = ApiResponse<CreatedApiToken>;
+
+/// List personal access tokens
+///
+/// Returns the current user's personal access tokens. Secrets are never included.
+/// Requires Authentication: Bearer token from /api/auth/login
+#[utoipa::path(
+    get,
+    path = "/api/tokens",
+    responses(
+        (status = 200, description = "Tokens retrieved successfully", body = ApiTokensResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "Called with a personal access token", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "tokens"
+)]
+pub async fn list_tokens(
+    State(api_tokens): State<Arc<dyn ApiTokenRepository>>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    let tokens = api_tokens.list(user.id).await?;
+    Ok((StatusCode::OK, Json(ApiResponse::success(tokens))))
+}
+
+/// Create a personal access token
+///
+/// Creates a token with the requested scopes. The full token is only returned in this response.
+/// Requires Authentication: Bearer token from /api/auth/login
+#[utoipa::path(
+    post,
+    path = "/api/tokens",
+    request_body = CreateApiTokenRequest,
+    responses(
+        (status = 201, description = "Token created successfully", body = CreatedApiTokenResponse),
+        (status = 422, description = "Invalid request data", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "Called with a personal access token", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "tokens"
+)]
+pub async fn create_token(
+    State(api_tokens): State<Arc<dyn ApiTokenRepository>>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<CreateApiTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload.validate()?;
+
+    let mut scopes = payload.scopes;
+    scopes.sort_by_key(|scope| scope.as_str());
+    scopes.dedup();
+
+    let expires_at = payload
+        .expires_in_days
+        .map(|days| Utc::now() + Duration::days(i64::from(days)));
+
+    let generated = generate_api_token();
+    let details = api_tokens
+        .create(NewApiToken {
+            user_id: user.id,
+            name: payload.name,
+            token_prefix: generated.prefix,
+            token_hash: generated.hash,
+            scopes,
+            expires_at,
+        })
+        .await?;
+
+    tracing::info!(user_id = %user.id, token_id = %details.id, "API token created");
+    let response = ApiResponse::success_with_message(
+        CreatedApiToken {
+            token: generated.token,
+            details,
+        },
+        "Token created. Copy it now, it will not be shown again.",
+    );
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Revoke a personal access token
+///
+/// Deletes one of the current user's tokens. Requests using it are rejected immediately.
+/// Requires Authentication: Bearer token from /api/auth/login
+#[utoipa::path(
+    delete,
+    path = "/api/tokens/{id}",
+    params(
+        ("id" = Uuid, Path, description = "ID of the token to revoke")
+    ),
+    responses(
+        (status = 200, description = "Token revoked successfully", body = EmptyResponse),
+        (status = 401, description = "Missing or invalid JWT token", body = ErrorResponse),
+        (status = 403, description = "Called with a personal access token", body = ErrorResponse),
+        (status = 404, description = "Token not found", body = ErrorResponse),
+        (status = 500, description = "Server error", body = ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "tokens"
+)]
+pub async fn revoke_token(
+    State(api_tokens): State<Arc<dyn ApiTokenRepository>>,
+    Extension(user): Extension<AuthUser>,
+    Path(token_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    if !api_tokens.delete(user.id, token_id).await? {
+        return Err(AppError::not_found("NOT_FOUND", "Token not found"));
+    }
+
+    tracing::info!(user_id = %user.id, token_id = %token_id, "API token revoked");
+    let response = ApiResponse::success_with_message((), "Token revoked successfully");
+    Ok((StatusCode::OK, Json(response)))
+}
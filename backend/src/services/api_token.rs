@@ -0,0 +1,50 @@
+use rand::{distr::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+/// Marker that distinguishes personal access tokens from JWTs
+pub const API_TOKEN_MARKER: &str = "lsp_";
+
+const PREFIX_LENGTH: usize = 8;
+const SECRET_LENGTH: usize = 40;
+
+/// A freshly generated personal access token
+pub struct GeneratedApiToken {
+    /// The full token, shown to the user exactly once
+    pub token: String,
+    /// The displayable prefix stored alongside the hash
+    pub prefix: String,
+    /// The SHA-256 hash of the full token
+    pub hash: String,
+}
+
+/// Generates a new random token of the form `lsp_<prefix>_<secret>`
+pub fn generate_api_token() -> GeneratedApiToken {
+    let mut rng = rand::rng();
+    let mut random_string = |length: usize| -> String {
+        (&mut rng)
+            .sample_iter(&Alphanumeric)
+            .take(length)
+            .map(char::from)
+            .collect()
+    };
+
+    let prefix = format!("{API_TOKEN_MARKER}{}", random_string(PREFIX_LENGTH));
+    let token = format!("{prefix}_{}", random_string(SECRET_LENGTH));
+    let hash = hash_api_token(&token);
+
+    GeneratedApiToken {
+        token,
+        prefix,
+        hash,
+    }
+}
+
+/// Hashes a token for storage and lookup
+pub fn hash_api_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Returns true if the bearer credential looks like a personal access token
+pub fn is_api_token(token: &str) -> bool {
+    token.starts_with(API_TOKEN_MARKER)
+}
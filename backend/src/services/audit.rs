@@ -0,0 +1,89 @@
+//! Audit trail of admin actions.
+//!
+//! Every change an admin makes to another user's account is recorded together with the acting
+//! admin's ID. Record the entry on the same transaction as the change, so that one is never
+//! stored without the other.
+
+use serde_json::Value as JsonValue;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::database::queries;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    SuspendUser,
+    DeactivateUser,
+    ReactivateUser,
+    VerifyEmail,
+    ResetOtpAttempts,
+    UnlockAccount,
+    CreateAdmin,
+    DeleteUser,
+}
+
+impl AuditAction {
+    /// Name stored in the `action` column
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::SuspendUser => "suspend_user",
+            AuditAction::DeactivateUser => "deactivate_user",
+            AuditAction::ReactivateUser => "reactivate_user",
+            AuditAction::VerifyEmail => "verify_email",
+            AuditAction::ResetOtpAttempts => "reset_otp_attempts",
+            AuditAction::UnlockAccount => "unlock_account",
+            AuditAction::CreateAdmin => "create_admin",
+            AuditAction::DeleteUser => "delete_user",
+        }
+    }
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Records that `admin_id` took `action` on `target_user_id`
+pub async fn record<'e>(
+    executor: impl PgExecutor<'e>,
+    admin_id: Uuid,
+    action: AuditAction,
+    target_user_id: Option<Uuid>,
+    details: JsonValue,
+) -> Result<(), sqlx::Error> {
+    queries::insert_audit_log(
+        executor,
+        Some(admin_id),
+        action.as_str(),
+        target_user_id,
+        &details,
+    )
+    .await?;
+
+    tracing::info!(
+        admin_id = %admin_id,
+        action = %action,
+        target_user_id = ?target_user_id,
+        "Recorded admin action"
+    );
+    Ok(())
+}
+
+/// Records `action` taken with the `linksphere-admin` command line tool, which acts without
+/// an admin account
+pub async fn record_cli<'e>(
+    executor: impl PgExecutor<'e>,
+    action: AuditAction,
+    target_user_id: Option<Uuid>,
+    details: JsonValue,
+) -> Result<(), sqlx::Error> {
+    queries::insert_audit_log(executor, None, action.as_str(), target_user_id, &details).await?;
+
+    tracing::info!(
+        action = %action,
+        target_user_id = ?target_user_id,
+        "Recorded command line admin action"
+    );
+    Ok(())
+}
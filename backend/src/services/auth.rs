@@ -1,118 +1,468 @@
+use axum::http::StatusCode;
 use bcrypt::{hash, verify, DEFAULT_COST};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{encode, EncodingKey, Header};
-use sqlx::PgPool;
+use rand::{distr::Alphanumeric, Rng};
+use serde_json::json;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use uuid::Uuid;
 
 use crate::{
-    database::queries,
-    models::auth::{AuthResponse, Claims, RegisterRequest, User, UserStatus},
+    database::{
+        models::{NewEmail, NewUser},
+        repository::{Registration, Repositories},
+    },
+    models::auth::{
+        AuthResponse, Claims, Gender, MagicLinkClaims, RegisterRequest, User, UserStatus,
+    },
+    services::{
+        email_templates::Locale,
+        jwt::JwtKeys,
+        login_guard::{IpCheck, LoginGuard},
+        oidc::OidcIdentity,
+        registration::{RegistrationError, RegistrationPolicy},
+    },
 };
 
+/// Consecutive failed logins before an account is locked
+const MAX_FAILED_LOGINS: i32 = 5;
+/// Lock duration after reaching the limit; doubles with every further failure
+const BASE_LOCK_SECONDS: f64 = 15.0 * 60.0;
+/// How long an emailed login link stays valid
+pub const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+
+// Hash verified when the email is unknown so the response takes as long as a real check
+static DUMMY_PASSWORD_HASH: OnceLock<String> = OnceLock::new();
+
+/// Checks `password` against a throwaway hash, taking as long as a real password check
+fn verify_dummy_password(password: &str) {
+    let dummy_hash = DUMMY_PASSWORD_HASH.get_or_init(|| {
+        hash("dummy-password", DEFAULT_COST).expect("Failed to hash dummy password")
+    });
+    let _ = verify(password.as_bytes(), dummy_hash);
+}
+
+/// Errors returned by [`AuthService`]
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Invalid email or password")]
+    InvalidCredentials,
+    #[error("Too many failed login attempts. Please try again later.")]
+    TooManyAttempts { retry_after_secs: u64 },
+    #[error("Email address has not been verified")]
+    EmailNotVerified { email: String },
+    #[error("Account has been suspended")]
+    AccountSuspended,
+    #[error("Account is not active")]
+    AccountInactive,
+    #[error("This login link is invalid, expired or has already been used")]
+    InvalidMagicLink,
+    #[error(transparent)]
+    Registration(#[from] RegistrationError),
+    #[error("Failed to hash or verify password: {0}")]
+    PasswordHash(#[from] bcrypt::BcryptError),
+    #[error("Failed to create token: {0}")]
+    Token(#[from] jsonwebtoken::errors::Error),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl AuthError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::InvalidCredentials | AuthError::InvalidMagicLink => StatusCode::UNAUTHORIZED,
+            AuthError::TooManyAttempts { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AuthError::EmailNotVerified { .. }
+            | AuthError::AccountSuspended
+            | AuthError::AccountInactive => StatusCode::FORBIDDEN,
+            AuthError::Registration(e) => e.status_code(),
+            AuthError::PasswordHash(_) | AuthError::Token(_) | AuthError::Database(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthError::InvalidCredentials => "INVALID_CREDENTIALS",
+            AuthError::TooManyAttempts { .. } => "TOO_MANY_ATTEMPTS",
+            AuthError::EmailNotVerified { .. } => "EMAIL_NOT_VERIFIED",
+            AuthError::AccountSuspended => "ACCOUNT_SUSPENDED",
+            AuthError::AccountInactive => "ACCOUNT_INACTIVE",
+            AuthError::InvalidMagicLink => "INVALID_MAGIC_LINK",
+            AuthError::Registration(e) => e.code(),
+            AuthError::PasswordHash(_) | AuthError::Token(_) | AuthError::Database(_) => {
+                "INTERNAL_ERROR"
+            }
+        }
+    }
+
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AuthError::TooManyAttempts { retry_after_secs } => {
+                Some(json!({ "retry_after_secs": retry_after_secs }))
+            }
+            AuthError::EmailNotVerified { email } => Some(json!({
+                "email": email,
+                "resend_otp_endpoint": "/api/auth/resend-otp",
+                "redirect": "/verify-email"
+            })),
+            AuthError::Registration(e) => e.details(),
+            _ => None,
+        }
+    }
+}
+
+/// A signed, single-use login link token
+pub struct MagicLink {
+    pub token: String,
+    pub jti: String,
+    /// Language of the email carrying the link
+    pub locale: Locale,
+}
+
 #[derive(Clone)]
 pub struct AuthService {
-    pool: PgPool,
-    jwt_secret: String,
+    repositories: Repositories,
+    jwt_keys: JwtKeys,
+    login_guard: LoginGuard,
 }
 
 impl AuthService {
-    pub fn new(pool: PgPool, jwt_secret: String) -> Self {
-        Self { pool, jwt_secret }
+    pub fn new(repositories: Repositories, jwt_keys: JwtKeys) -> Self {
+        Self {
+            repositories,
+            jwt_keys,
+            login_guard: LoginGuard::new(),
+        }
     }
 
-    pub fn get_jwt_secret(&self) -> &str {
-        &self.jwt_secret
+    pub fn jwt_keys(&self) -> &JwtKeys {
+        &self.jwt_keys
     }
 
-    pub fn get_pool(&self) -> &PgPool {
-        &self.pool
+    pub fn repositories(&self) -> &Repositories {
+        &self.repositories
     }
 
-    pub async fn check_user_exists(
+    /// Creates an account waiting for email verification, together with its verification
+    /// email. With `invite_hash`, one use of that invite code is redeemed as well.
+    pub async fn register(
         &self,
-        email: &str,
-        username: &str,
-    ) -> Result<bool, sqlx::Error> {
-        queries::check_user_exists(&self.pool, email, username).await
+        req: RegisterRequest,
+        invite_hash: Option<&str>,
+        verification: NewEmail,
+    ) -> Result<Registration, AuthError> {
+        let password_hash = hash(req.password.as_bytes(), DEFAULT_COST)?;
+
+        let user = NewUser {
+            email: req.email,
+            username: req.username,
+            password_hash,
+            gender: req.gender,
+            status: UserStatus::PendingVerification,
+            is_verified: false,
+            locale: req.locale.as_str().to_string(),
+        };
+        Ok(self
+            .repositories
+            .users
+            .register(user, invite_hash, verification)
+            .await?)
     }
 
-    pub async fn register(&self, req: RegisterRequest) -> Result<User, sqlx::Error> {
-        let password_hash = hash(req.password.as_bytes(), DEFAULT_COST)
-            .map_err(|e| sqlx::Error::Protocol(format!("Failed to hash password: {e}")))?;
+    pub async fn login(
+        &self,
+        email: &str,
+        password: &str,
+        client_ip: IpAddr,
+    ) -> Result<AuthResponse, AuthError> {
+        match self.login_guard.check(client_ip) {
+            IpCheck::Blocked(remaining) => {
+                return Err(AuthError::TooManyAttempts {
+                    retry_after_secs: remaining.as_secs().max(1),
+                });
+            }
+            IpCheck::Allowed(delay) if !delay.is_zero() => tokio::time::sleep(delay).await,
+            IpCheck::Allowed(_) => {}
+        }
 
-        sqlx::query_as!(
-            User,
-            r#"
-            INSERT INTO users (
-                email, username, password_hash, gender, 
-                status, is_verified, verification_attempts
-            )
-            VALUES ($1, $2, $3, $4, $5, false, 0)
-            RETURNING 
-                id, email, username, password_hash, 
-                gender as "gender: _", 
-                status as "status: _",
-                is_verified,
-                verification_attempts,
-                verified_at,
-                created_at, 
-                updated_at
-            "#,
-            req.email,
-            req.username,
-            password_hash,
-            req.gender as _,
-            UserStatus::PendingVerification as _
-        )
-        .fetch_one(&self.pool)
-        .await
-    }
-
-    pub async fn login(&self, email: &str, password: &str) -> Result<AuthResponse, sqlx::Error> {
-        let user = sqlx::query_as!(
-            User,
-            r#"
-            SELECT 
-                id, email, username, password_hash, 
-                gender as "gender: _",
-                status as "status: _",
-                is_verified,
-                verification_attempts,
-                verified_at,
-                created_at, 
-                updated_at
-            FROM users
-            WHERE email = $1
-            "#,
-            email
-        )
-        .fetch_one(&self.pool)
-        .await?;
-
-        if !verify(password.as_bytes(), &user.password_hash)
-            .map_err(|e| sqlx::Error::Protocol(format!("Failed to verify password: {e}")))?
-        {
-            return Err(sqlx::Error::Protocol("Invalid password".to_string()));
+        let Some(user) = self.find_user_by_email(email).await? else {
+            // Spend the same time as a real password check so unknown emails can't be detected
+            verify_dummy_password(password);
+            self.login_guard.record_failure(client_ip);
+            return Err(AuthError::InvalidCredentials);
+        };
+
+        // A locked account answers like a wrong password whatever was sent, so guessing
+        // on gets nowhere and learns nothing. These attempts don't extend the lock.
+        if user.locked_until.is_some_and(|until| until > Utc::now()) {
+            verify_dummy_password(password);
+            self.login_guard.record_failure(client_ip);
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        let password_valid = verify(password.as_bytes(), &user.password_hash)?;
+
+        if !password_valid {
+            self.login_guard.record_failure(client_ip);
+            let locked_until = self
+                .repositories
+                .users
+                .record_failed_login(user.id, MAX_FAILED_LOGINS, BASE_LOCK_SECONDS)
+                .await?;
+            if let Some(until) = locked_until.filter(|until| *until > Utc::now()) {
+                tracing::warn!(
+                    user_id = %user.id,
+                    ip = %client_ip,
+                    locked_until = %until,
+                    "Account locked after repeated failed logins"
+                );
+            }
+            return Err(AuthError::InvalidCredentials);
+        }
+
+        if user.failed_login_attempts > 0 || user.locked_until.is_some() {
+            self.repositories.users.reset_failed_logins(user.id).await?;
         }
 
         // Check if user is verified
         if !user.is_verified {
-            return Err(sqlx::Error::Protocol("Email not verified".to_string()));
+            return Err(AuthError::EmailNotVerified { email: user.email });
         }
 
-        // Check if user is active
-        if user.status != UserStatus::Active {
-            return Err(sqlx::Error::Protocol("Account is not active".to_string()));
+        Self::ensure_active(&user)?;
+
+        let token = self.create_token(&user)?;
+        Ok(AuthResponse { token, user })
+    }
+
+    /// Creates a signed login link token for an active, verified account.
+    ///
+    /// Returns `None` if the email doesn't belong to such an account, so callers can
+    /// respond the same way either way.
+    pub async fn create_magic_link(&self, email: &str) -> Result<Option<MagicLink>, AuthError> {
+        let Some(user) = self.find_user_by_email(email).await? else {
+            return Ok(None);
+        };
+        if !user.is_verified || user.status != UserStatus::Active {
+            return Ok(None);
         }
 
+        let jti = Uuid::new_v4().to_string();
+        let locale = Locale::from_tag(&user.locale);
+        let claims = MagicLinkClaims {
+            sub: user.id,
+            exp: (Utc::now() + Duration::minutes(MAGIC_LINK_TTL_MINUTES)).timestamp(),
+            email: user.email,
+            jti: jti.clone(),
+        };
+        let token = self.jwt_keys.sign(&claims)?;
+
+        Ok(Some(MagicLink { token, jti, locale }))
+    }
+
+    /// Checks the signature and expiry of a login link token.
+    /// Callers must also make sure the link's `jti` hasn't been used before.
+    pub fn decode_magic_link(&self, token: &str) -> Result<MagicLinkClaims, AuthError> {
+        self.jwt_keys
+            .verify::<MagicLinkClaims>(token)
+            .map(|token| token.claims)
+            .map_err(|_| AuthError::InvalidMagicLink)
+    }
+
+    /// Signs in the owner of a consumed login link
+    pub async fn login_with_magic_link(
+        &self,
+        claims: MagicLinkClaims,
+    ) -> Result<AuthResponse, AuthError> {
+        let user = self
+            .find_user_by_id(claims.sub)
+            .await?
+            .filter(|user| user.email == claims.email)
+            .ok_or(AuthError::InvalidMagicLink)?;
+
+        if !user.is_verified {
+            return Err(AuthError::EmailNotVerified { email: user.email });
+        }
+        Self::ensure_active(&user)?;
+
+        let token = self.create_token(&user)?;
+        Ok(AuthResponse { token, user })
+    }
+
+    /// Signs in a user authenticated by the OpenID Connect provider.
+    ///
+    /// The identity is matched by issuer and subject first. An unknown identity is linked
+    /// to a verified account with the same (provider-verified) email. Otherwise a new
+    /// verified account is created for it, replacing any unverified registration for the
+    /// email, whose password was set by someone who never proved they own the address.
+    ///
+    /// New accounts must pass `registration` like any other. There is no invite code to
+    /// redeem, so in invite mode only existing accounts can sign in this way.
+    pub async fn login_with_oidc(
+        &self,
+        identity: OidcIdentity,
+        registration: &RegistrationPolicy,
+    ) -> Result<AuthResponse, AuthError> {
+        let linked = self
+            .repositories
+            .users
+            .find_identity_user(&identity.issuer, &identity.subject)
+            .await?;
+
+        let user = match linked {
+            Some(user_id) => self.find_user_by_id(user_id).await?,
+            None => None,
+        };
+
+        let user = match user {
+            Some(user) => user,
+            None => match self.find_user_by_email(&identity.email).await? {
+                Some(user) if user.is_verified => {
+                    tracing::info!(
+                        user_id = %user.id,
+                        issuer = %identity.issuer,
+                        "Linked existing account to OpenID Connect identity"
+                    );
+                    user
+                }
+                existing => {
+                    registration.check_email(&identity.email)?;
+                    registration.invite_hash(None)?;
+
+                    if existing.is_some() {
+                        let deleted = self
+                            .repositories
+                            .users
+                            .delete_unverified(&identity.email)
+                            .await?;
+                        tracing::info!(
+                            issuer = %identity.issuer,
+                            deleted,
+                            "Replaced unverified registration with OpenID Connect account"
+                        );
+                    }
+                    self.provision_oidc_user(&identity).await?
+                }
+            },
+        };
+
+        self.repositories
+            .users
+            .upsert_identity(
+                user.id,
+                &identity.issuer,
+                &identity.subject,
+                &identity.email,
+            )
+            .await?;
+
+        // Reload so the linked account's current state is checked
+        let user = self
+            .find_user_by_id(user.id)
+            .await?
+            .ok_or(AuthError::InvalidCredentials)?;
+
+        Self::ensure_active(&user)?;
+
         let token = self.create_token(&user)?;
         Ok(AuthResponse { token, user })
     }
 
+    /// Creates a verified, active account for a first-time single sign-on user
+    async fn provision_oidc_user(&self, identity: &OidcIdentity) -> Result<User, AuthError> {
+        let username = self.unique_username(identity).await?;
+        // The account has no usable password until the user sets one
+        let random_password: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let password_hash = hash(random_password.as_bytes(), DEFAULT_COST)?;
+
+        let user = self
+            .repositories
+            .users
+            .create(NewUser {
+                email: identity.email.clone(),
+                username,
+                password_hash,
+                gender: Gender::Other,
+                status: UserStatus::Active,
+                is_verified: true,
+                locale: Locale::default().as_str().to_string(),
+            })
+            .await?;
+
+        tracing::info!(
+            user_id = %user.id,
+            issuer = %identity.issuer,
+            "Provisioned account for OpenID Connect identity"
+        );
+        Ok(user)
+    }
+
+    /// Derives a free username from the provider's preferred username or the email
+    async fn unique_username(&self, identity: &OidcIdentity) -> Result<String, sqlx::Error> {
+        let source = identity
+            .preferred_username
+            .as_deref()
+            .unwrap_or_else(|| identity.email.split('@').next().unwrap_or_default());
+        let mut base: String = source
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .take(40)
+            .collect();
+        if base.len() < 3 {
+            base = format!("user_{base}");
+        }
+
+        let users = &self.repositories.users;
+        if !users.username_exists(&base).await? {
+            return Ok(base);
+        }
+        loop {
+            let candidate = format!("{base}_{}", rand::rng().random_range(1000..10000));
+            if !users.username_exists(&candidate).await? {
+                return Ok(candidate);
+            }
+        }
+    }
+
+    fn ensure_active(user: &User) -> Result<(), AuthError> {
+        match user.status {
+            UserStatus::Active => Ok(()),
+            UserStatus::Suspended => Err(AuthError::AccountSuspended),
+            UserStatus::Inactive => Err(AuthError::AccountInactive),
+            UserStatus::PendingVerification => Err(AuthError::EmailNotVerified {
+                email: user.email.clone(),
+            }),
+        }
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        self.repositories.users.find_by_email(email).await
+    }
+
+    async fn find_user_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        self.repositories.users.find_by_id(id).await
+    }
+
+    /// Unlocks an account locked after failed logins
+    pub async fn unlock_account(&self, email: &str) -> Result<bool, sqlx::Error> {
+        let unlocked = self.repositories.users.unlock_account(email).await?;
+        if unlocked {
+            tracing::info!(email = %email, "Account unlocked");
+        }
+        Ok(unlocked)
+    }
+
     pub async fn complete_verification(&self, email: &str) -> Result<(), sqlx::Error> {
-        queries::complete_registration(&self.pool, email).await
+        self.repositories.users.complete_registration(email).await
     }
 
-    fn create_token(&self, user: &User) -> Result<String, sqlx::Error> {
+    fn create_token(&self, user: &User) -> Result<String, AuthError> {
         let expiration = Utc::now()
             .checked_add_signed(Duration::hours(24))
             .expect("Valid timestamp")
@@ -123,13 +473,12 @@ impl AuthService {
             exp: expiration,
             email: user.email.clone(),
             username: user.username.clone(),
+            role: user.role,
+            session_version: user.session_version,
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| sqlx::Error::Protocol(format!("Failed to create token: {e}")))
+        let token = self.jwt_keys.sign(&claims)?;
+
+        Ok(token)
     }
 }
@@ -0,0 +1,204 @@
+//! Background delivery of the email outbox.
+//!
+//! Emails are queued in the `email_outbox` table, usually in the same transaction as the change
+//! that triggers them. The worker claims due emails, renders and sends them, and reschedules
+//! failures with exponential backoff. After [`MAX_DELIVERY_ATTEMPTS`] an email is moved to the
+//! dead letter state, where an admin can replay it. On Postgres, workers are woken through
+//! notifications and poll as a fallback, so several instances can share one outbox. Other
+//! backends are only polled.
+
+use rand::Rng;
+use sqlx::{postgres::PgListener, PgPool};
+use std::{sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+
+use crate::{
+    database::{models::QueuedEmail, repository::OutboxRepository},
+    metrics,
+    services::{
+        email_sender::{EmailSender, OutgoingEmail},
+        email_templates::{self, EmailTemplate, Locale},
+    },
+    shutdown::Shutdown,
+};
+
+/// Notification channel used by the `email_outbox_notify` trigger
+const NOTIFY_CHANNEL: &str = "email_outbox";
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+const BATCH_SIZE: i64 = 20;
+/// How long a claimed email is hidden from other workers
+const LEASE_SECONDS: i64 = 120;
+/// Delivery attempts before an email is moved to the dead letter state
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+const BASE_RETRY_SECONDS: i64 = 15;
+const MAX_RETRY_SECONDS: i64 = 60 * 60;
+
+/// Why an email couldn't be delivered
+enum DeliveryError {
+    /// Retrying can't help, e.g. the template no longer exists
+    Permanent(String),
+    Transient(String),
+}
+
+pub struct OutboxWorker {
+    outbox: Arc<dyn OutboxRepository>,
+    sender: Arc<dyn EmailSender>,
+    /// Where to listen for outbox notifications, polling only without it
+    notifications: Option<PgPool>,
+}
+
+impl OutboxWorker {
+    pub fn new(outbox: Arc<dyn OutboxRepository>, sender: Arc<dyn EmailSender>) -> Self {
+        Self {
+            outbox,
+            sender,
+            notifications: None,
+        }
+    }
+
+    /// Wakes the worker through the notifications of the `email_outbox_notify` trigger on
+    /// this database instead of waiting for the next poll
+    pub fn listen_on(mut self, pool: PgPool) -> Self {
+        self.notifications = Some(pool);
+        self
+    }
+
+    /// Starts delivering queued emails in the background. On shutdown the worker finishes
+    /// the batch it is sending; the rest stays queued for the next start.
+    pub fn spawn(self, shutdown: &Shutdown) -> JoinHandle<()> {
+        shutdown.spawn(self.run(shutdown.clone()))
+    }
+
+    async fn run(self, shutdown: Shutdown) {
+        let mut listener = match &self.notifications {
+            Some(pool) => match listen(pool).await {
+                Ok(listener) => Some(listener),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to listen for outbox notifications, polling every {}s instead: {e}",
+                        POLL_INTERVAL.as_secs()
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        tracing::info!("Email outbox worker started");
+
+        loop {
+            self.deliver_due(&shutdown).await;
+
+            let wait = async {
+                match listener.as_mut() {
+                    Some(listener) => {
+                        if let Ok(Err(e)) =
+                            tokio::time::timeout(POLL_INTERVAL, listener.recv()).await
+                        {
+                            tracing::warn!("Lost connection for outbox notifications: {e}");
+                            tokio::time::sleep(POLL_INTERVAL).await;
+                        }
+                    }
+                    None => tokio::time::sleep(POLL_INTERVAL).await,
+                }
+            };
+            tokio::select! {
+                _ = wait => {}
+                _ = shutdown.triggered() => break,
+            }
+        }
+        tracing::info!("Email outbox worker stopped");
+    }
+
+    /// Delivers emails until none are due or shutdown starts
+    async fn deliver_due(&self, shutdown: &Shutdown) {
+        while !shutdown.is_triggered() {
+            let batch = match self.outbox.claim_due(BATCH_SIZE, LEASE_SECONDS).await {
+                Ok(batch) => batch,
+                Err(e) => {
+                    tracing::error!("Failed to claim emails from the outbox: {e}");
+                    return;
+                }
+            };
+            let exhausted = (batch.len() as i64) < BATCH_SIZE;
+
+            for email in batch {
+                self.deliver(email).await;
+            }
+
+            if exhausted {
+                return;
+            }
+        }
+    }
+
+    async fn deliver(&self, email: QueuedEmail) {
+        let result = match self.send(&email).await {
+            Ok(()) => {
+                tracing::info!(email_id = %email.id, template = %email.template, "Email sent");
+                metrics::record_email_delivery(&email.template, "sent");
+                self.outbox.mark_sent(email.id).await
+            }
+            Err(DeliveryError::Transient(e)) if email.attempts < MAX_DELIVERY_ATTEMPTS => {
+                let retry_in = retry_delay(email.attempts);
+                tracing::warn!(
+                    email_id = %email.id,
+                    attempt = email.attempts,
+                    retry_in_secs = retry_in,
+                    "Failed to send email: {e}"
+                );
+                metrics::record_email_delivery(&email.template, "retrying");
+                self.outbox.mark_failed(email.id, &e, Some(retry_in)).await
+            }
+            Err(DeliveryError::Transient(e)) | Err(DeliveryError::Permanent(e)) => {
+                tracing::error!(
+                    email_id = %email.id,
+                    attempts = email.attempts,
+                    "Giving up on email, moved to the dead letter state: {e}"
+                );
+                metrics::record_email_delivery(&email.template, "dead");
+                self.outbox.mark_failed(email.id, &e, None).await
+            }
+        };
+
+        if let Err(e) = result {
+            // The lease runs out and the email is picked up again
+            tracing::error!(email_id = %email.id, "Failed to record email delivery: {e}");
+        }
+    }
+
+    async fn send(&self, email: &QueuedEmail) -> Result<(), DeliveryError> {
+        let template: EmailTemplate = email
+            .template
+            .parse()
+            .map_err(|e: email_templates::TemplateError| DeliveryError::Permanent(e.to_string()))?;
+        let rendered =
+            email_templates::render(template, Locale::from_tag(&email.locale), &email.variables)
+                .map_err(|e| DeliveryError::Permanent(e.to_string()))?;
+
+        let outgoing = OutgoingEmail {
+            to: email.recipient.clone(),
+            subject: rendered.subject,
+            html: rendered.html,
+            text: rendered.text,
+        };
+        self.sender
+            .send(&outgoing)
+            .await
+            .map_err(|e| DeliveryError::Transient(e.to_string()))
+    }
+}
+
+async fn listen(pool: &PgPool) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(NOTIFY_CHANNEL).await?;
+    Ok(listener)
+}
+
+/// Exponential backoff with up to 25% jitter, so failed emails don't retry in lockstep
+fn retry_delay(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    let delay = BASE_RETRY_SECONDS
+        .saturating_mul(1 << exponent)
+        .min(MAX_RETRY_SECONDS);
+    delay + rand::rng().random_range(0..=delay / 4)
+}
@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use lettre::{message::Mailbox, AsyncFileTransport, AsyncTransport, Tokio1Executor};
+use std::path::PathBuf;
+
+use super::{EmailSender, EmailSenderError, OutgoingEmail};
+
+/// Writes every email to `<dir>/<uuid>.eml`, for inspecting mail in development and CI
+#[derive(Clone)]
+pub struct FileEmailSender {
+    from: Mailbox,
+    dir: PathBuf,
+    transport: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileEmailSender {
+    /// Creates `dir` if it doesn't exist
+    pub fn new(from: Mailbox, dir: impl Into<PathBuf>) -> Result<Self, EmailSenderError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            EmailSenderError::Config(format!("cannot create {}: {e}", dir.display()))
+        })?;
+
+        Ok(Self {
+            from,
+            transport: AsyncFileTransport::new(&dir),
+            dir,
+        })
+    }
+}
+
+#[async_trait]
+impl EmailSender for FileEmailSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailSenderError> {
+        let id = self.transport.send(email.to_message(&self.from)?).await?;
+        tracing::info!(
+            to = %email.to,
+            path = %self.dir.join(format!("{id}.eml")).display(),
+            "Email written to file"
+        );
+        Ok(())
+    }
+
+    /// The drop directory may have been removed or made read-only since startup
+    async fn check(&self) -> Result<(), EmailSenderError> {
+        let unusable = |reason: String| {
+            EmailSenderError::Config(format!("cannot write to {}: {reason}", self.dir.display()))
+        };
+        let metadata = tokio::fs::metadata(&self.dir)
+            .await
+            .map_err(|e| unusable(e.to_string()))?;
+        if !metadata.is_dir() {
+            return Err(unusable("not a directory".to_string()));
+        }
+        if metadata.permissions().readonly() {
+            return Err(unusable("read-only".to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+
+use super::{EmailSender, EmailSenderError, OutgoingEmail};
+
+/// Logs the plain text body instead of sending anything. Development only: the log
+/// then contains verification codes and login links.
+#[derive(Clone)]
+pub struct LogEmailSender {
+    from: Mailbox,
+}
+
+impl LogEmailSender {
+    pub fn new(from: Mailbox) -> Self {
+        Self { from }
+    }
+}
+
+#[async_trait]
+impl EmailSender for LogEmailSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailSenderError> {
+        tracing::info!(
+            from = %self.from,
+            to = %email.to,
+            subject = %email.subject,
+            "Email not delivered (log backend):\n{}",
+            email.text
+        );
+        Ok(())
+    }
+}
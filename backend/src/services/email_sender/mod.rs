@@ -0,0 +1,133 @@
+//! Delivery of outgoing emails. [`EmailService`](super::email::EmailService) decides what
+//! to send; an [`EmailSender`] decides how it leaves the process.
+
+mod file;
+mod log;
+mod resend;
+mod smtp;
+
+pub use self::file::FileEmailSender;
+pub use self::log::LogEmailSender;
+pub use self::resend::ResendEmailSender;
+pub use self::smtp::SmtpEmailSender;
+
+use async_trait::async_trait;
+use lettre::{
+    message::{Mailbox, MultiPart, SinglePart},
+    Message,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::config::{EmailBackend, EmailConfig};
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailSenderError {
+    #[error("Invalid email configuration: {0}")]
+    Config(String),
+    #[error("Invalid email address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("Failed to build email: {0}")]
+    Message(#[from] lettre::error::Error),
+    #[error("SMTP delivery failed: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("SMTP server is unavailable: {0}")]
+    SmtpUnavailable(String),
+    #[error("Failed to write email file: {0}")]
+    File(#[from] lettre::transport::file::Error),
+    #[error("Resend request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Resend rejected the email: {0}")]
+    Resend(String),
+}
+
+/// A rendered email ready for delivery
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+impl OutgoingEmail {
+    /// Builds a MIME message with plain text and HTML alternatives
+    fn to_message(&self, from: &Mailbox) -> Result<Message, EmailSenderError> {
+        Ok(Message::builder()
+            .from(from.clone())
+            .to(self.to.parse()?)
+            .subject(&self.subject)
+            .multipart(
+                MultiPart::alternative()
+                    .singlepart(SinglePart::plain(self.text.clone()))
+                    .singlepart(SinglePart::html(self.html.clone())),
+            )?)
+    }
+}
+
+#[async_trait]
+pub trait EmailSender: Send + Sync {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailSenderError>;
+
+    /// Checks that emails could be delivered right now, without sending one
+    async fn check(&self) -> Result<(), EmailSenderError> {
+        Ok(())
+    }
+}
+
+/// Backend selected with `email.backend`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailBackendKind {
+    Smtp,
+    Resend,
+    File,
+    Log,
+}
+
+impl std::str::FromStr for EmailBackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "smtp" => Ok(Self::Smtp),
+            "resend" => Ok(Self::Resend),
+            "file" => Ok(Self::File),
+            "log" => Ok(Self::Log),
+            _ => Err("expected smtp, resend, file or log".to_string()),
+        }
+    }
+}
+
+/// Creates the sender selected by `email.backend`. The sender address is the same for
+/// every backend.
+pub fn from_config(config: &EmailConfig) -> Result<Arc<dyn EmailSender>, EmailSenderError> {
+    let from = Mailbox::new(Some(config.from_name.clone()), config.from_email.parse()?);
+
+    let sender: Arc<dyn EmailSender> = match &config.backend {
+        EmailBackend::Smtp {
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+        } => Arc::new(SmtpEmailSender::new(
+            from,
+            smtp_host,
+            *smtp_port,
+            smtp_username.clone(),
+            smtp_password.expose().to_string(),
+        )?),
+        EmailBackend::Resend { resend_api_key } => Arc::new(ResendEmailSender::new(
+            from,
+            resend_api_key.expose().to_string(),
+        )),
+        EmailBackend::File { drop_dir } => Arc::new(FileEmailSender::new(from, drop_dir)?),
+        EmailBackend::Log => {
+            tracing::warn!("Emails are only logged, not delivered (email backend is log)");
+            Arc::new(LogEmailSender::new(from))
+        }
+    };
+
+    tracing::info!(backend = ?config.backend.kind(), "Email sender initialized");
+    Ok(sender)
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use reqwest::Client;
+use serde_json::json;
+use std::time::Duration;
+
+use super::{EmailSender, EmailSenderError, OutgoingEmail};
+
+const RESEND_API_URL: &str = "https://api.resend.com/emails";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delivers through the Resend HTTP API
+#[derive(Clone)]
+pub struct ResendEmailSender {
+    from: Mailbox,
+    api_key: String,
+    client: Client,
+}
+
+impl ResendEmailSender {
+    pub fn new(from: Mailbox, api_key: String) -> Self {
+        let client = Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            from,
+            api_key,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailSender for ResendEmailSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailSenderError> {
+        let response = self
+            .client
+            .post(RESEND_API_URL)
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "from": self.from.to_string(),
+                "to": [email.to],
+                "subject": email.subject,
+                "html": email.html,
+                "text": email.text,
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(EmailSenderError::Resend(format!("{status}: {body}")));
+        }
+
+        Ok(())
+    }
+}
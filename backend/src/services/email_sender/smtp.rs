@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox,
+    transport::smtp::authentication::Credentials,
+    transport::smtp::client::{Tls, TlsParameters},
+    AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
+};
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{EmailSender, EmailSenderError, OutgoingEmail};
+
+/// How long readiness checks reuse the outcome of the last connection check or send, so
+/// probes don't open a connection to the relay every time
+const CHECK_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// When the relay was last found reachable or not, and the error if it wasn't
+type LastCheck = Option<(Instant, Result<(), String>)>;
+
+/// Delivers through an SMTP relay
+#[derive(Clone)]
+pub struct SmtpEmailSender {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    last_check: Arc<Mutex<LastCheck>>,
+}
+
+impl SmtpEmailSender {
+    pub fn new(
+        from: Mailbox,
+        host: &str,
+        port: u16,
+        username: String,
+        password: String,
+    ) -> Result<Self, EmailSenderError> {
+        let creds = Credentials::new(username, password);
+
+        // Configure TLS parameters
+        let tls_parameters = TlsParameters::new(host.to_string())?;
+
+        let transport = if port == 465 {
+            // Use implicit TLS for port 465
+            AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+                .port(port)
+                .credentials(creds)
+                .tls(Tls::Wrapper(tls_parameters))
+                .build()
+        } else {
+            // Use STARTTLS for port 587 and others
+            AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+                .port(port)
+                .credentials(creds)
+                .tls(Tls::Required(tls_parameters))
+                .build()
+        };
+
+        Ok(Self {
+            from,
+            transport,
+            last_check: Arc::default(),
+        })
+    }
+
+    fn last_check(&self) -> std::sync::MutexGuard<'_, LastCheck> {
+        self.last_check.lock().expect("SMTP check lock poisoned")
+    }
+}
+
+#[async_trait]
+impl EmailSender for SmtpEmailSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailSenderError> {
+        let result = self.transport.send(email.to_message(&self.from)?).await;
+        // A delivered email shows the relay works. A failed one may only be a rejected
+        // recipient, so the next check connects to find out.
+        *self.last_check() = result.is_ok().then(|| (Instant::now(), Ok(())));
+        result?;
+        Ok(())
+    }
+
+    /// Connects and authenticates with the relay, at most once per [`CHECK_CACHE_TTL`]
+    async fn check(&self) -> Result<(), EmailSenderError> {
+        let cached = self
+            .last_check()
+            .as_ref()
+            .filter(|(checked_at, _)| checked_at.elapsed() < CHECK_CACHE_TTL)
+            .map(|(_, outcome)| outcome.clone());
+
+        let outcome = match cached {
+            Some(outcome) => outcome,
+            None => {
+                let outcome = match self.transport.test_connection().await {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err("the server did not accept the connection".to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                *self.last_check() = Some((Instant::now(), outcome.clone()));
+                outcome
+            }
+        };
+        outcome.map_err(EmailSenderError::SmtpUnavailable)
+    }
+}
@@ -0,0 +1,321 @@
+//! Named, localized email templates.
+//!
+//! Every template has an HTML and a plain text variant per locale under
+//! `templates/email/<locale>/`. The first line of the text variant is `Subject: ...`.
+//! HTML variants are wrapped in the locale's `layout.html`. Variables are written as
+//! `{{name}}`; values are HTML-escaped in the HTML variant.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, str::FromStr, sync::OnceLock};
+use utoipa::ToSchema;
+
+// Parsed templates, built on first use
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Unknown email template '{0}'")]
+    UnknownTemplate(String),
+    #[error("Unsupported locale '{0}'")]
+    UnsupportedLocale(String),
+    #[error("Template '{template}' is missing the variable '{variable}'")]
+    MissingVariable {
+        template: &'static str,
+        variable: String,
+    },
+}
+
+/// Language emails are sent in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Fr];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Fr => "fr",
+        }
+    }
+
+    /// Best match for a stored preference or language tag such as `fr-CA`,
+    /// falling back to English
+    pub fn from_tag(tag: &str) -> Self {
+        let language = tag.split(['-', '_']).next().unwrap_or_default();
+        language.parse().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| TemplateError::UnsupportedLocale(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmailTemplate {
+    OtpVerification,
+    MagicLink,
+    PasswordReset,
+    EmailChange,
+    SecurityAlert,
+    Digest,
+}
+
+impl EmailTemplate {
+    pub const ALL: [EmailTemplate; 6] = [
+        EmailTemplate::OtpVerification,
+        EmailTemplate::MagicLink,
+        EmailTemplate::PasswordReset,
+        EmailTemplate::EmailChange,
+        EmailTemplate::SecurityAlert,
+        EmailTemplate::Digest,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::OtpVerification => "otp_verification",
+            EmailTemplate::MagicLink => "magic_link",
+            EmailTemplate::PasswordReset => "password_reset",
+            EmailTemplate::EmailChange => "email_change",
+            EmailTemplate::SecurityAlert => "security_alert",
+            EmailTemplate::Digest => "digest",
+        }
+    }
+
+    /// Variables the template expects, with sample values used for previews
+    pub fn sample_variables(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            EmailTemplate::OtpVerification => &[("otp", "123456"), ("minutes", "5")],
+            EmailTemplate::MagicLink | EmailTemplate::PasswordReset => &[
+                (
+                    "link",
+                    "https://linksphere.example/magic-link?token=preview",
+                ),
+                ("minutes", "15"),
+            ],
+            EmailTemplate::EmailChange => &[
+                ("new_email", "new.address@example.com"),
+                (
+                    "link",
+                    "https://linksphere.example/confirm-email?token=preview",
+                ),
+                ("minutes", "60"),
+            ],
+            EmailTemplate::SecurityAlert => &[
+                ("event", "New sign-in"),
+                ("time", "2024-01-01 12:00 UTC"),
+                ("ip_address", "203.0.113.7"),
+            ],
+            EmailTemplate::Digest => &[
+                ("username", "john_doe"),
+                ("new_links", "4"),
+                ("total_clicks", "128"),
+                ("dashboard_url", "https://linksphere.example/dashboard"),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for EmailTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EmailTemplate {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EmailTemplate::ALL
+            .into_iter()
+            .find(|template| template.name() == s)
+            .ok_or_else(|| TemplateError::UnknownTemplate(s.to_string()))
+    }
+}
+
+/// A template rendered for one recipient
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+struct TemplateSource {
+    subject: &'static str,
+    html: &'static str,
+    text: &'static str,
+}
+
+struct Catalog {
+    layouts: HashMap<Locale, &'static str>,
+    templates: HashMap<(Locale, EmailTemplate), TemplateSource>,
+}
+
+macro_rules! template_files {
+    ($($locale:ident $dir:literal: [$($template:ident $file:literal),* $(,)?]),* $(,)?) => {
+        &[$($((
+            Locale::$locale,
+            EmailTemplate::$template,
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"), "/templates/email/", $dir, "/", $file, ".html"
+            )),
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"), "/templates/email/", $dir, "/", $file, ".txt"
+            )),
+        )),*),*]
+    };
+}
+
+type TemplateFile = (Locale, EmailTemplate, &'static str, &'static str);
+
+const TEMPLATE_FILES: &[TemplateFile] = template_files! {
+    En "en": [
+        OtpVerification "otp_verification",
+        MagicLink "magic_link",
+        PasswordReset "password_reset",
+        EmailChange "email_change",
+        SecurityAlert "security_alert",
+        Digest "digest",
+    ],
+    Fr "fr": [
+        OtpVerification "otp_verification",
+        MagicLink "magic_link",
+        PasswordReset "password_reset",
+        EmailChange "email_change",
+        SecurityAlert "security_alert",
+        Digest "digest",
+    ],
+};
+
+const LAYOUT_FILES: &[(Locale, &str)] = &[
+    (
+        Locale::En,
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/templates/email/en/layout.html"
+        )),
+    ),
+    (
+        Locale::Fr,
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/templates/email/fr/layout.html"
+        )),
+    ),
+];
+
+fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| {
+        let templates = TEMPLATE_FILES
+            .iter()
+            .map(|&(locale, template, html, text)| {
+                let (subject, text) = text
+                    .strip_prefix("Subject: ")
+                    .and_then(|rest| rest.split_once('\n'))
+                    .expect("Text email templates must start with a 'Subject: ' line");
+                let source = TemplateSource {
+                    subject: subject.trim(),
+                    html,
+                    text: text.trim_start_matches('\n'),
+                };
+                ((locale, template), source)
+            })
+            .collect();
+
+        Catalog {
+            layouts: LAYOUT_FILES.iter().copied().collect(),
+            templates,
+        }
+    })
+}
+
+/// Renders a template, falling back to English if it has no translation for `locale`
+pub fn render(
+    template: EmailTemplate,
+    locale: Locale,
+    variables: &HashMap<String, String>,
+) -> Result<RenderedEmail, TemplateError> {
+    let catalog = catalog();
+    let (locale, source) = match catalog.templates.get(&(locale, template)) {
+        Some(source) => (locale, source),
+        None => (Locale::En, &catalog.templates[&(Locale::En, template)]),
+    };
+
+    let lookup = |name: &str| variables.get(name).cloned();
+    let missing = |variable: String| TemplateError::MissingVariable {
+        template: template.name(),
+        variable,
+    };
+
+    let subject = substitute(source.subject, lookup).map_err(missing)?;
+    let text = substitute(source.text, lookup).map_err(missing)?;
+    let body =
+        substitute(source.html, |name| lookup(name).map(|v| escape_html(&v))).map_err(missing)?;
+
+    let layout = catalog.layouts[&locale];
+    let html = substitute(layout, |name| match name {
+        "body" => Some(body.clone()),
+        "subject" => Some(escape_html(&subject)),
+        _ => None,
+    })
+    .map_err(missing)?;
+
+    Ok(RenderedEmail {
+        subject,
+        html,
+        text,
+    })
+}
+
+/// Replaces every `{{name}}`. Returns the name of the first variable `lookup` doesn't know.
+fn substitute(source: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        output.push_str(&rest[..start]);
+        output.push_str(&lookup(name).ok_or_else(|| name.to_string())?);
+        rest = &rest[start + 2 + end + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,339 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode, get_current_timestamp,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
+};
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use crate::config::{JwtConfig, Secret};
+
+pub const PRIVATE_KEY_SUFFIX: &str = ".pem";
+pub const PUBLIC_KEY_SUFFIX: &str = ".pub.pem";
+
+/// `iss` of every token signed with [`JwtKeys`]
+pub const ISSUER: &str = "linksphere";
+
+/// Claims of one kind of token signed with [`JwtKeys`].
+///
+/// Each kind has its own `aud`, so a token issued for one purpose, such as an emailed login
+/// link, is never accepted as another, such as a session.
+pub trait TokenClaims: Serialize + DeserializeOwned {
+    /// `aud` of tokens of this kind
+    const AUDIENCE: &'static str;
+    /// Whether HS256 tokens signed with `jwt.legacy_secret`, which have no `aud`, are accepted
+    const ACCEPTS_LEGACY: bool = false;
+}
+
+/// `claims` with the registered claims added when signing
+#[derive(Serialize)]
+struct Registered<'a, T> {
+    iss: &'static str,
+    aud: &'static str,
+    iat: u64,
+    #[serde(flatten)]
+    claims: &'a T,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtKeyError {
+    #[error("Failed to read JWT keys: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid JWT key '{kid}': {reason}")]
+    InvalidKey { kid: String, reason: String },
+    #[error("No private key found in the JWT key directory")]
+    NoSigningKey,
+    #[error("Active JWT key '{0}' has no private key in the key directory")]
+    UnknownActiveKey(String),
+    #[error("No JWT key directory is configured and ephemeral keys are not enabled")]
+    NoKeyDir,
+}
+
+struct SigningKey {
+    kid: String,
+    key: EncodingKey,
+}
+
+struct VerificationKey {
+    key: DecodingKey,
+    jwk: Jwk,
+}
+
+struct KeyRing {
+    signing: SigningKey,
+    verification: HashMap<String, VerificationKey>,
+    /// Shared secret still accepted for HS256 tokens issued before the switch to EdDSA
+    legacy_secret: Option<DecodingKey>,
+}
+
+/// EdDSA keys used to sign and verify JWTs.
+///
+/// Keys are read from a directory of PEM files named after their key ID:
+/// `<kid>.pem` holds an Ed25519 private key (PKCS#8) that can sign and verify, and
+/// `<kid>.pub.pem` holds the public key of a retired key that is only used to verify
+/// tokens issued before a rotation.
+#[derive(Clone)]
+pub struct JwtKeys {
+    inner: Arc<KeyRing>,
+}
+
+impl JwtKeys {
+    /// Loads keys from `jwt.keys_dir`, signing with `jwt.active_kid` or, if unset, the
+    /// private key whose ID sorts last. Without a key directory, `jwt.ephemeral_key` must be
+    /// set and a key is generated, which invalidates all tokens on restart. If
+    /// `jwt.legacy_secret` is set, HS256 tokens signed with it are still accepted.
+    pub fn from_config(config: &JwtConfig) -> Result<Self, JwtKeyError> {
+        let legacy_secret = config.legacy_secret.as_ref().map(Secret::expose);
+
+        match &config.keys_dir {
+            Some(dir) => Self::from_dir(dir, config.active_kid.as_deref(), legacy_secret),
+            None if !config.ephemeral_key => Err(JwtKeyError::NoKeyDir),
+            None => {
+                tracing::warn!(
+                    "No JWT key directory is configured, signing tokens with an ephemeral key. \
+                     Tokens will be invalid after a restart."
+                );
+                let pem = generate_private_key_pem()?;
+                Self::from_private_pems(
+                    vec![("ephemeral".to_string(), pem.into_bytes())],
+                    Vec::new(),
+                    None,
+                    legacy_secret,
+                )
+            }
+        }
+    }
+
+    /// Loads every key in `dir`. See [`JwtKeys`] for the file layout.
+    pub fn from_dir(
+        dir: impl AsRef<Path>,
+        active_kid: Option<&str>,
+        legacy_secret: Option<&str>,
+    ) -> Result<Self, JwtKeyError> {
+        let mut private_keys = Vec::new();
+        let mut public_keys = Vec::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            if let Some(kid) = file_name.strip_suffix(PUBLIC_KEY_SUFFIX) {
+                public_keys.push((kid.to_string(), fs::read(&path)?));
+            } else if let Some(kid) = file_name.strip_suffix(PRIVATE_KEY_SUFFIX) {
+                private_keys.push((kid.to_string(), fs::read(&path)?));
+            }
+        }
+
+        Self::from_private_pems(private_keys, public_keys, active_kid, legacy_secret)
+    }
+
+    fn from_private_pems(
+        mut private_keys: Vec<(String, Vec<u8>)>,
+        public_keys: Vec<(String, Vec<u8>)>,
+        active_kid: Option<&str>,
+        legacy_secret: Option<&str>,
+    ) -> Result<Self, JwtKeyError> {
+        private_keys.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let active_kid = match active_kid {
+            Some(kid) => kid.to_string(),
+            None => private_keys
+                .last()
+                .map(|(kid, _)| kid.clone())
+                .ok_or(JwtKeyError::NoSigningKey)?,
+        };
+
+        let mut signing = None;
+        let mut verification = HashMap::new();
+
+        for (kid, pem_bytes) in private_keys {
+            let der = parse_pem(&kid, &pem_bytes)?;
+            let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der).map_err(|e| {
+                JwtKeyError::InvalidKey {
+                    kid: kid.clone(),
+                    reason: format!("not an Ed25519 private key: {e}"),
+                }
+            })?;
+
+            verification.insert(
+                kid.clone(),
+                verification_key(&kid, key_pair.public_key().as_ref()),
+            );
+            if kid == active_kid {
+                signing = Some(SigningKey {
+                    kid,
+                    key: EncodingKey::from_ed_der(&der),
+                });
+            }
+        }
+
+        for (kid, pem_bytes) in public_keys {
+            let der = parse_pem(&kid, &pem_bytes)?;
+            let Some(public_key) = der
+                .strip_prefix(&ED25519_SPKI_PREFIX)
+                .filter(|key| key.len() == ED25519_PUBLIC_KEY_LEN)
+            else {
+                return Err(JwtKeyError::InvalidKey {
+                    kid,
+                    reason: "not an Ed25519 public key".to_string(),
+                });
+            };
+            verification
+                .entry(kid.clone())
+                .or_insert_with(|| verification_key(&kid, public_key));
+        }
+
+        let signing = signing.ok_or(JwtKeyError::UnknownActiveKey(active_kid))?;
+        tracing::info!(
+            active_kid = %signing.kid,
+            verification_keys = verification.len(),
+            "Loaded JWT signing keys"
+        );
+
+        Ok(Self {
+            inner: Arc::new(KeyRing {
+                signing,
+                verification,
+                legacy_secret: legacy_secret.map(|s| DecodingKey::from_secret(s.as_bytes())),
+            }),
+        })
+    }
+
+    /// ID of the key new tokens are signed with
+    pub fn active_kid(&self) -> &str {
+        &self.inner.signing.kid
+    }
+
+    /// Signs `claims` with the active key, adding `iss`, `aud` and `iat`
+    pub fn sign<T: TokenClaims>(&self, claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(self.inner.signing.kid.clone());
+        let claims = Registered {
+            iss: ISSUER,
+            aud: T::AUDIENCE,
+            iat: get_current_timestamp(),
+            claims,
+        };
+        encode(&header, &claims, &self.inner.signing.key)
+    }
+
+    /// Verifies a token of kind `T` signed with any known key and returns its claims
+    pub fn verify<T: TokenClaims>(
+        &self,
+        token: &str,
+    ) -> Result<TokenData<T>, jsonwebtoken::errors::Error> {
+        use jsonwebtoken::errors::ErrorKind;
+
+        let header = decode_header(token)?;
+        match header.alg {
+            Algorithm::EdDSA => {
+                let key = header
+                    .kid
+                    .as_deref()
+                    .and_then(|kid| self.inner.verification.get(kid))
+                    .ok_or(ErrorKind::InvalidSignature)?;
+                let mut validation = Validation::new(Algorithm::EdDSA);
+                validation.set_issuer(&[ISSUER]);
+                validation.set_audience(&[T::AUDIENCE]);
+                validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+                decode(token, &key.key, &validation)
+            }
+            Algorithm::HS256 if T::ACCEPTS_LEGACY => {
+                let secret = self
+                    .inner
+                    .legacy_secret
+                    .as_ref()
+                    .ok_or(ErrorKind::InvalidAlgorithm)?;
+                decode(token, secret, &Validation::new(Algorithm::HS256))
+            }
+            _ => Err(ErrorKind::InvalidAlgorithm.into()),
+        }
+    }
+
+    /// Public keys in JWKS format, for other services verifying LinkSphere tokens
+    pub fn jwks(&self) -> JwkSet {
+        let mut keys: Vec<Jwk> = self
+            .inner
+            .verification
+            .values()
+            .map(|key| key.jwk.clone())
+            .collect();
+        keys.sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+        JwkSet { keys }
+    }
+}
+
+fn parse_pem(kid: &str, bytes: &[u8]) -> Result<Vec<u8>, JwtKeyError> {
+    pem::parse(bytes)
+        .map(|pem| pem.into_contents())
+        .map_err(|e| JwtKeyError::InvalidKey {
+            kid: kid.to_string(),
+            reason: e.to_string(),
+        })
+}
+
+fn verification_key(kid: &str, public_key: &[u8]) -> VerificationKey {
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(KeyAlgorithm::EdDSA),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(public_key),
+        }),
+    };
+
+    VerificationKey {
+        key: DecodingKey::from_ed_der(public_key),
+        jwk,
+    }
+}
+
+/// Length of a raw Ed25519 public key
+const ED25519_PUBLIC_KEY_LEN: usize = 32;
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo, followed by the raw 32-byte key
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// The public half of the private key `<kid>.pem`, as the contents of `<kid>.pub.pem`
+pub fn public_key_pem(kid: &str, private_pem: &[u8]) -> Result<String, JwtKeyError> {
+    let der = parse_pem(kid, private_pem)?;
+    let key_pair =
+        Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der).map_err(|e| JwtKeyError::InvalidKey {
+            kid: kid.to_string(),
+            reason: format!("not an Ed25519 private key: {e}"),
+        })?;
+
+    let mut spki = ED25519_SPKI_PREFIX.to_vec();
+    spki.extend_from_slice(key_pair.public_key().as_ref());
+    Ok(pem::encode(&pem::Pem::new("PUBLIC KEY", spki)))
+}
+
+/// Generates a new Ed25519 private key as a PKCS#8 PEM document
+pub fn generate_private_key_pem() -> Result<String, JwtKeyError> {
+    let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).map_err(|e| {
+        JwtKeyError::InvalidKey {
+            kid: "generated".to_string(),
+            reason: e.to_string(),
+        }
+    })?;
+    Ok(pem::encode(&pem::Pem::new(
+        "PRIVATE KEY",
+        document.as_ref(),
+    )))
+}
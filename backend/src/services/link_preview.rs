@@ -1,11 +1,22 @@
-use crate::database::models::LinkPreview;
+use crate::{database::models::LinkPreview, metrics};
 use anyhow::{anyhow, Context, Result};
 use reqwest::{header, Client};
 use scraper::{Html, Selector};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use url::Url;
 
 pub async fn fetch_link_preview(url: &str) -> Result<LinkPreview> {
+    let extractor = match Url::parse(url) {
+        Ok(parsed) if is_youtube_url(&parsed) => "youtube",
+        _ => "html",
+    };
+    let start = Instant::now();
+    let result = fetch(url).await;
+    metrics::record_preview_fetch(extractor, result.is_ok(), start.elapsed());
+    result
+}
+
+async fn fetch(url: &str) -> Result<LinkPreview> {
     let client = Client::builder()
         .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .timeout(Duration::from_secs(10))
@@ -0,0 +1,99 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Failures from one IP are forgotten after this window
+const IP_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// Failures from one IP before each attempt is slowed down
+const IP_DELAY_THRESHOLD: u32 = 3;
+/// Failures from one IP before it is blocked until the window ends
+const IP_MAX_FAILURES: u32 = 20;
+const BASE_DELAY_MS: u64 = 250;
+const MAX_DELAY: Duration = Duration::from_secs(5);
+/// Tracked IPs above which expired entries are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct IpFailures {
+    count: u32,
+    window_start: Instant,
+}
+
+impl IpFailures {
+    fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.window_start) >= IP_WINDOW
+    }
+}
+
+/// Outcome of checking an IP before a login attempt
+#[derive(Debug, PartialEq, Eq)]
+pub enum IpCheck {
+    /// The attempt may proceed after waiting for the given delay
+    Allowed(Duration),
+    /// The IP is blocked for the given remaining time
+    Blocked(Duration),
+}
+
+/// Tracks failed logins per client IP and slows down or blocks repeat offenders
+#[derive(Clone, Default)]
+pub struct LoginGuard {
+    failures: Arc<Mutex<HashMap<IpAddr, IpFailures>>>,
+}
+
+impl LoginGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks whether a login attempt from `ip` may proceed and how long it should be delayed
+    pub fn check(&self, ip: IpAddr) -> IpCheck {
+        let now = Instant::now();
+        let failures = self.failures.lock().expect("login guard lock poisoned");
+
+        let Some(entry) = failures.get(&ip).filter(|entry| !entry.is_expired(now)) else {
+            return IpCheck::Allowed(Duration::ZERO);
+        };
+
+        if entry.count >= IP_MAX_FAILURES {
+            let remaining = IP_WINDOW.saturating_sub(now.duration_since(entry.window_start));
+            return IpCheck::Blocked(remaining);
+        }
+
+        if entry.count < IP_DELAY_THRESHOLD {
+            return IpCheck::Allowed(Duration::ZERO);
+        }
+
+        let exponent = (entry.count - IP_DELAY_THRESHOLD).min(5);
+        let delay = Duration::from_millis(BASE_DELAY_MS << exponent).min(MAX_DELAY);
+        IpCheck::Allowed(delay)
+    }
+
+    /// Records a failed login from `ip`
+    pub fn record_failure(&self, ip: IpAddr) {
+        let now = Instant::now();
+        let mut failures = self.failures.lock().expect("login guard lock poisoned");
+
+        if failures.len() > PRUNE_THRESHOLD {
+            failures.retain(|_, entry| !entry.is_expired(now));
+        }
+
+        let entry = failures.entry(ip).or_insert(IpFailures {
+            count: 0,
+            window_start: now,
+        });
+        if entry.is_expired(now) {
+            *entry = IpFailures {
+                count: 0,
+                window_start: now,
+            };
+        }
+        entry.count += 1;
+
+        if entry.count == IP_MAX_FAILURES {
+            tracing::warn!(ip = %ip, "Blocking logins from IP after repeated failures");
+        }
+    }
+}
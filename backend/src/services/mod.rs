@@ -1,3 +1,15 @@
+pub mod api_token;
+pub mod audit;
 pub mod auth;
 pub mod email;
+pub mod email_outbox;
+pub mod email_sender;
+pub mod email_templates;
+pub mod jwt;
 pub mod link_preview;
+pub mod login_guard;
+pub mod oidc;
+pub mod otp_store;
+pub mod pending_cleanup;
+pub mod readiness;
+pub mod registration;
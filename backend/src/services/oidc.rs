@@ -0,0 +1,420 @@
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use rand::{distr::Alphanumeric, Rng};
+use reqwest::Client;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{OnceCell, RwLock};
+use url::Url;
+
+use crate::{
+    config::OidcConfig,
+    services::{
+        jwt::{JwtKeys, TokenClaims},
+        otp_store::{OtpStore, OtpStoreError},
+    },
+};
+
+/// How long a user has to complete the login at the identity provider
+pub const PENDING_LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Name of the cookie holding the signed login state
+pub const STATE_COOKIE: &str = "oidc_state";
+/// The cookie is only sent to the single sign-on endpoints
+const STATE_COOKIE_PATH: &str = "/api/auth/oidc";
+
+#[derive(Debug, thiserror::Error)]
+pub enum OidcError {
+    #[error("Single sign-on is not configured")]
+    NotConfigured,
+    #[error("Unknown or expired login state")]
+    InvalidState,
+    #[error("Identity provider request failed: {0}")]
+    Provider(String),
+    #[error("Invalid ID token: {0}")]
+    InvalidIdToken(String),
+    #[error("The identity provider did not return a verified email address")]
+    EmailNotVerified,
+    #[error("Failed to access login state: {0}")]
+    Store(#[from] OtpStoreError),
+    #[error("Failed to sign login state: {0}")]
+    Signing(#[from] jsonwebtoken::errors::Error),
+}
+
+impl OidcError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            OidcError::NotConfigured => StatusCode::NOT_FOUND,
+            OidcError::InvalidState | OidcError::InvalidIdToken(_) => StatusCode::UNAUTHORIZED,
+            OidcError::EmailNotVerified => StatusCode::FORBIDDEN,
+            OidcError::Provider(_) => StatusCode::BAD_GATEWAY,
+            OidcError::Store(_) | OidcError::Signing(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            OidcError::NotConfigured => "SSO_DISABLED",
+            OidcError::InvalidState => "SSO_INVALID_STATE",
+            OidcError::Provider(_) => "SSO_PROVIDER_ERROR",
+            OidcError::InvalidIdToken(_) => "SSO_INVALID_TOKEN",
+            OidcError::EmailNotVerified => "SSO_EMAIL_NOT_VERIFIED",
+            OidcError::Store(_) | OidcError::Signing(_) => "INTERNAL_ERROR",
+        }
+    }
+}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(e: reqwest::Error) -> Self {
+        OidcError::Provider(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    email_verified: bool,
+    preferred_username: Option<String>,
+}
+
+/// Some providers send `email_verified` as a string
+fn bool_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Bool(b) => Ok(b),
+        serde_json::Value::String(s) => Ok(s.eq_ignore_ascii_case("true")),
+        _ => Ok(false),
+    }
+}
+
+/// Identity asserted by a validated ID token
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: String,
+    pub preferred_username: Option<String>,
+}
+
+/// A started login, returned to the browser that started it
+#[derive(Debug)]
+pub struct AuthorizationRequest {
+    /// Provider URL to redirect the browser to
+    pub url: String,
+    /// Signed `state`, set as a cookie so the callback can only be completed by the same browser
+    pub state_cookie: String,
+}
+
+/// Kept in the OTP store under the login's `state` until the callback
+#[derive(Serialize, Deserialize)]
+struct PendingLogin {
+    code_verifier: String,
+    nonce: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateCookieClaims {
+    state: String,
+    exp: i64,
+}
+
+impl TokenClaims for StateCookieClaims {
+    const AUDIENCE: &'static str = "linksphere:oidc_state";
+}
+
+struct OidcClientInner {
+    config: OidcConfig,
+    http: Client,
+    metadata: OnceCell<ProviderMetadata>,
+    jwks: RwLock<Option<JwkSet>>,
+    /// Shared by all instances, so the callback may reach a different one than the login
+    store: Arc<dyn OtpStore>,
+    jwt_keys: JwtKeys,
+}
+
+/// OpenID Connect relying party using the authorization-code flow with PKCE
+#[derive(Clone)]
+pub struct OidcClient {
+    inner: Arc<OidcClientInner>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig, store: Arc<dyn OtpStore>, jwt_keys: JwtKeys) -> Self {
+        let http = Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            inner: Arc::new(OidcClientInner {
+                config,
+                http,
+                metadata: OnceCell::new(),
+                jwks: RwLock::new(None),
+                store,
+                jwt_keys,
+            }),
+        }
+    }
+
+    /// `Set-Cookie` value storing a login's signed state in the browser
+    pub fn state_cookie(&self, value: &str) -> String {
+        self.cookie(value, PENDING_LOGIN_TTL.as_secs())
+    }
+
+    /// `Set-Cookie` value removing the login state once it is used
+    pub fn expired_state_cookie(&self) -> String {
+        self.cookie("", 0)
+    }
+
+    fn cookie(&self, value: &str, max_age_secs: u64) -> String {
+        let mut cookie = format!(
+            "{STATE_COOKIE}={value}; Path={STATE_COOKIE_PATH}; Max-Age={max_age_secs}; HttpOnly; SameSite=Lax"
+        );
+        // Browsers drop Secure cookies set over plain HTTP, as in local development
+        if self.inner.config.redirect_url.starts_with("https://") {
+            cookie.push_str("; Secure");
+        }
+        cookie
+    }
+
+    async fn metadata(&self) -> Result<&ProviderMetadata, OidcError> {
+        self.inner
+            .metadata
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.inner.config.issuer_url
+                );
+                let metadata: ProviderMetadata = self
+                    .inner
+                    .http
+                    .get(&url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                if metadata.issuer.trim_end_matches('/') != self.inner.config.issuer_url {
+                    return Err(OidcError::Provider(format!(
+                        "discovery document issuer {} does not match {}",
+                        metadata.issuer, self.inner.config.issuer_url
+                    )));
+                }
+                tracing::info!(issuer = %metadata.issuer, "Loaded OpenID Connect discovery document");
+                Ok(metadata)
+            })
+            .await
+    }
+
+    /// Builds the provider URL that starts a login and remembers the PKCE verifier and nonce
+    pub async fn authorization_request(&self) -> Result<AuthorizationRequest, OidcError> {
+        let metadata = self.metadata().await?;
+        let config = &self.inner.config;
+
+        let state = random_string(32);
+        let nonce = random_string(32);
+        let code_verifier = random_string(64);
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        let mut url = Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| OidcError::Provider(format!("invalid authorization endpoint: {e}")))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", &config.redirect_url)
+            .append_pair("scope", &config.scopes)
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        let pending = serde_json::to_string(&PendingLogin {
+            code_verifier,
+            nonce,
+        })
+        .expect("pending login serializes");
+        self.inner
+            .store
+            .set(&pending_key(&state), &pending, PENDING_LOGIN_TTL)
+            .await?;
+
+        let state_cookie = self.inner.jwt_keys.sign(&StateCookieClaims {
+            exp: Utc::now().timestamp() + PENDING_LOGIN_TTL.as_secs() as i64,
+            state,
+        })?;
+
+        Ok(AuthorizationRequest {
+            url: url.into(),
+            state_cookie,
+        })
+    }
+
+    /// Exchanges an authorization code for an ID token and validates it.
+    ///
+    /// `state_cookie` is the cookie set when the login started; it must carry `state`.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        state: &str,
+        state_cookie: Option<&str>,
+    ) -> Result<OidcIdentity, OidcError> {
+        let cookie_state = state_cookie
+            .and_then(|cookie| self.inner.jwt_keys.verify::<StateCookieClaims>(cookie).ok())
+            .map(|token| token.claims.state);
+        if cookie_state.as_deref() != Some(state) {
+            return Err(OidcError::InvalidState);
+        }
+
+        let pending = self
+            .inner
+            .store
+            .take(&pending_key(state))
+            .await?
+            .and_then(|pending| serde_json::from_str::<PendingLogin>(&pending).ok())
+            .ok_or(OidcError::InvalidState)?;
+
+        let metadata = self.metadata().await?;
+        let config = &self.inner.config;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ];
+        if let Some(secret) = &config.client_secret {
+            form.push(("client_secret", secret.expose()));
+        }
+
+        let response = self
+            .inner
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(OidcError::Provider(format!(
+                "token endpoint returned {status}: {body}"
+            )));
+        }
+        let tokens: TokenResponse = response.json().await?;
+
+        let claims = self.validate_id_token(&tokens.id_token, metadata).await?;
+        if claims.nonce.as_deref() != Some(pending.nonce.as_str()) {
+            return Err(OidcError::InvalidIdToken("nonce mismatch".to_string()));
+        }
+
+        let email = match claims.email {
+            Some(email) if claims.email_verified => email,
+            _ => return Err(OidcError::EmailNotVerified),
+        };
+
+        Ok(OidcIdentity {
+            issuer: metadata.issuer.clone(),
+            subject: claims.sub,
+            email,
+            preferred_username: claims.preferred_username,
+        })
+    }
+
+    async fn validate_id_token(
+        &self,
+        id_token: &str,
+        metadata: &ProviderMetadata,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let header =
+            decode_header(id_token).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(OidcError::InvalidIdToken(
+                "symmetric signatures are not accepted".to_string(),
+            ));
+        }
+        let kid = header
+            .kid
+            .ok_or_else(|| OidcError::InvalidIdToken("missing kid header".to_string()))?;
+        let key = self.decoding_key(&kid, metadata).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.inner.config.client_id]);
+        validation.set_issuer(&[&metadata.issuer]);
+
+        decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| OidcError::InvalidIdToken(e.to_string()))
+    }
+
+    /// Looks up a provider signing key, reloading the JWKS once if the key is unknown
+    async fn decoding_key(
+        &self,
+        kid: &str,
+        metadata: &ProviderMetadata,
+    ) -> Result<DecodingKey, OidcError> {
+        if let Some(jwk) = self
+            .inner
+            .jwks
+            .read()
+            .await
+            .as_ref()
+            .and_then(|jwks| jwks.find(kid))
+        {
+            return DecodingKey::from_jwk(jwk).map_err(|e| OidcError::Provider(e.to_string()));
+        }
+
+        let jwks: JwkSet = self
+            .inner
+            .http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let key = jwks
+            .find(kid)
+            .map(DecodingKey::from_jwk)
+            .transpose()
+            .map_err(|e| OidcError::Provider(e.to_string()))?;
+        *self.inner.jwks.write().await = Some(jwks);
+
+        key.ok_or_else(|| OidcError::InvalidIdToken(format!("unknown signing key {kid}")))
+    }
+}
+
+fn pending_key(state: &str) -> String {
+    format!("oidc:{state}")
+}
+
+fn random_string(length: usize) -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,138 @@
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{OtpStore, OtpStoreError, TokenBucket, TokenBucketState};
+
+/// How often expired entries that nobody asks for again are dropped
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Entry {
+    value: String,
+    expires_at: Instant,
+}
+
+struct Entries {
+    map: HashMap<String, Entry>,
+    last_sweep: Instant,
+}
+
+impl Entries {
+    /// The entry under `key`, dropping it first if it has expired
+    fn live(&mut self, key: &str) -> Option<&mut Entry> {
+        if self
+            .map
+            .get(key)
+            .is_some_and(|entry| entry.expires_at <= Instant::now())
+        {
+            self.map.remove(key);
+        }
+        self.map.get_mut(key)
+    }
+}
+
+/// Process-local store for tests and single-node deployments
+#[derive(Clone)]
+pub struct MemoryOtpStore {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl Default for MemoryOtpStore {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Entries {
+                map: HashMap::new(),
+                last_sweep: Instant::now(),
+            })),
+        }
+    }
+}
+
+impl MemoryOtpStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expired entries are dropped when their key is accessed, and all of them at most once
+    /// per [`SWEEP_INTERVAL`]
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        let mut entries = self.entries.lock().expect("OTP store lock poisoned");
+        let now = Instant::now();
+        if now.duration_since(entries.last_sweep) >= SWEEP_INTERVAL {
+            entries.map.retain(|_, entry| entry.expires_at > now);
+            entries.last_sweep = now;
+        }
+        entries
+    }
+}
+
+#[async_trait]
+impl OtpStore for MemoryOtpStore {
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), OtpStoreError> {
+        self.entries().map.insert(
+            key.to_string(),
+            Entry {
+                value: value.to_string(),
+                expires_at: Instant::now() + ttl,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, OtpStoreError> {
+        Ok(self.entries().live(key).map(|entry| entry.value.clone()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool, OtpStoreError> {
+        Ok(self.take(key).await?.is_some())
+    }
+
+    async fn take(&self, key: &str) -> Result<Option<String>, OtpStoreError> {
+        let mut entries = self.entries();
+        entries.live(key);
+        Ok(entries.map.remove(key).map(|entry| entry.value))
+    }
+
+    async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, OtpStoreError> {
+        let mut entries = self.entries();
+        entries.live(key);
+        let entry = entries.map.entry(key.to_string()).or_insert_with(|| Entry {
+            value: "0".to_string(),
+            expires_at: Instant::now() + ttl,
+        });
+        let count = entry.value.parse::<i64>().map_err(|_| {
+            OtpStoreError::UnexpectedResponse(format!("{key} does not hold a counter"))
+        })? + 1;
+        entry.value = count.to_string();
+        Ok(count)
+    }
+
+    async fn take_token(
+        &self,
+        key: &str,
+        bucket: TokenBucket,
+        now_ms: i64,
+    ) -> Result<TokenBucketState, OtpStoreError> {
+        let mut entries = self.entries();
+        let full_at_ms = entries.live(key).and_then(|entry| entry.value.parse().ok());
+        let state = bucket.take(full_at_ms, now_ms);
+        if state.allowed {
+            let ttl = Duration::from_millis((state.full_at_ms - now_ms).max(0) as u64);
+            entries.map.insert(
+                key.to_string(),
+                Entry {
+                    value: state.full_at_ms.to_string(),
+                    expires_at: Instant::now() + ttl,
+                },
+            );
+        }
+        Ok(state)
+    }
+
+    async fn ping(&self) -> Result<(), OtpStoreError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+//! Storage for short-lived OTP state: verification codes, per-email attempt counters and
+//! login link IDs. Rate limit buckets can be kept here too, so they are shared between
+//! instances.
+
+mod memory;
+mod postgres;
+mod redis;
+mod upstash;
+
+pub use self::memory::MemoryOtpStore;
+pub use self::postgres::PostgresOtpStore;
+pub use self::redis::RedisOtpStore;
+pub use self::upstash::UpstashOtpStore;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+
+use crate::{config::OtpStoreBackend, shutdown::Shutdown};
+
+/// How often expired entries are purged from stores that keep them
+const PURGE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum OtpStoreError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] ::redis::RedisError),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Upstash request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Unexpected response from the store: {0}")]
+    UnexpectedResponse(String),
+    #[error("The postgres OTP store needs a Postgres database")]
+    PostgresRequired,
+}
+
+/// Key-value store with per-key expiry
+#[async_trait]
+pub trait OtpStore: Send + Sync {
+    /// Stores `value` under `key`, replacing any previous value
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), OtpStoreError>;
+
+    /// Returns the value of an unexpired key
+    async fn get(&self, key: &str) -> Result<Option<String>, OtpStoreError>;
+
+    /// Removes a key. Returns whether it existed.
+    async fn delete(&self, key: &str) -> Result<bool, OtpStoreError>;
+
+    /// Atomically removes a key and returns its value, so only one caller can use it
+    async fn take(&self, key: &str) -> Result<Option<String>, OtpStoreError>;
+
+    /// Atomically increments a counter and returns the new value. A counter that doesn't
+    /// exist yet starts at 1 and expires after `ttl`; later increments keep that expiry.
+    async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, OtpStoreError>;
+
+    /// Atomically takes a token from the rate limit bucket under `key`
+    async fn take_token(
+        &self,
+        key: &str,
+        bucket: TokenBucket,
+        now_ms: i64,
+    ) -> Result<TokenBucketState, OtpStoreError>;
+
+    /// Checks that the store can be reached
+    async fn ping(&self) -> Result<(), OtpStoreError>;
+
+    /// Deletes expired entries and returns how many there were. Stores that expire keys on
+    /// their own have nothing to do.
+    async fn purge_expired(&self) -> Result<u64, OtpStoreError> {
+        Ok(0)
+    }
+}
+
+/// A token bucket that holds `capacity_ms / interval_ms` tokens and regains one every
+/// `interval_ms`. It is stored as the single timestamp at which it is full again (GCRA), so
+/// taking a token is one read and one write.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBucket {
+    pub interval_ms: i64,
+    pub capacity_ms: i64,
+}
+
+/// Outcome of taking a token
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBucketState {
+    pub allowed: bool,
+    /// Unix time in milliseconds at which the bucket is full again
+    pub full_at_ms: i64,
+}
+
+impl TokenBucket {
+    /// Takes a token from a bucket that is full again at `full_at_ms`, or already full if
+    /// `None`. Mirrors [`TOKEN_BUCKET_SCRIPT`] for the stores that don't run scripts.
+    pub fn take(&self, full_at_ms: Option<i64>, now_ms: i64) -> TokenBucketState {
+        let current = full_at_ms.unwrap_or(now_ms).max(now_ms);
+        let next = current + self.interval_ms;
+        if next - now_ms > self.capacity_ms {
+            TokenBucketState {
+                allowed: false,
+                full_at_ms: current,
+            }
+        } else {
+            TokenBucketState {
+                allowed: true,
+                full_at_ms: next,
+            }
+        }
+    }
+}
+
+/// Backend selected with `otp.store`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtpStoreKind {
+    Memory,
+    Redis,
+    Postgres,
+    Upstash,
+}
+
+impl std::str::FromStr for OtpStoreKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "memory" => Ok(Self::Memory),
+            "redis" => Ok(Self::Redis),
+            "postgres" => Ok(Self::Postgres),
+            "upstash" => Ok(Self::Upstash),
+            _ => Err("expected memory, redis, postgres or upstash".to_string()),
+        }
+    }
+}
+
+/// Creates the store selected by `otp.store`. `pool` is the main database when it is
+/// Postgres, which the `postgres` store keeps its entries in.
+pub async fn from_config(
+    config: &OtpStoreBackend,
+    pool: Option<PgPool>,
+) -> Result<Arc<dyn OtpStore>, OtpStoreError> {
+    let store: Arc<dyn OtpStore> = match config {
+        OtpStoreBackend::Memory => {
+            tracing::warn!("Using the in-memory OTP store; codes are lost on restart");
+            Arc::new(MemoryOtpStore::new())
+        }
+        OtpStoreBackend::Redis { redis_url } => {
+            Arc::new(RedisOtpStore::connect(redis_url.expose()).await?)
+        }
+        OtpStoreBackend::Postgres => Arc::new(PostgresOtpStore::new(
+            pool.ok_or(OtpStoreError::PostgresRequired)?,
+        )),
+        OtpStoreBackend::Upstash {
+            upstash_url,
+            upstash_token,
+        } => Arc::new(UpstashOtpStore::new(
+            upstash_url.clone(),
+            upstash_token.expose().to_string(),
+        )),
+    };
+
+    tracing::info!(store = ?config.kind(), "OTP store initialized");
+    Ok(store)
+}
+
+/// Purges expired entries from `store` every [`PURGE_INTERVAL`] in the background, until
+/// shutdown
+pub fn spawn_purge(store: Arc<dyn OtpStore>, shutdown: &Shutdown) -> JoinHandle<()> {
+    let stop = shutdown.clone();
+    shutdown.spawn(async move {
+        let mut ticker = tokio::time::interval(PURGE_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = stop.triggered() => return,
+            }
+            match store.purge_expired().await {
+                Ok(0) => {}
+                Ok(count) => tracing::debug!(count, "Purged expired OTP store entries"),
+                Err(e) => tracing::error!("Failed to purge expired OTP store entries: {e}"),
+            }
+        }
+    })
+}
+
+/// Lua script shared by the Redis-protocol backends: increment, and set the expiry only
+/// when the counter was just created
+const INCREMENT_SCRIPT: &str = r"
+local count = redis.call('INCR', KEYS[1])
+if count == 1 then
+    redis.call('EXPIRE', KEYS[1], ARGV[1])
+end
+return count
+";
+
+/// Lua script shared by the Redis-protocol backends: [`TokenBucket::take`], storing the time
+/// the bucket is full again with an expiry at that time. Returns `{allowed, full_at_ms}`.
+const TOKEN_BUCKET_SCRIPT: &str = r"
+local now = tonumber(ARGV[1])
+local interval = tonumber(ARGV[2])
+local capacity = tonumber(ARGV[3])
+local current = tonumber(redis.call('GET', KEYS[1]) or now)
+if current < now then
+    current = now
+end
+local next = current + interval
+if next - now > capacity then
+    return {0, current}
+end
+redis.call('SET', KEYS[1], next, 'PX', next - now)
+return {1, next}
+";
@@ -0,0 +1,185 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::{OtpStore, OtpStoreError, TokenBucket, TokenBucketState};
+
+/// Store backed by the `otp_store` table, for deployments without Redis
+#[derive(Clone)]
+pub struct PostgresOtpStore {
+    pool: PgPool,
+}
+
+impl PostgresOtpStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OtpStore for PostgresOtpStore {
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), OtpStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO otp_store (key, value, expires_at)
+            VALUES ($1, $2, NOW() + make_interval(secs => $3))
+            ON CONFLICT (key)
+            DO UPDATE SET value = EXCLUDED.value, expires_at = EXCLUDED.expires_at
+            "#,
+            key,
+            value,
+            ttl.as_secs_f64()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, OtpStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT value
+            FROM otp_store
+            WHERE key = $1 AND expires_at > NOW()
+            "#,
+            key
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| row.value))
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool, OtpStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM otp_store
+            WHERE key = $1 AND expires_at > NOW()
+            "#,
+            key
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn take(&self, key: &str) -> Result<Option<String>, OtpStoreError> {
+        let row = sqlx::query!(
+            r#"
+            DELETE FROM otp_store
+            WHERE key = $1 AND expires_at > NOW()
+            RETURNING value
+            "#,
+            key
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| row.value))
+    }
+
+    async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, OtpStoreError> {
+        // An expired counter restarts at 1 with a fresh expiry
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO otp_store (key, value, expires_at)
+            VALUES ($1, '1', NOW() + make_interval(secs => $2))
+            ON CONFLICT (key) DO UPDATE SET
+                value = CASE
+                    WHEN otp_store.expires_at <= NOW() THEN '1'
+                    ELSE (otp_store.value::BIGINT + 1)::TEXT
+                END,
+                expires_at = CASE
+                    WHEN otp_store.expires_at <= NOW() THEN EXCLUDED.expires_at
+                    ELSE otp_store.expires_at
+                END
+            RETURNING value
+            "#,
+            key,
+            ttl.as_secs_f64()
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.value.parse().map_err(|_| {
+            OtpStoreError::UnexpectedResponse(format!("{key} does not hold a counter"))
+        })
+    }
+
+    async fn take_token(
+        &self,
+        key: &str,
+        bucket: TokenBucket,
+        now_ms: i64,
+    ) -> Result<TokenBucketState, OtpStoreError> {
+        // One upsert, so concurrent requests queue on the row lock even when the bucket
+        // doesn't exist yet. The update only happens, and a row is only returned, when a
+        // token is available; this is `TokenBucket::take` in SQL.
+        let taken = sqlx::query_scalar!(
+            r#"
+            INSERT INTO otp_store (key, value, expires_at)
+            SELECT $1, ($2::BIGINT + $3::BIGINT)::TEXT, NOW() + make_interval(secs => $3 / 1000.0)
+            WHERE $3 <= $4::BIGINT
+            ON CONFLICT (key) DO UPDATE SET
+                value = (
+                    GREATEST(
+                        CASE WHEN otp_store.expires_at > NOW() THEN otp_store.value::BIGINT END,
+                        $2
+                    ) + $3
+                )::TEXT,
+                expires_at = NOW() + make_interval(secs => (
+                    GREATEST(
+                        CASE WHEN otp_store.expires_at > NOW() THEN otp_store.value::BIGINT END,
+                        $2
+                    ) + $3 - $2
+                ) / 1000.0)
+            WHERE GREATEST(
+                CASE WHEN otp_store.expires_at > NOW() THEN otp_store.value::BIGINT END,
+                $2
+            ) + $3 - $2 <= $4
+            RETURNING value
+            "#,
+            key,
+            now_ms,
+            bucket.interval_ms,
+            bucket.capacity_ms
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let state = match taken {
+            Some(value) => TokenBucketState {
+                allowed: true,
+                full_at_ms: value.parse().map_err(|_| {
+                    OtpStoreError::UnexpectedResponse(format!("{key} does not hold a bucket"))
+                })?,
+            },
+            // Denied, so only report when the bucket is full again
+            None => {
+                let full_at_ms = self.get(key).await?.and_then(|value| value.parse().ok());
+                TokenBucketState {
+                    allowed: false,
+                    full_at_ms: full_at_ms.unwrap_or(now_ms).max(now_ms),
+                }
+            }
+        };
+        Ok(state)
+    }
+
+    async fn ping(&self) -> Result<(), OtpStoreError> {
+        sqlx::query("SELECT 1 FROM otp_store LIMIT 1")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Reads ignore expired rows, which stay in the table until this runs
+    async fn purge_expired(&self) -> Result<u64, OtpStoreError> {
+        let result = sqlx::query!("DELETE FROM otp_store WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
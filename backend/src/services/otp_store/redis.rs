@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands, Script};
+use std::time::Duration;
+
+use super::{
+    OtpStore, OtpStoreError, TokenBucket, TokenBucketState, INCREMENT_SCRIPT, TOKEN_BUCKET_SCRIPT,
+};
+
+/// Store backed by a Redis server, spoken to over the native protocol
+#[derive(Clone)]
+pub struct RedisOtpStore {
+    connection: ConnectionManager,
+    increment_script: Script,
+    token_bucket_script: Script,
+}
+
+impl RedisOtpStore {
+    /// Connects to `url`, e.g. `redis://localhost:6379/0`. The connection is re-established
+    /// automatically if it drops.
+    pub async fn connect(url: &str) -> Result<Self, OtpStoreError> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self {
+            connection,
+            increment_script: Script::new(INCREMENT_SCRIPT),
+            token_bucket_script: Script::new(TOKEN_BUCKET_SCRIPT),
+        })
+    }
+}
+
+#[async_trait]
+impl OtpStore for RedisOtpStore {
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), OtpStoreError> {
+        let mut connection = self.connection.clone();
+        let _: () = connection.set_ex(key, value, ttl.as_secs().max(1)).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, OtpStoreError> {
+        let mut connection = self.connection.clone();
+        Ok(connection.get(key).await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool, OtpStoreError> {
+        let mut connection = self.connection.clone();
+        let removed: i64 = connection.del(key).await?;
+        Ok(removed > 0)
+    }
+
+    async fn take(&self, key: &str) -> Result<Option<String>, OtpStoreError> {
+        let mut connection = self.connection.clone();
+        Ok(connection.get_del(key).await?)
+    }
+
+    async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, OtpStoreError> {
+        let mut connection = self.connection.clone();
+        Ok(self
+            .increment_script
+            .key(key)
+            .arg(ttl.as_secs().max(1))
+            .invoke_async(&mut connection)
+            .await?)
+    }
+
+    async fn take_token(
+        &self,
+        key: &str,
+        bucket: TokenBucket,
+        now_ms: i64,
+    ) -> Result<TokenBucketState, OtpStoreError> {
+        let mut connection = self.connection.clone();
+        let (allowed, full_at_ms): (i64, i64) = self
+            .token_bucket_script
+            .key(key)
+            .arg(now_ms)
+            .arg(bucket.interval_ms)
+            .arg(bucket.capacity_ms)
+            .invoke_async(&mut connection)
+            .await?;
+        Ok(TokenBucketState {
+            allowed: allowed == 1,
+            full_at_ms,
+        })
+    }
+
+    async fn ping(&self) -> Result<(), OtpStoreError> {
+        let mut connection = self.connection.clone();
+        let _: String = redis::cmd("PING").query_async(&mut connection).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{
+    OtpStore, OtpStoreError, TokenBucket, TokenBucketState, INCREMENT_SCRIPT, TOKEN_BUCKET_SCRIPT,
+};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct UpstashResponse<T> {
+    result: Option<T>,
+    error: Option<String>,
+}
+
+/// Store backed by Upstash's Redis REST API
+#[derive(Clone)]
+pub struct UpstashOtpStore {
+    client: Client,
+    url: String,
+    token: String,
+}
+
+impl UpstashOtpStore {
+    pub fn new(url: String, token: String) -> Self {
+        let client = Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    /// Sends one Redis command as a JSON array, so keys and values need no URL escaping
+    async fn command<T: DeserializeOwned>(
+        &self,
+        command: Value,
+    ) -> Result<Option<T>, OtpStoreError> {
+        let response: UpstashResponse<T> = self
+            .client
+            .post(&self.url)
+            .bearer_auth(&self.token)
+            .json(&command)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        match response.error {
+            Some(error) => Err(OtpStoreError::UnexpectedResponse(error)),
+            None => Ok(response.result),
+        }
+    }
+}
+
+#[async_trait]
+impl OtpStore for UpstashOtpStore {
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), OtpStoreError> {
+        self.command::<String>(json!(["SET", key, value, "EX", ttl.as_secs().max(1)]))
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, OtpStoreError> {
+        self.command(json!(["GET", key])).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool, OtpStoreError> {
+        let removed: Option<i64> = self.command(json!(["DEL", key])).await?;
+        Ok(removed.unwrap_or(0) > 0)
+    }
+
+    async fn take(&self, key: &str) -> Result<Option<String>, OtpStoreError> {
+        self.command(json!(["GETDEL", key])).await
+    }
+
+    async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, OtpStoreError> {
+        self.command(json!([
+            "EVAL",
+            INCREMENT_SCRIPT,
+            1,
+            key,
+            ttl.as_secs().max(1)
+        ]))
+        .await?
+        .ok_or_else(|| OtpStoreError::UnexpectedResponse(format!("no count returned for {key}")))
+    }
+
+    async fn take_token(
+        &self,
+        key: &str,
+        bucket: TokenBucket,
+        now_ms: i64,
+    ) -> Result<TokenBucketState, OtpStoreError> {
+        let result: Option<Vec<i64>> = self
+            .command(json!([
+                "EVAL",
+                TOKEN_BUCKET_SCRIPT,
+                1,
+                key,
+                now_ms,
+                bucket.interval_ms,
+                bucket.capacity_ms
+            ]))
+            .await?;
+        match result.as_deref() {
+            Some(&[allowed, full_at_ms]) => Ok(TokenBucketState {
+                allowed: allowed == 1,
+                full_at_ms,
+            }),
+            _ => Err(OtpStoreError::UnexpectedResponse(format!(
+                "no token bucket state returned for {key}"
+            ))),
+        }
+    }
+
+    async fn ping(&self) -> Result<(), OtpStoreError> {
+        self.command::<String>(json!(["PING"])).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+//! Removal of abandoned sign-ups.
+//!
+//! Accounts that never verified their email are deleted once they are older than
+//! `pending_cleanup.max_age_hours`, which frees their usernames and email addresses. The task
+//! runs every `pending_cleanup.interval_minutes` and can be triggered by admins.
+
+use chrono::Utc;
+use std::{sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::PendingCleanupConfig, database::repository::UserRepository, shutdown::Shutdown,
+};
+
+#[derive(Clone)]
+pub struct PendingAccountCleanup {
+    users: Arc<dyn UserRepository>,
+    max_age: Duration,
+    interval: Duration,
+}
+
+impl PendingAccountCleanup {
+    pub fn new(users: Arc<dyn UserRepository>, max_age: Duration, interval: Duration) -> Self {
+        Self {
+            users,
+            max_age,
+            interval,
+        }
+    }
+
+    pub fn from_config(users: Arc<dyn UserRepository>, config: &PendingCleanupConfig) -> Self {
+        Self::new(
+            users,
+            Duration::from_secs(config.max_age_hours * 60 * 60),
+            Duration::from_secs(config.interval_minutes * 60),
+        )
+    }
+
+    /// Accounts pending verification for longer than this are deleted
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Deletes pending accounts older than the maximum age
+    ///
+    /// # Returns
+    /// * `Result<u64, sqlx::Error>` - The number of deleted accounts
+    pub async fn run_once(&self) -> Result<u64, sqlx::Error> {
+        let max_age = chrono::Duration::from_std(self.max_age).unwrap_or(chrono::Duration::MAX);
+        let cutoff = Utc::now() - max_age;
+        let usernames = self.users.delete_stale_pending(cutoff).await?;
+
+        if !usernames.is_empty() {
+            tracing::info!(
+                count = usernames.len(),
+                older_than_hours = self.max_age.as_secs() / 3600,
+                "Deleted unverified accounts"
+            );
+            tracing::debug!(?usernames, "Freed usernames of unverified accounts");
+        }
+        Ok(usernames.len() as u64)
+    }
+
+    /// Runs the cleanup every interval in the background, starting immediately, until
+    /// shutdown
+    pub fn spawn(self, shutdown: &Shutdown) -> JoinHandle<()> {
+        let stop = shutdown.clone();
+        shutdown.spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = stop.triggered() => return,
+                }
+                if let Err(e) = self.run_once().await {
+                    tracing::error!("Failed to delete unverified accounts: {e}");
+                }
+            }
+        })
+    }
+}
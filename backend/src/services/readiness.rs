@@ -0,0 +1,137 @@
+//! Readiness checks behind `/readyz`.
+//!
+//! Each dependency is checked concurrently and gets `health.check_timeout_ms` to answer. The
+//! instance is ready when every critical check passes and shutdown hasn't started. The email
+//! backend is reported but not critical: emails wait in the outbox until it is back.
+//! Failure details are logged rather than returned, since the endpoint is unauthenticated.
+
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use utoipa::ToSchema;
+
+use crate::{
+    config::HealthConfig,
+    database::Database,
+    services::{email_sender::EmailSender, otp_store::OtpStore},
+    shutdown::Shutdown,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+    Timeout,
+}
+
+/// Result of checking one dependency
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    /// Whether a failure makes the instance not ready
+    pub critical: bool,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    /// Set while shutdown drains requests, which makes the instance not ready
+    pub draining: bool,
+    pub checks: BTreeMap<&'static str, CheckResult>,
+}
+
+#[derive(Clone)]
+pub struct Readiness {
+    database: Database,
+    otp_store: Arc<dyn OtpStore>,
+    email_sender: Arc<dyn EmailSender>,
+    shutdown: Shutdown,
+    timeout: Duration,
+}
+
+impl Readiness {
+    pub fn new(
+        database: Database,
+        otp_store: Arc<dyn OtpStore>,
+        email_sender: Arc<dyn EmailSender>,
+        shutdown: Shutdown,
+        config: &HealthConfig,
+    ) -> Self {
+        Self {
+            database,
+            otp_store,
+            email_sender,
+            shutdown,
+            timeout: Duration::from_millis(config.check_timeout_ms),
+        }
+    }
+
+    /// Runs all checks
+    pub async fn check(&self) -> ReadinessReport {
+        let (database, migrations, otp_store, email) = tokio::join!(
+            self.run("database", true, async { Ok(self.database.ping().await?) }),
+            self.run("migrations", true, async {
+                let pending = self.database.pending_migrations().await?;
+                match pending.as_slice() {
+                    [] => Ok(()),
+                    versions => Err(format!("pending migrations: {versions:?}").into()),
+                }
+            }),
+            self.run("otp_store", true, async {
+                Ok(self.otp_store.ping().await?)
+            }),
+            self.run("email", false, async {
+                Ok(self.email_sender.check().await?)
+            }),
+        );
+
+        let checks = BTreeMap::from([database, migrations, otp_store, email]);
+        let draining = self.shutdown.is_triggered();
+        let ready = !draining
+            && checks
+                .values()
+                .all(|check| !check.critical || check.status == CheckStatus::Ok);
+
+        ReadinessReport {
+            ready,
+            draining,
+            checks,
+        }
+    }
+
+    async fn run<F>(
+        &self,
+        name: &'static str,
+        critical: bool,
+        check: F,
+    ) -> (&'static str, CheckResult)
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>>,
+    {
+        let start = Instant::now();
+        let status = match tokio::time::timeout(self.timeout, check).await {
+            Ok(Ok(())) => CheckStatus::Ok,
+            Ok(Err(e)) => {
+                tracing::warn!(check = name, "Readiness check failed: {e}");
+                CheckStatus::Failed
+            }
+            Err(_) => {
+                tracing::warn!(check = name, timeout = ?self.timeout, "Readiness check timed out");
+                CheckStatus::Timeout
+            }
+        };
+
+        let result = CheckResult {
+            status,
+            critical,
+            duration_ms: start.elapsed().as_millis() as u64,
+        };
+        (name, result)
+    }
+}
@@ -0,0 +1,218 @@
+//! Who may create an account.
+//!
+//! `registration.mode` selects between open registration, invite-only registration and
+//! registration restricted to the domains in `registration.allowed_domains`. Independently of the
+//! mode, addresses at known disposable email providers are rejected unless
+//! `registration.block_disposable_emails` is `false`.
+
+use axum::http::StatusCode;
+use rand::Rng;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
+
+use crate::config::RegistrationConfig;
+
+/// Built-in list of disposable email domains, parsed on first use
+static DISPOSABLE_DOMAINS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+
+const DISPOSABLE_DOMAINS_FILE: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/data/disposable_email_domains.txt"
+));
+
+/// Unambiguous characters for invite codes, which are often typed in by hand
+const INVITE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const INVITE_GROUPS: usize = 3;
+const INVITE_GROUP_LENGTH: usize = 4;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegistrationError {
+    #[error("Registration requires an invite code")]
+    InviteRequired,
+    #[error("This invite code is invalid, expired or has been used up")]
+    InvalidInvite,
+    #[error("Registration is not open to addresses at {domain}")]
+    DomainNotAllowed { domain: String },
+    #[error("Disposable email addresses can't be used to register")]
+    DisposableEmail,
+}
+
+impl RegistrationError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            RegistrationError::InviteRequired
+            | RegistrationError::InvalidInvite
+            | RegistrationError::DomainNotAllowed { .. } => StatusCode::FORBIDDEN,
+            RegistrationError::DisposableEmail => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            RegistrationError::InviteRequired => "INVITE_REQUIRED",
+            RegistrationError::InvalidInvite => "INVALID_INVITE",
+            RegistrationError::DomainNotAllowed { .. } => "DOMAIN_NOT_ALLOWED",
+            RegistrationError::DisposableEmail => "DISPOSABLE_EMAIL",
+        }
+    }
+
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            RegistrationError::DomainNotAllowed { domain } => Some(json!({ "domain": domain })),
+            _ => None,
+        }
+    }
+}
+
+/// Mode selected with `registration.mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrationMode {
+    /// Anyone can register
+    #[default]
+    Open,
+    /// A valid invite code is required
+    Invite,
+    /// Only addresses at the allowed domains can register
+    Domain,
+}
+
+impl FromStr for RegistrationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "open" => Ok(Self::Open),
+            "invite" => Ok(Self::Invite),
+            "domain" => Ok(Self::Domain),
+            _ => Err("expected open, invite or domain".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RegistrationPolicy {
+    mode: RegistrationMode,
+    allowed_domains: Arc<HashSet<String>>,
+    block_disposable: bool,
+}
+
+impl RegistrationPolicy {
+    pub fn from_config(config: &RegistrationConfig) -> Self {
+        tracing::info!(
+            mode = ?config.mode,
+            allowed_domains = config.allowed_domains.len(),
+            block_disposable = config.block_disposable_emails,
+            "Registration policy loaded"
+        );
+
+        Self {
+            mode: config.mode,
+            allowed_domains: Arc::new(config.allowed_domains.iter().cloned().collect()),
+            block_disposable: config.block_disposable_emails,
+        }
+    }
+
+    pub fn mode(&self) -> RegistrationMode {
+        self.mode
+    }
+
+    /// Checks an address against the domain allow-list and the disposable domain list
+    pub fn check_email(&self, email: &str) -> Result<(), RegistrationError> {
+        let domain = email
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.trim_end_matches('.').to_ascii_lowercase())
+            .unwrap_or_default();
+
+        if self.mode == RegistrationMode::Domain && !self.allowed_domains.contains(&domain) {
+            return Err(RegistrationError::DomainNotAllowed { domain });
+        }
+        if self.block_disposable && is_disposable_domain(&domain) {
+            return Err(RegistrationError::DisposableEmail);
+        }
+        Ok(())
+    }
+
+    /// In invite mode, the hash of the invite code the registration must redeem. Redeeming it
+    /// is left to [`UserRepository::register`], so a use is only counted if the account is
+    /// created.
+    ///
+    /// [`UserRepository::register`]: crate::database::repository::UserRepository::register
+    pub fn invite_hash(&self, code: Option<&str>) -> Result<Option<String>, RegistrationError> {
+        if self.mode != RegistrationMode::Invite {
+            return Ok(None);
+        }
+
+        let code = code
+            .filter(|code| !code.trim().is_empty())
+            .ok_or(RegistrationError::InviteRequired)?;
+        Ok(Some(hash_invite_code(code)))
+    }
+}
+
+/// Whether the domain, or a domain it belongs to, is a known disposable email provider
+pub fn is_disposable_domain(domain: &str) -> bool {
+    let domains = DISPOSABLE_DOMAINS.get_or_init(|| {
+        DISPOSABLE_DOMAINS_FILE
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect()
+    });
+
+    let mut candidate = domain;
+    loop {
+        if domains.contains(candidate) {
+            return true;
+        }
+        match candidate.split_once('.') {
+            Some((_, parent)) if parent.contains('.') => candidate = parent,
+            _ => return false,
+        }
+    }
+}
+
+/// A freshly generated invite code
+pub struct GeneratedInvite {
+    /// The full code, shown to the admin exactly once
+    pub code: String,
+    /// The first group of the code, stored for display
+    pub prefix: String,
+    /// The SHA-256 hash of the normalized code
+    pub hash: String,
+}
+
+/// Generates a code of the form `ABCD-EFGH-JKLM`
+pub fn generate_invite_code() -> GeneratedInvite {
+    let mut rng = rand::rng();
+    let groups: Vec<String> = (0..INVITE_GROUPS)
+        .map(|_| {
+            (0..INVITE_GROUP_LENGTH)
+                .map(|_| char::from(INVITE_ALPHABET[rng.random_range(0..INVITE_ALPHABET.len())]))
+                .collect()
+        })
+        .collect();
+
+    let code = groups.join("-");
+    GeneratedInvite {
+        hash: hash_invite_code(&code),
+        prefix: groups[0].clone(),
+        code,
+    }
+}
+
+/// Hashes a code for storage and lookup, ignoring case, spaces and dashes
+pub fn hash_invite_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
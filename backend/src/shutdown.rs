@@ -0,0 +1,96 @@
+//! Graceful shutdown.
+//!
+//! SIGTERM or Ctrl+C starts the shutdown: the server stops accepting connections, background
+//! loops stop picking up new work, and the process waits up to `server.drain_timeout_secs` for
+//! in-flight requests and for the tasks spawned through [`Shutdown::spawn`] before it exits.
+
+use std::future::Future;
+use tokio::{task::JoinHandle, time::Instant};
+use tokio_util::{
+    sync::{CancellationToken, WaitForCancellationFuture, WaitForCancellationFutureOwned},
+    task::TaskTracker,
+};
+
+/// Shutdown signal and the background tasks that shutdown waits for
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawns a task that shutdown waits for
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(task)
+    }
+
+    /// Starts the shutdown
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolves once shutdown has started
+    pub fn triggered(&self) -> WaitForCancellationFuture<'_> {
+        self.token.cancelled()
+    }
+
+    /// Like [`Shutdown::triggered`], for futures that must not borrow
+    pub fn triggered_owned(&self) -> WaitForCancellationFutureOwned {
+        self.token.clone().cancelled_owned()
+    }
+
+    /// Triggers the shutdown on SIGTERM or Ctrl+C
+    pub async fn listen_for_signals(self) {
+        let ctrl_c = async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                tracing::error!("Failed to listen for Ctrl+C: {e}");
+                std::future::pending::<()>().await;
+            }
+        };
+
+        #[cfg(unix)]
+        let terminate = async {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut sigterm) => {
+                    sigterm.recv().await;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to listen for SIGTERM: {e}");
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = ctrl_c => tracing::info!("Received Ctrl+C"),
+            _ = terminate => tracing::info!("Received SIGTERM"),
+            _ = self.triggered() => return,
+        }
+        self.trigger();
+    }
+
+    /// Waits for the background tasks until `deadline`. Returns the number of tasks that
+    /// were still running.
+    pub async fn drain(&self, deadline: Instant) -> usize {
+        self.tasks.close();
+        match tokio::time::timeout_at(deadline, self.tasks.wait()).await {
+            Ok(()) => 0,
+            Err(_) => self.tasks.len(),
+        }
+    }
+}
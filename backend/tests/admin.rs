@@ -0,0 +1,131 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, PASSWORD};
+use serde_json::json;
+use std::net::Ipv4Addr;
+
+#[tokio::test]
+async fn only_admins_reach_the_admin_api() {
+    let app = TestApp::new();
+    let user = app.signed_up_user("alan@example.com", "alan").await;
+    let admin = app.admin_user("grace@example.com", "grace").await;
+
+    let anonymous = app.get("/api/admin/users", None).await;
+    assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED);
+
+    let forbidden = app.get("/api/admin/users", Some(&user)).await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+
+    let allowed = app.get("/api/admin/users", Some(&admin)).await;
+    assert_eq!(allowed.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn suspending_a_user_ends_their_sessions() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+    let session = app.signed_up_user("alan@example.com", "alan").await;
+    let alan = app.user_id("alan@example.com").await;
+    assert_eq!(
+        app.get("/api/links", Some(&session)).await.status,
+        StatusCode::OK
+    );
+
+    app.post(
+        &format!("/api/admin/users/{alan}/suspend"),
+        Some(&admin),
+        json!({}),
+    )
+    .await;
+    let suspended = app.get("/api/links", Some(&session)).await;
+    assert_eq!(suspended.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(suspended.body["code"], "SESSION_REVOKED");
+
+    // Reactivating the account doesn't bring the old session back
+    app.post(
+        &format!("/api/admin/users/{alan}/reactivate"),
+        Some(&admin),
+        json!({}),
+    )
+    .await;
+    let reactivated = app.get("/api/links", Some(&session)).await;
+    assert_eq!(reactivated.status, StatusCode::UNAUTHORIZED);
+
+    let login = app
+        .login_from(Ipv4Addr::LOCALHOST.into(), "alan@example.com", PASSWORD)
+        .await;
+    assert_eq!(login.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn admins_manage_users_through_the_repositories() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+    app.signed_up_user("alan@example.com", "alan").await;
+    let alan = app.user_id("alan@example.com").await;
+
+    let search = app.get("/api/admin/users?q=ALAN", Some(&admin)).await;
+    assert_eq!(search.status, StatusCode::OK);
+    assert_eq!(search.body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(search.body["pagination"]["total_items"], 1);
+
+    let suspended = app
+        .post(
+            &format!("/api/admin/users/{alan}/suspend"),
+            Some(&admin),
+            json!({ "reason": "spam" }),
+        )
+        .await;
+    assert_eq!(suspended.status, StatusCode::OK);
+    assert_eq!(suspended.body["data"]["status"], "Suspended");
+
+    let again = app
+        .post(
+            &format!("/api/admin/users/{alan}/suspend"),
+            Some(&admin),
+            json!({}),
+        )
+        .await;
+    assert_eq!(again.status, StatusCode::CONFLICT);
+
+    let log = app
+        .get(
+            &format!("/api/admin/audit-log?user_id={alan}"),
+            Some(&admin),
+        )
+        .await;
+    assert_eq!(log.status, StatusCode::OK);
+    assert_eq!(log.body["data"][0]["action"], "suspend_user");
+    assert_eq!(log.body["data"][0]["details"]["reason"], "spam");
+
+    let health = app.get("/api/admin/db/health", Some(&admin)).await;
+    assert_eq!(health.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn admins_create_list_and_revoke_invites() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+
+    let created = app
+        .post(
+            "/api/admin/invites",
+            Some(&admin),
+            json!({ "note": "Team", "max_uses": 2 }),
+        )
+        .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert!(created.body["data"]["code"].is_string());
+    let id = created.body["data"]["id"].as_str().unwrap();
+
+    let listed = app.get("/api/admin/invites", Some(&admin)).await;
+    assert_eq!(listed.body["data"][0]["note"], "Team");
+
+    let revoked = app
+        .delete(&format!("/api/admin/invites/{id}"), Some(&admin))
+        .await;
+    assert_eq!(revoked.status, StatusCode::OK);
+    let listed = app.get("/api/admin/invites", Some(&admin)).await;
+    assert_eq!(listed.body["data"], json!([]));
+}
@@ -0,0 +1,177 @@
+use backend::services::jwt::{generate_private_key_pem, PRIVATE_KEY_SUFFIX, PUBLIC_KEY_SUFFIX};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+use uuid::Uuid;
+
+/// A scratch directory with a config file whose keys live in `keys/`
+struct Workspace(PathBuf);
+
+impl Workspace {
+    fn new(database_url: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("linksphere-admin-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("keys")).unwrap();
+        fs::write(
+            dir.join("config.toml"),
+            format!(
+                r#"
+[server]
+host = "127.0.0.1"
+port = 8080
+frontend_url = "http://localhost:5173"
+
+[database]
+url = "{database_url}"
+
+[jwt]
+keys_dir = "{}"
+"#,
+                dir.join("keys").display()
+            ),
+        )
+        .unwrap();
+        Self(dir)
+    }
+
+    fn keys(&self) -> PathBuf {
+        self.0.join("keys")
+    }
+
+    fn key_files(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(self.keys())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Runs the tool in the workspace, without the developer's environment
+    fn run(&self, args: &[&str]) -> Output {
+        admin(&self.0, args)
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn admin(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_linksphere-admin"))
+        .args(args)
+        .current_dir(dir)
+        .env_clear()
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn unknown_commands_print_the_usage() {
+    let dir = std::env::temp_dir();
+
+    for args in [&[][..], &["frobnicate"], &["user", "verify"], &["--help"]] {
+        let output = admin(&dir, args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(stderr(&output).starts_with("Usage: linksphere-admin"));
+    }
+
+    let output = admin(&dir, &["--force", "migrate", "up"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("unknown option '--force'"));
+}
+
+#[test]
+fn destructive_commands_need_confirmation() {
+    let dir = std::env::temp_dir();
+
+    let output = admin(&dir, &["user", "delete", "ada@example.com"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("confirm with --yes"));
+
+    let output = admin(&dir, &["migrate", "down", "2"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("confirm with --yes"));
+
+    let output = admin(&dir, &["migrate", "down", "two", "--yes"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("invalid number of steps 'two'"));
+}
+
+#[test]
+fn invalid_configuration_is_reported() {
+    let dir = std::env::temp_dir().join(format!("linksphere-admin-{}", Uuid::new_v4()));
+    fs::create_dir(&dir).unwrap();
+
+    let output = admin(&dir, &["migrate", "status"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("database.url (DATABASE_URL) must be set"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sqlite_databases_are_refused() {
+    let workspace = Workspace::new("sqlite://linksphere.db");
+
+    let output = workspace.run(&["user", "verify", "ada@example.com"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("only supports Postgres databases"));
+}
+
+#[test]
+fn rotating_adds_a_key_and_can_retire_the_old_ones() {
+    // Key rotation never connects to the database
+    let workspace = Workspace::new("postgres://linksphere@127.0.0.1:1/linksphere");
+    fs::write(
+        workspace
+            .keys()
+            .join(format!("2020-01-01{PRIVATE_KEY_SUFFIX}")),
+        generate_private_key_pem().unwrap(),
+    )
+    .unwrap();
+
+    let output = workspace.run(&["rotate-jwt-key"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let files = workspace.key_files();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0], format!("2020-01-01{PRIVATE_KEY_SUFFIX}"));
+    let new_key = files[1].clone();
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!(
+        "Signing key after restart: {}",
+        new_key.strip_suffix(PRIVATE_KEY_SUFFIX).unwrap()
+    )));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(workspace.keys().join(&new_key))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o077, 0, "the private key is readable by others");
+    }
+
+    // Rotating again within the same second would reuse the name
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let output = workspace.run(&["rotate-jwt-key", "--retire-old"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let files = workspace.key_files();
+    assert_eq!(files.len(), 3);
+    assert!(files.contains(&format!("2020-01-01{PUBLIC_KEY_SUFFIX}")));
+    assert!(!files.contains(&new_key));
+    assert_eq!(
+        files
+            .iter()
+            .filter(|name| !name.ends_with(PUBLIC_KEY_SUFFIX))
+            .count(),
+        1
+    );
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, PASSWORD};
+use serde_json::json;
+use std::net::Ipv4Addr;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn admins_see_a_users_details_and_link_count() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+    let session = app.signed_up_user("alan@example.com", "alan").await;
+    let alan = app.user_id("alan@example.com").await;
+    for url in ["https://example.com/a", "https://example.com/b"] {
+        let created = app
+            .post(
+                "/api/links",
+                Some(&session),
+                json!({ "url": url, "title": "Example", "description": "An example" }),
+            )
+            .await;
+        assert_eq!(created.status, StatusCode::CREATED);
+    }
+
+    let user = app
+        .get(&format!("/api/admin/users/{alan}"), Some(&admin))
+        .await;
+    assert_eq!(user.status, StatusCode::OK);
+    assert_eq!(user.body["data"]["email"], "alan@example.com");
+    assert_eq!(user.body["data"]["status"], "Active");
+    assert_eq!(user.body["data"]["is_verified"], true);
+    assert_eq!(user.body["data"]["link_count"], 2);
+
+    let unknown = app
+        .get(
+            &format!("/api/admin/users/{}", Uuid::new_v4()),
+            Some(&admin),
+        )
+        .await;
+    assert_eq!(unknown.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn admins_page_through_and_filter_users() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+    app.signed_up_user("alan@example.com", "alan").await;
+    app.register("ada@example.com", "ada").await;
+
+    let first = app
+        .get("/api/admin/users?per_page=2&page=1", Some(&admin))
+        .await;
+    assert_eq!(first.body["data"].as_array().unwrap().len(), 2);
+    assert_eq!(first.body["pagination"]["total_items"], 3);
+    assert_eq!(first.body["pagination"]["total_pages"], 2);
+
+    let second = app
+        .get("/api/admin/users?per_page=2&page=2", Some(&admin))
+        .await;
+    assert_eq!(second.body["data"].as_array().unwrap().len(), 1);
+
+    let pending = app
+        .get("/api/admin/users?status=PendingVerification", Some(&admin))
+        .await;
+    assert_eq!(pending.status, StatusCode::OK);
+    assert_eq!(pending.body["pagination"]["total_items"], 1);
+    assert_eq!(pending.body["data"][0]["username"], "ada");
+}
+
+#[tokio::test]
+async fn force_verifying_activates_a_pending_account() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+    let grace = app.user_id("grace@example.com").await;
+    assert_eq!(
+        app.register("ada@example.com", "ada").await.status,
+        StatusCode::CREATED
+    );
+    let ada = app.user_id("ada@example.com").await;
+
+    let verified = app
+        .post(
+            &format!("/api/admin/users/{ada}/verify"),
+            Some(&admin),
+            json!({ "reason": "Confirmed by phone" }),
+        )
+        .await;
+    assert_eq!(verified.status, StatusCode::OK);
+    assert_eq!(verified.body["data"]["status"], "Active");
+    assert_eq!(verified.body["data"]["is_verified"], true);
+
+    let login = app
+        .login_from(Ipv4Addr::LOCALHOST.into(), "ada@example.com", PASSWORD)
+        .await;
+    assert_eq!(login.status, StatusCode::OK);
+
+    let again = app
+        .post(
+            &format!("/api/admin/users/{ada}/verify"),
+            Some(&admin),
+            json!({}),
+        )
+        .await;
+    assert_eq!(again.status, StatusCode::CONFLICT);
+    assert_eq!(again.body["code"], "ALREADY_VERIFIED");
+
+    let log = app
+        .get(&format!("/api/admin/audit-log?user_id={ada}"), Some(&admin))
+        .await;
+    assert_eq!(log.body["data"][0]["action"], "verify_email");
+    assert_eq!(log.body["data"][0]["admin_id"], grace.to_string());
+    assert_eq!(
+        log.body["data"][0]["details"]["reason"],
+        "Confirmed by phone"
+    );
+}
+
+#[tokio::test]
+async fn reactivating_an_unverified_account_returns_it_to_pending() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+    app.register("ada@example.com", "ada").await;
+    let ada = app.user_id("ada@example.com").await;
+
+    let deactivated = app
+        .post(
+            &format!("/api/admin/users/{ada}/deactivate"),
+            Some(&admin),
+            json!({}),
+        )
+        .await;
+    assert_eq!(deactivated.status, StatusCode::OK);
+    assert_eq!(deactivated.body["data"]["status"], "Inactive");
+
+    let reactivated = app
+        .post(
+            &format!("/api/admin/users/{ada}/reactivate"),
+            Some(&admin),
+            json!({}),
+        )
+        .await;
+    assert_eq!(reactivated.status, StatusCode::OK);
+    assert_eq!(reactivated.body["data"]["status"], "PendingVerification");
+
+    // Only suspended or inactive accounts can be reactivated
+    let again = app
+        .post(
+            &format!("/api/admin/users/{ada}/reactivate"),
+            Some(&admin),
+            json!({}),
+        )
+        .await;
+    assert_eq!(again.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn admins_cannot_suspend_themselves() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+    let grace = app.user_id("grace@example.com").await;
+
+    for action in ["suspend", "deactivate"] {
+        let response = app
+            .post(
+                &format!("/api/admin/users/{grace}/{action}"),
+                Some(&admin),
+                json!({}),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert_eq!(response.body["code"], "CANNOT_MODIFY_SELF");
+    }
+
+    let too_long = app
+        .post(
+            &format!("/api/admin/users/{}/suspend", Uuid::new_v4()),
+            Some(&admin),
+            json!({ "reason": "x".repeat(501) }),
+        )
+        .await;
+    assert_eq!(too_long.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::auth::UserStatus;
+use common::{TestApp, PASSWORD};
+use std::net::{IpAddr, Ipv4Addr};
+
+const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+#[tokio::test]
+async fn unverified_logins_point_to_the_resend_endpoint() {
+    let app = TestApp::new();
+    app.register("ada@example.com", "ada").await;
+
+    let login = app.login_from(CLIENT, "ada@example.com", PASSWORD).await;
+    assert_eq!(login.status, StatusCode::FORBIDDEN);
+    assert_eq!(login.body["code"], "EMAIL_NOT_VERIFIED");
+    assert_eq!(login.body["details"]["email"], "ada@example.com");
+    assert_eq!(
+        login.body["details"]["resend_otp_endpoint"],
+        "/api/auth/resend-otp"
+    );
+
+    // The hint is only given to someone who knows the password
+    let wrong = app
+        .login_from(CLIENT, "ada@example.com", "wrong password")
+        .await;
+    assert_eq!(wrong.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(wrong.body["code"], "INVALID_CREDENTIALS");
+    assert!(wrong.body["details"].is_null());
+}
+
+#[tokio::test]
+async fn suspended_and_inactive_accounts_get_their_own_codes() {
+    let app = TestApp::new();
+    app.signed_up_user("ada@example.com", "ada").await;
+    let ada = app.user_id("ada@example.com").await;
+
+    app.repository.set_status(ada, UserStatus::Suspended);
+    let suspended = app.login_from(CLIENT, "ada@example.com", PASSWORD).await;
+    assert_eq!(suspended.status, StatusCode::FORBIDDEN);
+    assert_eq!(suspended.body["code"], "ACCOUNT_SUSPENDED");
+
+    app.repository.set_status(ada, UserStatus::Inactive);
+    let inactive = app.login_from(CLIENT, "ada@example.com", PASSWORD).await;
+    assert_eq!(inactive.status, StatusCode::FORBIDDEN);
+    assert_eq!(inactive.body["code"], "ACCOUNT_INACTIVE");
+
+    let wrong = app
+        .login_from(CLIENT, "ada@example.com", "wrong password")
+        .await;
+    assert_eq!(wrong.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(wrong.body["code"], "INVALID_CREDENTIALS");
+}
+
+#[tokio::test]
+async fn unknown_emails_and_wrong_passwords_look_the_same() {
+    let app = TestApp::new();
+    app.signed_up_user("ada@example.com", "ada").await;
+
+    let unknown = app.login_from(CLIENT, "nobody@example.com", PASSWORD).await;
+    let wrong = app
+        .login_from(CLIENT, "ada@example.com", "wrong password")
+        .await;
+    assert_eq!(unknown.status, wrong.status);
+    assert_eq!(unknown.body["code"], wrong.body["code"]);
+    assert_eq!(unknown.body["message"], wrong.body["message"]);
+}
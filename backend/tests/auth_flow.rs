@@ -0,0 +1,305 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use backend::{
+    config::RateLimitConfig,
+    database::repository::UserRepository,
+    middleware::rate_limit::{RateLimitStoreKind, RatePolicy},
+};
+use common::{TestApp, PASSWORD};
+use serde_json::json;
+use std::net::{IpAddr, Ipv4Addr};
+
+fn client(n: u8) -> IpAddr {
+    Ipv4Addr::new(192, 0, 2, n).into()
+}
+
+#[tokio::test]
+async fn register_verify_and_login() {
+    let app = TestApp::new();
+
+    let registered = app.register("ada@example.com", "ada").await;
+    assert_eq!(registered.status, StatusCode::CREATED);
+    assert_eq!(registered.body["data"]["status"], "pending_verification");
+
+    let unverified = app.login_from(client(1), "ada@example.com", PASSWORD).await;
+    assert_eq!(unverified.status, StatusCode::FORBIDDEN);
+    assert_eq!(unverified.body["code"], "EMAIL_NOT_VERIFIED");
+
+    let otp = app.last_otp("ada@example.com");
+    let wrong_otp = if otp == "000000" { "111111" } else { "000000" };
+    let rejected = app
+        .post(
+            "/api/auth/verify",
+            None,
+            json!({ "email": "ada@example.com", "otp": wrong_otp }),
+        )
+        .await;
+    assert_eq!(rejected.status, StatusCode::BAD_REQUEST);
+    assert_eq!(rejected.body["code"], "INVALID_OTP");
+    assert_eq!(rejected.body["details"]["remaining_attempts"], 4);
+
+    let verified = app
+        .post(
+            "/api/auth/verify",
+            None,
+            json!({ "email": "ada@example.com", "otp": otp }),
+        )
+        .await;
+    assert_eq!(verified.status, StatusCode::OK);
+
+    let login = app.login_from(client(2), "ada@example.com", PASSWORD).await;
+    assert_eq!(login.status, StatusCode::OK);
+    assert_eq!(login.body["data"]["user"]["username"], "ada");
+    assert!(login.body["data"]["token"].is_string());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn parallel_otp_guesses_share_the_attempt_limit() {
+    let app = TestApp::new();
+    app.register("barbara@example.com", "barbara").await;
+    let otp = app.last_otp("barbara@example.com");
+    let wrong_otp = if otp == "000000" { "111111" } else { "000000" };
+
+    let mut guesses = tokio::task::JoinSet::new();
+    for _ in 0..12 {
+        let app = app.clone();
+        let body = json!({ "email": "barbara@example.com", "otp": wrong_otp });
+        guesses.spawn(async move { app.post("/api/auth/verify", None, body).await });
+    }
+    let responses = guesses.join_all().await;
+
+    // Only four wrong codes were compared before the fifth used up the OTP
+    let compared = responses
+        .iter()
+        .filter(|response| response.body["details"]["remaining_attempts"].is_number())
+        .count();
+    assert_eq!(compared, 4);
+    assert!(responses
+        .iter()
+        .any(|response| response.body["code"] == "TOO_MANY_OTP_ATTEMPTS"));
+
+    // The code was discarded once the limit was reached
+    let correct = app
+        .post(
+            "/api/auth/verify",
+            None,
+            json!({ "email": "barbara@example.com", "otp": otp }),
+        )
+        .await;
+    assert_eq!(correct.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn used_otp_cannot_be_replayed() {
+    let app = TestApp::new();
+    app.register("grace@example.com", "grace").await;
+    let otp = app.last_otp("grace@example.com");
+
+    let body = json!({ "email": "grace@example.com", "otp": otp });
+    assert_eq!(
+        app.post("/api/auth/verify", None, body.clone())
+            .await
+            .status,
+        StatusCode::OK
+    );
+    assert_eq!(
+        app.post("/api/auth/verify", None, body).await.status,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn registering_a_taken_account() {
+    let app = TestApp::new();
+    app.signed_up_user("linus@example.com", "linus").await;
+
+    let same_email = app.register("linus@example.com", "someone_else").await;
+    assert_eq!(same_email.status, StatusCode::CONFLICT);
+    assert_eq!(same_email.body["code"], "USER_EXISTS");
+
+    let same_username = app.register("other@example.com", "linus").await;
+    assert_eq!(same_username.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn registering_again_while_pending_resends_the_code() {
+    let app = TestApp::new();
+    app.register("margaret@example.com", "margaret").await;
+    let first = app.last_otp("margaret@example.com");
+
+    let again = app.register("margaret@example.com", "margaret").await;
+    assert_eq!(again.status, StatusCode::OK);
+    assert_eq!(app.repository.emails().len(), 2);
+
+    // Only the newest code is valid
+    let second = app.last_otp("margaret@example.com");
+    if first != second {
+        let stale = app
+            .post(
+                "/api/auth/verify",
+                None,
+                json!({ "email": "margaret@example.com", "otp": first }),
+            )
+            .await;
+        assert_eq!(stale.status, StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn invalid_registrations_are_rejected() {
+    let app = TestApp::new();
+
+    let invalid = app
+        .post(
+            "/api/auth/register",
+            None,
+            json!({
+                "email": "not-an-email",
+                "username": "x",
+                "password": "short",
+                "gender": "Other",
+            }),
+        )
+        .await;
+    assert_eq!(invalid.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(invalid.body["code"], "VALIDATION_ERROR");
+
+    let disposable = app.register("throwaway@mailinator.com", "throwaway").await;
+    assert_eq!(disposable.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(disposable.body["code"], "DISPOSABLE_EMAIL");
+
+    assert!(app.repository.emails().is_empty());
+}
+
+#[tokio::test]
+async fn wrong_passwords_lock_the_account() {
+    let app = TestApp::new();
+    app.signed_up_user("alan@example.com", "alan").await;
+
+    let unknown = app
+        .login_from(client(10), "nobody@example.com", PASSWORD)
+        .await;
+    assert_eq!(unknown.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(unknown.body["code"], "INVALID_CREDENTIALS");
+
+    // A different address each time, so the per-IP guard doesn't get involved
+    for n in 0..5 {
+        let wrong = app
+            .login_from(client(20 + n), "alan@example.com", "wrong password")
+            .await;
+        assert_eq!(wrong.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(wrong.body["code"], "INVALID_CREDENTIALS");
+    }
+
+    let locked_until = || async {
+        app.repository
+            .find_by_email("alan@example.com")
+            .await
+            .unwrap()
+            .and_then(|user| user.locked_until)
+            .expect("a locked account")
+    };
+    let until = locked_until().await;
+
+    // While locked, the right password looks just like a wrong one
+    let locked = app
+        .login_from(client(30), "alan@example.com", PASSWORD)
+        .await;
+    let wrong = app
+        .login_from(client(31), "alan@example.com", "wrong password")
+        .await;
+    for response in [&locked, &wrong] {
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(response.body["code"], "INVALID_CREDENTIALS");
+        assert_eq!(response.body["message"], wrong.body["message"]);
+    }
+
+    // Guesses while locked don't push the lock further out
+    assert_eq!(locked_until().await, until);
+
+    assert!(app
+        .repository
+        .unlock_account("alan@example.com")
+        .await
+        .unwrap());
+    let unlocked = app
+        .login_from(client(32), "alan@example.com", PASSWORD)
+        .await;
+    assert_eq!(unlocked.status, StatusCode::OK);
+}
+
+// Paused time skips the delays the login guard adds between failures
+#[tokio::test(start_paused = true)]
+async fn failed_logins_are_counted_per_client_behind_a_proxy() {
+    let proxy = client(1);
+    let app = TestApp::with_rate_limit(RateLimitConfig {
+        enabled: false,
+        store: RateLimitStoreKind::Memory,
+        trusted_proxies: vec!["192.0.2.1/32".parse().unwrap()],
+        auth: RatePolicy::per_minute(1000),
+        email: RatePolicy::per_minute(1000),
+        links: RatePolicy::per_minute(1000),
+    });
+    app.signed_up_user("edsger@example.com", "edsger").await;
+
+    let login_via_proxy = |forwarded_for: &'static str, email: &'static str| {
+        let app = app.clone();
+        async move {
+            app.request_with_headers(
+                proxy,
+                Method::POST,
+                "/api/auth/login",
+                None,
+                Some(json!({ "email": email, "password": PASSWORD })),
+                &[("x-forwarded-for", forwarded_for)],
+            )
+            .await
+        }
+    };
+
+    // Unknown emails fail without locking the account, until the client's address is blocked
+    for _ in 0..20 {
+        let response = login_via_proxy("198.51.100.7", "nobody@example.com").await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+    let blocked = login_via_proxy("198.51.100.7", "edsger@example.com").await;
+    assert_eq!(blocked.status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Another client behind the same proxy is not affected
+    let other = login_via_proxy("198.51.100.8", "edsger@example.com").await;
+    assert_eq!(other.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn auth_requests_are_rate_limited_per_client() {
+    let app = TestApp::with_rate_limit(RateLimitConfig {
+        enabled: true,
+        store: RateLimitStoreKind::Memory,
+        trusted_proxies: Vec::new(),
+        auth: RatePolicy::per_minute(3),
+        email: RatePolicy::per_minute(1000),
+        links: RatePolicy::per_minute(1000),
+    });
+
+    for _ in 0..3 {
+        let allowed = app
+            .login_from(client(40), "nobody@example.com", PASSWORD)
+            .await;
+        assert_eq!(allowed.status, StatusCode::UNAUTHORIZED);
+    }
+
+    let limited = app
+        .login_from(client(40), "nobody@example.com", PASSWORD)
+        .await;
+    assert_eq!(limited.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(limited.body["code"], "RATE_LIMITED");
+    assert_eq!(limited.headers["ratelimit-limit"], "3");
+    assert_eq!(limited.headers["ratelimit-remaining"], "0");
+    assert!(limited.headers.contains_key("retry-after"));
+
+    let other = app
+        .login_from(client(41), "nobody@example.com", PASSWORD)
+        .await;
+    assert_eq!(other.status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,326 @@
+//! An app wired like `main`, on the in-memory repositories, and helpers to call it.
+
+#![allow(dead_code)]
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::ConnectInfo,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    middleware::from_fn_with_state,
+    Router,
+};
+use backend::{
+    auth,
+    config::{JwtConfig, OidcConfig, RateLimitConfig, RegistrationConfig},
+    database::repository::{MemoryRepository, Repositories, UserRepository},
+    middleware::{
+        auth::{auth, require_role},
+        rate_limit::{RateLimitStoreKind, RateLimiter, RatePolicy},
+    },
+    models::auth::UserRole,
+    routes,
+    services::{
+        auth::AuthService,
+        email::EmailService,
+        jwt::JwtKeys,
+        otp_store::{MemoryOtpStore, OtpStore},
+        pending_cleanup::PendingAccountCleanup,
+        registration::{RegistrationMode, RegistrationPolicy},
+    },
+    shutdown::Shutdown,
+};
+use serde_json::{json, Value};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+pub const PASSWORD: &str = "correct horse battery";
+
+#[derive(Clone)]
+pub struct TestApp {
+    router: Router,
+    pub repository: Arc<MemoryRepository>,
+    pub auth_service: AuthService,
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+impl TestApp {
+    /// Rate limiting is off, so tests can make as many requests as they need
+    pub fn new() -> Self {
+        Self::with_rate_limit(no_rate_limit())
+    }
+
+    pub fn with_rate_limit(rate_limit: RateLimitConfig) -> Self {
+        Self::build(rate_limit, None, open_registration())
+    }
+
+    /// Single sign-on with the given identity provider
+    pub fn with_oidc(oidc: OidcConfig) -> Self {
+        Self::build(no_rate_limit(), Some(oidc), open_registration())
+    }
+
+    pub fn with_registration(registration: RegistrationConfig) -> Self {
+        Self::build(no_rate_limit(), None, registration)
+    }
+
+    fn build(
+        rate_limit: RateLimitConfig,
+        oidc: Option<OidcConfig>,
+        registration: RegistrationConfig,
+    ) -> Self {
+        let repository = Arc::new(MemoryRepository::new());
+        let repositories = Repositories::new(repository.clone());
+        let otp_store: Arc<dyn OtpStore> = Arc::new(MemoryOtpStore::new());
+
+        let jwt_keys = JwtKeys::from_config(&JwtConfig {
+            keys_dir: None,
+            ephemeral_key: true,
+            active_kid: None,
+            legacy_secret: None,
+        })
+        .expect("ephemeral JWT key");
+        let auth_service = AuthService::new(repositories.clone(), jwt_keys.clone());
+        let email_service = EmailService::new(
+            repositories.outbox.clone(),
+            otp_store.clone(),
+            "http://localhost:3000",
+            None,
+        );
+        let registration = RegistrationPolicy::from_config(&registration);
+        let rate_limiter = RateLimiter::from_config(&rate_limit, otp_store.clone());
+        let pending_cleanup = PendingAccountCleanup::new(
+            repositories.users.clone(),
+            Duration::from_secs(24 * 60 * 60),
+            Duration::from_secs(60 * 60),
+        );
+
+        let router = Router::new()
+            .merge(auth::create_router(
+                repositories.clone(),
+                jwt_keys,
+                email_service,
+                registration,
+                oidc,
+                otp_store,
+                rate_limiter.clone(),
+            ))
+            .merge(
+                routes::create_protected_router(
+                    repositories.clone(),
+                    Shutdown::new(),
+                    rate_limiter,
+                )
+                .layer(from_fn_with_state(auth_service.clone(), auth)),
+            )
+            .merge(
+                routes::create_admin_router(repositories, pending_cleanup)
+                    .route_layer(from_fn_with_state(UserRole::Admin, require_role))
+                    .route_layer(from_fn_with_state(auth_service.clone(), auth)),
+            );
+
+        Self {
+            router,
+            repository,
+            auth_service,
+        }
+    }
+
+    pub async fn get(&self, uri: &str, token: Option<&str>) -> TestResponse {
+        self.request(Method::GET, uri, token, None).await
+    }
+
+    pub async fn post(&self, uri: &str, token: Option<&str>, body: Value) -> TestResponse {
+        self.request(Method::POST, uri, token, Some(body)).await
+    }
+
+    pub async fn delete(&self, uri: &str, token: Option<&str>) -> TestResponse {
+        self.request(Method::DELETE, uri, token, None).await
+    }
+
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> TestResponse {
+        self.request_from([127, 0, 0, 1].into(), method, uri, token, body)
+            .await
+    }
+
+    /// Sends a request as if it came from `ip`
+    pub async fn request_from(
+        &self,
+        ip: IpAddr,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> TestResponse {
+        self.request_with_headers(ip, method, uri, token, body, &[])
+            .await
+    }
+
+    /// Sends a request from `ip` with extra headers
+    pub async fn request_with_headers(
+        &self,
+        ip: IpAddr,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+        headers: &[(&str, &str)],
+    ) -> TestResponse {
+        let mut request = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let mut request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .expect("valid request");
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(ip, 40000)));
+
+        let response = self
+            .router
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("infallible router");
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("readable body");
+        let body = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes).expect("JSON body")
+        };
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    pub async fn register(&self, email: &str, username: &str) -> TestResponse {
+        self.post(
+            "/api/auth/register",
+            None,
+            json!({
+                "email": email,
+                "username": username,
+                "password": PASSWORD,
+                "gender": "Other",
+            }),
+        )
+        .await
+    }
+
+    /// The code in the last OTP email queued for `email`
+    pub fn last_otp(&self, email: &str) -> String {
+        self.repository
+            .emails()
+            .into_iter()
+            .rev()
+            .find(|message| message.recipient == email && message.template == "otp_verification")
+            .and_then(|message| message.variables.get("otp").cloned())
+            .expect("an OTP email")
+    }
+
+    pub async fn login_from(&self, ip: IpAddr, email: &str, password: &str) -> TestResponse {
+        self.request_from(
+            ip,
+            Method::POST,
+            "/api/auth/login",
+            None,
+            Some(json!({ "email": email, "password": password })),
+        )
+        .await
+    }
+
+    /// Registers and verifies an account, returning a session token for it
+    pub async fn signed_up_user(&self, email: &str, username: &str) -> String {
+        assert_eq!(
+            self.register(email, username).await.status,
+            StatusCode::CREATED
+        );
+        let verified = self
+            .post(
+                "/api/auth/verify",
+                None,
+                json!({ "email": email, "otp": self.last_otp(email) }),
+            )
+            .await;
+        assert_eq!(verified.status, StatusCode::OK);
+
+        let login = self
+            .login_from(Ipv4Addr::LOCALHOST.into(), email, PASSWORD)
+            .await;
+        assert_eq!(login.status, StatusCode::OK);
+        login.body["data"]["token"]
+            .as_str()
+            .expect("token in login response")
+            .to_string()
+    }
+
+    /// The ID of the account registered with `email`
+    pub async fn user_id(&self, email: &str) -> Uuid {
+        self.repository
+            .find_id_by_email(email)
+            .await
+            .expect("memory repository")
+            .expect("a registered account")
+    }
+
+    /// Signs up an account with the admin role, returning a session token for it
+    pub async fn admin_user(&self, email: &str, username: &str) -> String {
+        self.signed_up_user(email, username).await;
+        self.repository
+            .set_role(self.user_id(email).await, UserRole::Admin);
+        let login = self
+            .login_from(Ipv4Addr::LOCALHOST.into(), email, PASSWORD)
+            .await;
+        login.body["data"]["token"]
+            .as_str()
+            .expect("token in login response")
+            .to_string()
+    }
+}
+
+fn open_registration() -> RegistrationConfig {
+    RegistrationConfig {
+        mode: RegistrationMode::Open,
+        allowed_domains: Vec::new(),
+        block_disposable_emails: true,
+    }
+}
+
+fn no_rate_limit() -> RateLimitConfig {
+    RateLimitConfig {
+        enabled: false,
+        store: RateLimitStoreKind::Memory,
+        trusted_proxies: Vec::new(),
+        auth: RatePolicy::per_minute(1000),
+        email: RatePolicy::per_minute(1000),
+        links: RatePolicy::per_minute(1000),
+    }
+}
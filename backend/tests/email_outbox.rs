@@ -0,0 +1,227 @@
+use async_trait::async_trait;
+use backend::{
+    database::{
+        models::{EmailStatus, NewEmail, OutboxEmail, QueuedEmail},
+        repository::OutboxRepository,
+    },
+    services::{
+        email_outbox::{OutboxWorker, MAX_DELIVERY_ATTEMPTS},
+        email_sender::{EmailSender, EmailSenderError, OutgoingEmail},
+    },
+    shutdown::Shutdown,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// How the worker settled an email
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Sent,
+    Retry { error: String, in_seconds: i64 },
+    Dead { error: String },
+}
+
+/// Hands out a fixed batch once and records what the worker does with it
+#[derive(Default)]
+struct FakeOutbox {
+    due: Mutex<Vec<QueuedEmail>>,
+    outcomes: Mutex<HashMap<Uuid, Outcome>>,
+}
+
+impl FakeOutbox {
+    fn with(emails: Vec<QueuedEmail>) -> Arc<Self> {
+        Arc::new(Self {
+            due: Mutex::new(emails),
+            outcomes: Mutex::default(),
+        })
+    }
+
+    fn outcome(&self, id: Uuid) -> Option<Outcome> {
+        self.outcomes.lock().unwrap().get(&id).cloned()
+    }
+}
+
+#[async_trait]
+impl OutboxRepository for FakeOutbox {
+    async fn enqueue(&self, _email: NewEmail) -> Result<Uuid, sqlx::Error> {
+        unimplemented!("the worker doesn't queue emails")
+    }
+
+    async fn claim_due(
+        &self,
+        limit: i64,
+        _lease_seconds: i64,
+    ) -> Result<Vec<QueuedEmail>, sqlx::Error> {
+        let mut due = self.due.lock().unwrap();
+        let count = due.len().min(limit as usize);
+        Ok(due.drain(..count).collect())
+    }
+
+    async fn mark_sent(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        self.outcomes.lock().unwrap().insert(id, Outcome::Sent);
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: Uuid,
+        error: &str,
+        retry_in_seconds: Option<i64>,
+    ) -> Result<(), sqlx::Error> {
+        let error = error.to_string();
+        let outcome = match retry_in_seconds {
+            Some(in_seconds) => Outcome::Retry { error, in_seconds },
+            None => Outcome::Dead { error },
+        };
+        self.outcomes.lock().unwrap().insert(id, outcome);
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        _status: EmailStatus,
+        _limit: i64,
+    ) -> Result<Vec<OutboxEmail>, sqlx::Error> {
+        Ok(Vec::new())
+    }
+
+    async fn replay(&self, _id: Uuid) -> Result<bool, sqlx::Error> {
+        Ok(false)
+    }
+}
+
+/// Records what it sends; addresses at `down.example.com` can't be reached
+#[derive(Default)]
+struct FakeSender {
+    sent: Mutex<Vec<OutgoingEmail>>,
+}
+
+#[async_trait]
+impl EmailSender for FakeSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailSenderError> {
+        if email.to.ends_with("@down.example.com") {
+            return Err(EmailSenderError::Resend("mailbox unavailable".to_string()));
+        }
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+fn queued(recipient: &str, template: &str, attempts: i32) -> QueuedEmail {
+    QueuedEmail {
+        id: Uuid::new_v4(),
+        recipient: recipient.to_string(),
+        template: template.to_string(),
+        locale: "fr".to_string(),
+        variables: HashMap::from([
+            ("otp".to_string(), "123456".to_string()),
+            ("minutes".to_string(), "5".to_string()),
+        ]),
+        attempts,
+    }
+}
+
+/// Runs the worker until it has settled the emails with `ids`
+async fn deliver(outbox: &Arc<FakeOutbox>, sender: &Arc<FakeSender>, ids: &[Uuid]) {
+    let shutdown = Shutdown::new();
+    let worker = OutboxWorker::new(outbox.clone(), sender.clone()).spawn(&shutdown);
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while ids.iter().any(|id| outbox.outcome(*id).is_none()) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the worker settles every email");
+
+    shutdown.trigger();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn due_emails_are_rendered_in_their_locale_and_sent() {
+    let email = queued("ada@example.com", "otp_verification", 1);
+    let outbox = FakeOutbox::with(vec![email.clone()]);
+    let sender = Arc::new(FakeSender::default());
+
+    deliver(&outbox, &sender, &[email.id]).await;
+
+    assert_eq!(outbox.outcome(email.id), Some(Outcome::Sent));
+    let sent = sender.sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "ada@example.com");
+    assert_eq!(sent[0].subject, "Vérifiez votre compte LinkSphere");
+    assert!(sent[0].text.contains("123456"));
+}
+
+#[tokio::test]
+async fn failed_sends_are_retried_with_backoff() {
+    let first = queued("ada@down.example.com", "otp_verification", 1);
+    let third = queued("bob@down.example.com", "otp_verification", 3);
+    let outbox = FakeOutbox::with(vec![first.clone(), third.clone()]);
+    let sender = Arc::new(FakeSender::default());
+
+    deliver(&outbox, &sender, &[first.id, third.id]).await;
+
+    for (email, base) in [(first, 15), (third, 60)] {
+        match outbox.outcome(email.id) {
+            Some(Outcome::Retry { error, in_seconds }) => {
+                assert!(error.contains("mailbox unavailable"), "{error}");
+                // Up to a quarter of jitter on top of the doubled delay
+                assert!(
+                    (base..=base + base / 4).contains(&in_seconds),
+                    "attempt {}: {in_seconds}s",
+                    email.attempts
+                );
+            }
+            other => panic!("expected a retry, got {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn emails_are_given_up_on_after_the_last_attempt() {
+    let last = queued(
+        "ada@down.example.com",
+        "otp_verification",
+        MAX_DELIVERY_ATTEMPTS,
+    );
+    let outbox = FakeOutbox::with(vec![last.clone()]);
+    let sender = Arc::new(FakeSender::default());
+
+    deliver(&outbox, &sender, &[last.id]).await;
+
+    assert!(matches!(
+        outbox.outcome(last.id),
+        Some(Outcome::Dead { .. })
+    ));
+}
+
+#[tokio::test]
+async fn emails_that_cannot_be_rendered_are_not_retried() {
+    let unknown = queued("ada@example.com", "welcome", 1);
+    let incomplete = QueuedEmail {
+        variables: HashMap::new(),
+        ..queued("bob@example.com", "otp_verification", 1)
+    };
+    let outbox = FakeOutbox::with(vec![unknown.clone(), incomplete.clone()]);
+    let sender = Arc::new(FakeSender::default());
+
+    deliver(&outbox, &sender, &[unknown.id, incomplete.id]).await;
+
+    assert_eq!(
+        outbox.outcome(unknown.id),
+        Some(Outcome::Dead {
+            error: "Unknown email template 'welcome'".to_string()
+        })
+    );
+    assert!(matches!(
+        outbox.outcome(incomplete.id),
+        Some(Outcome::Dead { .. })
+    ));
+    assert!(sender.sent.lock().unwrap().is_empty());
+}
@@ -0,0 +1,143 @@
+use backend::{
+    config::{EmailBackend, EmailConfig},
+    services::email_sender::{
+        self, EmailBackendKind, EmailSender, EmailSenderError, FileEmailSender, LogEmailSender,
+        OutgoingEmail, SmtpEmailSender,
+    },
+};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+fn email(to: &str) -> OutgoingEmail {
+    OutgoingEmail {
+        to: to.to_string(),
+        subject: "Your code".to_string(),
+        html: "<p>Your code is <b>123456</b></p>".to_string(),
+        text: "Your code is 123456".to_string(),
+    }
+}
+
+fn drop_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("linksphere-mail-{}", Uuid::new_v4()))
+}
+
+fn eml_files(dir: &PathBuf) -> Vec<String> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn the_file_backend_writes_each_email_to_the_drop_directory() {
+    let dir = drop_dir();
+    let sender = email_sender::from_config(&EmailConfig {
+        backend: EmailBackend::File {
+            drop_dir: dir.clone(),
+        },
+        from_name: "LinkSphere".to_string(),
+        from_email: "noreply@example.com".to_string(),
+    })
+    .unwrap();
+    sender.check().await.unwrap();
+
+    sender.send(&email("ada@example.com")).await.unwrap();
+    sender.send(&email("bob@example.com")).await.unwrap();
+
+    let files = eml_files(&dir);
+    assert_eq!(files.len(), 2);
+    let ada = files
+        .iter()
+        .find(|file| file.contains("To: ada@example.com"))
+        .unwrap();
+    assert!(ada.contains("From: LinkSphere <noreply@example.com>"));
+    assert!(ada.contains("Subject: Your code"));
+    assert!(ada.contains("multipart/alternative"));
+    assert!(ada.contains("Content-Type: text/plain"));
+    assert!(ada.contains("Content-Type: text/html"));
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(matches!(
+        sender.check().await,
+        Err(EmailSenderError::Config(_))
+    ));
+}
+
+#[tokio::test]
+async fn invalid_recipients_are_rejected_before_delivery() {
+    let dir = drop_dir();
+    let sender =
+        FileEmailSender::new("LinkSphere <noreply@example.com>".parse().unwrap(), &dir).unwrap();
+
+    assert!(matches!(
+        sender.send(&email("not an address")).await,
+        Err(EmailSenderError::Address(_))
+    ));
+    assert!(eml_files(&dir).is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn the_log_backend_accepts_everything() {
+    let sender = LogEmailSender::new("LinkSphere <noreply@example.com>".parse().unwrap());
+
+    sender.send(&email("ada@example.com")).await.unwrap();
+    sender.check().await.unwrap();
+}
+
+#[test]
+fn backends_are_selected_by_name() {
+    assert_eq!("SMTP".parse(), Ok(EmailBackendKind::Smtp));
+    assert_eq!("resend".parse(), Ok(EmailBackendKind::Resend));
+    assert_eq!("file".parse(), Ok(EmailBackendKind::File));
+    assert_eq!("log".parse(), Ok(EmailBackendKind::Log));
+    assert!("sendmail".parse::<EmailBackendKind>().is_err());
+}
+
+#[test]
+fn sender_addresses_are_validated() {
+    let result = email_sender::from_config(&EmailConfig {
+        backend: EmailBackend::Log,
+        from_name: "LinkSphere".to_string(),
+        from_email: "nobody".to_string(),
+    });
+
+    assert!(matches!(result, Err(EmailSenderError::Address(_))));
+}
+
+#[tokio::test]
+async fn smtp_checks_reuse_the_last_connection_attempt() {
+    // A relay that hangs up on everyone, counting the connections it gets
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            drop(socket);
+        }
+    });
+
+    let sender = SmtpEmailSender::new(
+        "LinkSphere <noreply@example.com>".parse().unwrap(),
+        "127.0.0.1",
+        port,
+        "user".to_string(),
+        "password".to_string(),
+    )
+    .unwrap();
+
+    for _ in 0..3 {
+        assert!(sender.check().await.is_err());
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use backend::services::email_templates::{render, EmailTemplate, Locale, TemplateError};
+use common::{TestApp, PASSWORD};
+use serde_json::json;
+use std::collections::HashMap;
+
+fn samples(template: EmailTemplate) -> HashMap<String, String> {
+    template
+        .sample_variables()
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn every_template_renders_in_every_locale() {
+    for template in EmailTemplate::ALL {
+        for locale in Locale::ALL {
+            let rendered = render(template, locale, &samples(template))
+                .unwrap_or_else(|e| panic!("{template} ({locale}): {e}"));
+
+            assert!(!rendered.subject.is_empty(), "{template} ({locale})");
+            assert!(!rendered.text.starts_with("Subject:"));
+            for part in [&rendered.subject, &rendered.html, &rendered.text] {
+                assert!(!part.contains("{{"), "{template} ({locale}): {part}");
+            }
+            // The layout wraps the body and carries the subject
+            assert!(rendered.html.contains("<html"), "{template} ({locale})");
+        }
+    }
+}
+
+#[test]
+fn emails_are_written_in_the_recipients_language() {
+    let variables = samples(EmailTemplate::OtpVerification);
+    let english = render(EmailTemplate::OtpVerification, Locale::En, &variables).unwrap();
+    let french = render(EmailTemplate::OtpVerification, Locale::Fr, &variables).unwrap();
+
+    assert_eq!(english.subject, "Verify Your LinkSphere Account");
+    assert_eq!(french.subject, "Vérifiez votre compte LinkSphere");
+    assert!(english.text.contains("123456"));
+    assert!(french.text.contains("123456"));
+}
+
+#[test]
+fn variables_are_only_escaped_in_html() {
+    let mut variables = samples(EmailTemplate::Digest);
+    variables.insert(
+        "username".to_string(),
+        "<script>alert('hi')</script>".to_string(),
+    );
+
+    let rendered = render(EmailTemplate::Digest, Locale::En, &variables).unwrap();
+
+    assert!(!rendered.html.contains("<script>"));
+    assert!(rendered
+        .html
+        .contains("&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;"));
+    assert!(rendered.text.contains("<script>alert('hi')</script>"));
+}
+
+#[test]
+fn missing_variables_are_reported() {
+    let mut variables = samples(EmailTemplate::MagicLink);
+    variables.remove("link");
+
+    match render(EmailTemplate::MagicLink, Locale::Fr, &variables) {
+        Err(TemplateError::MissingVariable { template, variable }) => {
+            assert_eq!(template, "magic_link");
+            assert_eq!(variable, "link");
+        }
+        other => panic!("expected a missing variable, got {other:?}"),
+    }
+}
+
+#[test]
+fn locales_and_templates_are_looked_up_by_name() {
+    assert_eq!(Locale::from_tag("fr-CA"), Locale::Fr);
+    assert_eq!(Locale::from_tag("FR_be"), Locale::Fr);
+    assert_eq!(Locale::from_tag("de-DE"), Locale::En);
+    assert_eq!(Locale::from_tag(""), Locale::En);
+    assert!("de".parse::<Locale>().is_err());
+
+    for template in EmailTemplate::ALL {
+        assert_eq!(template.name().parse::<EmailTemplate>().unwrap(), template);
+    }
+    assert!(matches!(
+        "welcome".parse::<EmailTemplate>(),
+        Err(TemplateError::UnknownTemplate(_))
+    ));
+}
+
+#[tokio::test]
+async fn emails_follow_the_language_chosen_at_registration() {
+    let app = TestApp::new();
+    let registered = app
+        .post(
+            "/api/auth/register",
+            None,
+            json!({
+                "email": "ada@example.com",
+                "username": "ada",
+                "password": PASSWORD,
+                "gender": "Other",
+                "locale": "fr",
+            }),
+        )
+        .await;
+    assert_eq!(registered.status, StatusCode::CREATED);
+
+    let emails = app.repository.emails();
+    let otp = emails.last().unwrap();
+    assert_eq!(otp.template, "otp_verification");
+    assert_eq!(otp.locale, "fr");
+}
+
+#[tokio::test]
+async fn the_language_can_be_changed_later() {
+    let app = TestApp::new();
+    let token = app.signed_up_user("ada@example.com", "ada").await;
+
+    let updated = app
+        .request(
+            Method::PUT,
+            "/api/account/locale",
+            Some(&token),
+            Some(json!({ "locale": "fr" })),
+        )
+        .await;
+    assert_eq!(updated.status, StatusCode::OK);
+
+    app.post(
+        "/api/auth/magic-link",
+        None,
+        json!({ "email": "ada@example.com" }),
+    )
+    .await;
+    let emails = app.repository.emails();
+    let link = emails.last().unwrap();
+    assert_eq!(link.template, "magic_link");
+    assert_eq!(link.locale, "fr");
+}
+
+#[tokio::test]
+async fn admins_preview_templates() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+
+    let listed = app.get("/api/admin/email-templates", Some(&admin)).await;
+    assert_eq!(listed.status, StatusCode::OK);
+    assert_eq!(
+        listed.body["data"].as_array().unwrap().len(),
+        EmailTemplate::ALL.len()
+    );
+
+    let preview = app
+        .post(
+            "/api/admin/email-templates/otp_verification/preview",
+            Some(&admin),
+            json!({ "locale": "fr", "variables": { "otp": "654321" } }),
+        )
+        .await;
+    assert_eq!(preview.status, StatusCode::OK);
+    assert_eq!(
+        preview.body["data"]["subject"],
+        "Vérifiez votre compte LinkSphere"
+    );
+    assert!(preview.body["data"]["text"]
+        .as_str()
+        .unwrap()
+        .contains("654321"));
+
+    let unknown = app
+        .post(
+            "/api/admin/email-templates/welcome/preview",
+            Some(&admin),
+            json!({}),
+        )
+        .await;
+    assert_eq!(unknown.status, StatusCode::NOT_FOUND);
+    assert_eq!(unknown.body["code"], "TEMPLATE_NOT_FOUND");
+}
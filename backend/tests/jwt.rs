@@ -0,0 +1,205 @@
+use backend::{
+    config::{JwtConfig, Secret},
+    models::auth::{Claims, MagicLinkClaims, UserRole},
+    services::jwt::{
+        generate_private_key_pem, public_key_pem, JwtKeyError, JwtKeys, ISSUER, PRIVATE_KEY_SUFFIX,
+        PUBLIC_KEY_SUFFIX,
+    },
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::Value;
+use std::{fs, path::PathBuf};
+use uuid::Uuid;
+
+const LEGACY_SECRET: &str = "legacy-secret";
+
+fn keys() -> JwtKeys {
+    JwtKeys::from_config(&JwtConfig {
+        keys_dir: None,
+        ephemeral_key: true,
+        active_kid: None,
+        legacy_secret: Some(Secret::new(LEGACY_SECRET)),
+    })
+    .expect("ephemeral JWT key")
+}
+
+/// A key directory that is removed again when dropped
+struct KeyDir(PathBuf);
+
+impl KeyDir {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("linksphere-keys-{}", Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn add_private(&self, kid: &str) {
+        let pem = generate_private_key_pem().unwrap();
+        fs::write(self.0.join(format!("{kid}{PRIVATE_KEY_SUFFIX}")), pem).unwrap();
+    }
+
+    /// Replaces a private key with its public half, as when a key is retired
+    fn retire(&self, kid: &str) {
+        let private_path = self.0.join(format!("{kid}{PRIVATE_KEY_SUFFIX}"));
+        let public = public_key_pem(kid, &fs::read(&private_path).unwrap()).unwrap();
+        fs::write(self.0.join(format!("{kid}{PUBLIC_KEY_SUFFIX}")), public).unwrap();
+        fs::remove_file(private_path).unwrap();
+    }
+
+    fn load(&self, active_kid: Option<&str>) -> Result<JwtKeys, JwtKeyError> {
+        JwtKeys::from_dir(&self.0, active_kid, None)
+    }
+}
+
+impl Drop for KeyDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn kid(token: &str) -> String {
+    jsonwebtoken::decode_header(token).unwrap().kid.unwrap()
+}
+
+fn expiry() -> i64 {
+    (Utc::now() + Duration::minutes(5)).timestamp()
+}
+
+fn session(user_id: Uuid) -> Claims {
+    Claims {
+        sub: user_id,
+        exp: expiry(),
+        email: "ada@example.com".to_string(),
+        username: "ada".to_string(),
+        role: UserRole::User,
+        session_version: 0,
+    }
+}
+
+fn magic_link(user_id: Uuid) -> MagicLinkClaims {
+    MagicLinkClaims {
+        sub: user_id,
+        exp: expiry(),
+        email: "ada@example.com".to_string(),
+        jti: Uuid::new_v4().to_string(),
+    }
+}
+
+fn payload(token: &str) -> Value {
+    let payload = token.split('.').nth(1).expect("JWT payload");
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap()
+}
+
+#[test]
+fn tokens_name_their_issuer_and_audience() {
+    let keys = keys();
+    let token = keys.sign(&session(Uuid::new_v4())).unwrap();
+
+    let payload = payload(&token);
+    assert_eq!(payload["iss"], ISSUER);
+    assert_eq!(payload["aud"], "linksphere:session");
+    assert!(payload["iat"].is_u64());
+    assert!(keys.verify::<Claims>(&token).is_ok());
+}
+
+#[test]
+fn tokens_are_only_accepted_as_their_own_kind() {
+    let keys = keys();
+    let user_id = Uuid::new_v4();
+
+    let link = keys.sign(&magic_link(user_id)).unwrap();
+    assert!(keys.verify::<MagicLinkClaims>(&link).is_ok());
+    assert!(keys.verify::<Claims>(&link).is_err());
+
+    let session = keys.sign(&session(user_id)).unwrap();
+    assert!(keys.verify::<MagicLinkClaims>(&session).is_err());
+}
+
+#[test]
+fn legacy_tokens_are_only_accepted_as_sessions() {
+    let keys = keys();
+    let user_id = Uuid::new_v4();
+    let secret = EncodingKey::from_secret(LEGACY_SECRET.as_bytes());
+
+    let session = encode(&Header::default(), &session(user_id), &secret).unwrap();
+    assert_eq!(keys.verify::<Claims>(&session).unwrap().claims.sub, user_id);
+
+    let link = encode(&Header::default(), &magic_link(user_id), &secret).unwrap();
+    assert!(keys.verify::<MagicLinkClaims>(&link).is_err());
+}
+
+#[test]
+fn the_newest_key_signs_unless_another_is_chosen() {
+    let dir = KeyDir::new();
+    dir.add_private("2024-01-01");
+    dir.add_private("2024-06-01");
+
+    let keys = dir.load(None).unwrap();
+    assert_eq!(keys.active_kid(), "2024-06-01");
+    let token = keys.sign(&session(Uuid::new_v4())).unwrap();
+    assert_eq!(kid(&token), "2024-06-01");
+
+    let pinned = dir.load(Some("2024-01-01")).unwrap();
+    assert_eq!(pinned.active_kid(), "2024-01-01");
+
+    assert!(matches!(
+        dir.load(Some("2025-01-01")),
+        Err(JwtKeyError::UnknownActiveKey(kid)) if kid == "2025-01-01"
+    ));
+}
+
+#[test]
+fn tokens_signed_before_a_rotation_stay_valid() {
+    let dir = KeyDir::new();
+    dir.add_private("2024-01-01");
+    let before = dir
+        .load(None)
+        .unwrap()
+        .sign(&session(Uuid::new_v4()))
+        .unwrap();
+
+    // Rotate, keeping only the public half of the old key
+    dir.add_private("2024-06-01");
+    dir.retire("2024-01-01");
+    let keys = dir.load(None).unwrap();
+
+    assert_eq!(keys.active_kid(), "2024-06-01");
+    assert!(keys.verify::<Claims>(&before).is_ok());
+    let kids: Vec<_> = keys
+        .jwks()
+        .keys
+        .into_iter()
+        .filter_map(|jwk| jwk.common.key_id)
+        .collect();
+    assert_eq!(kids, ["2024-01-01", "2024-06-01"]);
+
+    // Once the old key is gone, its tokens are too
+    fs::remove_file(dir.0.join(format!("2024-01-01{PUBLIC_KEY_SUFFIX}"))).unwrap();
+    assert!(dir.load(None).unwrap().verify::<Claims>(&before).is_err());
+}
+
+#[test]
+fn unusable_key_directories_are_rejected() {
+    let dir = KeyDir::new();
+    assert!(matches!(dir.load(None), Err(JwtKeyError::NoSigningKey)));
+
+    fs::write(
+        dir.0.join(format!("broken{PRIVATE_KEY_SUFFIX}")),
+        "not a key",
+    )
+    .unwrap();
+    assert!(matches!(
+        dir.load(None),
+        Err(JwtKeyError::InvalidKey { kid, .. }) if kid == "broken"
+    ));
+
+    let without_dir = JwtKeys::from_config(&JwtConfig {
+        keys_dir: None,
+        ephemeral_key: false,
+        active_kid: None,
+        legacy_secret: None,
+    });
+    assert!(matches!(without_dir, Err(JwtKeyError::NoKeyDir)));
+}
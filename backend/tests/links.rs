@@ -0,0 +1,141 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+// Nothing listens on the discard port, so the preview fetch fails quickly
+const URL: &str = "http://127.0.0.1:9/article";
+
+fn new_link(url: &str) -> Value {
+    json!({
+        "url": url,
+        "title": "An article",
+        "description": "Worth reading",
+    })
+}
+
+#[tokio::test]
+async fn links_require_a_token() {
+    let app = TestApp::new();
+
+    let anonymous = app.get("/api/links", None).await;
+    assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED);
+
+    let forged = app.get("/api/links", Some("not.a.token")).await;
+    assert_eq!(forged.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(forged.body["code"], "INVALID_TOKEN");
+    // The reason the token was rejected is only logged
+    assert_eq!(forged.body["message"], "Invalid or expired token");
+
+    let create = app.post("/api/links", None, new_link(URL)).await;
+    assert_eq!(create.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn create_and_list_links() {
+    let app = TestApp::new();
+    let token = app.signed_up_user("ada@example.com", "ada").await;
+
+    let empty = app.get("/api/links", Some(&token)).await;
+    assert_eq!(empty.status, StatusCode::OK);
+    assert_eq!(empty.body["data"], json!([]));
+
+    let created = app.post("/api/links", Some(&token), new_link(URL)).await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["data"]["url"], URL);
+    assert_eq!(created.body["data"]["click_count"], 0);
+
+    let second = app
+        .post("/api/links", Some(&token), new_link("https://example.com/"))
+        .await;
+    assert_eq!(second.status, StatusCode::CREATED);
+
+    let listed = app.get("/api/links", Some(&token)).await;
+    let links = listed.body["data"].as_array().expect("list of links");
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0]["id"], second.body["data"]["id"]);
+    assert_eq!(links[1]["id"], created.body["data"]["id"]);
+    assert_eq!(links[1]["user"]["username"], "ada");
+}
+
+#[tokio::test]
+async fn invalid_links_are_rejected() {
+    let app = TestApp::new();
+    let token = app.signed_up_user("ada@example.com", "ada").await;
+
+    let not_a_url = app
+        .post("/api/links", Some(&token), new_link("not a url"))
+        .await;
+    assert_eq!(not_a_url.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(not_a_url.body["code"], "VALIDATION_ERROR");
+
+    let wrong_scheme = app
+        .post(
+            "/api/links",
+            Some(&token),
+            new_link("ftp://example.com/file"),
+        )
+        .await;
+    assert_eq!(wrong_scheme.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(wrong_scheme.body["code"], "INVALID_URL");
+
+    let untitled = app
+        .post(
+            "/api/links",
+            Some(&token),
+            json!({ "url": URL, "title": "", "description": "Worth reading" }),
+        )
+        .await;
+    assert_eq!(untitled.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let listed = app.get("/api/links", Some(&token)).await;
+    assert_eq!(listed.body["data"], json!([]));
+}
+
+#[tokio::test]
+async fn only_the_owner_can_delete_a_link() {
+    let app = TestApp::new();
+    let owner = app.signed_up_user("ada@example.com", "ada").await;
+    let other = app.signed_up_user("grace@example.com", "grace").await;
+
+    let created = app.post("/api/links", Some(&owner), new_link(URL)).await;
+    let uri = format!(
+        "/api/links/{}",
+        created.body["data"]["id"].as_str().unwrap()
+    );
+
+    let forbidden = app.delete(&uri, Some(&other)).await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+    assert_eq!(forbidden.body["code"], "FORBIDDEN");
+
+    let deleted = app.delete(&uri, Some(&owner)).await;
+    assert_eq!(deleted.status, StatusCode::OK);
+
+    let missing = app.delete(&uri, Some(&owner)).await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+
+    let listed = app.get("/api/links", Some(&owner)).await;
+    assert_eq!(listed.body["data"], json!([]));
+}
+
+#[tokio::test]
+async fn clicks_are_counted() {
+    let app = TestApp::new();
+    let token = app.signed_up_user("ada@example.com", "ada").await;
+
+    let created = app.post("/api/links", Some(&token), new_link(URL)).await;
+    let uri = format!(
+        "/api/links/{}/click",
+        created.body["data"]["id"].as_str().unwrap()
+    );
+    for _ in 0..3 {
+        assert_eq!(
+            app.post(&uri, Some(&token), json!({})).await.status,
+            StatusCode::OK
+        );
+    }
+
+    let listed = app.get("/api/links", Some(&token)).await;
+    assert_eq!(listed.body["data"][0]["click_count"], 3);
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn requests_look_the_same_with_or_without_an_account() {
+    let app = TestApp::new();
+    app.signed_up_user("ada@example.com", "ada").await;
+
+    for email in ["ada@example.com", "nobody@example.com"] {
+        // More than the per-email limit, which only accounts use up
+        for _ in 0..8 {
+            let response = app
+                .post("/api/auth/magic-link", None, json!({ "email": email }))
+                .await;
+            assert_eq!(response.status, StatusCode::OK);
+        }
+    }
+
+    let links = app
+        .repository
+        .emails()
+        .into_iter()
+        .filter(|message| message.template == "magic_link")
+        .count();
+    assert_eq!(links, 4);
+}
+
+/// The token from the last login link emailed to `email`
+fn last_magic_link_token(app: &TestApp, email: &str) -> String {
+    let link = app
+        .repository
+        .emails()
+        .into_iter()
+        .rev()
+        .find(|message| message.recipient == email && message.template == "magic_link")
+        .and_then(|message| message.variables.get("link").cloned())
+        .expect("a login link email");
+    link.split_once("token=")
+        .map(|(_, token)| token.to_string())
+        .expect("a token in the login link")
+}
+
+#[tokio::test]
+async fn login_links_sign_in_once_and_are_not_session_tokens() {
+    let app = TestApp::new();
+    let session = app.signed_up_user("ada@example.com", "ada").await;
+
+    app.post(
+        "/api/auth/magic-link",
+        None,
+        json!({ "email": "ada@example.com" }),
+    )
+    .await;
+    let token = last_magic_link_token(&app, "ada@example.com");
+
+    // The link's token doesn't authenticate requests by itself
+    let as_bearer = app.get("/api/links", Some(&token)).await;
+    assert_eq!(as_bearer.status, StatusCode::UNAUTHORIZED);
+
+    // A session token isn't a login link
+    let session_as_link = app
+        .post(
+            "/api/auth/magic-link/consume",
+            None,
+            json!({ "token": session }),
+        )
+        .await;
+    assert_eq!(session_as_link.status, StatusCode::UNAUTHORIZED);
+
+    let consumed = app
+        .post(
+            "/api/auth/magic-link/consume",
+            None,
+            json!({ "token": token }),
+        )
+        .await;
+    assert_eq!(consumed.status, StatusCode::OK);
+    let signed_in = consumed.body["data"]["token"].as_str().unwrap();
+    assert_eq!(
+        app.get("/api/links", Some(signed_in)).await.status,
+        StatusCode::OK
+    );
+
+    let replayed = app
+        .post(
+            "/api/auth/magic-link/consume",
+            None,
+            json!({ "token": token }),
+        )
+        .await;
+    assert_eq!(replayed.status, StatusCode::UNAUTHORIZED);
+}
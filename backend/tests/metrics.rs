@@ -0,0 +1,116 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Path,
+    http::{Request, StatusCode},
+    middleware::from_fn,
+    routing::get,
+    Router,
+};
+use backend::{
+    database::Database,
+    metrics::{self, MetricsState},
+};
+use sqlx::postgres::PgPoolOptions;
+use std::time::Duration;
+use tower::ServiceExt;
+
+async fn get_path(router: &Router, uri: &str) -> (StatusCode, String) {
+    let response = router
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// The sample of `metric` whose labels include all of `labels`
+fn sample<'a>(rendered: &'a str, metric: &str, labels: &[&str]) -> Option<&'a str> {
+    rendered
+        .lines()
+        .filter(|line| {
+            line.starts_with(&format!("{metric}{{")) || line.starts_with(&format!("{metric} "))
+        })
+        .find(|line| labels.iter().all(|label| line.contains(label)))
+        .and_then(|line| line.rsplit(' ').next())
+}
+
+// The recorder is global, so everything is checked in one test
+#[tokio::test]
+async fn requests_and_events_are_exported() {
+    let handle = metrics::install().unwrap();
+    // Nothing listens there; the pool gauges are still reported
+    let pool = PgPoolOptions::new()
+        .max_connections(3)
+        .acquire_timeout(Duration::from_millis(100))
+        .connect_lazy("postgres://linksphere@127.0.0.1:1/linksphere")
+        .unwrap();
+
+    let router = Router::new()
+        .route(
+            "/api/links/{id}",
+            get(|Path(id): Path<u32>| async move { id.to_string() }),
+        )
+        .merge(metrics::router(MetricsState::new(
+            handle,
+            Database::Postgres(pool),
+        )))
+        .layer(from_fn(metrics::track_requests));
+
+    for uri in [
+        "/api/links/1",
+        "/api/links/2",
+        "/api/links/x",
+        "/wp-login.php",
+    ] {
+        get_path(&router, uri).await;
+    }
+    metrics::record_otp_verification("invalid");
+    metrics::record_email_delivery("magic_link", "retrying");
+    metrics::record_rate_limited("auth");
+
+    let (status, rendered) = get_path(&router, "/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let requests = |labels: &[&str]| sample(&rendered, "http_requests_total", labels);
+    // IDs are folded into the route template
+    assert_eq!(
+        requests(&[r#"route="/api/links/{id}""#, r#"status="200""#]),
+        Some("2")
+    );
+    assert_eq!(
+        requests(&[r#"route="/api/links/{id}""#, r#"status="400""#]),
+        Some("1")
+    );
+    assert_eq!(
+        requests(&[r#"route="unmatched""#, r#"status="404""#]),
+        Some("1")
+    );
+    assert!(rendered.contains("# TYPE http_request_duration_seconds histogram"));
+    assert_eq!(
+        sample(
+            &rendered,
+            "otp_verifications_total",
+            &[r#"outcome="invalid""#]
+        ),
+        Some("1")
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "email_deliveries_total",
+            &[r#"template="magic_link""#, r#"result="retrying""#]
+        ),
+        Some("1")
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "rate_limited_requests_total",
+            &[r#"group="auth""#]
+        ),
+        Some("1")
+    );
+    assert_eq!(sample(&rendered, "db_pool_max_connections", &[]), Some("3"));
+}
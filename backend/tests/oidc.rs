@@ -0,0 +1,321 @@
+mod common;
+
+use axum::{
+    extract::State,
+    http::{header, Method, StatusCode},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use backend::{
+    config::{OidcConfig, RegistrationConfig},
+    services::{
+        oidc::OidcIdentity,
+        registration::{RegistrationMode, RegistrationPolicy},
+    },
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use common::{TestApp, TestResponse, PASSWORD};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    net::Ipv4Addr,
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+const CLIENT_ID: &str = "linksphere";
+const IDP_KID: &str = "idp";
+
+/// An identity provider that hands out ID tokens for codes a test has authorized
+#[derive(Clone)]
+struct MockIdp {
+    issuer: String,
+    key: EncodingKey,
+    /// The public half of `key`, for the JWKS
+    public_key: Vec<u8>,
+    /// Authorized codes with their PKCE challenge and nonce
+    codes: Arc<Mutex<HashMap<String, (String, String)>>>,
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    code: String,
+    code_verifier: String,
+    client_id: String,
+}
+
+impl MockIdp {
+    async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock identity provider");
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).expect("signing key");
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).expect("signing key");
+        let idp = MockIdp {
+            issuer: format!("http://{}", listener.local_addr().unwrap()),
+            key: EncodingKey::from_ed_der(pkcs8.as_ref()),
+            public_key: key_pair.public_key().as_ref().to_vec(),
+            codes: Arc::default(),
+        };
+
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(Self::discovery))
+            .route("/jwks", get(Self::jwks))
+            .route("/token", post(Self::token))
+            .with_state(idp.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        idp
+    }
+
+    /// Lets `code` be exchanged, as if the user had logged in at the provider
+    fn authorize(&self, code: &str, authorization_url: &Url) {
+        let params: HashMap<String, String> =
+            authorization_url.query_pairs().into_owned().collect();
+        self.codes.lock().unwrap().insert(
+            code.to_string(),
+            (params["code_challenge"].clone(), params["nonce"].clone()),
+        );
+    }
+
+    fn config(&self) -> OidcConfig {
+        OidcConfig {
+            issuer_url: self.issuer.clone(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_url: "http://localhost:3000/sso/callback".to_string(),
+            scopes: "openid email profile".to_string(),
+        }
+    }
+
+    async fn discovery(State(idp): State<MockIdp>) -> Json<Value> {
+        Json(json!({
+            "issuer": idp.issuer,
+            "authorization_endpoint": format!("{}/authorize", idp.issuer),
+            "token_endpoint": format!("{}/token", idp.issuer),
+            "jwks_uri": format!("{}/jwks", idp.issuer),
+        }))
+    }
+
+    async fn jwks(State(idp): State<MockIdp>) -> Json<Value> {
+        Json(json!({
+            "keys": [{
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "x": URL_SAFE_NO_PAD.encode(&idp.public_key),
+                "kid": IDP_KID,
+                "alg": "EdDSA",
+                "use": "sig",
+            }]
+        }))
+    }
+
+    async fn token(
+        State(idp): State<MockIdp>,
+        Form(request): Form<TokenRequest>,
+    ) -> Result<Json<Value>, StatusCode> {
+        let (challenge, nonce) = idp
+            .codes
+            .lock()
+            .unwrap()
+            .remove(&request.code)
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        let verified = URL_SAFE_NO_PAD.encode(Sha256::digest(request.code_verifier.as_bytes()));
+        if verified != challenge || request.client_id != CLIENT_ID {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(IDP_KID.to_string());
+        let id_token = encode(
+            &header,
+            &json!({
+                "iss": idp.issuer,
+                "aud": CLIENT_ID,
+                "sub": "subject-1",
+                "exp": Utc::now().timestamp() + 300,
+                "nonce": nonce,
+                "email": "ada@example.com",
+                "email_verified": true,
+                "preferred_username": "ada",
+            }),
+            &idp.key,
+        )
+        .expect("signed ID token");
+        Ok(Json(
+            json!({ "id_token": id_token, "token_type": "Bearer" }),
+        ))
+    }
+}
+
+/// Starts a login, returning the provider URL and the state cookie
+async fn start_login(app: &TestApp) -> (Url, String) {
+    let response = app.get("/api/auth/oidc/login", None).await;
+    assert!(response.status.is_redirection());
+    let location = response.headers[header::LOCATION].to_str().unwrap();
+    let cookie = response.headers[header::SET_COOKIE].to_str().unwrap();
+    assert!(cookie.contains("HttpOnly") && cookie.contains("SameSite=Lax"));
+    let cookie = cookie.split(';').next().unwrap().to_string();
+    (Url::parse(location).unwrap(), cookie)
+}
+
+async fn callback(app: &TestApp, url: &Url, cookie: Option<&str>) -> TestResponse {
+    let state = url
+        .query_pairs()
+        .find(|(key, _)| key == "state")
+        .map(|(_, value)| value.into_owned())
+        .expect("state parameter");
+    let headers: Vec<(&str, &str)> = cookie
+        .map(|cookie| ("cookie", cookie))
+        .into_iter()
+        .collect();
+    app.request_with_headers(
+        Ipv4Addr::LOCALHOST.into(),
+        Method::POST,
+        "/api/auth/oidc/callback",
+        None,
+        Some(json!({ "code": "good-code", "state": state })),
+        &headers,
+    )
+    .await
+}
+
+fn registration(mode: RegistrationMode, allowed_domains: &[&str]) -> RegistrationPolicy {
+    RegistrationPolicy::from_config(&RegistrationConfig {
+        mode,
+        allowed_domains: allowed_domains.iter().map(|d| d.to_string()).collect(),
+        block_disposable_emails: true,
+    })
+}
+
+fn open_registration() -> RegistrationPolicy {
+    registration(RegistrationMode::Open, &[])
+}
+
+fn identity(email: &str) -> OidcIdentity {
+    OidcIdentity {
+        issuer: "https://idp.example.com".to_string(),
+        subject: "subject-1".to_string(),
+        email: email.to_string(),
+        preferred_username: Some("ada".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn sign_in_links_a_verified_account_with_the_same_email() {
+    let app = TestApp::new();
+    app.signed_up_user("ada@example.com", "ada").await;
+
+    let signed_in = app
+        .auth_service
+        .login_with_oidc(identity("ada@example.com"), &open_registration())
+        .await
+        .expect("linked sign-in");
+    assert_eq!(signed_in.user.username, "ada");
+
+    // The password keeps working for the linked account
+    let login = app
+        .login_from(Ipv4Addr::LOCALHOST.into(), "ada@example.com", PASSWORD)
+        .await;
+    assert_eq!(login.status, StatusCode::OK);
+    assert_eq!(
+        login.body["data"]["user"]["id"],
+        signed_in.user.id.to_string()
+    );
+}
+
+#[tokio::test]
+async fn sign_in_does_not_take_over_an_unverified_registration() {
+    let app = TestApp::new();
+    // Someone registers the address before its owner, but can't verify it
+    assert_eq!(
+        app.register("ada@example.com", "squatter").await.status,
+        StatusCode::CREATED
+    );
+
+    let signed_in = app
+        .auth_service
+        .login_with_oidc(identity("ada@example.com"), &open_registration())
+        .await
+        .expect("sign-in with a new account");
+    assert_ne!(signed_in.user.username, "squatter");
+    assert!(signed_in.user.is_verified);
+
+    // The squatter's password does not open the owner's account
+    let login = app
+        .login_from(Ipv4Addr::LOCALHOST.into(), "ada@example.com", PASSWORD)
+        .await;
+    assert_eq!(login.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn new_accounts_follow_the_registration_policy() {
+    let app = TestApp::new();
+
+    let domain_only = registration(RegistrationMode::Domain, &["example.com"]);
+    let outsider = app
+        .auth_service
+        .login_with_oidc(identity("ada@elsewhere.org"), &domain_only)
+        .await
+        .expect_err("address outside the allowed domains");
+    assert_eq!(outsider.code(), "DOMAIN_NOT_ALLOWED");
+
+    let disposable = app
+        .auth_service
+        .login_with_oidc(identity("ada@mailinator.com"), &open_registration())
+        .await
+        .expect_err("disposable address");
+    assert_eq!(disposable.code(), "DISPOSABLE_EMAIL");
+
+    // Invite-only deployments let existing accounts sign in, but don't create new ones
+    let invite_only = registration(RegistrationMode::Invite, &[]);
+    let newcomer = app
+        .auth_service
+        .login_with_oidc(identity("ada@example.com"), &invite_only)
+        .await
+        .expect_err("new account without an invite");
+    assert_eq!(newcomer.code(), "INVITE_REQUIRED");
+
+    app.signed_up_user("ada@example.com", "ada").await;
+    let member = app
+        .auth_service
+        .login_with_oidc(identity("ada@example.com"), &invite_only)
+        .await
+        .expect("sign-in to an existing account");
+    assert_eq!(member.user.username, "ada");
+}
+
+#[tokio::test]
+async fn callback_needs_the_state_cookie_of_the_browser_that_started_the_login() {
+    let idp = MockIdp::start().await;
+    let app = TestApp::with_oidc(idp.config());
+
+    let (url, cookie) = start_login(&app).await;
+    let (_, other_cookie) = start_login(&app).await;
+    idp.authorize("good-code", &url);
+
+    let without_cookie = callback(&app, &url, None).await;
+    assert_eq!(without_cookie.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(without_cookie.body["code"], "SSO_INVALID_STATE");
+
+    // A cookie from another login doesn't match the state
+    let wrong_cookie = callback(&app, &url, Some(&other_cookie)).await;
+    assert_eq!(wrong_cookie.status, StatusCode::UNAUTHORIZED);
+
+    let signed_in = callback(&app, &url, Some(&cookie)).await;
+    assert_eq!(signed_in.status, StatusCode::OK);
+    assert_eq!(signed_in.body["data"]["user"]["email"], "ada@example.com");
+    assert!(signed_in.body["data"]["token"].is_string());
+
+    // The state can only be used once
+    let replayed = callback(&app, &url, Some(&cookie)).await;
+    assert_eq!(replayed.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(replayed.body["code"], "SSO_INVALID_STATE");
+}
@@ -0,0 +1,166 @@
+use backend::{
+    config::Secret,
+    database::repository::{MemoryRepository, Repositories},
+    services::{
+        email::{EmailService, OtpVerification},
+        email_templates::Locale,
+        otp_store::{MemoryOtpStore, OtpStore},
+    },
+};
+use std::{sync::Arc, time::Duration};
+
+const EMAIL: &str = "ada@example.com";
+
+fn service(store: &MemoryOtpStore, key: &str) -> EmailService {
+    let repositories = Repositories::new(Arc::new(MemoryRepository::new()));
+    EmailService::new(
+        repositories.outbox,
+        Arc::new(store.clone()),
+        "http://localhost:3000",
+        Some(&Secret::new(key)),
+    )
+}
+
+async fn issue(service: &EmailService) -> String {
+    let email = service.issue_otp(EMAIL, Locale::En).await.unwrap();
+    email.variables["otp"].clone()
+}
+
+/// A six digit code that isn't `otp`
+fn wrong(otp: &str) -> String {
+    let first = (otp.as_bytes()[0] - b'0' + 1) % 10;
+    format!("{first}{}", &otp[1..])
+}
+
+#[tokio::test]
+async fn only_a_keyed_hash_of_the_code_is_stored() {
+    let store = MemoryOtpStore::new();
+    let otp = issue(&service(&store, "first-key")).await;
+
+    assert_eq!(otp.len(), 6);
+    assert!(otp.chars().all(|c| c.is_ascii_digit()));
+    let stored = store.get(&format!("otp:{EMAIL}")).await.unwrap().unwrap();
+    assert_ne!(stored, otp);
+    assert!(!stored.contains(&otp));
+    assert_eq!(stored.len(), 64);
+    assert!(stored.chars().all(|c| c.is_ascii_hexdigit()));
+
+    // Without the key the stored hash can't be matched
+    assert_eq!(
+        service(&store, "other-key")
+            .verify_otp(EMAIL, &otp)
+            .await
+            .unwrap(),
+        OtpVerification::Invalid {
+            remaining_attempts: 4
+        }
+    );
+    assert_eq!(
+        service(&store, "first-key")
+            .verify_otp(EMAIL, &otp)
+            .await
+            .unwrap(),
+        OtpVerification::Valid
+    );
+}
+
+#[tokio::test]
+async fn codes_are_bound_to_their_email() {
+    let store = MemoryOtpStore::new();
+    let service = service(&store, "key");
+    let otp = issue(&service).await;
+
+    // Moving the stored hash to another email doesn't carry the code with it
+    let stored = store.get(&format!("otp:{EMAIL}")).await.unwrap().unwrap();
+    store
+        .set("otp:bob@example.com", &stored, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(
+        service.verify_otp("bob@example.com", &otp).await.unwrap(),
+        OtpVerification::Invalid {
+            remaining_attempts: 4
+        }
+    );
+    assert_eq!(
+        service.verify_otp(EMAIL, &otp).await.unwrap(),
+        OtpVerification::Valid
+    );
+}
+
+#[tokio::test]
+async fn codes_can_only_be_used_once() {
+    let store = MemoryOtpStore::new();
+    let service = service(&store, "key");
+    let otp = issue(&service).await;
+
+    assert_eq!(
+        service.verify_otp(EMAIL, &otp).await.unwrap(),
+        OtpVerification::Valid
+    );
+    assert_eq!(
+        service.verify_otp(EMAIL, &otp).await.unwrap(),
+        OtpVerification::Expired
+    );
+}
+
+#[tokio::test]
+async fn codes_are_discarded_after_five_wrong_guesses() {
+    let store = MemoryOtpStore::new();
+    let service = service(&store, "key");
+    let otp = issue(&service).await;
+    let wrong = wrong(&otp);
+
+    for remaining_attempts in (1..=4).rev() {
+        assert_eq!(
+            service.verify_otp(EMAIL, &wrong).await.unwrap(),
+            OtpVerification::Invalid { remaining_attempts }
+        );
+    }
+    assert_eq!(
+        service.verify_otp(EMAIL, &wrong).await.unwrap(),
+        OtpVerification::TooManyAttempts
+    );
+    assert_eq!(
+        service.verify_otp(EMAIL, &otp).await.unwrap(),
+        OtpVerification::Expired
+    );
+}
+
+#[tokio::test]
+async fn a_new_code_resets_the_wrong_guesses() {
+    let store = MemoryOtpStore::new();
+    let service = service(&store, "key");
+    let otp = issue(&service).await;
+    for _ in 0..4 {
+        service.verify_otp(EMAIL, &wrong(&otp)).await.unwrap();
+    }
+
+    let otp = issue(&service).await;
+    assert_eq!(
+        service.verify_otp(EMAIL, &wrong(&otp)).await.unwrap(),
+        OtpVerification::Invalid {
+            remaining_attempts: 4
+        }
+    );
+    assert_eq!(
+        service.verify_otp(EMAIL, &otp).await.unwrap(),
+        OtpVerification::Valid
+    );
+}
+
+#[tokio::test]
+async fn sends_are_limited_per_email() {
+    let store = MemoryOtpStore::new();
+    let service = service(&store, "key");
+
+    for _ in 0..5 {
+        service.issue_otp(EMAIL, Locale::En).await.unwrap();
+        service.record_email_sent(EMAIL).await;
+    }
+    assert!(service.attempts_exhausted(EMAIL).await.unwrap());
+    assert!(service.issue_otp(EMAIL, Locale::En).await.is_err());
+
+    service.admin_reset_attempts(EMAIL).await.unwrap();
+    assert!(service.issue_otp(EMAIL, Locale::En).await.is_ok());
+}
@@ -0,0 +1,76 @@
+use backend::services::otp_store::{MemoryOtpStore, OtpStore};
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(60);
+// Long enough to outlast on a slow machine, short enough to wait for
+const SHORT_TTL: Duration = Duration::from_millis(50);
+
+async fn expire() {
+    tokio::time::sleep(SHORT_TTL * 3).await;
+}
+
+#[tokio::test]
+async fn values_can_be_read_until_removed() {
+    let store = MemoryOtpStore::new();
+
+    store.set("otp:ada", "first", TTL).await.unwrap();
+    store.set("otp:ada", "second", TTL).await.unwrap();
+    assert_eq!(
+        store.get("otp:ada").await.unwrap().as_deref(),
+        Some("second")
+    );
+
+    assert!(store.delete("otp:ada").await.unwrap());
+    assert!(!store.delete("otp:ada").await.unwrap());
+    assert_eq!(store.get("otp:ada").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn values_can_only_be_taken_once() {
+    let store = MemoryOtpStore::new();
+    store.set("magic:1", "1", TTL).await.unwrap();
+
+    assert_eq!(store.take("magic:1").await.unwrap().as_deref(), Some("1"));
+    assert_eq!(store.take("magic:1").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn expired_values_are_gone() {
+    let store = MemoryOtpStore::new();
+    store.set("otp:ada", "123456", SHORT_TTL).await.unwrap();
+    store.set("magic:1", "1", SHORT_TTL).await.unwrap();
+    expire().await;
+
+    assert_eq!(store.get("otp:ada").await.unwrap(), None);
+    assert_eq!(store.take("magic:1").await.unwrap(), None);
+    assert!(!store.delete("otp:ada").await.unwrap());
+}
+
+#[tokio::test]
+async fn counters_keep_their_first_expiry() {
+    let store = MemoryOtpStore::new();
+
+    assert_eq!(store.increment("attempts:ada", SHORT_TTL).await.unwrap(), 1);
+    // A later increment with a longer TTL doesn't extend the window
+    assert_eq!(store.increment("attempts:ada", TTL).await.unwrap(), 2);
+    expire().await;
+
+    assert_eq!(store.increment("attempts:ada", TTL).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn parallel_increments_are_all_counted() {
+    let store = MemoryOtpStore::new();
+
+    let increments = (0..20).map(|_| {
+        let store = store.clone();
+        tokio::spawn(async move { store.increment("attempts:ada", TTL).await.unwrap() })
+    });
+    let mut counts = Vec::new();
+    for increment in increments {
+        counts.push(increment.await.unwrap());
+    }
+    counts.sort();
+
+    assert_eq!(counts, (1..=20).collect::<Vec<i64>>());
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use axum::http::StatusCode;
+use backend::{
+    database::repository::UserRepository, services::pending_cleanup::PendingAccountCleanup,
+    shutdown::Shutdown,
+};
+use common::TestApp;
+use serde_json::json;
+use std::time::Duration;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// An app with a verified account and one that never verified its email
+async fn app_with_pending_account() -> TestApp {
+    let app = TestApp::new();
+    app.signed_up_user("ada@example.com", "ada").await;
+    assert_eq!(
+        app.register("bob@example.com", "bob").await.status,
+        StatusCode::CREATED
+    );
+    app
+}
+
+async fn exists(app: &TestApp, email: &str) -> bool {
+    app.repository.find_by_email(email).await.unwrap().is_some()
+}
+
+#[tokio::test]
+async fn only_old_unverified_accounts_are_deleted() {
+    let app = app_with_pending_account().await;
+
+    let recent = PendingAccountCleanup::new(app.repository.clone(), HOUR, HOUR);
+    assert_eq!(recent.run_once().await.unwrap(), 0);
+    assert!(exists(&app, "bob@example.com").await);
+
+    // Everything created so far is older than a zero maximum age
+    let all = PendingAccountCleanup::new(app.repository.clone(), Duration::ZERO, HOUR);
+    assert_eq!(all.run_once().await.unwrap(), 1);
+    assert!(!exists(&app, "bob@example.com").await);
+    assert!(exists(&app, "ada@example.com").await);
+}
+
+#[tokio::test]
+async fn deleted_sign_ups_free_their_username() {
+    let app = app_with_pending_account().await;
+    // While the sign-up is pending, registering with its username only resends its code
+    let taken = app.register("robert@example.com", "bob").await;
+    assert_eq!(taken.status, StatusCode::OK);
+    assert!(!exists(&app, "robert@example.com").await);
+
+    PendingAccountCleanup::new(app.repository.clone(), Duration::ZERO, HOUR)
+        .run_once()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        app.register("robert@example.com", "bob").await.status,
+        StatusCode::CREATED
+    );
+}
+
+#[tokio::test]
+async fn the_background_task_runs_right_away() {
+    let app = app_with_pending_account().await;
+    let shutdown = Shutdown::new();
+
+    let task =
+        PendingAccountCleanup::new(app.repository.clone(), Duration::ZERO, HOUR).spawn(&shutdown);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while exists(&app, "bob@example.com").await {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the pending account is deleted");
+
+    shutdown.trigger();
+    task.await.unwrap();
+}
+
+#[tokio::test]
+async fn admins_can_run_the_cleanup() {
+    let app = app_with_pending_account().await;
+    let admin = app.admin_user("grace@example.com", "grace").await;
+
+    let response = app
+        .post("/api/admin/users/cleanup-pending", Some(&admin), json!({}))
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    // The test app only deletes accounts older than a day
+    assert_eq!(
+        response.body["data"],
+        json!({ "deleted": 0, "older_than_hours": 24 })
+    );
+    assert!(exists(&app, "bob@example.com").await);
+
+    let user = app.signed_up_user("carol@example.com", "carol").await;
+    let forbidden = app
+        .post("/api/admin/users/cleanup-pending", Some(&user), json!({}))
+        .await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,159 @@
+use axum::http::{HeaderMap, HeaderValue};
+use backend::{
+    config::RateLimitConfig,
+    middleware::rate_limit::{RateLimitStoreKind, RateLimiter, RatePolicy},
+    services::otp_store::{MemoryOtpStore, OtpStore, TokenBucket},
+};
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+/// The bucket of a `3/min` policy: a token every 20 seconds, at most three at once
+const THREE_PER_MINUTE: TokenBucket = TokenBucket {
+    interval_ms: 20_000,
+    capacity_ms: 60_000,
+};
+
+fn policy(requests: u32, period_secs: u64) -> RatePolicy {
+    RatePolicy {
+        requests,
+        period: Duration::from_secs(period_secs),
+    }
+}
+
+#[test]
+fn policies_are_parsed_from_requests_per_period() {
+    for (text, expected) in [
+        ("10/min", policy(10, 60)),
+        ("100/15min", policy(100, 15 * 60)),
+        ("5/h", policy(5, 60 * 60)),
+        ("2/30s", policy(2, 30)),
+        (" 7 / Minute ", policy(7, 60)),
+    ] {
+        assert_eq!(text.parse::<RatePolicy>(), Ok(expected), "{text}");
+    }
+}
+
+#[test]
+fn malformed_policies_are_rejected() {
+    for text in [
+        "", "10", "10/", "/min", "ten/min", "10/day", "10/min5", "-1/min", "0/min", "10/0min",
+    ] {
+        assert!(text.parse::<RatePolicy>().is_err(), "{text} was accepted");
+    }
+}
+
+#[test]
+fn policies_print_in_the_largest_whole_unit() {
+    for (policy, text) in [
+        (policy(10, 60), "10/min"),
+        (policy(100, 15 * 60), "100/15min"),
+        (policy(5, 2 * 60 * 60), "5/2h"),
+        (policy(2, 90), "2/90s"),
+    ] {
+        assert_eq!(policy.to_string(), text);
+        assert_eq!(text.parse::<RatePolicy>(), Ok(policy));
+    }
+}
+
+#[test]
+fn a_full_bucket_allows_a_burst_of_its_capacity() {
+    let mut full_at = None;
+    for expected in [20_000, 40_000, 60_000] {
+        let state = THREE_PER_MINUTE.take(full_at, 0);
+        assert!(state.allowed);
+        assert_eq!(state.full_at_ms, expected);
+        full_at = Some(state.full_at_ms);
+    }
+
+    let denied = THREE_PER_MINUTE.take(full_at, 0);
+    assert!(!denied.allowed);
+    assert_eq!(denied.full_at_ms, 60_000);
+}
+
+#[test]
+fn tokens_come_back_one_interval_at_a_time() {
+    let empty = Some(60_000);
+
+    assert!(!THREE_PER_MINUTE.take(empty, 19_999).allowed);
+    let refilled = THREE_PER_MINUTE.take(empty, 20_000);
+    assert!(refilled.allowed);
+    assert_eq!(refilled.full_at_ms, 80_000);
+
+    // A bucket that was full long ago doesn't hold more than its capacity
+    let idle = THREE_PER_MINUTE.take(Some(0), 1_000_000);
+    assert!(idle.allowed);
+    assert_eq!(idle.full_at_ms, 1_020_000);
+}
+
+#[tokio::test]
+async fn stores_keep_buckets_per_key() {
+    let store = MemoryOtpStore::new();
+
+    for _ in 0..3 {
+        let state = store
+            .take_token("ratelimit:auth:ip:1", THREE_PER_MINUTE, 0)
+            .await
+            .unwrap();
+        assert!(state.allowed);
+    }
+    let denied = store
+        .take_token("ratelimit:auth:ip:1", THREE_PER_MINUTE, 0)
+        .await
+        .unwrap();
+    assert!(!denied.allowed);
+
+    let other = store
+        .take_token("ratelimit:auth:ip:2", THREE_PER_MINUTE, 0)
+        .await
+        .unwrap();
+    assert!(other.allowed);
+
+    let later = store
+        .take_token("ratelimit:auth:ip:1", THREE_PER_MINUTE, 20_000)
+        .await
+        .unwrap();
+    assert!(later.allowed);
+}
+
+#[test]
+fn forwarded_addresses_are_only_believed_from_trusted_proxies() {
+    let limiter = RateLimiter::from_config(
+        &RateLimitConfig {
+            enabled: true,
+            store: RateLimitStoreKind::Memory,
+            trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+            auth: RatePolicy::per_minute(10),
+            email: RatePolicy::per_minute(10),
+            links: RatePolicy::per_minute(10),
+        },
+        Arc::new(MemoryOtpStore::new()) as Arc<dyn OtpStore>,
+    );
+    let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+    let forwarded = |value: &'static str| {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static(value));
+        headers
+    };
+
+    // Anyone else could claim to be anyone
+    assert_eq!(
+        limiter.client_ip(ip("203.0.113.5"), &forwarded("198.51.100.7")),
+        ip("203.0.113.5")
+    );
+    // Hops are read from the right, skipping trusted proxies
+    assert_eq!(
+        limiter.client_ip(
+            ip("10.0.0.1"),
+            &forwarded("192.0.2.1, 198.51.100.7, 10.0.0.2")
+        ),
+        ip("198.51.100.7")
+    );
+    // A garbled header leaves the last address that could be read
+    assert_eq!(
+        limiter.client_ip(ip("10.0.0.1"), &forwarded("198.51.100.7, unknown")),
+        ip("10.0.0.1")
+    );
+    assert_eq!(
+        limiter.client_ip(ip("10.0.0.1"), &HeaderMap::new()),
+        ip("10.0.0.1")
+    );
+}
@@ -0,0 +1,165 @@
+use async_trait::async_trait;
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use backend::{
+    config::HealthConfig,
+    database::Database,
+    routes::create_probe_router,
+    services::{
+        email_sender::{EmailSender, EmailSenderError, OutgoingEmail},
+        otp_store::MemoryOtpStore,
+        readiness::Readiness,
+    },
+    shutdown::Shutdown,
+};
+use serde_json::Value;
+use sqlx::postgres::PgPoolOptions;
+use std::{sync::Arc, time::Duration};
+use tower::ServiceExt;
+
+/// An email backend whose health check fails, or hangs past any timeout
+enum FakeSender {
+    Failing,
+    Hanging,
+}
+
+#[async_trait]
+impl EmailSender for FakeSender {
+    async fn send(&self, _email: &OutgoingEmail) -> Result<(), EmailSenderError> {
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<(), EmailSenderError> {
+        match self {
+            FakeSender::Failing => Err(EmailSenderError::Resend("unreachable".to_string())),
+            FakeSender::Hanging => {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A Postgres pool where nothing listens
+fn unreachable_database() -> Database {
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(100))
+        .connect_lazy("postgres://linksphere@127.0.0.1:1/linksphere")
+        .unwrap();
+    Database::Postgres(pool)
+}
+
+fn probes(database: Database, sender: FakeSender, shutdown: &Shutdown) -> Router {
+    let readiness = Readiness::new(
+        database,
+        Arc::new(MemoryOtpStore::new()),
+        Arc::new(sender),
+        shutdown.clone(),
+        &HealthConfig {
+            check_timeout_ms: 500,
+        },
+    );
+    create_probe_router(readiness)
+}
+
+async fn get_json(router: &Router, uri: &str) -> (StatusCode, Value) {
+    let response = router
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn liveness_does_not_depend_on_the_database() {
+    let router = probes(
+        unreachable_database(),
+        FakeSender::Failing,
+        &Shutdown::new(),
+    );
+
+    let (status, body) = get_json(&router, "/livez").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["status"], "alive");
+}
+
+#[tokio::test]
+async fn readiness_reports_each_dependency() {
+    let router = probes(
+        unreachable_database(),
+        FakeSender::Failing,
+        &Shutdown::new(),
+    );
+
+    let (status, body) = get_json(&router, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["success"], false);
+    assert_eq!(body["message"], "A dependency is unavailable");
+    let checks = &body["data"]["checks"];
+    assert_eq!(checks["database"]["status"], "failed");
+    assert_eq!(checks["database"]["critical"], true);
+    assert_eq!(checks["migrations"]["status"], "failed");
+    assert_eq!(checks["otp_store"]["status"], "ok");
+    assert_eq!(checks["email"]["status"], "failed");
+    assert_eq!(checks["email"]["critical"], false);
+
+    // Failure details are only logged, since the probe is unauthenticated
+    assert!(!body.to_string().contains("127.0.0.1"));
+}
+
+#[tokio::test]
+async fn slow_checks_time_out() {
+    let router = probes(
+        unreachable_database(),
+        FakeSender::Hanging,
+        &Shutdown::new(),
+    );
+
+    let (_, body) = get_json(&router, "/readyz").await;
+    let email = &body["data"]["checks"]["email"];
+    assert_eq!(email["status"], "timeout");
+    assert!(email["duration_ms"].as_u64().unwrap() < 5000);
+}
+
+#[tokio::test]
+async fn draining_is_reported() {
+    let shutdown = Shutdown::new();
+    let router = probes(unreachable_database(), FakeSender::Failing, &shutdown);
+    shutdown.trigger();
+
+    let (status, body) = get_json(&router, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["message"], "Shutting down");
+    assert_eq!(body["data"]["draining"], true);
+}
+
+/// Readiness on a database that is actually reachable
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn ready_once_migrated_until_shutdown() {
+    let database = Database::connect("sqlite::memory:").await.unwrap();
+    let shutdown = Shutdown::new();
+    // The email backend isn't critical, emails wait in the outbox
+    let router = probes(database.clone(), FakeSender::Failing, &shutdown);
+
+    let (status, body) = get_json(&router, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["data"]["checks"]["database"]["status"], "ok");
+    assert_eq!(body["data"]["checks"]["migrations"]["status"], "failed");
+
+    database.run_migrations().await.unwrap();
+    let (status, body) = get_json(&router, "/readyz").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["ready"], true);
+
+    shutdown.trigger();
+    let (status, body) = get_json(&router, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["data"]["draining"], true);
+}
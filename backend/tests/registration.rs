@@ -0,0 +1,179 @@
+mod common;
+
+use axum::http::StatusCode;
+use backend::{
+    config::RegistrationConfig,
+    database::{models::NewInvite, repository::InviteRepository},
+    services::registration::{
+        generate_invite_code, hash_invite_code, is_disposable_domain, RegistrationError,
+        RegistrationMode, RegistrationPolicy,
+    },
+};
+use common::{TestApp, PASSWORD};
+use serde_json::json;
+use uuid::Uuid;
+
+fn config(mode: RegistrationMode, allowed_domains: &[&str]) -> RegistrationConfig {
+    RegistrationConfig {
+        mode,
+        allowed_domains: allowed_domains.iter().map(|d| d.to_string()).collect(),
+        block_disposable_emails: true,
+    }
+}
+
+fn policy(mode: RegistrationMode, allowed_domains: &[&str]) -> RegistrationPolicy {
+    RegistrationPolicy::from_config(&config(mode, allowed_domains))
+}
+
+#[test]
+fn disposable_domains_and_their_subdomains_are_recognized() {
+    assert!(is_disposable_domain("mailinator.com"));
+    assert!(is_disposable_domain("inbox.mailinator.com"));
+    assert!(!is_disposable_domain("example.com"));
+    assert!(!is_disposable_domain("notmailinator.com"));
+    // A bare public suffix never matches
+    assert!(!is_disposable_domain("com"));
+}
+
+#[test]
+fn disposable_addresses_are_rejected_unless_allowed() {
+    let open = policy(RegistrationMode::Open, &[]);
+    assert!(open.check_email("ada@example.com").is_ok());
+    assert!(matches!(
+        open.check_email("ada@Mailinator.COM."),
+        Err(RegistrationError::DisposableEmail)
+    ));
+
+    let permissive = RegistrationPolicy::from_config(&RegistrationConfig {
+        block_disposable_emails: false,
+        ..config(RegistrationMode::Open, &[])
+    });
+    assert!(permissive.check_email("ada@mailinator.com").is_ok());
+}
+
+#[test]
+fn domain_mode_only_accepts_the_allowed_domains() {
+    let policy = policy(RegistrationMode::Domain, &["example.com"]);
+
+    assert!(policy.check_email("ada@Example.com").is_ok());
+    let err = policy.check_email("ada@sub.example.com").unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+    assert_eq!(err.code(), "DOMAIN_NOT_ALLOWED");
+    assert_eq!(err.details(), Some(json!({ "domain": "sub.example.com" })));
+}
+
+#[test]
+fn invite_mode_requires_a_code() {
+    let invite = policy(RegistrationMode::Invite, &[]);
+
+    assert!(matches!(
+        invite.invite_hash(None),
+        Err(RegistrationError::InviteRequired)
+    ));
+    assert!(matches!(
+        invite.invite_hash(Some("  ")),
+        Err(RegistrationError::InviteRequired)
+    ));
+    assert_eq!(
+        invite.invite_hash(Some("abcd-efgh-jklm")).unwrap(),
+        Some(hash_invite_code("ABCD-EFGH-JKLM"))
+    );
+    assert_eq!(
+        policy(RegistrationMode::Open, &[])
+            .invite_hash(Some("ABCD-EFGH-JKLM"))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn invite_codes_are_grouped_and_hashed_loosely() {
+    let invite = generate_invite_code();
+
+    let groups: Vec<&str> = invite.code.split('-').collect();
+    assert_eq!(groups.len(), 3);
+    assert!(groups.iter().all(|group| group.len() == 4
+        && group
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())));
+    assert!(!invite.code.contains(['0', '1', 'I', 'O']));
+    assert_eq!(invite.prefix, groups[0]);
+    assert_eq!(invite.hash, hash_invite_code(&invite.code));
+
+    let typed = invite.code.to_lowercase().replace('-', " ");
+    assert_eq!(hash_invite_code(&typed), invite.hash);
+    assert_ne!(
+        hash_invite_code("ABCD-EFGH-JKLN"),
+        hash_invite_code("ABCD-EFGH-JKLM")
+    );
+}
+
+#[tokio::test]
+async fn invite_only_registration_redeems_the_code() {
+    let app = TestApp::with_registration(config(RegistrationMode::Invite, &[]));
+    let invite = generate_invite_code();
+    app.repository
+        .create(NewInvite {
+            code_prefix: invite.prefix,
+            code_hash: invite.hash,
+            note: None,
+            max_uses: 1,
+            expires_at: None,
+            created_by: Uuid::new_v4(),
+        })
+        .await
+        .unwrap();
+    let code = invite.code;
+
+    let register = |email: &'static str, username: &'static str, code: Option<String>| {
+        let app = app.clone();
+        async move {
+            app.post(
+                "/api/auth/register",
+                None,
+                json!({
+                    "email": email,
+                    "username": username,
+                    "password": PASSWORD,
+                    "gender": "Other",
+                    "invite_code": code,
+                }),
+            )
+            .await
+        }
+    };
+
+    let missing = register("ada@example.com", "ada", None).await;
+    assert_eq!(missing.status, StatusCode::FORBIDDEN);
+    assert_eq!(missing.body["code"], "INVITE_REQUIRED");
+
+    let wrong = register("ada@example.com", "ada", Some("AAAA-BBBB-CCCC".into())).await;
+    assert_eq!(wrong.status, StatusCode::FORBIDDEN);
+    assert_eq!(wrong.body["code"], "INVALID_INVITE");
+
+    let redeemed = register("ada@example.com", "ada", Some(code.to_lowercase())).await;
+    assert_eq!(redeemed.status, StatusCode::CREATED);
+
+    let used_up = register("bob@example.com", "bob", Some(code)).await;
+    assert_eq!(used_up.status, StatusCode::FORBIDDEN);
+    assert_eq!(used_up.body["code"], "INVALID_INVITE");
+}
+
+#[tokio::test]
+async fn registration_rejects_addresses_outside_the_policy() {
+    let app = TestApp::with_registration(config(RegistrationMode::Domain, &["example.com"]));
+
+    let outside = app.register("ada@example.org", "ada").await;
+    assert_eq!(outside.status, StatusCode::FORBIDDEN);
+    assert_eq!(outside.body["code"], "DOMAIN_NOT_ALLOWED");
+    assert_eq!(outside.body["details"]["domain"], "example.org");
+
+    assert_eq!(
+        app.register("ada@example.com", "ada").await.status,
+        StatusCode::CREATED
+    );
+
+    let disposable = TestApp::new().register("ada@mailinator.com", "ada").await;
+    assert_eq!(disposable.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(disposable.body["code"], "DISPOSABLE_EMAIL");
+}
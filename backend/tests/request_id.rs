@@ -0,0 +1,127 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{HeaderName, HeaderValue, Request, StatusCode},
+    middleware::from_fn,
+    routing::get,
+    Router,
+};
+use backend::{
+    api::AppError,
+    middleware::request_logger::{current_request_id, request_logger, REQUEST_ID_HEADER},
+};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use serde_json::Value;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn router() -> Router {
+    Router::new()
+        .route(
+            "/id",
+            get(|| async { current_request_id().unwrap_or_default() }),
+        )
+        .route(
+            "/missing",
+            get(|| async { Err::<(), _>(AppError::not_found("LINK_NOT_FOUND", "Link not found")) }),
+        )
+        .route(
+            "/broken",
+            get(|| async {
+                Err::<(), _>(AppError::internal("Failed to load links", "disk on fire"))
+            }),
+        )
+        .layer(from_fn(request_logger))
+}
+
+/// The request ID header and body of a response to `uri`
+async fn get_with(uri: &str, headers: &[(&str, &str)]) -> (StatusCode, String, Vec<u8>) {
+    let mut request = Request::get(uri).body(Body::empty()).unwrap();
+    for (name, value) in headers {
+        request.headers_mut().insert(
+            HeaderName::from_bytes(name.as_bytes()).unwrap(),
+            HeaderValue::from_str(value).unwrap(),
+        );
+    }
+    let response = router().oneshot(request).await.unwrap();
+    let status = response.status();
+    let id = response.headers()[REQUEST_ID_HEADER]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, id, body.to_vec())
+}
+
+#[tokio::test]
+async fn every_response_names_its_request() {
+    let (status, id, body) = get_with("/id", &[]).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(Uuid::parse_str(&id).is_ok(), "{id}");
+    // Handlers see the same ID
+    assert_eq!(String::from_utf8(body).unwrap(), id);
+
+    let (_, other, _) = get_with("/id", &[]).await;
+    assert_ne!(id, other);
+}
+
+#[tokio::test]
+async fn error_bodies_carry_the_request_id() {
+    for uri in ["/missing", "/broken"] {
+        let (status, id, body) = get_with(uri, &[(REQUEST_ID_HEADER, "support-ticket-42")]).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert!(status.is_client_error() || status.is_server_error());
+        assert_eq!(id, "support-ticket-42");
+        assert_eq!(body["request_id"], "support-ticket-42");
+    }
+
+    let (_, _, body) = get_with("/broken", &[]).await;
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert!(!body["message"].as_str().unwrap().contains("disk on fire"));
+}
+
+#[tokio::test]
+async fn unusable_client_ids_are_replaced() {
+    let too_long = "a".repeat(129);
+    for supplied in [
+        "",
+        "two words",
+        "line\tbreak",
+        "<script>",
+        too_long.as_str(),
+    ] {
+        let (_, id, _) = get_with("/id", &[(REQUEST_ID_HEADER, supplied)]).await;
+
+        assert_ne!(id, supplied);
+        assert!(Uuid::parse_str(&id).is_ok(), "{supplied:?} gave {id}");
+    }
+
+    let longest = "a".repeat(128);
+    let (_, id, _) = get_with("/id", &[(REQUEST_ID_HEADER, &longest)]).await;
+    assert_eq!(id, longest);
+}
+
+#[tokio::test]
+async fn requests_continue_the_callers_trace() {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    let (_, id, _) = get_with(
+        "/id",
+        &[("traceparent", &format!("00-{trace_id}-00f067aa0ba902b7-01"))],
+    )
+    .await;
+    assert_eq!(id, trace_id);
+
+    // An explicit request ID still wins
+    let (_, id, _) = get_with(
+        "/id",
+        &[
+            ("traceparent", &format!("00-{trace_id}-00f067aa0ba902b7-01")),
+            (REQUEST_ID_HEADER, "support-ticket-42"),
+        ],
+    )
+    .await;
+    assert_eq!(id, "support-ticket-42");
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::auth::UserRole;
+use common::{TestApp, PASSWORD};
+use serde_json::json;
+use std::net::Ipv4Addr;
+
+/// Logs in again so the session token carries the account's current role
+async fn relogin(app: &TestApp, email: &str) -> String {
+    let login = app
+        .login_from(Ipv4Addr::LOCALHOST.into(), email, PASSWORD)
+        .await;
+    assert_eq!(login.status, StatusCode::OK);
+    login.body["data"]["token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn moderators_cannot_reach_admin_routes() {
+    let app = TestApp::new();
+    app.signed_up_user("alan@example.com", "alan").await;
+    app.repository
+        .set_role(app.user_id("alan@example.com").await, UserRole::Moderator);
+    let moderator = relogin(&app, "alan@example.com").await;
+
+    let users = app.get("/api/admin/users", Some(&moderator)).await;
+    assert_eq!(users.status, StatusCode::FORBIDDEN);
+    assert_eq!(users.body["code"], "FORBIDDEN");
+
+    let unlock = app
+        .post(
+            "/api/admin/auth/unlock-account",
+            Some(&moderator),
+            json!({ "email": "alan@example.com" }),
+        )
+        .await;
+    assert_eq!(unlock.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn the_role_is_read_from_the_login_token() {
+    let app = TestApp::new();
+    let before = app.signed_up_user("grace@example.com", "grace").await;
+    app.repository
+        .set_role(app.user_id("grace@example.com").await, UserRole::Admin);
+
+    // A token issued before the promotion still carries the user role
+    let stale = app.get("/api/admin/users", Some(&before)).await;
+    assert_eq!(stale.status, StatusCode::FORBIDDEN);
+
+    let admin = relogin(&app, "grace@example.com").await;
+    let fresh = app.get("/api/admin/users", Some(&admin)).await;
+    assert_eq!(fresh.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn api_tokens_of_admins_cannot_use_the_admin_api() {
+    let app = TestApp::new();
+    let admin = app.admin_user("grace@example.com", "grace").await;
+    let created = app
+        .post(
+            "/api/tokens",
+            Some(&admin),
+            json!({ "name": "Script", "scopes": ["links:read", "links:write"] }),
+        )
+        .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    let token = created.body["data"]["token"].as_str().unwrap();
+
+    let users = app.get("/api/admin/users", Some(token)).await;
+    assert_eq!(users.status, StatusCode::FORBIDDEN);
+
+    let reset = app
+        .post(
+            "/api/admin/auth/reset-otp-attempts",
+            Some(token),
+            json!({ "email": "grace@example.com" }),
+        )
+        .await;
+    assert_eq!(reset.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn admin_auth_routes_require_the_admin_role() {
+    let app = TestApp::new();
+    let user = app.signed_up_user("alan@example.com", "alan").await;
+    let admin = app.admin_user("grace@example.com", "grace").await;
+
+    let anonymous = app
+        .post(
+            "/api/admin/auth/reset-otp-attempts",
+            None,
+            json!({ "email": "alan@example.com" }),
+        )
+        .await;
+    assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED);
+
+    let forbidden = app
+        .post(
+            "/api/admin/auth/reset-otp-attempts",
+            Some(&user),
+            json!({ "email": "alan@example.com" }),
+        )
+        .await;
+    assert_eq!(forbidden.status, StatusCode::FORBIDDEN);
+
+    let reset = app
+        .post(
+            "/api/admin/auth/reset-otp-attempts",
+            Some(&admin),
+            json!({ "email": "alan@example.com" }),
+        )
+        .await;
+    assert_eq!(reset.status, StatusCode::OK);
+
+    let unlocked = app
+        .post(
+            "/api/admin/auth/unlock-account",
+            Some(&admin),
+            json!({ "email": "alan@example.com" }),
+        )
+        .await;
+    assert_eq!(unlocked.status, StatusCode::OK);
+    assert_eq!(unlocked.body["data"]["status"], "unlocked");
+
+    let unknown = app
+        .post(
+            "/api/admin/auth/unlock-account",
+            Some(&admin),
+            json!({ "email": "nobody@example.com" }),
+        )
+        .await;
+    assert_eq!(unknown.status, StatusCode::NOT_FOUND);
+    assert_eq!(unknown.body["code"], "USER_NOT_FOUND");
+}
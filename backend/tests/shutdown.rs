@@ -0,0 +1,71 @@
+use backend::shutdown::Shutdown;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::time::Instant;
+
+#[tokio::test]
+async fn clones_share_the_signal() {
+    let shutdown = Shutdown::new();
+    let handle = shutdown.clone();
+    assert!(!handle.is_triggered());
+
+    shutdown.trigger();
+
+    assert!(handle.is_triggered());
+    handle.triggered().await;
+    handle.triggered_owned().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn draining_waits_for_tasks_to_wind_down() {
+    let shutdown = Shutdown::new();
+    let finished = Arc::new(AtomicBool::new(false));
+    let stop = shutdown.clone();
+    let done = finished.clone();
+    shutdown.spawn(async move {
+        stop.triggered().await;
+        // Finishing the current piece of work
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        done.store(true, Ordering::SeqCst);
+    });
+
+    shutdown.trigger();
+    let still_running = shutdown
+        .drain(Instant::now() + Duration::from_secs(30))
+        .await;
+
+    assert_eq!(still_running, 0);
+    assert!(finished.load(Ordering::SeqCst));
+}
+
+#[tokio::test(start_paused = true)]
+async fn draining_gives_up_at_the_deadline() {
+    let shutdown = Shutdown::new();
+    shutdown.spawn(std::future::pending::<()>());
+    shutdown.spawn(async {});
+    let started = Instant::now();
+
+    shutdown.trigger();
+    let still_running = shutdown.drain(started + Duration::from_secs(30)).await;
+
+    assert_eq!(still_running, 1);
+    assert_eq!(started.elapsed(), Duration::from_secs(30));
+}
+
+#[tokio::test]
+async fn signal_listeners_stop_once_shutdown_starts() {
+    let shutdown = Shutdown::new();
+    let listener = tokio::spawn(shutdown.clone().listen_for_signals());
+
+    shutdown.trigger();
+
+    tokio::time::timeout(Duration::from_secs(5), listener)
+        .await
+        .expect("the listener returns")
+        .unwrap();
+}
@@ -0,0 +1,230 @@
+//! The SQLite backend against the same expectations as the others. Run with
+//! `cargo test --features sqlite`.
+#![cfg(feature = "sqlite")]
+
+use backend::{
+    database::{
+        models::{EmailStatus, NewEmail, NewInvite, NewLink, NewUser},
+        repository::{Registration, Repositories},
+        Database,
+    },
+    models::auth::{Gender, UserStatus},
+    services::registration::generate_invite_code,
+};
+use chrono::{Duration, Utc};
+use std::collections::HashMap;
+
+/// A fresh, migrated in-memory database
+async fn repositories() -> Repositories {
+    let database = Database::connect("sqlite::memory:").await.unwrap();
+    database.run_migrations().await.unwrap();
+    database.repositories()
+}
+
+fn new_user(email: &str, username: &str, status: UserStatus) -> NewUser {
+    NewUser {
+        email: email.to_string(),
+        username: username.to_string(),
+        password_hash: "hash".to_string(),
+        gender: Gender::Other,
+        is_verified: status == UserStatus::Active,
+        status,
+        locale: "en".to_string(),
+    }
+}
+
+fn otp_email(recipient: &str) -> NewEmail {
+    NewEmail {
+        recipient: recipient.to_string(),
+        template: "otp_verification".to_string(),
+        locale: "en".to_string(),
+        variables: HashMap::from([("otp".to_string(), "123456".to_string())]),
+    }
+}
+
+#[tokio::test]
+async fn migrations_are_applied_once() {
+    let database = Database::connect("sqlite::memory:").await.unwrap();
+    database.run_migrations().await.unwrap();
+
+    database.run_migrations().await.unwrap();
+    database.repositories().users.ping().await.unwrap();
+}
+
+#[tokio::test]
+async fn registration_uses_up_invites_together_with_the_account() {
+    let repositories = repositories().await;
+    let admin = repositories
+        .users
+        .create(new_user("grace@example.com", "grace", UserStatus::Active))
+        .await
+        .unwrap();
+    let invite = generate_invite_code();
+    repositories
+        .invites
+        .create(NewInvite {
+            code_prefix: invite.prefix,
+            code_hash: invite.hash.clone(),
+            note: None,
+            max_uses: 1,
+            expires_at: None,
+            created_by: admin.id,
+        })
+        .await
+        .unwrap();
+
+    let registered = repositories
+        .users
+        .register(
+            new_user("ada@example.com", "ada", UserStatus::PendingVerification),
+            Some(&invite.hash),
+            otp_email("ada@example.com"),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(registered, Registration::Created(_)));
+
+    let refused = repositories
+        .users
+        .register(
+            new_user("bob@example.com", "bob", UserStatus::PendingVerification),
+            Some(&invite.hash),
+            otp_email("bob@example.com"),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(refused, Registration::InvalidInvite));
+
+    // Nothing of the refused registration was stored
+    assert!(repositories
+        .users
+        .find_by_email("bob@example.com")
+        .await
+        .unwrap()
+        .is_none());
+    let queued = repositories
+        .outbox
+        .list(EmailStatus::Pending, 10)
+        .await
+        .unwrap();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].recipient, "ada@example.com");
+    assert_eq!(repositories.invites.list().await.unwrap()[0].uses, 1);
+}
+
+#[tokio::test]
+async fn outbox_emails_are_leased_retried_and_replayed() {
+    let outbox = repositories().await.outbox;
+    let id = outbox.enqueue(otp_email("ada@example.com")).await.unwrap();
+
+    let claimed = outbox.claim_due(10, 60).await.unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].id, id);
+    assert_eq!(claimed[0].attempts, 1);
+    assert_eq!(claimed[0].variables["otp"], "123456");
+    // Leased emails are hidden from other workers
+    assert!(outbox.claim_due(10, 60).await.unwrap().is_empty());
+
+    outbox
+        .mark_failed(id, "Connection refused", Some(0))
+        .await
+        .unwrap();
+    let retried = outbox.claim_due(10, 60).await.unwrap();
+    assert_eq!(retried[0].attempts, 2);
+
+    outbox
+        .mark_failed(id, "Mailbox unavailable", None)
+        .await
+        .unwrap();
+    assert!(outbox.claim_due(10, 60).await.unwrap().is_empty());
+    let dead = outbox.list(EmailStatus::Dead, 10).await.unwrap();
+    assert_eq!(dead[0].last_error.as_deref(), Some("Mailbox unavailable"));
+
+    assert!(outbox.replay(id).await.unwrap());
+    assert!(!outbox.replay(id).await.unwrap());
+    let replayed = outbox.claim_due(10, 60).await.unwrap();
+    assert_eq!(replayed[0].attempts, 1);
+
+    outbox.mark_sent(id).await.unwrap();
+    let sent = outbox.list(EmailStatus::Sent, 10).await.unwrap();
+    assert_eq!(sent[0].id, id);
+    assert!(sent[0].sent_at.is_some());
+}
+
+#[tokio::test]
+async fn stale_sign_ups_are_deleted_with_their_links() {
+    let repositories = repositories().await;
+    let active = repositories
+        .users
+        .create(new_user("ada@example.com", "ada", UserStatus::Active))
+        .await
+        .unwrap();
+    let pending = repositories
+        .users
+        .create(new_user(
+            "bob@example.com",
+            "bob",
+            UserStatus::PendingVerification,
+        ))
+        .await
+        .unwrap();
+    for user_id in [active.id, pending.id] {
+        repositories
+            .links
+            .create(NewLink {
+                url: "https://example.com".to_string(),
+                title: "Example".to_string(),
+                description: "An example".to_string(),
+                user_id,
+            })
+            .await
+            .unwrap();
+    }
+
+    let recent = Utc::now() - Duration::hours(1);
+    assert!(repositories
+        .users
+        .delete_stale_pending(recent)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let deleted = repositories
+        .users
+        .delete_stale_pending(Utc::now() + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert_eq!(deleted, ["bob"]);
+    let links = repositories.links.list().await.unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].user_id, active.id);
+}
+
+#[tokio::test]
+async fn failed_logins_lock_the_account() {
+    let users = repositories().await.users;
+    let user = users
+        .create(new_user("ada@example.com", "ada", UserStatus::Active))
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(
+            users.record_failed_login(user.id, 3, 60.0).await.unwrap(),
+            None
+        );
+    }
+    let locked_until = users
+        .record_failed_login(user.id, 3, 60.0)
+        .await
+        .unwrap()
+        .expect("locked after the third failure");
+    assert!(locked_until > Utc::now());
+
+    assert!(users.unlock_account("ada@example.com").await.unwrap());
+    assert!(!users.unlock_account("nobody@example.com").await.unwrap());
+    assert_eq!(
+        users.record_failed_login(user.id, 3, 60.0).await.unwrap(),
+        None
+    );
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn tokens_are_limited_to_their_scopes() {
+    let app = TestApp::new();
+    let session = app.signed_up_user("ada@example.com", "ada").await;
+
+    let created = app
+        .post(
+            "/api/tokens",
+            Some(&session),
+            json!({ "name": "Reader", "scopes": ["links:read"] }),
+        )
+        .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    let token = created.body["data"]["token"].as_str().unwrap();
+
+    let read = app.get("/api/links", Some(token)).await;
+    assert_eq!(read.status, StatusCode::OK);
+
+    let write = app
+        .post(
+            "/api/links",
+            Some(token),
+            json!({ "url": "https://example.com/" }),
+        )
+        .await;
+    assert_eq!(write.status, StatusCode::FORBIDDEN);
+
+    // Tokens can't be used to manage tokens
+    let manage = app.get("/api/tokens", Some(token)).await;
+    assert_eq!(manage.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn revoked_tokens_are_rejected() {
+    let app = TestApp::new();
+    let session = app.signed_up_user("ada@example.com", "ada").await;
+    let other = app.signed_up_user("alan@example.com", "alan").await;
+
+    let created = app
+        .post(
+            "/api/tokens",
+            Some(&session),
+            json!({ "name": "Extension", "scopes": ["links:read", "links:write"] }),
+        )
+        .await;
+    let token = created.body["data"]["token"].as_str().unwrap();
+    let id = created.body["data"]["id"].as_str().unwrap();
+    assert_eq!(
+        app.get("/api/links", Some(token)).await.status,
+        StatusCode::OK
+    );
+
+    // Only the owner can revoke it
+    let not_theirs = app.delete(&format!("/api/tokens/{id}"), Some(&other)).await;
+    assert_eq!(not_theirs.status, StatusCode::NOT_FOUND);
+
+    let revoked = app
+        .delete(&format!("/api/tokens/{id}"), Some(&session))
+        .await;
+    assert_eq!(revoked.status, StatusCode::OK);
+
+    let after = app.get("/api/links", Some(token)).await;
+    assert_eq!(after.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn link_statistics_need_the_analytics_scope() {
+    let app = TestApp::new();
+    let session = app.signed_up_user("ada@example.com", "ada").await;
+    let other = app.signed_up_user("alan@example.com", "alan").await;
+
+    let link = app
+        .post(
+            "/api/links",
+            Some(&session),
+            json!({
+                "url": "http://127.0.0.1:9/article",
+                "title": "An article",
+                "description": "Worth reading",
+            }),
+        )
+        .await;
+    let id = link.body["data"]["id"].as_str().unwrap();
+    app.post(&format!("/api/links/{id}/click"), Some(&session), json!({}))
+        .await;
+
+    let token = |scopes: Value| {
+        let app = app.clone();
+        let session = session.clone();
+        async move {
+            let created = app
+                .post(
+                    "/api/tokens",
+                    Some(&session),
+                    json!({ "name": "Dashboard", "scopes": scopes }),
+                )
+                .await;
+            created.body["data"]["token"].as_str().unwrap().to_string()
+        }
+    };
+    let analytics = token(json!(["analytics:read"])).await;
+    let links_only = token(json!(["links:read", "links:write"])).await;
+    let stats = format!("/api/links/{id}/stats");
+
+    let allowed = app.get(&stats, Some(&analytics)).await;
+    assert_eq!(allowed.status, StatusCode::OK);
+    assert_eq!(allowed.body["data"]["click_count"], 1);
+
+    assert_eq!(
+        app.get(&stats, Some(&links_only)).await.status,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(app.get(&stats, Some(&session)).await.status, StatusCode::OK);
+    assert_eq!(
+        app.get(&stats, Some(&other)).await.status,
+        StatusCode::FORBIDDEN
+    );
+}